@@ -34,6 +34,8 @@
 //! the local filesystem for configuration and authentication material.
 
 use crate::config::{AuraeConfig, CertMaterial, ClientCertDetails};
+use backoff::backoff::Backoff;
+use std::future::Future;
 use std::str::FromStr;
 use thiserror::Error;
 use tokio::net::UnixStream;
@@ -67,6 +69,7 @@ impl AuraeClient {
     }
 
     /// Create a new AuraeClient.
+    /// Fails fast if auraed can't be connected to (see [AuraeClient::connect_with_retry]).
     ///
     /// Note: A new client is required for every independent execution of this process.
     pub async fn new(
@@ -100,4 +103,124 @@ impl AuraeClient {
 
         Ok(Self { channel, client_cert_details })
     }
+
+    /// Like [AuraeClient::new], but retries connecting, waiting between attempts as
+    /// `backoff` says. Gives up after `max_attempts` attempts, or once `backoff` stops.
+    /// Meant for long-running callers, which should outlast auraed restarting.
+    ///
+    /// Only connection errors are retried: a config that can't be used fails on the
+    /// first attempt.
+    pub async fn connect_with_retry<B: Backoff>(
+        config: AuraeConfig,
+        max_attempts: u32,
+        backoff: B,
+    ) -> Result<Self> {
+        retry(max_attempts, backoff, || async {
+            match Self::new(config.clone()).await {
+                Ok(client) => Ok(client),
+                Err(e @ AuraeClientError::ConnectionError(_)) => {
+                    Err(backoff::Error::transient(e))
+                }
+                Err(e) => Err(backoff::Error::Permanent(e)),
+            }
+        })
+        .await
+    }
+
+    /// Returns the channel of the client, for calls the service traits don't cover
+    /// (e.g., a request with metadata).
+    pub fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+}
+
+/// Calls `connect` until it succeeds, fails with a permanent error, or has been called
+/// `max_attempts` times, waiting between attempts as `backoff` says.
+async fn retry<T, E, B, F, Fut>(
+    max_attempts: u32,
+    backoff: B,
+    mut connect: F,
+) -> std::result::Result<T, E>
+where
+    B: Backoff,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<T, backoff::Error<E>>>,
+{
+    let mut attempts = 0;
+    backoff::future::retry(backoff, || {
+        attempts += 1;
+        let is_last_attempt = attempts >= max_attempts;
+        let attempt = connect();
+        async move {
+            match attempt.await {
+                Err(backoff::Error::Transient { err, .. })
+                    if is_last_attempt =>
+                {
+                    Err(backoff::Error::Permanent(err))
+                }
+                res => res,
+            }
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+    use std::time::Duration;
+    use tokio::net::UnixListener;
+
+    fn fast_backoff() -> ExponentialBackoff {
+        ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_millis(10))
+            .with_max_interval(Duration::from_millis(10))
+            .with_randomization_factor(0.0)
+            .with_max_elapsed_time(None)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_once_the_socket_appears() {
+        let socket = std::env::temp_dir()
+            .join(format!("aurae-client-{}.sock", std::process::id()));
+
+        // auraed starts listening after the first attempts
+        let listener = {
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                UnixListener::bind(socket).expect("bind")
+            })
+        };
+
+        let res = retry(100, fast_backoff(), || {
+            let socket = socket.clone();
+            async move {
+                UnixStream::connect(socket)
+                    .await
+                    .map_err(backoff::Error::transient)
+            }
+        })
+        .await;
+        let _listener = listener.await.expect("listener");
+        let _ = std::fs::remove_file(&socket);
+
+        let _stream = res.expect("connected once the socket appeared");
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let mut attempts = 0;
+        let res: std::result::Result<(), &str> =
+            retry(3, fast_backoff(), || {
+                attempts += 1;
+                async { Err(backoff::Error::transient("connection refused")) }
+            })
+            .await;
+
+        assert_eq!(res, Err("connection refused"));
+        assert_eq!(attempts, 3);
+    }
 }
@@ -32,7 +32,27 @@ macros::service!(
     runtime,
     CellService,
     allocate(CellServiceAllocateRequest) -> CellServiceAllocateResponse,
+    update(CellServiceUpdateRequest) -> CellServiceUpdateResponse,
     free(CellServiceFreeRequest) -> CellServiceFreeResponse,
     start(CellServiceStartRequest) -> CellServiceStartResponse,
     stop(CellServiceStopRequest) -> CellServiceStopResponse,
+    restart(CellServiceRestartRequest) -> CellServiceRestartResponse,
+    stop_generation(CellServiceStopGenerationRequest) -> CellServiceStopGenerationResponse,
+    run(CellServiceRunRequest) -> CellServiceRunResponse,
+    list(CellServiceListRequest) -> CellServiceListResponse,
+    free_by_selector(CellServiceFreeBySelectorRequest) -> CellServiceFreeBySelectorResponse,
+    list_executables(CellServiceListExecutablesRequest) -> CellServiceListExecutablesResponse,
+    get_cell_by_tid(CellServiceGetCellByTidRequest) -> CellServiceGetCellByTidResponse,
+    describe(CellServiceDescribeRequest) -> CellServiceDescribeResponse,
+    stat(CellServiceStatRequest) -> CellServiceStatResponse,
+    drain(CellServiceDrainRequest) -> CellServiceDrainResponse,
+    freeze(CellServiceFreezeRequest) -> CellServiceFreezeResponse,
+    thaw(CellServiceThawRequest) -> CellServiceThawResponse,
+    list_fds(CellServiceListFdsRequest) -> CellServiceListFdsResponse,
+    commitment(CellServiceCommitmentRequest) -> CellServiceCommitmentResponse,
+    retry_config(CellServiceRetryConfigRequest) -> CellServiceRetryConfigResponse,
+    list_orphans(CellServiceListOrphansRequest) -> CellServiceListOrphansResponse,
 );
+
+// TODO: The macro does not support streaming, so log_stream(CellServiceLogStreamRequest)
+//  -> stream OutputLine is only available through the generated client for now.
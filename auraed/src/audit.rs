@@ -0,0 +1,274 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Audit log of the operations that mutate the state of auraed.
+//!
+//! Each audited call produces two JSON records (one per line), sharing an `id`:
+//!
+//! * `request_received` - written before the operation runs, with the
+//!   authenticated client identity (the common name of the client certificate)
+//!   and the full request.
+//! * `response_complete` - written after the operation, with its result.
+//!
+//! If the audit log is fail-closed, an operation whose `request_received`
+//! record can't be written is rejected without running. A `response_complete`
+//! record that can't be written is logged, as the operation already ran.
+//!
+//! The records are appended to a file opened with `O_APPEND`.
+
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tonic::{Request, Response, Status};
+use tracing::error;
+use x509_certificate::X509Certificate;
+
+/// Where audit records are written. Disabled unless opened with [AuditLog::open].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuditLog {
+    sink: Option<Arc<AuditSink>>,
+}
+
+#[derive(Debug)]
+struct AuditSink {
+    file: Mutex<File>,
+    fail_closed: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum AuditStage {
+    RequestReceived,
+    ResponseComplete,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    id: &'a str,
+    stage: AuditStage,
+    timestamp_ms: u128,
+    method: &'static str,
+    client: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the audit log file at `path` for appending.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        fail_closed: bool,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            sink: Some(Arc::new(AuditSink {
+                file: Mutex::new(file),
+                fail_closed,
+            })),
+        })
+    }
+
+    /// Runs `operation` with `request`, recording the request and the result.
+    pub async fn record<Req, Res, F, Fut>(
+        &self,
+        method: &'static str,
+        request: Request<Req>,
+        operation: F,
+    ) -> Result<Response<Res>, Status>
+    where
+        Req: Serialize,
+        F: FnOnce(Request<Req>) -> Fut,
+        Fut: Future<Output = Result<Response<Res>, Status>>,
+    {
+        let Some(sink) = &self.sink else {
+            return operation(request).await;
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let client = client_identity(&request);
+
+        let received = AuditRecord {
+            id: &id,
+            stage: AuditStage::RequestReceived,
+            timestamp_ms: now_ms(),
+            method,
+            client: client.as_deref(),
+            request: Some(
+                serde_json::to_value(request.get_ref())
+                    .unwrap_or_else(|e| e.to_string().into()),
+            ),
+            code: None,
+            message: None,
+        };
+
+        if let Err(e) = sink.write(&received) {
+            error!("failed to write audit record for '{method}': {e}");
+            if sink.fail_closed {
+                return Err(Status::unavailable(
+                    "request rejected: failed to write audit log",
+                ));
+            }
+        }
+
+        let result = operation(request).await;
+
+        let (code, message) = match &result {
+            Ok(_) => (tonic::Code::Ok, ""),
+            Err(status) => (status.code(), status.message()),
+        };
+
+        let complete = AuditRecord {
+            id: &id,
+            stage: AuditStage::ResponseComplete,
+            timestamp_ms: now_ms(),
+            method,
+            client: client.as_deref(),
+            request: None,
+            code: Some(format!("{code:?}")),
+            message: Some(message),
+        };
+
+        if let Err(e) = sink.write(&complete) {
+            error!("failed to write audit record for '{method}': {e}");
+        }
+
+        result
+    }
+}
+
+impl AuditSink {
+    fn write(&self, record: &AuditRecord<'_>) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().expect("audit log lock poisoned");
+        file.write_all(&line)?;
+        file.flush()
+    }
+}
+
+/// Returns the common name of the client certificate, if the client presented one.
+pub(crate) fn client_identity<T>(request: &Request<T>) -> Option<String> {
+    let certs = request.peer_certs()?;
+    let cert = X509Certificate::from_der(certs.first()?.get_ref()).ok()?;
+    cert.subject_common_name()
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aurae_proto::runtime::{
+        CellServiceFreeRequest, CellServiceFreeResponse,
+    };
+    use simple_test_case::test_case;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn free_request() -> Request<CellServiceFreeRequest> {
+        Request::new(CellServiceFreeRequest {
+            cell_name: "ae-audit".into(),
+            return_final_stats: false,
+            children_policy: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_record_contents() {
+        let path = std::env::temp_dir()
+            .join(format!("aurae-audit-{}.log", uuid::Uuid::new_v4()));
+        let audit = AuditLog::open(&path, false).expect("open audit log");
+
+        let result = audit
+            .record("free", free_request(), |_| async {
+                Err::<Response<CellServiceFreeResponse>, _>(Status::not_found(
+                    "cell 'ae-audit' not found",
+                ))
+            })
+            .await;
+        assert!(result.is_err());
+
+        let log = std::fs::read_to_string(&path).expect("read audit log");
+        let records: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json record"))
+            .collect();
+        assert_eq!(records.len(), 2);
+
+        let (received, complete) = (&records[0], &records[1]);
+        assert_eq!(received["id"], complete["id"]);
+        assert_eq!(received["stage"], "request_received");
+        assert_eq!(received["method"], "free");
+        assert_eq!(received["client"], serde_json::Value::Null);
+        assert_eq!(received["request"]["cellName"], "ae-audit");
+        assert!(received["timestamp_ms"].as_u64().expect("timestamp") > 0);
+
+        assert_eq!(complete["stage"], "response_complete");
+        assert_eq!(complete["code"], "NotFound");
+        assert_eq!(complete["message"], "cell 'ae-audit' not found");
+        assert!(complete.get("request").is_none());
+
+        std::fs::remove_file(&path).expect("remove audit log");
+    }
+
+    #[test_case(true; "fail closed")]
+    #[test_case(false; "fail open")]
+    #[tokio::test]
+    async fn test_failure_to_write(fail_closed: bool) {
+        // writes to /dev/full always fail with ENOSPC
+        let audit = AuditLog::open("/dev/full", fail_closed).expect("open");
+
+        let ran = AtomicBool::new(false);
+        let result = audit
+            .record("free", free_request(), |_| async {
+                ran.store(true, Ordering::SeqCst);
+                Ok(Response::new(CellServiceFreeResponse::default()))
+            })
+            .await;
+
+        assert_eq!(ran.load(Ordering::SeqCst), !fail_closed);
+        assert_eq!(result.is_ok(), !fail_closed);
+    }
+}
@@ -0,0 +1,925 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Configuration of auraed which can be changed while auraed is running.
+//!
+//! The configuration is read from the TOML file passed with `--config` at
+//! startup, and read again each time auraed receives a [SIGHUP].
+//!
+//! Reloadable settings:
+//!
+//! * `[retry]` - the backoff used when connecting to a nested auraed.
+//!   Set `disabled = true` to fail immediately (primarily for testing).
+//! * `[templates.<name>]` - cell settings an allocate request can reference
+//!   by name (see [CellTemplate]).
+//! * `[defaults]` - cell settings applied to every cell allocated by auraed
+//!   (see [ReloadableConfig::apply_defaults]).
+//! * `[capacity]` - the number of cells auraed allocates (see [CapacityConfig]).
+//! * `[logging]` - the level of the logs of auraed (see [LoggingConfig]).
+//! * `[kill]` - how hard auraed tries to kill the processes left in a cell
+//!   when it frees all cells on shutdown (see [KillConfig]).
+//! * `[admin]` - the clients allowed to call admin RPCs (see [AdminConfig]).
+//! * `[memory]` - the budget of the memory cells can pin (see [MemoryConfig]).
+//! * `[cpuset]` - how the NUMA locality of cpusets is checked (see [CpusetConfig]).
+//!
+//! Everything configured by command line flags (certificates, socket,
+//! runtime directory, verbosity, ...) requires a restart of auraed.
+//! A restart frees all cells.
+//!
+//! A reloaded configuration is validated before it is applied. If the file
+//! can not be read, parsed, or is invalid, the previous configuration is
+//! kept.
+//!
+//! Reloading never changes the cells that are already allocated: the new
+//! defaults and limits apply to the cells allocated after the reload.
+//!
+//! [SIGHUP]: https://aurae.io/signals
+
+use crate::init::set_log_level;
+use crate::runtime::{validate_cell, KillEscalation};
+use aurae_proto::runtime::{
+    Cell, CellServiceAllocateRequest, CpuController, CpusetController,
+};
+use backoff::{
+    backoff::{Backoff, Stop},
+    ExponentialBackoffBuilder,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::signal::unix::SignalKind;
+use tokio::sync::RwLock;
+use tracing::{error, info, Level};
+use validation::ValidationError;
+
+pub(crate) type SharedConfig = Arc<RwLock<ReloadableConfig>>;
+
+#[derive(Debug, Error)]
+pub(crate) enum ConfigError {
+    #[error("failed to read config '{}': {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("failed to parse config '{}': {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("invalid config field '{field}': {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+/// The subset of auraed configuration that can be reloaded at runtime.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ReloadableConfig {
+    pub retry: RetryConfig,
+    pub templates: HashMap<String, CellTemplate>,
+    pub defaults: CellTemplate,
+    pub capacity: CapacityConfig,
+    pub logging: LoggingConfig,
+    pub kill: KillConfig,
+    pub admin: AdminConfig,
+    pub memory: MemoryConfig,
+    pub cpuset: CpusetConfig,
+}
+
+impl ReloadableConfig {
+    /// Reads, parses, and validates a config file.
+    pub fn parse_from_file<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::Read { path: path.to_path_buf(), source: e }
+        })?;
+
+        let config: Self = toml::from_str(&contents).map_err(|e| {
+            ConfigError::Parse { path: path.to_path_buf(), source: e }
+        })?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.retry.validate()?;
+        self.kill.validate()?;
+        self.admin.validate()?;
+        self.capacity.validate()?;
+        self.logging.validate()?;
+
+        self.defaults.validate().map_err(|e| ConfigError::Invalid {
+            field: "defaults",
+            reason: e.to_string(),
+        })?;
+
+        for (name, template) in &self.templates {
+            template.validate().map_err(|e| ConfigError::Invalid {
+                field: "templates",
+                reason: format!("template '{name}': {e}"),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the template the allocate request references (if any) to its cell,
+    /// and clears the reference, so the request can be forwarded to a nested auraed
+    /// that doesn't know the template.
+    pub fn apply_template(
+        &self,
+        request: &mut CellServiceAllocateRequest,
+    ) -> Result<(), ValidationError> {
+        if request.template.is_empty() {
+            return Ok(());
+        }
+
+        let Some(template) = self.templates.get(&request.template) else {
+            return Err(ValidationError::Invalid { field: "template".into() });
+        };
+
+        if let Some(cell) = &mut request.cell {
+            template.apply(cell);
+        }
+
+        request.template.clear();
+
+        Ok(())
+    }
+
+    /// Fills in the settings of the cell of the allocate request that are set
+    /// in neither the request nor its template from the `[defaults]`. Applied
+    /// after [ReloadableConfig::apply_template].
+    ///
+    /// Only applied to cells allocated by this auraed, not to cells nested in
+    /// them, which are allocated by the nested auraed of their parent cell.
+    pub fn apply_defaults(&self, request: &mut CellServiceAllocateRequest) {
+        if let Some(cell) = &mut request.cell {
+            self.defaults.apply(cell);
+        }
+    }
+}
+
+/// A named set of cell settings, which an allocate request can reference to
+/// avoid repeating a common configuration.
+///
+/// Settings of the requested cell override the template:
+///
+/// * Controller values set on the cell replace the template's values.
+/// * Labels are merged, with the cell's value winning for keys in both.
+/// * Isolation can only be added: the cell is isolated if either the template
+///   or the cell asks for it, as an unset bool can't be told apart from false.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct CellTemplate {
+    pub cpu_weight: Option<u64>,
+    pub cpu_max: Option<i64>,
+    pub cpuset_cpus: Option<String>,
+    pub cpuset_mems: Option<String>,
+    pub isolate_process: bool,
+    pub isolate_network: bool,
+    pub labels: HashMap<String, String>,
+}
+
+impl CellTemplate {
+    /// Fills in the settings of `cell` from the template (see [CellTemplate]).
+    pub fn apply(&self, cell: &mut Cell) {
+        if self.cpu_weight.is_some() || self.cpu_max.is_some() {
+            let cpu = cell.cpu.get_or_insert_with(CpuController::default);
+            if cpu.weight.is_none() {
+                cpu.weight = self.cpu_weight;
+            }
+            // cpu_percent is an alternative to max
+            if cpu.max.is_none() && cpu.cpu_percent.is_none() {
+                cpu.max = self.cpu_max;
+            }
+        }
+
+        if self.cpuset_cpus.is_some() || self.cpuset_mems.is_some() {
+            let cpuset =
+                cell.cpuset.get_or_insert_with(CpusetController::default);
+            if cpuset.cpus.is_none() {
+                cpuset.cpus = self.cpuset_cpus.clone();
+            }
+            if cpuset.mems.is_none() {
+                cpuset.mems = self.cpuset_mems.clone();
+            }
+        }
+
+        for (key, value) in &self.labels {
+            let _ =
+                cell.labels.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        cell.isolate_process |= self.isolate_process;
+        cell.isolate_network |= self.isolate_network;
+    }
+
+    /// Validates the template as the cell of an allocate request.
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut cell = Cell { name: "template".into(), ..Default::default() };
+        self.apply(&mut cell);
+        validate_cell(cell)
+    }
+}
+
+/// The exponential backoff used to retry connecting to, and calling, a nested auraed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct RetryConfig {
+    /// Delay before the first retry.
+    pub initial_interval_ms: u64,
+    /// Factor the delay is multiplied by after each retry.
+    pub multiplier: f64,
+    /// Randomness applied to each delay (0.5 is +/-50%).
+    pub randomization_factor: f64,
+    /// Upper bound of a single delay.
+    pub max_interval_ms: u64,
+    /// Total time after which we stop retrying.
+    pub max_elapsed_ms: u64,
+    /// Fail on the first error instead of retrying.
+    ///
+    /// Primarily for testing, where waiting on the backoff slows down
+    /// deliberately failing calls to nested cells.
+    pub disabled: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval_ms: 50,   // 1st retry in 50ms
+            multiplier: 10.0,          // 10x the delay after 1st retry (500ms)
+            randomization_factor: 0.5, // with a randomness of +/-50% (250-750ms)
+            max_interval_ms: 3_000,    // but never delay more than 3s
+            max_elapsed_ms: 20_000,    // or 20s total
+            disabled: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.initial_interval_ms == 0 {
+            return Err(ConfigError::Invalid {
+                field: "retry.initial_interval_ms",
+                reason: "must be greater than 0".into(),
+            });
+        }
+
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err(ConfigError::Invalid {
+                field: "retry.multiplier",
+                reason: "must be at least 1.0".into(),
+            });
+        }
+
+        if !(0.0..=1.0).contains(&self.randomization_factor) {
+            return Err(ConfigError::Invalid {
+                field: "retry.randomization_factor",
+                reason: "must be between 0.0 and 1.0".into(),
+            });
+        }
+
+        if self.max_interval_ms < self.initial_interval_ms {
+            return Err(ConfigError::Invalid {
+                field: "retry.max_interval_ms",
+                reason: "must not be less than retry.initial_interval_ms"
+                    .into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Builds a new backoff strategy from the configuration.
+    /// If retries are disabled, the strategy never retries.
+    pub fn backoff(&self) -> Box<dyn Backoff + Send> {
+        if self.disabled {
+            return Box::new(Stop {});
+        }
+
+        Box::new(
+            ExponentialBackoffBuilder::new()
+                .with_initial_interval(Duration::from_millis(
+                    self.initial_interval_ms,
+                ))
+                .with_multiplier(self.multiplier)
+                .with_randomization_factor(self.randomization_factor)
+                .with_max_interval(Duration::from_millis(self.max_interval_ms))
+                .with_max_elapsed_time(Some(Duration::from_millis(
+                    self.max_elapsed_ms,
+                )))
+                .build(),
+        )
+    }
+}
+
+/// Limits on the number of cells of auraed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct CapacityConfig {
+    /// Maximum number of cells auraed allocates. An allocation that would exceed
+    /// it is rejected. Cells nested in a cell are not counted, as they are
+    /// allocated by the nested auraed of the cell. No limit if unset.
+    pub max_cells: Option<usize>,
+}
+
+impl CapacityConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_cells == Some(0) {
+            return Err(ConfigError::Invalid {
+                field: "capacity.max_cells",
+                reason: "must be greater than 0".into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The logs of auraed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct LoggingConfig {
+    /// Level of the logs (`error`, `warn`, `info`, `debug`, or `trace`).
+    /// Defaults to the level set by `--verbose` at startup.
+    pub level: Option<String>,
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(level) = &self.level {
+            let _ =
+                Level::from_str(level).map_err(|e| ConfigError::Invalid {
+                    field: "logging.level",
+                    reason: e.to_string(),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configured level, if any.
+    pub fn level(&self) -> Option<Level> {
+        self.level.as_deref().and_then(|level| level.parse().ok())
+    }
+
+    /// Sets the level of the logs of auraed. Errors are logged, as they leave
+    /// the previous level in place.
+    pub fn apply(&self) {
+        if let Err(e) = set_log_level(self.level()) {
+            error!("failed to set the log level: {e}");
+        }
+    }
+}
+
+/// How the processes left in a cell are killed after it failed to free gracefully.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct KillConfig {
+    /// Number of times the processes are sent a SIGKILL before giving up.
+    /// A cell with processes left after the last attempt is not removed.
+    pub max_attempts: u32,
+    /// Time given to the processes to exit after each SIGKILL,
+    /// and to the cells to settle after being freed gracefully.
+    pub escalation_delay_ms: u64,
+}
+
+impl Default for KillConfig {
+    fn default() -> Self {
+        let KillEscalation { attempts, delay } = KillEscalation::default();
+        Self {
+            max_attempts: attempts,
+            escalation_delay_ms: delay.as_millis() as u64,
+        }
+    }
+}
+
+impl KillConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_attempts == 0 {
+            return Err(ConfigError::Invalid {
+                field: "kill.max_attempts",
+                reason: "must be greater than 0".into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn escalation(&self) -> KillEscalation {
+        KillEscalation {
+            attempts: self.max_attempts,
+            delay: Duration::from_millis(self.escalation_delay_ms),
+        }
+    }
+}
+
+/// The clients allowed to call admin RPCs (e.g., ListFds), which expose
+/// details of the processes auraed runs for debugging. No client is an admin
+/// by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct AdminConfig {
+    /// Common names of the client certificates of the admins.
+    pub clients: Vec<String>,
+    /// Common name of the client certificate of the auraed of the parent cell,
+    /// which is an admin as it authorized the calls it forwards.
+    ///
+    /// Only set for a nested auraed, by the parent (see `--parent-client`).
+    #[serde(skip)]
+    pub parent_client: Option<String>,
+    /// Treat every client as an admin, for tests without client certificates.
+    #[cfg(test)]
+    #[serde(skip)]
+    pub trust_all_clients: bool,
+}
+
+impl AdminConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.clients.iter().any(|client| client.is_empty()) {
+            return Err(ConfigError::Invalid {
+                field: "admin.clients",
+                reason: "must not contain an empty name".into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `client` (the common name of its certificate) is an admin.
+    pub fn is_admin(&self, client: Option<&str>) -> bool {
+        #[cfg(test)]
+        if self.trust_all_clients {
+            return true;
+        }
+
+        client.map_or(false, |client| {
+            self.parent_client.as_deref() == Some(client)
+                || self.clients.iter().any(|admin| admin == client)
+        })
+    }
+}
+
+/// Limits on the memory of the cells of auraed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct MemoryConfig {
+    /// Total memory, in bytes, the cells allocated by auraed can pin in RAM.
+    /// An allocation that would exceed it is rejected. No budget if unset.
+    ///
+    /// Only the cells of this auraed are counted. A nested auraed has no config, so
+    /// the memory its cells pin is only bounded by the memory of their parent cell.
+    pub pinned_budget_bytes: Option<u64>,
+}
+
+/// Checks of the cpusets of the cells of auraed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct CpusetConfig {
+    /// What to do when a cell is allocated with `cpuset.mems` that don't include
+    /// the NUMA nodes local to its `cpuset.cpus`, according to the topology in
+    /// `/sys/devices/system/node`. Not checked by default.
+    pub numa_locality: NumaLocality,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum NumaLocality {
+    /// Don't check.
+    #[default]
+    Ignore,
+    /// Log a warning, and allocate the cell.
+    Warn,
+    /// Reject the allocation.
+    Reject,
+}
+
+/// Reloads the config file into `config` every time a SIGHUP is received.
+/// The previous configuration is kept if the file fails to load.
+pub(crate) async fn reload_on_sighup(path: PathBuf, config: SharedConfig) {
+    let mut stream = tokio::signal::unix::signal(SignalKind::hangup())
+        .expect("failed to listen for SIGHUP");
+
+    while stream.recv().await.is_some() {
+        info!("Received SIGHUP, reloading config '{}'", path.display());
+
+        match ReloadableConfig::parse_from_file(&path) {
+            Ok(mut reloaded) => {
+                let mut config = config.write().await;
+                // set at startup, not read from the file
+                reloaded.admin.parent_client =
+                    config.admin.parent_client.clone();
+                reloaded.logging.apply();
+                *config = reloaded;
+                info!("Reloaded config '{}'", path.display());
+            }
+            Err(e) => {
+                error!("{e}. Keeping the previous config.");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_is_default() {
+        let config: ReloadableConfig = toml::from_str("").expect("parse");
+        assert_eq!(config, ReloadableConfig::default());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_partial_retry_config() {
+        let config: ReloadableConfig =
+            toml::from_str("[retry]\nmax_elapsed_ms = 1000\n").expect("parse");
+
+        assert_eq!(config.retry.max_elapsed_ms, 1000);
+        assert_eq!(
+            config.retry.initial_interval_ms,
+            RetryConfig::default().initial_interval_ms
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        assert!(
+            toml::from_str::<ReloadableConfig>("[retry]\nfoo = 1\n").is_err()
+        );
+    }
+
+    #[test]
+    fn test_kill_config() {
+        let config: ReloadableConfig = toml::from_str(
+            "[kill]\nmax_attempts = 3\nescalation_delay_ms = 250\n",
+        )
+        .expect("parse");
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.kill.escalation(),
+            KillEscalation { attempts: 3, delay: Duration::from_millis(250) }
+        );
+
+        let config: ReloadableConfig =
+            toml::from_str("[kill]\nmax_attempts = 0\n").expect("parse");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "kill.max_attempts", .. })
+        ));
+    }
+
+    #[test]
+    fn test_admin_config() {
+        let config: ReloadableConfig =
+            toml::from_str("[admin]\nclients = [\"ops\"]\n").expect("parse");
+        assert!(config.validate().is_ok());
+
+        assert!(config.admin.is_admin(Some("ops")));
+        assert!(!config.admin.is_admin(Some("dev")));
+        assert!(!config.admin.is_admin(None));
+        assert!(!ReloadableConfig::default().admin.is_admin(Some("ops")));
+
+        // set at startup of a nested auraed, never read from the file
+        assert!(toml::from_str::<ReloadableConfig>(
+            "[admin]\nparent_client = \"ops\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_admin_config_of_nested_auraed() {
+        let mut config: ReloadableConfig =
+            toml::from_str("[admin]\nclients = [\"ops\"]\n").expect("parse");
+        config.admin.parent_client = Some("parent".into());
+
+        // only the auraed of the parent cell is trusted, not every client
+        assert!(config.admin.is_admin(Some("parent")));
+        assert!(config.admin.is_admin(Some("ops")));
+        assert!(!config.admin.is_admin(Some("dev")));
+        assert!(!config.admin.is_admin(None));
+    }
+
+    #[test]
+    fn test_memory_config() {
+        assert_eq!(
+            ReloadableConfig::default().memory.pinned_budget_bytes,
+            None
+        );
+
+        let config: ReloadableConfig =
+            toml::from_str("[memory]\npinned_budget_bytes = 1073741824\n")
+                .expect("parse");
+        assert_eq!(config.memory.pinned_budget_bytes, Some(1 << 30));
+    }
+
+    #[test]
+    fn test_cpuset_config() {
+        assert_eq!(
+            ReloadableConfig::default().cpuset.numa_locality,
+            NumaLocality::Ignore
+        );
+
+        let config: ReloadableConfig =
+            toml::from_str("[cpuset]\nnuma_locality = \"reject\"\n")
+                .expect("parse");
+        assert_eq!(config.cpuset.numa_locality, NumaLocality::Reject);
+
+        assert!(toml::from_str::<ReloadableConfig>(
+            "[cpuset]\nnuma_locality = \"fail\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_capacity_config() {
+        assert_eq!(ReloadableConfig::default().capacity.max_cells, None);
+
+        let config: ReloadableConfig =
+            toml::from_str("[capacity]\nmax_cells = 10\n").expect("parse");
+        assert!(config.validate().is_ok());
+        assert_eq!(config.capacity.max_cells, Some(10));
+
+        let config: ReloadableConfig =
+            toml::from_str("[capacity]\nmax_cells = 0\n").expect("parse");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "capacity.max_cells", .. })
+        ));
+    }
+
+    #[test]
+    fn test_logging_config() {
+        assert_eq!(ReloadableConfig::default().logging.level(), None);
+
+        let config: ReloadableConfig =
+            toml::from_str("[logging]\nlevel = \"debug\"\n").expect("parse");
+        assert!(config.validate().is_ok());
+        assert_eq!(config.logging.level(), Some(Level::DEBUG));
+
+        let config: ReloadableConfig =
+            toml::from_str("[logging]\nlevel = \"loud\"\n").expect("parse");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "logging.level", .. })
+        ));
+    }
+
+    #[test]
+    fn test_defaults_fill_in_what_the_request_and_template_leave_unset() {
+        let mut config = templates();
+        config.defaults = CellTemplate {
+            cpu_weight: Some(10),
+            cpu_max: Some(100000),
+            cpuset_mems: Some("0".into()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let mut request = allocate_request(
+            "small",
+            Cell {
+                name: "ae-1".into(),
+                cpu: Some(CpuController {
+                    weight: Some(500),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        config.apply_template(&mut request).expect("apply template");
+        config.apply_defaults(&mut request);
+
+        let cell = request.cell.expect("cell");
+        assert_eq!(
+            cell.cpu,
+            Some(CpuController {
+                weight: Some(500),
+                max: Some(200000),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            cell.cpuset,
+            Some(CpusetController {
+                cpus: Some("0-1".into()),
+                mems: Some("0".into())
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_defaults_are_rejected() {
+        let config: ReloadableConfig =
+            toml::from_str("[defaults]\ncpu_weight = 0\n").expect("parse");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "defaults", .. })
+        ));
+    }
+
+    fn templates() -> ReloadableConfig {
+        toml::from_str(
+            r#"
+            [templates.small]
+            cpu_weight = 100
+            cpu_max = 200000
+            cpuset_cpus = "0-1"
+            isolate_process = true
+
+            [templates.small.labels]
+            tier = "batch"
+            team = "infra"
+            "#,
+        )
+        .expect("parse")
+    }
+
+    fn allocate_request(
+        template: &str,
+        cell: Cell,
+    ) -> CellServiceAllocateRequest {
+        CellServiceAllocateRequest {
+            cell: Some(cell),
+            template: template.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_template_fills_in_unset_settings() {
+        let config = templates();
+        assert!(config.validate().is_ok());
+
+        let mut request = allocate_request(
+            "small",
+            Cell { name: "ae-1".into(), ..Default::default() },
+        );
+        config.apply_template(&mut request).expect("apply template");
+
+        let cell = request.cell.expect("cell");
+        assert_eq!(cell.name, "ae-1");
+        assert_eq!(
+            cell.cpu,
+            Some(CpuController {
+                weight: Some(100),
+                max: Some(200000),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            cell.cpuset,
+            Some(CpusetController { cpus: Some("0-1".into()), mems: None })
+        );
+        assert_eq!(cell.labels.len(), 2);
+        assert!(cell.isolate_process);
+        assert!(!cell.isolate_network);
+        assert!(request.template.is_empty());
+    }
+
+    #[test]
+    fn test_overrides_win_over_template() {
+        let mut request = allocate_request(
+            "small",
+            Cell {
+                name: "ae-1".into(),
+                cpu: Some(CpuController {
+                    weight: Some(500),
+                    ..Default::default()
+                }),
+                labels: HashMap::from([("tier".into(), "web".into())]),
+                isolate_network: true,
+                ..Default::default()
+            },
+        );
+        templates().apply_template(&mut request).expect("apply template");
+
+        let cell = request.cell.expect("cell");
+        assert_eq!(
+            cell.cpu,
+            Some(CpuController {
+                weight: Some(500),
+                max: Some(200000),
+                ..Default::default()
+            })
+        );
+        assert_eq!(cell.labels["tier"], "web");
+        assert_eq!(cell.labels["team"], "infra");
+        assert!(cell.isolate_process);
+        assert!(cell.isolate_network);
+    }
+
+    #[test]
+    fn test_unknown_template_is_rejected() {
+        let mut request = allocate_request(
+            "large",
+            Cell { name: "ae-1".into(), ..Default::default() },
+        );
+        assert!(matches!(
+            templates().apply_template(&mut request),
+            Err(ValidationError::Invalid { field }) if field == "template"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_template_is_rejected() {
+        let config: ReloadableConfig = toml::from_str(
+            "[templates.bad]
+cpu_weight = 0
+",
+        )
+        .expect("parse");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "templates", .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_retry_config() {
+        let config = RetryConfig { multiplier: 0.5, ..Default::default() };
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "retry.multiplier", .. })
+        ));
+
+        let config =
+            RetryConfig { randomization_factor: 1.5, ..Default::default() };
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                field: "retry.randomization_factor",
+                ..
+            })
+        ));
+
+        let config = RetryConfig {
+            initial_interval_ms: 100,
+            max_interval_ms: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid { field: "retry.max_interval_ms", .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_retry_fails_immediately() {
+        let config: ReloadableConfig =
+            toml::from_str("[retry]\ndisabled = true\n").expect("parse");
+        assert!(config.validate().is_ok());
+
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let start = std::time::Instant::now();
+
+        let res: Result<(), &str> =
+            backoff::future::retry(config.retry.backoff(), || async {
+                let _ =
+                    attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(backoff::Error::transient("connection refused"))
+            })
+            .await;
+
+        assert_eq!(res, Err("connection refused"));
+        assert_eq!(attempts.into_inner(), 1);
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_enabled_retry_backs_off() {
+        let mut backoff = RetryConfig::default().backoff();
+        assert!(backoff.next_backoff().is_some());
+    }
+}
@@ -1,9 +1,10 @@
+use std::sync::OnceLock;
 use tracing::{info, Level};
 use tracing_rfc_5424::{
     rfc3164::Rfc3164, tracing::TrivialTracingFormatter, transport::UnixSocket,
 };
 use tracing_subscriber::{
-    layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -19,6 +20,47 @@ pub(crate) enum LoggingError {
 
     #[error(transparent)]
     SyslogError(#[from] tracing_rfc_5424::layer::Error),
+
+    #[error(transparent)]
+    ReloadError(#[from] reload::Error),
+}
+
+type SetLevel = Box<dyn Fn(Level) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Changes the level of the initialized logger.
+struct LevelReloader {
+    /// The level set at startup (by verbose).
+    initial: Level,
+    set: SetLevel,
+}
+
+static LEVEL_RELOADER: OnceLock<LevelReloader> = OnceLock::new();
+
+fn filter(tracing_level: Level) -> EnvFilter {
+    EnvFilter::new(format!("auraed={tracing_level}"))
+}
+
+fn reloadable(
+    initial: Level,
+    set: impl Fn(Level) -> Result<(), reload::Error> + Send + Sync + 'static,
+) {
+    let _ = LEVEL_RELOADER.set(LevelReloader { initial, set: Box::new(set) });
+}
+
+/// Changes the level of the logs while auraed is running.
+/// `None` restores the level auraed was started with.
+///
+/// Does nothing if the logger was not initialized (e.g., in tests).
+pub(crate) fn set_log_level(level: Option<Level>) -> Result<(), LoggingError> {
+    let Some(reloader) = LEVEL_RELOADER.get() else {
+        return Ok(());
+    };
+
+    let level = level.unwrap_or(reloader.initial);
+    (reloader.set)(level)?;
+    info!("log level set to {level}");
+
+    Ok(())
 }
 
 pub(crate) fn init(verbose: bool, container: bool) -> Result<(), LoggingError> {
@@ -45,15 +87,16 @@ fn init_container_logging(tracing_level: Level) -> Result<(), LoggingError> {
     info!("initializing container logging");
 
     // Stdout
+    let (stdout_filter, handle) = reload::Layer::new(filter(tracing_level));
     let stdout_layer = tracing_subscriber::Layer::with_filter(
         tracing_subscriber::fmt::layer().compact(),
-        EnvFilter::new(format!("auraed={tracing_level}")),
+        stdout_filter,
     );
 
-    tracing_subscriber::registry()
-        .with(stdout_layer)
-        .try_init()
-        .map_err(|e| e.into())
+    tracing_subscriber::registry().with(stdout_layer).try_init()?;
+    reloadable(tracing_level, move |level| handle.reload(filter(level)));
+
+    Ok(())
 }
 
 /// when we run as a daemon we want to log to stdout and syslog.
@@ -69,16 +112,19 @@ fn init_daemon_logging(tracing_level: Level) -> Result<(), LoggingError> {
     >::try_default()?;
 
     // Stdout
+    let (stdout_filter, handle) = reload::Layer::new(filter(tracing_level));
     let stdout_layer = tracing_subscriber::Layer::with_filter(
         tracing_subscriber::fmt::layer().compact(),
-        EnvFilter::new(format!("auraed={tracing_level}")),
+        stdout_filter,
     );
 
     tracing_subscriber::registry()
         .with(syslog_layer)
         .with(stdout_layer)
-        .try_init()
-        .map_err(|e| e.into())
+        .try_init()?;
+    reloadable(tracing_level, move |level| handle.reload(filter(level)));
+
+    Ok(())
 }
 
 fn init_stdout_logging(tracing_level: Level) -> Result<(), LoggingError> {
@@ -93,9 +139,14 @@ fn init_stdout_logging(tracing_level: Level) -> Result<(), LoggingError> {
 
 fn init_pid1_logging(tracing_level: Level) -> Result<(), LoggingError> {
     info!("initializing pid1 logging");
-    tracing_subscriber::fmt()
+    let builder = tracing_subscriber::fmt()
         .compact()
-        .with_env_filter(format!("auraed={tracing_level}"))
-        .try_init()
-        .map_err(|e| LoggingError::SetupFailure { source: e })
+        .with_env_filter(filter(tracing_level))
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+
+    builder.try_init().map_err(|e| LoggingError::SetupFailure { source: e })?;
+    reloadable(tracing_level, move |level| handle.reload(filter(level)));
+
+    Ok(())
 }
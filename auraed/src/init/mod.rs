@@ -33,6 +33,7 @@
 //! The Aurae daemon assumes that if the current process id (PID) is 1 to
 //! run itself as an initialization program, otherwise bypass the init module.
 
+pub(crate) use self::logging::set_log_level;
 pub use self::system_runtimes::SocketStream;
 use self::system_runtimes::{
     CellSystemRuntime, ContainerSystemRuntime, DaemonSystemRuntime,
@@ -64,30 +64,42 @@
 
 use crate::spawn::spawn_auraed_oci_to;
 use anyhow::Context;
+use audit::AuditLog;
 use aurae_proto::{
     discovery::discovery_service_server::DiscoveryServiceServer,
     runtime::cell_service_server::CellServiceServer,
     runtime::pod_service_server::PodServiceServer,
 };
 use clap::{Parser, Subcommand};
+use config::ReloadableConfig;
 use discovery::DiscoveryService;
 use init::SocketStream;
 use runtime::CellService;
 use runtime::PodService;
+use runtime::{ensure_daemon_cgroup, DaemonCgroup};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
+use tokio::sync::RwLock;
 use tonic::transport::server::Connected;
 use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
-use tracing::{error, info, trace};
+use tracing::{error, info, trace, warn};
 
+mod audit;
+mod config;
 mod discovery;
 mod graceful_shutdown;
 pub mod init;
 pub mod logging;
+mod max_request_size;
+mod metrics;
 mod observe;
+mod reflection;
 mod runtime;
 mod spawn;
+mod strict_fields;
 
 /// Default Unix domain socket path for `auraed`.
 ///
@@ -97,9 +109,10 @@ mod spawn;
 /// processes and commands. Access to the socket must be governed
 /// by an appropriate mTLS Authorization setting in order to maintain
 /// a secure multi tenant system.
-const AURAE_RUNTIME_DIR: &str = "/var/run/aurae";
+pub(crate) const AURAE_RUNTIME_DIR: &str = "/var/run/aurae";
 const AURAE_SOCK: &str = "aurae.sock";
 const AURAE_BUNDLE: &str = "/var/lib/aurae";
+pub(crate) const AURAE_SERVER_KEY: &str = "/etc/aurae/pki/server.key";
 
 const EXIT_OKAY: i32 = 0;
 const EXIT_ERROR: i32 = 1;
@@ -119,7 +132,7 @@ struct AuraedOptions {
     )]
     server_crt: String,
     /// The secret server key. Defaults to /etc/aurae/pki/server.key
-    #[clap(long, value_parser, default_value = "/etc/aurae/pki/server.key")]
+    #[clap(long, value_parser, default_value = AURAE_SERVER_KEY)]
     server_key: String,
     /// The CA certificate. Defaults to /etc/aurae/pki/ca.crt
     #[clap(long, value_parser, default_value = "/etc/aurae/pki/ca.crt")]
@@ -134,12 +147,48 @@ struct AuraedOptions {
     /// Aurae bundle path. Defaults to /var/lib/aurae
     #[clap(short, long, value_parser, default_value = AURAE_BUNDLE)]
     bundle: String,
+    /// Path to a TOML file of settings which are reloaded on SIGHUP.
+    /// Defaults to no file, using the default settings.
+    #[clap(long, value_parser)]
+    config: Option<String>,
     /// Toggle verbosity. Default false
     #[clap(short, long, alias = "ritz")]
     verbose: bool,
     /// Run auraed as a nested instance of itself in an Aurae cell.
     #[clap(long)]
     nested: bool,
+    /// Common name of the client certificate of the auraed of the parent cell, which is
+    /// trusted as an admin of a nested auraed. Defaults to no admin but the configured ones.
+    #[clap(long, value_parser, requires = "nested")]
+    parent_client: Option<String>,
+    /// Serve gRPC server reflection, allowing clients to discover the API without the
+    /// proto files. Disabled by default, as it exposes the API surface. Default false
+    #[clap(long)]
+    grpc_reflection: bool,
+    /// Append a record of every mutating cell operation (allocate, free, start, stop) to
+    /// this file. Defaults to no audit log.
+    #[clap(long, value_parser)]
+    audit_log: Option<String>,
+    /// Reject operations that can not be recorded in the audit log. Default false
+    #[clap(long)]
+    audit_fail_closed: bool,
+    /// Move auraed into a dedicated leaf cgroup at startup if it shares the cgroup cells
+    /// are created in, which would prevent allocating cells. Default false
+    #[clap(long)]
+    migrate_to_leaf_cgroup: bool,
+    /// Serve the resource usage of cells in the OpenMetrics text format at
+    /// http://{address}/metrics (e.g., 127.0.0.1:9100). Defaults to not serving metrics.
+    #[clap(long, value_parser)]
+    metrics_address: Option<SocketAddr>,
+    /// Reject requests with fields that are not in the protos auraed was built with
+    /// (e.g., set by a client built with newer protos), instead of ignoring the fields.
+    /// Default false
+    #[clap(long)]
+    reject_unknown_fields: bool,
+    /// Reject requests with a message larger than this many bytes, before decoding it.
+    /// Defaults to 4 MiB.
+    #[clap(long, value_parser, default_value_t = max_request_size::DEFAULT_MAX_REQUEST_SIZE)]
+    max_request_size: usize,
     // Subcommands for the project
     #[clap(subcommand)]
     subcmd: Option<SubCommands>,
@@ -174,6 +223,16 @@ pub async fn daemon() -> i32 {
         server_key: PathBuf::from(options.server_key),
         ca_crt: PathBuf::from(options.ca_crt),
         runtime_dir: PathBuf::from(options.runtime_dir),
+        config: options.config.map(PathBuf::from),
+        grpc_reflection: options.grpc_reflection,
+        audit_log: options.audit_log.map(PathBuf::from),
+        audit_fail_closed: options.audit_fail_closed,
+        nested: options.nested,
+        parent_client: options.parent_client,
+        migrate_to_leaf_cgroup: options.migrate_to_leaf_cgroup,
+        metrics_address: options.metrics_address,
+        reject_unknown_fields: options.reject_unknown_fields,
+        max_request_size: options.max_request_size,
     };
 
     let e = match init::init(options.verbose, options.nested, options.socket)
@@ -207,6 +266,26 @@ struct AuraedRuntime {
     pub server_key: PathBuf,
     /// Configurable runtime directory. Defaults to /var/run/aurae.
     pub runtime_dir: PathBuf,
+    /// Optional file of settings which are reloaded on SIGHUP.
+    pub config: Option<PathBuf>,
+    /// Serve gRPC server reflection.
+    pub grpc_reflection: bool,
+    /// Optional file mutating operations are recorded in.
+    pub audit_log: Option<PathBuf>,
+    /// Reject operations that can not be recorded in the audit log.
+    pub audit_fail_closed: bool,
+    /// Run as a nested instance of auraed in a cell.
+    pub nested: bool,
+    /// Optional common name of the client certificate of the parent auraed.
+    pub parent_client: Option<String>,
+    /// Move auraed into a dedicated leaf cgroup if it shares the cgroup of the cells.
+    pub migrate_to_leaf_cgroup: bool,
+    /// Optional address cell metrics are served on.
+    pub metrics_address: Option<SocketAddr>,
+    /// Reject requests with fields unknown to the protos of auraed.
+    pub reject_unknown_fields: bool,
+    /// Reject requests with a message larger than this many bytes.
+    pub max_request_size: usize,
     // /// Provides logging channels to expose auraed logging via grpc
     //pub log_collector: Arc<LogChannel>,
 }
@@ -258,14 +337,58 @@ impl AuraedRuntime {
 
         // Initialize the bundler
 
+        // Cells can't be allocated while we share the cgroup they are created in
+        match ensure_daemon_cgroup(self.migrate_to_leaf_cgroup) {
+            Ok(DaemonCgroup::Shared) => warn!(
+                "auraed is in the cgroup cells are created in, which will prevent allocating cells with controllers. Run with --migrate-to-leaf-cgroup to move auraed into a dedicated cgroup"
+            ),
+            Ok(DaemonCgroup::Migrated(path)) => {
+                info!("Moved auraed into cgroup {}", path.display())
+            }
+            Ok(_) => {}
+            Err(e) => warn!("failed to check the cgroup of auraed: {e}"),
+        }
+
+        // Load the reloadable configuration, and reload it on SIGHUP
+        let mut config = match &self.config {
+            Some(path) => ReloadableConfig::parse_from_file(path)?,
+            None => ReloadableConfig::default(),
+        };
+        config.admin.parent_client = self.parent_client.clone();
+        config.logging.apply();
+        let config = Arc::new(RwLock::new(config));
+        if let Some(path) = &self.config {
+            let _reload_handle = tokio::spawn(config::reload_on_sighup(
+                path.clone(),
+                config.clone(),
+            ));
+        }
+
         // Build gRPC Services
         let (mut health_reporter, health_service) =
             tonic_health::server::health_reporter();
 
-        let cell_service = CellService::new();
+        let audit = match &self.audit_log {
+            Some(path) => AuditLog::open(path, self.audit_fail_closed)
+                .with_context(|| {
+                    format!("Failed to open audit log: {}", path.display())
+                })?,
+            None => AuditLog::default(),
+        };
+
+        let cell_service = CellService::new(config, audit);
         let cell_service_server = CellServiceServer::new(cell_service.clone());
         health_reporter.set_serving::<CellServiceServer<CellService>>().await;
 
+        if let Some(address) = self.metrics_address {
+            let cell_service = cell_service.clone();
+            let _metrics_handle = tokio::spawn(async move {
+                if let Err(e) = metrics::serve(address, cell_service).await {
+                    error!("Metrics server exited with error: {e}");
+                }
+            });
+        }
+
         let discovery_service = DiscoveryService::new();
         let discovery_service_server =
             DiscoveryServiceServer::new(discovery_service.clone());
@@ -277,6 +400,21 @@ impl AuraedRuntime {
         let pod_service_server = PodServiceServer::new(pod_service.clone());
         health_reporter.set_serving::<PodServiceServer<PodService>>().await;
 
+        let reflection_service = if self.grpc_reflection {
+            info!("Serving gRPC server reflection");
+            Some(reflection::service()?)
+        } else {
+            None
+        };
+
+        if self.reject_unknown_fields {
+            info!("Rejecting requests with unknown fields");
+        }
+        let strict_fields =
+            strict_fields::StrictFieldsLayer::new(self.reject_unknown_fields)?;
+        let max_request_size =
+            max_request_size::MaxRequestSizeLayer::new(self.max_request_size);
+
         let graceful_shutdown = graceful_shutdown::GracefulShutdown::new(
             health_reporter,
             cell_service,
@@ -288,10 +426,14 @@ impl AuraedRuntime {
         let server_handle = tokio::spawn(async move {
             Server::builder()
                 .tls_config(tls)?
+                // checked first, so strict mode doesn't read oversized requests
+                .layer(max_request_size)
+                .layer(strict_fields)
                 .add_service(health_service)
                 .add_service(cell_service_server)
                 .add_service(discovery_service_server)
                 .add_service(pod_service_server)
+                .add_optional_service(reflection_service)
                 .serve_with_incoming_shutdown(socket_stream, async {
                     let mut graceful_shutdown_signal = graceful_shutdown_signal;
                     let _ = graceful_shutdown_signal.changed().await;
@@ -0,0 +1,305 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! A limit on the size of request messages.
+//!
+//! Decoding does not limit the size of a message, so a client could make auraed buffer
+//! and decode an arbitrarily large request (e.g., a huge label map or env block). The
+//! [tower] layer rejects a request as soon as the gRPC frame of a message declares a
+//! length above the limit, before the message is read, let alone decoded.
+
+use futures::StreamExt;
+use std::task::{Context, Poll};
+use tonic::{codegen::http::Request, transport::Body, Status};
+use tower::{Layer, Service};
+
+/// The default limit on the size of a request message, as in other gRPC implementations.
+pub(crate) const DEFAULT_MAX_REQUEST_SIZE: usize = 4 * 1024 * 1024;
+
+/// Each message is prefixed with a compressed flag and its length.
+const PREFIX_LEN: usize = 5;
+
+/// Follows the gRPC frames of a request body as its chunks are read.
+#[derive(Debug)]
+struct FrameLimit {
+    max: usize,
+    /// The bytes read so far of the prefix of the next message
+    prefix: Vec<u8>,
+    /// The bytes of the current message that are still to be read
+    remaining: usize,
+}
+
+impl FrameLimit {
+    fn new(max: usize) -> Self {
+        Self { max, prefix: Vec::with_capacity(PREFIX_LEN), remaining: 0 }
+    }
+
+    /// Returns the length of the first message whose prefix ends in `chunk`, the next
+    /// chunk of the body, that is longer than `max` bytes.
+    fn check(&mut self, mut chunk: &[u8]) -> Option<usize> {
+        while !chunk.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(chunk.len());
+                self.remaining -= n;
+                chunk = &chunk[n..];
+                continue;
+            }
+
+            let n = (PREFIX_LEN - self.prefix.len()).min(chunk.len());
+            self.prefix.extend_from_slice(&chunk[..n]);
+            chunk = &chunk[n..];
+            if self.prefix.len() < PREFIX_LEN {
+                break;
+            }
+
+            let len = u32::from_be_bytes([
+                self.prefix[1],
+                self.prefix[2],
+                self.prefix[3],
+                self.prefix[4],
+            ]) as usize;
+            self.prefix.clear();
+            if len > self.max {
+                return Some(len);
+            }
+            self.remaining = len;
+        }
+
+        None
+    }
+}
+
+/// Passes the body of a request through as it is read, failing it with
+/// [Status::resource_exhausted] once a message is longer than `max` bytes.
+/// Nothing is buffered, so streaming requests (which may never end) are not held up.
+fn limit_body(body: Body, max: usize) -> Body {
+    let mut limit = FrameLimit::new(max);
+
+    Body::wrap_stream(body.map(move |chunk| {
+        let chunk = chunk.map_err(|e| {
+            Status::cancelled(format!("failed to read request: {e}"))
+        })?;
+
+        match limit.check(&chunk) {
+            Some(len) => Err(Status::resource_exhausted(format!(
+                "request message of {len} bytes is larger than the limit of {max} bytes"
+            ))),
+            None => Ok(chunk),
+        }
+    }))
+}
+
+/// Rejects requests with a message longer than `max` bytes
+/// (see [DEFAULT_MAX_REQUEST_SIZE]).
+#[derive(Debug, Clone)]
+pub(crate) struct MaxRequestSizeLayer {
+    max: usize,
+}
+
+impl MaxRequestSizeLayer {
+    pub(crate) fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl<S> Layer<S> for MaxRequestSizeLayer {
+    type Service = MaxRequestSize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MaxRequestSize { inner, max: self.max }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MaxRequestSize<S> {
+    inner: S,
+    max: usize,
+}
+
+impl<S> Service<Request<Body>> for MaxRequestSize<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        self.inner.call(Request::from_parts(parts, limit_body(body, self.max)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aurae_proto::grpc::health::{
+        health_client::HealthClient, HealthCheckRequest,
+    };
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+    use tonic::{
+        transport::{Channel, Server},
+        Code,
+    };
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, ServerReflectionRequest,
+    };
+
+    /// Frames `message` as the body of a gRPC request.
+    fn framed(message: &[u8]) -> Vec<u8> {
+        let mut body = vec![0];
+        body.extend((message.len() as u32).to_be_bytes());
+        body.extend(message);
+        body
+    }
+
+    #[test]
+    fn test_frame_limit() {
+        assert_eq!(FrameLimit::new(16).check(&framed(&[0; 16])), None);
+        assert_eq!(FrameLimit::new(16).check(&framed(&[0; 17])), Some(17));
+
+        // the length is known before the message is read
+        assert_eq!(FrameLimit::new(16).check(&framed(&[0; 17])[..5]), Some(17));
+
+        let mut body = framed(&[0; 8]);
+        body.extend(framed(&[0; 32]));
+        assert_eq!(FrameLimit::new(16).check(&body), Some(32));
+    }
+
+    #[test]
+    fn test_frame_limit_across_chunks() {
+        let mut body = framed(&[0; 8]);
+        body.extend(framed(&[0; 32]));
+
+        // the prefix of the second message is split across chunks
+        let mut limit = FrameLimit::new(16);
+        assert_eq!(limit.check(&body[..11]), None);
+        assert_eq!(limit.check(&body[11..15]), None);
+        assert_eq!(limit.check(&body[15..]), Some(32));
+
+        // many small messages are each within the limit
+        let mut limit = FrameLimit::new(16);
+        for _ in 0..1024 {
+            assert_eq!(limit.check(&framed(&[0; 16])), None);
+        }
+    }
+
+    async fn check_health(max: usize, service: &str) -> Code {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+        let (_reporter, health_service) =
+            tonic_health::server::health_reporter();
+
+        let _server = tokio::spawn(
+            Server::builder()
+                .layer(MaxRequestSizeLayer::new(max))
+                .add_service(health_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .expect("uri")
+            .connect()
+            .await
+            .expect("connect");
+        let res = HealthClient::new(channel)
+            .check(HealthCheckRequest { service: service.into() })
+            .await;
+
+        match res {
+            Ok(_) => Code::Ok,
+            Err(status) => status.code(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_request_is_not_buffered() {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+
+        let _server = tokio::spawn(
+            Server::builder()
+                .layer(MaxRequestSizeLayer::new(DEFAULT_MAX_REQUEST_SIZE))
+                .add_service(
+                    crate::reflection::service().expect("reflection service"),
+                )
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client =
+            ServerReflectionClient::connect(format!("http://{addr}"))
+                .await
+                .expect("connect");
+
+        // the request stream is never closed, so the first response only arrives if the
+        // request is passed to the service as it is read
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let requests =
+            tokio_stream::once(request).chain(tokio_stream::pending());
+
+        let response = tokio::time::timeout(Duration::from_secs(5), async {
+            client
+                .server_reflection_info(requests)
+                .await
+                .expect("reflection request")
+                .into_inner()
+                .next()
+                .await
+        })
+        .await
+        .expect("the streaming request was held up");
+
+        assert!(matches!(response, Some(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_is_rejected_before_the_handler() {
+        // the health service doesn't know the service, if the request reaches it
+        let service = "a".repeat(64);
+        assert_eq!(check_health(1024, &service).await, Code::NotFound);
+        assert_eq!(check_health(16, &service).await, Code::ResourceExhausted);
+        assert_eq!(check_health(16, "").await, Code::Ok);
+    }
+}
@@ -0,0 +1,242 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Exports the resource usage of cells in the OpenMetrics text format, for
+//! Prometheus (or any compatible scraper) to collect from `/metrics` over HTTP.
+//!
+//! Each scrape reads the cgroups of the cells from a snapshot (see
+//! [crate::runtime::CellService::cell_stats]), so scraping doesn't block
+//! allocating or freeing cells.
+
+use crate::runtime::{CellService, CgroupStats};
+use std::{fmt::Write, io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{info, warn};
+
+const CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Maximum size of the head of a request, of which we only use the request line.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Time a client has to send the head of its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct MetricFamily {
+    name: &'static str,
+    /// Either "counter" or "gauge".
+    kind: &'static str,
+    unit: Option<&'static str>,
+    help: &'static str,
+    value: fn(&CgroupStats) -> Option<f64>,
+}
+
+const FAMILIES: &[MetricFamily] = &[
+    MetricFamily {
+        name: "aurae_cell_cpu_usage_seconds",
+        kind: "counter",
+        unit: Some("seconds"),
+        help: "Total CPU time consumed by the cell.",
+        value: |stats| stats.cpu_usage_usec.map(|usec| usec as f64 / 1e6),
+    },
+    MetricFamily {
+        name: "aurae_cell_memory_current_bytes",
+        kind: "gauge",
+        unit: Some("bytes"),
+        help: "Memory currently used by the cell.",
+        value: |stats| stats.memory_current.map(|bytes| bytes as f64),
+    },
+    MetricFamily {
+        name: "aurae_cell_memory_peak_bytes",
+        kind: "gauge",
+        unit: Some("bytes"),
+        help: "Highest memory used by the cell.",
+        value: |stats| stats.memory_peak.map(|bytes| bytes as f64),
+    },
+    MetricFamily {
+        name: "aurae_cell_pids_current",
+        kind: "gauge",
+        unit: None,
+        help: "Number of processes and threads in the cell.",
+        value: |stats| stats.pids_current.map(|pids| pids as f64),
+    },
+];
+
+/// Renders the stats of `cells` (by cell name) in the OpenMetrics text format.
+/// Stats that are not available for a cell are left out.
+pub(crate) fn render(cells: &[(String, CgroupStats)]) -> String {
+    let mut out = String::new();
+
+    for family in FAMILIES {
+        let MetricFamily { name, kind, unit, help, value } = family;
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        if let Some(unit) = unit {
+            let _ = writeln!(out, "# UNIT {name} {unit}");
+        }
+        let _ = writeln!(out, "# HELP {name} {help}");
+
+        // counter samples are named after the family with a _total suffix
+        let suffix = if *kind == "counter" { "_total" } else { "" };
+        for (cell_name, stats) in cells {
+            if let Some(value) = value(stats) {
+                let cell_name = escape_label_value(cell_name);
+                let _ = writeln!(
+                    out,
+                    "{name}{suffix}{{cell=\"{cell_name}\"}} {value}"
+                );
+            }
+        }
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Serves the metrics of the cells of `cell_service` at `http://{address}/metrics`.
+pub(crate) async fn serve(
+    address: SocketAddr,
+    cell_service: CellService,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    info!("Serving cell metrics on http://{address}/metrics");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let cell_service = cell_service.clone();
+        let _ = tokio::spawn(async move {
+            if let Err(e) = handle(stream, &cell_service).await {
+                warn!("failed to serve metrics: {e}");
+            }
+        });
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    cell_service: &CellService,
+) -> io::Result<()> {
+    let request_line =
+        tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(&mut stream))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let mut parts = request_line.split(' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            ("200 OK", render(&cell_service.cell_stats().await))
+        }
+        _ => ("404 Not Found", String::new()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {CONTENT_TYPE}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Reads the head of an HTTP request, returning its first line.
+async fn read_request_line(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request too large",
+            ));
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    Ok(head.lines().next().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sample_cell() {
+        let cells = vec![(
+            "ae-sample".to_string(),
+            CgroupStats {
+                cpu_usage_usec: Some(1500000),
+                memory_current: Some(4096),
+                memory_peak: None,
+                pids_current: Some(3),
+                oom_kills: None,
+            },
+        )];
+
+        let expected = "\
+# TYPE aurae_cell_cpu_usage_seconds counter
+# UNIT aurae_cell_cpu_usage_seconds seconds
+# HELP aurae_cell_cpu_usage_seconds Total CPU time consumed by the cell.
+aurae_cell_cpu_usage_seconds_total{cell=\"ae-sample\"} 1.5
+# TYPE aurae_cell_memory_current_bytes gauge
+# UNIT aurae_cell_memory_current_bytes bytes
+# HELP aurae_cell_memory_current_bytes Memory currently used by the cell.
+aurae_cell_memory_current_bytes{cell=\"ae-sample\"} 4096
+# TYPE aurae_cell_memory_peak_bytes gauge
+# UNIT aurae_cell_memory_peak_bytes bytes
+# HELP aurae_cell_memory_peak_bytes Highest memory used by the cell.
+# TYPE aurae_cell_pids_current gauge
+# HELP aurae_cell_pids_current Number of processes and threads in the cell.
+aurae_cell_pids_current{cell=\"ae-sample\"} 3
+# EOF
+";
+
+        assert_eq!(render(&cells), expected);
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,149 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! gRPC server reflection, allowing clients (e.g., grpcurl) to discover the services
+//! and message types served by auraed without the proto files.
+
+use tonic_reflection::server::{
+    Builder, Error, ServerReflection, ServerReflectionServer,
+};
+
+/// Builds the reflection service, registering the descriptors of the services
+/// served by auraed.
+pub(crate) fn service(
+) -> Result<ServerReflectionServer<impl ServerReflection>, Error> {
+    Builder::configure()
+        .register_encoded_file_descriptor_set(
+            aurae_proto::discovery::FILE_DESCRIPTOR_SET,
+        )
+        .register_encoded_file_descriptor_set(
+            aurae_proto::grpc::health::FILE_DESCRIPTOR_SET,
+        )
+        .register_encoded_file_descriptor_set(
+            aurae_proto::runtime::FILE_DESCRIPTOR_SET,
+        )
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use prost_types::FileDescriptorProto;
+    use tokio::net::TcpListener;
+    use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+    use tonic::transport::{Channel, Server};
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest,
+        server_reflection_response::MessageResponse, ServerReflectionRequest,
+    };
+
+    async fn reflect(
+        client: &mut ServerReflectionClient<Channel>,
+        message_request: MessageRequest,
+    ) -> MessageResponse {
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(message_request),
+        };
+
+        let mut responses = client
+            .server_reflection_info(tokio_stream::once(request))
+            .await
+            .expect("reflection request")
+            .into_inner();
+
+        responses
+            .next()
+            .await
+            .expect("a response")
+            .expect("reflection response")
+            .message_response
+            .expect("message response")
+    }
+
+    #[tokio::test]
+    async fn test_reflection_lists_cell_service_methods() {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+
+        let _server = tokio::spawn(
+            Server::builder()
+                .add_service(service().expect("reflection service"))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client =
+            ServerReflectionClient::connect(format!("http://{addr}"))
+                .await
+                .expect("connect");
+
+        let MessageResponse::ListServicesResponse(services) =
+            reflect(&mut client, MessageRequest::ListServices(String::new()))
+                .await
+        else {
+            panic!("expected a list services response");
+        };
+        assert!(services
+            .service
+            .iter()
+            .any(|s| s.name == "aurae.runtime.v0.CellService"));
+
+        let MessageResponse::FileDescriptorResponse(files) = reflect(
+            &mut client,
+            MessageRequest::FileContainingSymbol(
+                "aurae.runtime.v0.CellService".into(),
+            ),
+        )
+        .await
+        else {
+            panic!("expected a file descriptor response");
+        };
+
+        let methods: Vec<String> = files
+            .file_descriptor_proto
+            .iter()
+            .map(|file| {
+                FileDescriptorProto::decode(file.as_slice())
+                    .expect("file descriptor")
+            })
+            .flat_map(|file| file.service)
+            .filter(|service| service.name() == "CellService")
+            .flat_map(|service| service.method)
+            .map(|method| method.name().to_string())
+            .collect();
+
+        for method in ["Allocate", "Free", "Start", "Stop"] {
+            assert!(methods.iter().any(|m| m == method), "missing {method}");
+        }
+    }
+}
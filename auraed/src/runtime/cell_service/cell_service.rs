@@ -29,47 +29,100 @@
 \* -------------------------------------------------------------------------- */
 
 use super::{
-    cells::{cell_name_path, CellName, CellNamePath, Cells},
+    cells::{
+        cell_name_path,
+        cgroups::{
+            cpuset::NumaTopology, CgroupStats, FreezeState, HostCapacity,
+            ResourceCommitment,
+        },
+        Cell, CellName, CellNamePath, CellSnapshot, CellSpec, CellStatus,
+        Cells, CellsError, FreeChildrenPolicy,
+    },
     error::CellsServiceError,
-    executables::Executables,
+    executables::{
+        self, ExecutableName, ExecutableSpec, Executables, ExecutablesError,
+        OutputSubscription, PendingStart, PendingStarts, ProcessGroup,
+        Readiness, RestartStats, StopPolicy,
+    },
+    start_timeout::{start_executable, start_with_timeout, StartedExecutable},
     validation::{
-        ValidatedCellServiceAllocateRequest, ValidatedCellServiceFreeRequest,
-        ValidatedCellServiceStartRequest, ValidatedCellServiceStopRequest,
+        ValidatedCellServiceAllocateRequest,
+        ValidatedCellServiceDescribeRequest, ValidatedCellServiceDrainRequest,
+        ValidatedCellServiceFreeBySelectorRequest,
+        ValidatedCellServiceFreeRequest, ValidatedCellServiceFreezeRequest,
+        ValidatedCellServiceGetCellByTidRequest,
+        ValidatedCellServiceListExecutablesRequest,
+        ValidatedCellServiceListFdsRequest, ValidatedCellServiceListRequest,
+        ValidatedCellServiceLogStreamRequest,
+        ValidatedCellServiceRestartRequest, ValidatedCellServiceRunRequest,
+        ValidatedCellServiceStartRequest, ValidatedCellServiceStatRequest,
+        ValidatedCellServiceStopGenerationRequest,
+        ValidatedCellServiceStopRequest, ValidatedCellServiceThawRequest,
+        ValidatedCellServiceUpdateRequest,
     },
     Result,
 };
-use ::validation::ValidatedType;
-use aurae_client::{
-    runtime::cell_service::CellServiceClient, AuraeClient, AuraeClientError,
+use crate::{
+    audit::{client_identity, AuditLog},
+    config::{NumaLocality, SharedConfig},
 };
+use ::validation::{ValidatedField, ValidatedType, ValidationError};
+use aurae_client::{AuraeClient, AuraeClientError};
 use aurae_proto::runtime::{
+    cell_service_client::CellServiceClient as CellServiceGrpcClient,
     cell_service_server, CellServiceAllocateRequest,
-    CellServiceAllocateResponse, CellServiceFreeRequest,
-    CellServiceFreeResponse, CellServiceStartRequest, CellServiceStartResponse,
-    CellServiceStopRequest, CellServiceStopResponse,
+    CellServiceAllocateResponse, CellServiceCommitmentRequest,
+    CellServiceCommitmentResponse, CellServiceDescribeRequest,
+    CellServiceDescribeResponse, CellServiceDrainRequest,
+    CellServiceDrainResponse, CellServiceFreeBySelectorRequest,
+    CellServiceFreeBySelectorResponse, CellServiceFreeBySelectorResult,
+    CellServiceFreeRequest, CellServiceFreeResponse, CellServiceFreezeRequest,
+    CellServiceFreezeResponse, CellServiceGetCellByTidRequest,
+    CellServiceGetCellByTidResponse, CellServiceListExecutablesRequest,
+    CellServiceListExecutablesResponse, CellServiceListFdsRequest,
+    CellServiceListFdsResponse, CellServiceListOrphansRequest,
+    CellServiceListOrphansResponse, CellServiceListRequest,
+    CellServiceListResponse, CellServiceLogStreamRequest,
+    CellServiceRestartRequest, CellServiceRestartResponse,
+    CellServiceRetryConfigRequest, CellServiceRetryConfigResponse,
+    CellServiceRunRequest, CellServiceRunResponse, CellServiceStartRequest,
+    CellServiceStartResponse, CellServiceStatRequest, CellServiceStatResponse,
+    CellServiceStopGenerationRequest, CellServiceStopGenerationResponse,
+    CellServiceStopRequest, CellServiceStopResponse, CellServiceThawRequest,
+    CellServiceThawResponse, CellServiceUpdateRequest,
+    CellServiceUpdateResponse, ExecutablePlan, ExecutableStatus, ListedCell,
+    OrphanedCgroup, OutputLine,
 };
 use backoff::backoff::Backoff;
+use futures::{future, stream, Stream, StreamExt};
+use std::collections::HashMap;
+use std::os::unix::process::ExitStatusExt;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tonic::{Code, Request, Response, Status};
-use tracing::{info, trace};
+use tokio_stream::wrappers::{
+    errors::BroadcastStreamRecvError, BroadcastStream,
+};
+use tonic::{
+    metadata::MetadataMap, Code, Extensions, Request, Response, Status,
+};
+use tracing::{info, trace, warn};
 
 macro_rules! do_in_cell {
-    ($self:ident, $cell_name:ident, $function:ident, $request:ident) => {{
-        let mut cells = $self.cells.lock().await;
-
-        let client_config = cells
-            .get(&$cell_name, |cell| cell.client_config())
-            .map_err(CellsServiceError::CellsError)?;
+    ($self:ident, $cell_name:ident, $function:ident, $request:ident, $metadata:ident) => {{
+        // The lock is only held to look up the cell. Holding it for the call would
+        // serialize every forwarded call (and local operation) behind the slowest one,
+        // even though calls to different cells, or siblings in the same cell, are
+        // independent. Operations on the cell's own subtree are serialized by the
+        // nested auraed.
+        let client_config = $self
+            .cells
+            .lock()
+            .await
+            .get(&$cell_name, |cell| cell.client_config())?;
 
-        let mut retry_strategy = backoff::ExponentialBackoffBuilder::new()
-            .with_initial_interval(Duration::from_millis(50)) // 1st retry in 50ms
-            .with_multiplier(10.0) // 10x the delay after 1st retry (500ms)
-            .with_randomization_factor(0.5) // with a randomness of +/-50% (250-750ms)
-            .with_max_interval(Duration::from_secs(3)) // but never delay more than 3s
-            .with_max_elapsed_time(Some(Duration::from_secs(20))) // or 20s total
-            .build();
+        let mut retry_strategy = $self.config.read().await.retry.backoff();
 
         let client = loop {
             match AuraeClient::new(client_config.clone()).await {
@@ -85,12 +138,36 @@ macro_rules! do_in_cell {
                 }
                 e => break e
             }
-        }.map_err(CellsServiceError::from)?;
+        };
+        let client = match client {
+            Ok(client) => client,
+            Err(e @ AuraeClientError::ConnectionError(_)) => {
+                // Say why, if the nested auraed failed to come up
+                let stderr = $self
+                    .cells
+                    .lock()
+                    .await
+                    .get(&$cell_name, |cell| cell.nested_auraed_stderr())
+                    .unwrap_or_default();
+                return Err(CellsError::NestedAuraedUnreachable {
+                    cell_name: $cell_name.clone(),
+                    source: e,
+                    stderr,
+                }
+                .into());
+            }
+            Err(e) => return Err(CellsServiceError::from(e).into()),
+        };
+
+        // The client traits only take the message, so we call the generated client directly
+        // to forward the metadata of the request we received.
+        let client = CellServiceGrpcClient::new(client.channel());
 
         backoff::future::retry(
             retry_strategy,
             || async {
-                match client.$function($request.clone()).await {
+                let request = forwarded_request($metadata, $request.clone());
+                match client.clone().$function(request).await {
                     Ok(res) => Ok(res),
                     Err(e) if e.code() == Code::Unknown && e.message() == "transport error" => {
                         Err(e)?;
@@ -104,17 +181,120 @@ macro_rules! do_in_cell {
     }};
 }
 
+/// The output lines streamed by LogStream, either read locally or forwarded from the
+/// nested auraed of a cell.
+pub(crate) type LogStream =
+    Pin<Box<dyn Stream<Item = std::result::Result<OutputLine, Status>> + Send>>;
+
+/// Headers that describe how a received request was sent to us, rather than the call itself,
+/// so they are not forwarded to a nested auraed.
+const TRANSPORT_HEADERS: [&str; 3] =
+    ["content-length", "grpc-encoding", "grpc-accept-encoding"];
+
+/// Returns the request to send to a nested auraed, carrying the metadata (e.g., trace
+/// headers) of the request we received.
+fn forwarded_request<T>(metadata: &MetadataMap, message: T) -> Request<T> {
+    let mut metadata = metadata.clone();
+    for header in TRANSPORT_HEADERS {
+        let _ = metadata.remove(header);
+    }
+    Request::from_parts(metadata, Extensions::default(), message)
+}
+
+/// How often we check on an executable we are waiting for, e.g., for it to exit
+/// (see [CellService::run]) or to be started (see [CellService::wait_for_dependencies]).
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often we poll the nested auraed of a cell for the exit of its executable (see
+/// [CellService::free_cell_on_exit]). Each poll is a round trip over mTLS, so the
+/// interval doubles from the min to the max while the executable runs.
+const EXIT_POLL_MIN_INTERVAL: Duration = Duration::from_millis(250);
+const EXIT_POLL_MAX_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What to do about a cell to free once its executable exited, as polled from its
+/// nested auraed (see [CellService::free_cell_on_exit]).
+#[derive(Debug, PartialEq, Eq)]
+enum ExitPoll {
+    /// The executable is still running, so poll again.
+    Running,
+    /// The executable exited, and no other executable was started in the cell.
+    Free,
+    /// The executable exited, but other executables were started in the cell since.
+    KeepForOtherExecutables,
+}
+
+impl ExitPoll {
+    fn new(executables: &[ExecutableStatus], executable_name: &str) -> Self {
+        let running = executables.iter().any(|executable| {
+            executable.name == executable_name && executable.pid != 0
+        });
+        if running {
+            Self::Running
+        } else if executables
+            .iter()
+            .any(|executable| executable.name != executable_name)
+        {
+            Self::KeepForOtherExecutables
+        } else {
+            Self::Free
+        }
+    }
+}
+
+/// What we keep of an executable started by this auraed, so it can be restarted
+/// (see [CellService::restart]).
+#[derive(Debug, Clone)]
+struct KeptStart {
+    request: CellServiceStartRequest,
+    /// Carried over to the executable each time it is restarted.
+    restart_stats: RestartStats,
+    /// How the executable exited when it was last stopped (see [CellService::stop]).
+    last_exit_reason: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct CellService {
     cells: Arc<Mutex<Cells>>,
     executables: Arc<Mutex<Executables>>,
+    pending_starts: PendingStarts,
+    /// The last start request of each executable started by this auraed, so it can be
+    /// restarted without the client sending its spec again (see [CellService::restart]).
+    start_requests: Arc<Mutex<HashMap<ExecutableName, KeptStart>>>,
+    config: SharedConfig,
+    audit: AuditLog,
+    /// Delays the pre-exec hooks of the executables started by tests, to mock a slow
+    /// isolation step (e.g., a blocking mount).
+    #[cfg(test)]
+    pre_exec_delay: Option<Duration>,
 }
 
 impl CellService {
-    pub fn new() -> Self {
+    pub fn new(config: SharedConfig, audit: AuditLog) -> Self {
         CellService {
             cells: Default::default(),
             executables: Default::default(),
+            pending_starts: Default::default(),
+            start_requests: Default::default(),
+            config,
+            audit,
+            #[cfg(test)]
+            pre_exec_delay: None,
+        }
+    }
+
+    /// Runs `f` with the lock on the cells held, on a thread where blocking is allowed.
+    /// Freeing a cell waits for its processes to be killed (see [Cells::free]), and
+    /// draining or freezing it waits for them to stop, with [std::thread::sleep], which
+    /// must not block the threads of the runtime.
+    async fn with_cells_blocking<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Cells) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut cells = self.cells.clone().lock_owned().await;
+        match tokio::task::spawn_blocking(move || f(&mut cells)).await {
+            Ok(res) => res,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
         }
     }
 
@@ -124,7 +304,11 @@ impl CellService {
         request: ValidatedCellServiceAllocateRequest,
     ) -> Result<CellServiceAllocateResponse> {
         // Initialize the cell
-        let ValidatedCellServiceAllocateRequest { cell } = request;
+        let ValidatedCellServiceAllocateRequest {
+            cell,
+            reuse_existing,
+            template: (),
+        } = request;
         let (cell_name, empty) =
             cell.name.clone().into_child().expect("not empty");
 
@@ -132,25 +316,94 @@ impl CellService {
         // Otherwise, we should have called allocate_in_cell
         assert!(matches!(empty, CellNamePath::Empty));
 
-        let cell_spec = cell.into();
+        let cell_spec: CellSpec = cell.into();
+        let (pinned_budget, numa_locality, max_cells) = {
+            let config = self.config.read().await;
+            (
+                config.memory.pinned_budget_bytes,
+                config.cpuset.numa_locality,
+                config.capacity.max_cells,
+            )
+        };
 
-        let mut cells = self.cells.lock().await;
-        let cell = cells.allocate(cell_name, cell_spec)?;
+        if numa_locality != NumaLocality::Ignore {
+            let topology = NumaTopology::detect()?;
+            match Cells::check_numa_locality(&cell_name, &cell_spec, &topology)
+            {
+                Err(e) if numa_locality == NumaLocality::Warn => warn!("{e}"),
+                res => res?,
+            }
+        }
 
-        Ok(CellServiceAllocateResponse {
-            cell_name: cell.name().clone().into_inner(),
-            cgroup_v2: cell.v2().expect("allocated cell returns `Some`"),
-        })
+        let mut cell = {
+            let mut cells = self.cells.lock().await;
+            cells.check_cell_limit(&cell_name, max_cells)?;
+            cells.check_pinned_memory(&cell_name, &cell_spec, pinned_budget)?;
+
+            // Adopting the cgroup doesn't spawn anything, so it is done with the lock held
+            if reuse_existing && cells.is_adoptable(&cell_name) {
+                let cell = cells.allocate_or_adopt(cell_name, cell_spec)?;
+                return allocate_response(cell);
+            }
+
+            cells.reserve(cell_name, cell_spec)?
+        };
+
+        // The cell is allocated without the lock held, which spawns its nested auraed.
+        // Creating its cgroup enables the requested controllers in the
+        // cgroup.subtree_control of the parent cgroup, which is serialized by a lock on
+        // the parent instead (see [Cgroup::new]). On a blocking thread, the cell is
+        // cached even if we are dropped.
+        let cells = self.cells.clone();
+        let allocate = move || -> Result<CellServiceAllocateResponse> {
+            let allocated = cell.allocate();
+            let mut cells = cells.blocking_lock();
+            allocate_response(cells.insert_reserved(cell, allocated)?)
+        };
+        match tokio::task::spawn_blocking(allocate).await {
+            Ok(res) => res,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
     }
 
-    #[tracing::instrument(skip(self))]
+    #[tracing::instrument(skip(self, metadata))]
     async fn allocate_in_cell(
         &self,
         cell_name: &CellName,
         request: CellServiceAllocateRequest,
+        metadata: &MetadataMap,
     ) -> std::result::Result<Response<CellServiceAllocateResponse>, Status>
     {
-        do_in_cell!(self, cell_name, allocate, request)
+        do_in_cell!(self, cell_name, allocate, request, metadata)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update(
+        &self,
+        request: ValidatedCellServiceUpdateRequest,
+    ) -> Result<CellServiceUpdateResponse> {
+        let (cell_name, empty) =
+            request.cell_name.clone().into_child().expect("not empty");
+
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called update_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
+
+        info!("CellService: update() cell_name={:?}", cell_name);
+        let mut cells = self.cells.lock().await;
+        cells.update(&cell_name, request.into())?;
+
+        Ok(CellServiceUpdateResponse::default())
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn update_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceUpdateRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceUpdateResponse>, Status> {
+        do_in_cell!(self, cell_name, update, request, metadata)
     }
 
     #[tracing::instrument(skip(self))]
@@ -158,7 +411,11 @@ impl CellService {
         &self,
         request: ValidatedCellServiceFreeRequest,
     ) -> Result<CellServiceFreeResponse> {
-        let ValidatedCellServiceFreeRequest { cell_name } = request;
+        let ValidatedCellServiceFreeRequest {
+            cell_name,
+            return_final_stats,
+            children_policy,
+        } = request;
 
         let (cell_name, empty) = cell_name.into_child().expect("not empty");
 
@@ -167,243 +424,3361 @@ impl CellService {
         assert!(matches!(empty, CellNamePath::Empty));
 
         info!("CellService: free() cell_name={:?}", cell_name);
-        let mut cells = self.cells.lock().await;
-        cells.free(&cell_name)?;
+        if !return_final_stats {
+            self.with_cells_blocking(move |cells| {
+                cells.free(&cell_name, children_policy)
+            })
+            .await?;
+            return Ok(CellServiceFreeResponse::default());
+        }
 
-        Ok(CellServiceFreeResponse::default())
+        let final_stats = self
+            .with_cells_blocking(move |cells| {
+                cells.free_with_stats(&cell_name, children_policy)
+            })
+            .await?;
+
+        Ok(CellServiceFreeResponse {
+            final_stats: final_stats.map(|stats| stats.into()),
+        })
     }
 
-    #[tracing::instrument(skip(self))]
+    #[tracing::instrument(skip(self, metadata))]
     async fn free_in_cell(
         &self,
         cell_name: &CellName,
         request: CellServiceFreeRequest,
+        metadata: &MetadataMap,
     ) -> std::result::Result<Response<CellServiceFreeResponse>, Status> {
-        do_in_cell!(self, cell_name, free, request)
+        do_in_cell!(self, cell_name, free, request, metadata)
     }
 
+    /// Frees the cells of this auraed matching the selector.
+    /// Cells nested in another cell are not considered.
     #[tracing::instrument(skip(self))]
-    pub(crate) async fn free_all(&self) -> Result<()> {
-        let mut cells = self.cells.lock().await;
+    async fn free_by_selector(
+        &self,
+        request: ValidatedCellServiceFreeBySelectorRequest,
+    ) -> Result<CellServiceFreeBySelectorResponse> {
+        let ValidatedCellServiceFreeBySelectorRequest { selector } = request;
 
-        // First try to gracefully free all cells.
-        cells.broadcast_free();
-        // The cells that remain failed to shut down for some reason.
-        cells.broadcast_kill();
+        info!("CellService: free_by_selector() selector={:?}", selector);
+        let results = self
+            .with_cells_blocking(move |cells| cells.free_by_selector(&selector))
+            .await
+            .into_iter()
+            .map(|(cell_name, res)| CellServiceFreeBySelectorResult {
+                cell_name: cell_name.into_inner(),
+                freed: res.is_ok(),
+                error: res.err().map(|e| e.to_string()).unwrap_or_default(),
+            })
+            .collect();
 
-        Ok(())
+        Ok(CellServiceFreeBySelectorResponse { results })
     }
 
+    /// Returns the name of the cell of this auraed that the thread belongs to.
     #[tracing::instrument(skip(self))]
-    async fn start(
+    async fn get_cell_by_tid(
         &self,
-        request: ValidatedCellServiceStartRequest,
-    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
-        let ValidatedCellServiceStartRequest { cell_name, executable } =
-            request;
+        request: ValidatedCellServiceGetCellByTidRequest,
+    ) -> Result<CellServiceGetCellByTidResponse> {
+        let ValidatedCellServiceGetCellByTidRequest { tid } = request;
 
-        assert!(matches!(cell_name, CellNamePath::Empty));
-        info!("CellService: start() executable={:?}", executable);
+        info!("CellService: get_cell_by_tid() tid={tid}");
+        let cells = self.cells.lock().await;
 
-        let mut executables = self.executables.lock().await;
-        let executable = executables
-            .start(executable)
-            .map_err(CellsServiceError::ExecutablesError)?;
+        // The cgroup of the thread usually names its cell, without searching every cell
+        if let Ok(cell) = cells.get_cgroup_by_pid(tid) {
+            return Ok(CellServiceGetCellByTidResponse {
+                cell_name: cell.name().clone().into_inner(),
+            });
+        }
 
-        let pid = executable
-            .pid()
-            .map_err(CellsServiceError::Io)?
-            .expect("pid")
-            .as_raw();
+        let cell_name = cells.get_by_tid(tid)?;
 
-        // TODO: either tell the [ObserveService] about this executable's log channels, or
-        // provide a way for the observe service to extract the log channels from here.
+        Ok(CellServiceGetCellByTidResponse {
+            cell_name: cell_name.into_inner(),
+        })
+    }
 
-        Ok(Response::new(CellServiceStartResponse { pid }))
+    #[tracing::instrument(skip(self))]
+    async fn describe(
+        &self,
+        request: ValidatedCellServiceDescribeRequest,
+    ) -> Result<CellServiceDescribeResponse> {
+        let ValidatedCellServiceDescribeRequest { cell_name } = request;
+
+        let (cell_name, empty) = cell_name.into_child().expect("not empty");
+
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called describe_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
+
+        let mut cells = self.cells.lock().await;
+        let response = cells.get(&cell_name, |cell| {
+            // Errors if the cell is not allocated, before `v2` would return `None`
+            let cgroup_id = cell.cgroup_id()?;
+            let isolated_namespaces = cell
+                .isolated_namespaces()?
+                .into_iter()
+                .map(|namespace| namespace.to_string())
+                .collect();
+            let controllers = cell.cgroup_controllers()?;
+            let freeze_state = cell.freeze_state()?;
+
+            Ok(CellServiceDescribeResponse {
+                cell_name: cell.name().clone().into_inner(),
+                cgroup_v2: cell.v2().expect("allocated cell returns `Some`"),
+                cgroup_id,
+                labels: cell.labels().clone(),
+                isolated_namespaces,
+                cgroup_controllers: controllers.available,
+                cgroup_subtree_control: controllers.enabled,
+                freeze_state: aurae_proto::runtime::FreezeState::from(
+                    freeze_state,
+                ) as i32,
+            })
+        })?;
+
+        Ok(response)
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn describe_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceDescribeRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceDescribeResponse>, Status>
+    {
+        do_in_cell!(self, cell_name, describe, request, metadata)
     }
 
+    /// Reads the current resource usage of a cell from its cgroup.
     #[tracing::instrument(skip(self))]
-    async fn start_in_cell(
+    async fn stat(
+        &self,
+        request: ValidatedCellServiceStatRequest,
+    ) -> Result<CellServiceStatResponse> {
+        let ValidatedCellServiceStatRequest { cell_name } = request;
+
+        let (cell_name, empty) = cell_name.into_child().expect("not empty");
+
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called stat_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
+
+        let mut cells = self.cells.lock().await;
+        let stats = cells.get(&cell_name, |cell| cell.stats())?;
+
+        Ok(CellServiceStatResponse {
+            cell_name: cell_name.into_inner(),
+            stats: Some(stats.into()),
+        })
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn stat_in_cell(
         &self,
         cell_name: &CellName,
-        request: CellServiceStartRequest,
-    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
-        do_in_cell!(self, cell_name, start, request)
+        request: CellServiceStatRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceStatResponse>, Status> {
+        do_in_cell!(self, cell_name, stat, request, metadata)
     }
 
     #[tracing::instrument(skip(self))]
-    async fn stop(
+    async fn drain(
         &self,
-        request: ValidatedCellServiceStopRequest,
-    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
-        let ValidatedCellServiceStopRequest { cell_name, executable_name } =
+        request: ValidatedCellServiceDrainRequest,
+    ) -> Result<CellServiceDrainResponse> {
+        let ValidatedCellServiceDrainRequest { cell_name, include_memory_stat } =
             request;
 
-        assert!(matches!(cell_name, CellNamePath::Empty));
-        info!("CellService: stop() executable_name={:?}", executable_name,);
+        let (cell_name, empty) = cell_name.into_child().expect("not empty");
 
-        let mut executables = self.executables.lock().await;
-        let _exit_status = executables
-            .stop(&executable_name)
-            .await
-            .map_err(CellsServiceError::ExecutablesError)?;
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called drain_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
+
+        info!("CellService: drain() cell_name={:?}", cell_name);
+        let snapshot = self
+            .with_cells_blocking(move |cells| {
+                cells.get(&cell_name, |cell| cell.drain(include_memory_stat))
+            })
+            .await?;
 
-        Ok(Response::new(CellServiceStopResponse::default()))
+        Ok(snapshot.into())
     }
 
-    #[tracing::instrument(skip(self))]
-    async fn stop_in_cell(
+    #[tracing::instrument(skip(self, metadata))]
+    async fn drain_in_cell(
         &self,
         cell_name: &CellName,
-        request: CellServiceStopRequest,
-    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
-        do_in_cell!(self, cell_name, stop, request)
+        request: CellServiceDrainRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceDrainResponse>, Status> {
+        do_in_cell!(self, cell_name, drain, request, metadata)
     }
-}
 
-/// ### Mapping cgroup options to the Cell API
-///
-/// Here we *only* expose options from the CgroupBuilder
-/// as our features in Aurae need them! We do not try to
-/// "map" everything as much as we start with a few small
-/// features and add as needed.
-///
-// Example builder options can be found: https://github.com/kata-containers/cgroups-rs/blob/main/tests/builder.rs
-// Cgroup documentation: https://man7.org/linux/man-pages/man7/cgroups.7.html
-#[tonic::async_trait]
-impl cell_service_server::CellService for CellService {
-    async fn allocate(
+    #[tracing::instrument(skip(self))]
+    async fn freeze(
         &self,
-        request: Request<CellServiceAllocateRequest>,
-    ) -> std::result::Result<Response<CellServiceAllocateResponse>, Status>
-    {
-        let request = request.into_inner();
+        request: ValidatedCellServiceFreezeRequest,
+    ) -> Result<CellServiceFreezeResponse> {
+        let ValidatedCellServiceFreezeRequest { cell_name } = request;
 
-        // We execute allocate if cell_name is a direct child
-        if matches!(&request.cell, Some(cell) if !cell.name.contains(cell_name_path::SEPARATOR))
-        {
-            let request = ValidatedCellServiceAllocateRequest::validate(
-                request.clone(),
-                None,
-            )?;
+        let (cell_name, empty) = cell_name.into_child().expect("not empty");
 
-            Ok(Response::new(self.allocate(request).await?))
-        } else {
-            let validated = ValidatedCellServiceAllocateRequest::validate(
-                request.clone(),
-                None,
-            )?;
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called freeze_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
 
-            // validation has succeed, so we can make assumptions about the request and use expect
-            let (parent, cell_name) = validated
-                .cell
-                .name
-                .into_child()
-                .expect("CellNamePath was not empty");
+        info!("CellService: freeze() cell_name={:?}", cell_name);
+        self.with_cells_blocking(move |cells| {
+            cells.get(&cell_name, |cell| cell.freeze())
+        })
+        .await?;
 
-            let mut request = request;
-            if let Some(cell) = &mut request.cell {
-                cell.name = cell_name.into_string();
-            } else {
-                unreachable!("validation should have failed")
-            }
+        Ok(CellServiceFreezeResponse::default())
+    }
 
-            self.allocate_in_cell(&parent, request).await
-        }
+    #[tracing::instrument(skip(self, metadata))]
+    async fn freeze_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceFreezeRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceFreezeResponse>, Status> {
+        do_in_cell!(self, cell_name, freeze, request, metadata)
     }
 
-    async fn free(
+    #[tracing::instrument(skip(self))]
+    async fn thaw(
         &self,
-        request: Request<CellServiceFreeRequest>,
-    ) -> std::result::Result<Response<CellServiceFreeResponse>, Status> {
-        let request = request.into_inner();
+        request: ValidatedCellServiceThawRequest,
+    ) -> Result<CellServiceThawResponse> {
+        let ValidatedCellServiceThawRequest { cell_name } = request;
 
-        // We execute free if cell_name is a direct child
-        if !request.cell_name.contains(cell_name_path::SEPARATOR) {
-            let request = ValidatedCellServiceFreeRequest::validate(
-                request.clone(),
-                None,
-            )?;
-            Ok(Response::new(self.free(request).await?))
-        } else {
-            let validated = ValidatedCellServiceFreeRequest::validate(
-                request.clone(),
-                None,
-            )?;
+        let (cell_name, empty) = cell_name.into_child().expect("not empty");
 
-            // validation has succeeded, so we can make assumptions about the request and use expect
-            let mut request = request;
-            let (parent, cell_name) = validated
-                .cell_name
-                .into_child()
-                .expect("CellNamePath was not empty");
+        // There should have been a single cell name in the path.
+        // Otherwise, we should have called thaw_in_cell
+        assert!(matches!(empty, CellNamePath::Empty));
 
-            request.cell_name = cell_name.into_string();
+        info!("CellService: thaw() cell_name={:?}", cell_name);
+        let mut cells = self.cells.lock().await;
+        cells.get(&cell_name, |cell| cell.thaw())?;
 
-            self.free_in_cell(&parent, request).await
-        }
+        Ok(CellServiceThawResponse::default())
     }
 
-    async fn start(
+    #[tracing::instrument(skip(self, metadata))]
+    async fn thaw_in_cell(
         &self,
-        request: Request<CellServiceStartRequest>,
-    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
-        let request = request.into_inner();
+        cell_name: &CellName,
+        request: CellServiceThawRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceThawResponse>, Status> {
+        do_in_cell!(self, cell_name, thaw, request, metadata)
+    }
 
-        // We execute start if cell_name is empty
-        if request.cell_name.is_empty() {
-            let request =
-                ValidatedCellServiceStartRequest::validate(request, None)?;
-            Ok(self.start(request).await?)
-        } else {
-            // We are in a parent cell (or validation will fail)
-            let validated = ValidatedCellServiceStartRequest::validate(
-                request.clone(),
-                None,
-            )?;
+    /// Lists the allocated cells of this auraed, and the cached cells that disagree with
+    /// the cgroup filesystem (see [super::cells::CellInfo::is_stale]).
+    /// If `recursive`, the nested cells of every listed cell that isn't stale are listed
+    /// by its nested auraed. A cell whose nested cells can't be listed is marked as
+    /// unreachable, rather than failing the whole list.
+    /// Reading the stats can be slow, so we don't hold the lock while doing so.
+    #[tracing::instrument(skip(self, metadata))]
+    async fn list(
+        &self,
+        request: ValidatedCellServiceListRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<CellServiceListResponse, Status> {
+        let ValidatedCellServiceListRequest {
+            cell_name,
+            include_stats,
+            recursive,
+        } = request;
 
-            // validation has succeed, so we can make assumptions about the request and use expect
-            let mut request = request;
-            let (parent, cell_name) = validated
-                .cell_name
-                .into_child()
-                .expect("CellNamePath was not empty");
+        assert!(matches!(cell_name, CellNamePath::Empty));
 
-            request.cell_name = cell_name.into_string();
+        let (infos, snapshot) = {
+            let cells = self.cells.lock().await;
+            (cells.list(), cells.snapshot())
+        };
+
+        let mut cells = vec![];
+        for info in infos {
+            let stale = info.is_stale();
+            if info.status != CellStatus::Allocated && !stale {
+                continue;
+            }
+            let Some(cell) = snapshot.get(&info.name) else {
+                continue;
+            };
 
-            self.start_in_cell(&parent, request).await
+            let mut listed = ListedCell {
+                cell_name: cell.name.to_string(),
+                labels: cell.spec.labels.clone(),
+                stats: if include_stats {
+                    read_stats(cell).map(Into::into)
+                } else {
+                    None
+                },
+                stale,
+                unreachable: false,
+                freeze_state: read_freeze_state(cell).map(|state| {
+                    aurae_proto::runtime::FreezeState::from(state) as i32
+                }),
+            };
+
+            if !recursive || stale {
+                cells.push(listed);
+                continue;
+            }
+
+            let request = CellServiceListRequest {
+                cell_name: String::new(),
+                include_stats,
+                recursive,
+            };
+            match self.list_in_cell(&cell.name, request, metadata).await {
+                Ok(nested) => cells.extend(nested_listed_cells(
+                    &cell.name,
+                    nested.into_inner(),
+                )),
+                Err(e) => {
+                    warn!("failed to list nested cells of {}: {e}", cell.name);
+                    listed.unreachable = true;
+                }
+            }
+            cells.push(listed);
         }
+
+        cells.sort_by(|a, b| a.cell_name.cmp(&b.cell_name));
+
+        Ok(CellServiceListResponse { cells })
     }
 
-    async fn stop(
+    #[tracing::instrument(skip(self, metadata))]
+    async fn list_in_cell(
         &self,
-        request: Request<CellServiceStopRequest>,
-    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
-        let request = request.into_inner();
+        cell_name: &CellName,
+        request: CellServiceListRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceListResponse>, Status> {
+        do_in_cell!(self, cell_name, list, request, metadata)
+    }
 
-        // We execute stop if cell_name is empty.
-        // Otherwise, we execute in a child
-        if request.cell_name.is_empty() {
-            let request =
-                ValidatedCellServiceStopRequest::validate(request, None)?;
-            Ok(self.stop(request).await?)
-        } else {
-            // We are in a parent cell (or validation will fail)
-            let validated = ValidatedCellServiceStopRequest::validate(
-                request.clone(),
-                None,
-            )?;
+    /// Sums the resources committed to the allocated cells of this auraed, and compares
+    /// them with the [HostCapacity]. Cells nested in another cell are not considered.
+    #[tracing::instrument(skip(self))]
+    async fn commitment(&self) -> Result<CellServiceCommitmentResponse> {
+        let snapshot = self.cells.lock().await.snapshot();
+        let commitment = ResourceCommitment::sum(
+            snapshot
+                .iter()
+                .filter(|cell| cell.status == CellStatus::Allocated)
+                .map(|cell| &cell.spec.cgroup_spec),
+        );
+        let capacity = HostCapacity::detect()?;
 
-            // validation has succeed, so we can make assumptions about the request and use expect
-            let mut request = request;
-            let (parent, cell_name) = validated
-                .cell_name
-                .into_child()
-                .expect("CellNamePath was not empty");
+        Ok(CellServiceCommitmentResponse {
+            cpu_weight: commitment.cpu_weight,
+            cpu_max: commitment.cpu_max,
+            cpu_unlimited_cells: commitment.cpu_unlimited_cells,
+            cpu_capacity: capacity.cpu,
+            cpu_over_committed: commitment.cpu_over_committed(&capacity),
+            memory_max_bytes: commitment.memory_max_bytes,
+            memory_unlimited_cells: commitment.memory_unlimited_cells,
+            memory_capacity_bytes: capacity.memory_bytes,
+            memory_over_committed: commitment.memory_over_committed(&capacity),
+        })
+    }
 
-            request.cell_name = cell_name.into_string();
+    /// Returns the [RetryConfig](crate::config::RetryConfig) in effect, which is
+    /// read for every call forwarded to a nested auraed (see `do_in_cell!`).
+    #[tracing::instrument(skip(self))]
+    async fn retry_config(&self) -> CellServiceRetryConfigResponse {
+        let retry = self.config.read().await.retry.clone();
 
-            self.stop_in_cell(&parent, request).await
+        CellServiceRetryConfigResponse {
+            initial_interval_ms: retry.initial_interval_ms,
+            multiplier: retry.multiplier,
+            randomization_factor: retry.randomization_factor,
+            max_interval_ms: retry.max_interval_ms,
+            max_elapsed_ms: retry.max_elapsed_ms,
+            disabled: retry.disabled,
         }
     }
+
+    /// Returns the cgroups that look like cells of this auraed, but are not cached.
+    #[tracing::instrument(skip(self))]
+    async fn list_orphans(&self) -> Result<CellServiceListOrphansResponse> {
+        let orphans = self.cells.lock().await.list_orphans()?;
+
+        Ok(CellServiceListOrphansResponse {
+            orphans: orphans
+                .into_iter()
+                .map(|orphan| OrphanedCgroup {
+                    cell_name: orphan.cell_name.to_string(),
+                    path: orphan.path.display().to_string(),
+                    controller_values: orphan
+                        .controller_values
+                        .into_iter()
+                        .map(|(file, value)| (file.to_string(), value))
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the resource usage of the allocated cells of this auraed, by cell name.
+    /// Reading the cgroups can be slow, so we don't hold the lock while doing so.
+    pub(crate) async fn cell_stats(&self) -> Vec<(String, CgroupStats)> {
+        let snapshot = self.cells.lock().await.snapshot();
+
+        snapshot
+            .iter()
+            .filter(|cell| cell.status == CellStatus::Allocated)
+            .filter_map(|cell| {
+                read_stats(cell).map(|stats| (cell.name.to_string(), stats))
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub(crate) async fn free_all(&self) -> Result<()> {
+        let escalation = self.config.read().await.kill.escalation();
+
+        // First try to gracefully free all cells.
+        let freed = self
+            .with_cells_blocking(|cells| {
+                cells.broadcast_free();
+                cells.is_empty()
+            })
+            .await;
+        if freed {
+            return Ok(());
+        }
+
+        // The cells that remain failed to shut down for some reason.
+        // Give them a moment to settle before killing what is left in them.
+        tokio::time::sleep(escalation.delay).await;
+        let freed = self
+            .with_cells_blocking(move |cells| {
+                cells.broadcast_kill(escalation);
+                cells.is_empty()
+            })
+            .await;
+        if !freed {
+            warn!(
+                "cells with processes left after killing them were not freed"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn start(
+        &self,
+        request: ValidatedCellServiceStartRequest,
+    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
+        let ValidatedCellServiceStartRequest {
+            cell_name,
+            executable,
+            validate_only,
+            start_timeout_ms: _,
+            check_command_exists,
+            verify_placement,
+            ready_log_pattern,
+            ready_timeout_ms,
+            depends_on,
+            wait_for_ready,
+            new_session,
+            new_process_group,
+            generation,
+            ready_tcp_port,
+            free_cell_on_exit: _,
+        } = request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+        info!("CellService: start() executable={:?}", executable);
+
+        let mut executable_spec: ExecutableSpec = executable.into();
+        executable_spec.ready_log_pattern = ready_log_pattern;
+        executable_spec.process_group =
+            ProcessGroup::new(new_session, new_process_group);
+        executable_spec.generation = generation;
+
+        // We are running in the target cell, so PATH is resolved in its mount namespace
+        if check_command_exists {
+            executable_spec.check_command_exists()?;
+        }
+
+        if validate_only {
+            let executables = self.executables.lock().await;
+            executables.validate_start(&executable_spec)?;
+
+            let plan = ExecutablePlan {
+                program: executable_spec.program().to_string_lossy().into(),
+                args: executable_spec
+                    .command
+                    .as_std()
+                    .get_args()
+                    .map(|arg| arg.to_string_lossy().into())
+                    .collect(),
+                ..Default::default()
+            };
+
+            return Ok(Response::new(CellServiceStartResponse {
+                pid: 0,
+                plan: Some(plan),
+            }));
+        }
+
+        let executable_name = executable_spec.name.clone();
+
+        let pending_start = if depends_on.is_empty() {
+            None
+        } else {
+            let pending_start = self
+                .wait_for_dependencies(
+                    &executable_spec,
+                    &depends_on,
+                    wait_for_ready,
+                    ready_timeout_ms,
+                )
+                .await?;
+            Some(pending_start)
+        };
+
+        #[cfg(test)]
+        if let Some(delay) = self.pre_exec_delay {
+            executable_spec.pre_exec_hooks.push("delay", move || {
+                std::thread::sleep(delay);
+                Ok(())
+            });
+        }
+
+        let StartedExecutable { mut executables, pid, ready_log } =
+            start_executable(self.executables.clone(), executable_spec).await?;
+
+        // Only unregistered once started, so starts that depend on the executable
+        // find it either pending or started.
+        drop(pending_start);
+
+        if verify_placement {
+            if let Err(e) = executables::verify_placement(&executable_name, pid)
+            {
+                // Don't leave a process we can't account for running
+                if let Err(e) = executables
+                    .stop(&executable_name, 0, StopPolicy::KILL)
+                    .await
+                {
+                    warn!("failed to stop misplaced executable: {e:?}");
+                }
+                return Err(e.into());
+            }
+        }
+
+        // Don't block other executables while waiting for the executable to be ready
+        drop(executables);
+
+        if let Some(ready_log) = ready_log {
+            // Stops the executable if we are cancelled (e.g., by the start timeout)
+            let stop_on_drop = StopOnDrop::new(
+                self.executables.clone(),
+                executable_name.clone(),
+            );
+
+            let res =
+                tokio::time::timeout(ready_timeout_ms, ready_log.wait()).await;
+            let e = match res {
+                Ok(Readiness::Ready) => None,
+                Ok(_) => Some(ExecutablesError::OutputClosedBeforeReady {
+                    executable_name,
+                }),
+                Err(_) => Some(ExecutablesError::ReadyLogTimedOut {
+                    executable_name,
+                    timeout: ready_timeout_ms,
+                }),
+            };
+
+            match e {
+                None => stop_on_drop.disarm(),
+                Some(e) => {
+                    stop_on_drop.stop().await;
+                    return Err(e.into());
+                }
+            }
+        }
+
+        // We are running in the target cell, so the port is probed in its network namespace
+        if let Some(port) = ready_tcp_port {
+            // Stops the executable if we are cancelled (e.g., by the start timeout)
+            let stop_on_drop = StopOnDrop::new(
+                self.executables.clone(),
+                executable_name.clone(),
+            );
+
+            let res = tokio::time::timeout(
+                ready_timeout_ms,
+                self.wait_for_port(&executable_name, port),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(ExecutablesError::ReadyPortTimedOut {
+                    executable_name: executable_name.clone(),
+                    port,
+                    timeout: ready_timeout_ms,
+                })
+            });
+
+            match res {
+                Ok(()) => stop_on_drop.disarm(),
+                Err(e) => {
+                    stop_on_drop.stop().await;
+                    return Err(e.into());
+                }
+            }
+        }
+
+        // TODO: either tell the [ObserveService] about this executable's log channels, or
+        // provide a way for the observe service to extract the log channels from here.
+
+        Ok(Response::new(CellServiceStartResponse { pid, plan: None }))
+    }
+
+    /// Waits until the executable listens on `port`.
+    /// Returns an error if the executable exits first.
+    async fn wait_for_port(
+        &self,
+        executable_name: &ExecutableName,
+        port: u16,
+    ) -> executables::Result<()> {
+        loop {
+            if executables::is_port_listening(port).await {
+                return Ok(());
+            }
+
+            let mut executables = self.executables.lock().await;
+            if executables.try_wait(executable_name).await?.is_some() {
+                return Err(ExecutablesError::ExitedBeforeReady {
+                    executable_name: executable_name.clone(),
+                    port,
+                });
+            }
+            drop(executables);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Waits until every dependency has been started and, with `wait_for_ready`, has
+    /// logged its ready line. Dependencies started without a ready log pattern are
+    /// ready once started.
+    ///
+    /// Returns the registration of the pending start, to drop once the executable is
+    /// started.
+    async fn wait_for_dependencies(
+        &self,
+        executable_spec: &ExecutableSpec,
+        depends_on: &[ExecutableName],
+        wait_for_ready: bool,
+        timeout: Duration,
+    ) -> executables::Result<PendingStart> {
+        let executable_name = &executable_spec.name;
+
+        // Fail now, rather than after waiting
+        self.executables.lock().await.validate_start(executable_spec)?;
+        let pending_start =
+            self.pending_starts.register(executable_name, depends_on)?;
+
+        let wait = async {
+            for dependency in depends_on {
+                let ready_log = loop {
+                    let executables = self.executables.lock().await;
+                    if let Some(executable) = executables.get(dependency) {
+                        break executable.ready_log();
+                    }
+                    drop(executables);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                };
+
+                if !wait_for_ready {
+                    continue;
+                }
+                let Some(ready_log) = ready_log else {
+                    continue;
+                };
+
+                if ready_log.wait().await != Readiness::Ready {
+                    return Err(ExecutablesError::DependencyNotReady {
+                        executable_name: executable_name.clone(),
+                        dependency: dependency.clone(),
+                    });
+                }
+            }
+
+            Ok(())
+        };
+
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            ExecutablesError::DependenciesTimedOut {
+                executable_name: executable_name.clone(),
+                timeout,
+            }
+        })??;
+
+        Ok(pending_start)
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn start_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceStartRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
+        let mut response =
+            do_in_cell!(self, cell_name, start, request, metadata)?;
+
+        // A plan is returned by the auraed in the cell, which doesn't know about the cell itself.
+        // We are the auraed that allocated the cell, so we add what we know on the way out.
+        if let Some(plan) = &mut response.get_mut().plan {
+            if plan.cell_name.is_empty() {
+                let mut cells = self.cells.lock().await;
+                let (cgroup, cgroup_spec) = cells.get(cell_name, |cell| {
+                    Ok((cell.cgroup_path(), cell.spec().cgroup_spec.clone()))
+                })?;
+
+                plan.cell_name = cell_name.to_string();
+                plan.cgroup = cgroup.to_string_lossy().into();
+                plan.cpu = cgroup_spec.cpu.map(|x| x.into());
+                plan.cpuset = cgroup_spec.cpuset.map(|x| x.into());
+                plan.memory = cgroup_spec.memory.map(|x| x.into());
+                plan.io = cgroup_spec.io.map(|x| x.into());
+                plan.pids = cgroup_spec.pids.map(|x| x.into());
+            } else {
+                plan.cell_name = format!(
+                    "{cell_name}{}{}",
+                    cell_name_path::SEPARATOR,
+                    plan.cell_name
+                );
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Starts the executable with a timeout, and keeps the request to be able to restart it.
+    async fn start_and_keep_request(
+        &self,
+        request: CellServiceStartRequest,
+    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
+        let validated =
+            ValidatedCellServiceStartRequest::validate(request.clone(), None)?;
+        let timeout = validated.start_timeout_ms;
+        let validate_only = validated.validate_only;
+        let executable_name = validated.executable.name.clone();
+
+        // A start dropped while spawning kills the child, and stops the executable
+        // while holding the lock on the executables (see [start_executable]). Once
+        // started, it is stopped when the wait for readiness is dropped (see [StopOnDrop]).
+        let response =
+            start_with_timeout(timeout, self.start(validated), || async {
+                // Returns once the child of an abandoned spawn is gone
+                drop(self.executables.lock().await);
+            })
+            .await?;
+
+        if !validate_only {
+            let _ = self.start_requests.lock().await.insert(
+                executable_name,
+                KeptStart {
+                    request,
+                    restart_stats: Default::default(),
+                    last_exit_reason: None,
+                },
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Frees our child cell `cell_name` once `executable_name` exited in it, as polled
+    /// from the auraed nested in the cell, which reaps it.
+    /// The cell is not freed if other executables were started in it in the meantime.
+    fn free_cell_on_exit(
+        &self,
+        cell_name: CellName,
+        executable_name: ExecutableName,
+        metadata: MetadataMap,
+    ) {
+        let service = self.clone();
+        let executable_name = executable_name.into_inner();
+        let _ = tokio::spawn(async move {
+            let mut interval = EXIT_POLL_MIN_INTERVAL;
+            loop {
+                tokio::time::sleep(interval).await;
+                interval = (interval * 2).min(EXIT_POLL_MAX_INTERVAL);
+
+                let request = CellServiceListExecutablesRequest {
+                    cell_name: String::new(),
+                };
+                let executables = match service
+                    .list_executables_in_cell(&cell_name, request, &metadata)
+                    .await
+                {
+                    Ok(response) => response.into_inner().executables,
+                    Err(e) => {
+                        // The cell may have been freed by a client already
+                        if e.code() != Code::NotFound {
+                            warn!("failed to poll executable to free its cell: {e:?}");
+                        }
+                        return;
+                    }
+                };
+
+                match ExitPoll::new(&executables, &executable_name) {
+                    ExitPoll::Running => continue,
+                    ExitPoll::Free => break,
+                    ExitPoll::KeepForOtherExecutables => {
+                        warn!(
+                            cell_name = %cell_name,
+                            executable_name,
+                            "not freeing cell with other executables after its executable exited"
+                        );
+                        return;
+                    }
+                }
+            }
+
+            let free_cell_name = cell_name.clone();
+            match service
+                .with_cells_blocking(move |cells| {
+                    cells.free(&free_cell_name, FreeChildrenPolicy::Reject)
+                })
+                .await
+            {
+                Ok(()) => info!(
+                    cell_name = %cell_name,
+                    executable_name,
+                    status = ?CellStatus::Freed,
+                    "cell freed after its executable exited"
+                ),
+                Err(e) => {
+                    warn!("failed to free cell after its executable exited: {e:?}")
+                }
+            }
+        });
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn restart(
+        &self,
+        request: ValidatedCellServiceRestartRequest,
+    ) -> std::result::Result<Response<CellServiceRestartResponse>, Status> {
+        let ValidatedCellServiceRestartRequest { cell_name, executable_name } =
+            request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+        info!("CellService: restart() executable_name={:?}", executable_name);
+
+        let kept = self
+            .start_requests
+            .lock()
+            .await
+            .get(&executable_name)
+            .cloned()
+            .ok_or_else(|| ExecutablesError::NoStartToRestart {
+                executable_name: executable_name.clone(),
+            })?;
+
+        let response = self.start_and_keep_request(kept.request).await?;
+
+        // The executable may have been stopped in the meantime, which is only a reason
+        // not to record the restart
+        let exit_reason =
+            kept.last_exit_reason.unwrap_or_else(|| "unknown".into());
+        if let Some(restart_stats) = self
+            .executables
+            .lock()
+            .await
+            .record_restart(&executable_name, kept.restart_stats, exit_reason)
+        {
+            if let Some(kept) =
+                self.start_requests.lock().await.get_mut(&executable_name)
+            {
+                kept.restart_stats = restart_stats;
+            }
+        }
+
+        Ok(Response::new(CellServiceRestartResponse {
+            pid: response.into_inner().pid,
+        }))
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn restart_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceRestartRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceRestartResponse>, Status> {
+        do_in_cell!(self, cell_name, restart, request, metadata)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn stop(
+        &self,
+        request: ValidatedCellServiceStopRequest,
+    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
+        let ValidatedCellServiceStopRequest {
+            cell_name,
+            executable_name,
+            return_output_tail,
+            signal,
+            grace_period_ms,
+            signal_escalation,
+        } = request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+        info!("CellService: stop() executable_name={:?}", executable_name,);
+
+        let mut executables = self.executables.lock().await;
+
+        // The processes of our cells are stopped through the nested auraed of the cell.
+        // An executable whose process was moved into one of them is not ours to stop.
+        match self
+            .cells
+            .lock()
+            .await
+            .get_cgroup_by_executable(&executables, &executable_name)
+        {
+            Ok(cgroup) => {
+                return Err(CellsError::ExecutableInCell {
+                    executable_name,
+                    cell_name: cgroup.cell_name().clone(),
+                }
+                .into());
+            }
+            Err(CellsError::ExecutableNotInCell { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // The executable may be given minutes to exit, which the other executables
+        // shouldn't wait for
+        let executable = executables.take(&executable_name)?;
+        drop(executables);
+
+        let stop_policy = StopPolicy {
+            signal,
+            grace_period: grace_period_ms,
+            escalation: signal_escalation,
+        };
+        let (exit_status, output_tail) = Executables::stop_taken(
+            executable,
+            return_output_tail as usize,
+            stop_policy,
+        )
+        .await?;
+
+        if let Some(kept) =
+            self.start_requests.lock().await.get_mut(&executable_name)
+        {
+            kept.last_exit_reason = Some(exit_status.to_string());
+        }
+
+        Ok(Response::new(CellServiceStopResponse {
+            output_tail: output_tail.into_iter().map(Into::into).collect(),
+            exit_code: exit_status.code(),
+            signal: exit_status.signal(),
+        }))
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn stop_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceStopRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
+        do_in_cell!(self, cell_name, stop, request, metadata)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn stop_generation(
+        &self,
+        request: ValidatedCellServiceStopGenerationRequest,
+    ) -> Result<CellServiceStopGenerationResponse> {
+        let ValidatedCellServiceStopGenerationRequest { cell_name, below } =
+            request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+        info!("CellService: stop_generation() below={below}");
+
+        let mut executables = self.executables.lock().await;
+        let executable_names = executables.stop_generation(below).await?;
+
+        Ok(CellServiceStopGenerationResponse {
+            executable_names: executable_names
+                .into_iter()
+                .map(ExecutableName::into_inner)
+                .collect(),
+        })
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn stop_generation_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceStopGenerationRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceStopGenerationResponse>, Status>
+    {
+        do_in_cell!(self, cell_name, stop_generation, request, metadata)
+    }
+
+    /// Starts the executable, waits for it to exit (or kills it once `timeout_ms` elapses)
+    /// and returns how it exited along with its most recent output.
+    #[tracing::instrument(skip(self))]
+    async fn run(
+        &self,
+        request: ValidatedCellServiceRunRequest,
+    ) -> std::result::Result<Response<CellServiceRunResponse>, Status> {
+        let ValidatedCellServiceRunRequest {
+            cell_name,
+            executable,
+            timeout_ms,
+            return_output_tail,
+            free_cell: _,
+        } = request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+        info!("CellService: run() executable={:?}", executable);
+
+        let mut executable_spec: ExecutableSpec = executable.into();
+        executable_spec.output_tail_capacity =
+            executable_spec.output_tail_capacity.max(return_output_tail);
+
+        let executable_name = executable_spec.name.clone();
+        let started_at = Instant::now();
+        let _ = self.executables.lock().await.start(executable_spec)?;
+
+        // Stops the executable if we are cancelled (e.g., the client went away)
+        let stop_on_drop =
+            StopOnDrop::new(self.executables.clone(), executable_name.clone());
+
+        // The lock is released between checks, so other executables are not blocked
+        let exited = async {
+            loop {
+                let mut executables = self.executables.lock().await;
+                if executables.try_wait(&executable_name).await?.is_some() {
+                    return Ok::<_, ExecutablesError>(());
+                }
+                drop(executables);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+
+        let res = match timeout_ms {
+            Some(timeout) => tokio::time::timeout(timeout, exited).await.ok(),
+            None => Some(exited.await),
+        };
+        let duration = started_at.elapsed();
+
+        let timed_out = match res {
+            Some(res) => {
+                res?;
+                false
+            }
+            None => true,
+        };
+
+        // Stopping kills the executable if it timed out, and removes it from the cache
+        stop_on_drop.disarm();
+        let (exit_status, output_tail) = self
+            .executables
+            .lock()
+            .await
+            .stop(
+                &executable_name,
+                return_output_tail as usize,
+                StopPolicy::KILL,
+            )
+            .await?;
+
+        Ok(Response::new(CellServiceRunResponse {
+            exit_code: exit_status.code(),
+            signal: exit_status.signal(),
+            timed_out,
+            output_tail: output_tail.into_iter().map(Into::into).collect(),
+            duration_ms: u64::try_from(duration.as_millis())
+                .unwrap_or(u64::MAX),
+            cell_freed: false,
+        }))
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn run_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceRunRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceRunResponse>, Status> {
+        do_in_cell!(self, cell_name, run, request, metadata)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn list_executables(
+        &self,
+        request: ValidatedCellServiceListExecutablesRequest,
+    ) -> std::result::Result<Response<CellServiceListExecutablesResponse>, Status>
+    {
+        let ValidatedCellServiceListExecutablesRequest { cell_name } = request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+
+        let executables = self.executables.lock().await;
+
+        let executables = executables
+            .list()
+            .into_iter()
+            .map(|executable| -> Result<ExecutableStatus> {
+                // Executables that exited are listed with a pid of 0, but are only reaped
+                // when stopped
+                let pid = if executable.has_exited()? {
+                    0
+                } else {
+                    executable
+                        .pid()?
+                        .map(|pid| pid.as_raw())
+                        .unwrap_or_default()
+                };
+
+                let restart_stats = executable.restart_stats();
+
+                Ok(ExecutableStatus {
+                    name: executable.name.clone().into_inner(),
+                    description: executable.description.clone(),
+                    pid,
+                    restart_count: restart_stats.restart_count,
+                    last_restart_at: restart_stats
+                        .last_restart_at
+                        .unwrap_or_default(),
+                    last_exit_reason: restart_stats
+                        .last_exit_reason
+                        .clone()
+                        .unwrap_or_default(),
+                    crash_looping: restart_stats.is_crash_looping(),
+                    command: executable
+                        .original_command
+                        .to_string_lossy()
+                        .into(),
+                    argv: executable
+                        .argv
+                        .iter()
+                        .map(|arg| arg.to_string_lossy().into())
+                        .collect(),
+                    generation: executable.generation,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Response::new(CellServiceListExecutablesResponse { executables }))
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn list_executables_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceListExecutablesRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceListExecutablesResponse>, Status>
+    {
+        do_in_cell!(self, cell_name, list_executables, request, metadata)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn list_fds(
+        &self,
+        request: ValidatedCellServiceListFdsRequest,
+    ) -> Result<CellServiceListFdsResponse> {
+        let ValidatedCellServiceListFdsRequest { cell_name, executable_name } =
+            request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+
+        let pid = {
+            let executables = self.executables.lock().await;
+            let Some(executable) = executables.get(&executable_name) else {
+                return Err(ExecutablesError::ExecutableNotFound {
+                    executable_name,
+                }
+                .into());
+            };
+            executable.pid()?
+        };
+
+        let Some(pid) = pid else {
+            return Err(ExecutablesError::ExecutableNotRunning {
+                executable_name,
+            }
+            .into());
+        };
+
+        // Read without holding the lock, as the process may have many fds
+        let fds = executables::open_fds(&executable_name, pid.as_raw())?;
+
+        Ok(CellServiceListFdsResponse {
+            fds: fds.into_iter().map(|fd| fd.into()).collect(),
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn list_fds_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceListFdsRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<CellServiceListFdsResponse>, Status> {
+        do_in_cell!(self, cell_name, list_fds, request, metadata)
+    }
+
+    /// Streams the captured output lines of the executable, followed by the lines it
+    /// writes until it closes its output.
+    #[tracing::instrument(skip(self))]
+    async fn log_stream(
+        &self,
+        request: ValidatedCellServiceLogStreamRequest,
+    ) -> Result<LogStream> {
+        let ValidatedCellServiceLogStreamRequest { cell_name, executable_name } =
+            request;
+
+        assert!(matches!(cell_name, CellNamePath::Empty));
+
+        let OutputSubscription { buffered, live } = {
+            let executables = self.executables.lock().await;
+            let Some(executable) = executables.get(&executable_name) else {
+                return Err(ExecutablesError::ExecutableNotFound {
+                    executable_name,
+                }
+                .into());
+            };
+            executable.subscribe_output()
+        };
+
+        let buffered = stream::iter(buffered).map(|line| Ok(line.into()));
+        let live = stream::iter(live).flat_map(BroadcastStream::new).filter_map(
+            move |line| {
+                future::ready(match line {
+                    Ok(line) => Some(Ok(line.into())),
+                    Err(BroadcastStreamRecvError::Lagged(count)) => {
+                        warn!(
+                            "log stream of {executable_name} fell behind, skipping {count} lines"
+                        );
+                        None
+                    }
+                })
+            },
+        );
+
+        Ok(Box::pin(buffered.chain(live)))
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn log_stream_in_cell(
+        &self,
+        cell_name: &CellName,
+        request: CellServiceLogStreamRequest,
+        metadata: &MetadataMap,
+    ) -> std::result::Result<Response<tonic::Streaming<OutputLine>>, Status>
+    {
+        do_in_cell!(self, cell_name, log_stream, request, metadata)
+    }
+
+    /// Returns an error unless the client of `request` is an admin (see
+    /// [AdminConfig](crate::config::AdminConfig)).
+    async fn authorize_admin<T>(
+        &self,
+        method: &'static str,
+        request: &Request<T>,
+    ) -> Result<()> {
+        let client = client_identity(request);
+        if self.config.read().await.admin.is_admin(client.as_deref()) {
+            return Ok(());
+        }
+
+        warn!(?client, "denied call to admin rpc '{method}'");
+        Err(CellsServiceError::AdminOnly { method })
+    }
+}
+
+/// Returns the response to the allocation of `cell`, which is allocated.
+fn allocate_response(cell: &Cell) -> Result<CellServiceAllocateResponse> {
+    Ok(CellServiceAllocateResponse {
+        cell_name: cell.name().clone().into_inner(),
+        cgroup_v2: cell.v2().expect("allocated cell returns `Some`"),
+        cgroup_id: cell.cgroup_id()?,
+    })
+}
+
+/// Names the cells listed by the nested auraed of `parent` by their path from this
+/// auraed (e.g., "parent/child"). Cells whose path is not below `parent` (e.g., listed
+/// with an empty or invalid name) are skipped, as they can't be addressed through it.
+fn nested_listed_cells(
+    parent: &CellName,
+    response: CellServiceListResponse,
+) -> impl Iterator<Item = ListedCell> + '_ {
+    let parent_path = CellNamePath::from(parent.clone());
+    response.cells.into_iter().filter_map(move |mut cell| {
+        cell.cell_name =
+            format!("{parent}{}{}", cell_name_path::SEPARATOR, cell.cell_name);
+
+        match CellNamePath::validate(Some(cell.cell_name.clone()), "cell_name", None) {
+            Ok(path) if parent_path.is_ancestor_of(&path) => Some(cell),
+            _ => {
+                warn!(
+                    "skipping cell '{}' listed by the nested auraed of {parent}",
+                    cell.cell_name
+                );
+                None
+            }
+        }
+    })
+}
+
+/// Reads the stats of a cell of a [CellsSnapshot](super::cells::CellsSnapshot).
+/// Returns [None] if the cell has been freed since the snapshot was taken, or the stats
+/// could not be read.
+fn read_stats(cell: &CellSnapshot) -> Option<CgroupStats> {
+    match cell.stats() {
+        Ok(stats) => Some(stats),
+        // freed since the snapshot was taken
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("failed to read stats of cell {}: {e}", cell.name);
+            None
+        }
+    }
+}
+
+/// Reads the freeze state of a cell of a [CellsSnapshot](super::cells::CellsSnapshot).
+/// Returns [None] if the cell has been freed since the snapshot was taken, or the state
+/// could not be read.
+fn read_freeze_state(cell: &CellSnapshot) -> Option<FreezeState> {
+    match cell.freeze_state() {
+        Ok(state) => Some(state),
+        // freed since the snapshot was taken
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("failed to read freeze state of cell {}: {e}", cell.name);
+            None
+        }
+    }
+}
+
+/// Stops an executable when dropped, unless disarmed.
+///
+/// Start awaits the ready log line of an executable it already spawned, so the start
+/// future may be dropped (e.g., by [start_with_timeout]) with the executable running.
+#[derive(Debug)]
+struct StopOnDrop {
+    executables: Arc<Mutex<Executables>>,
+    executable_name: Option<ExecutableName>,
+}
+
+impl StopOnDrop {
+    fn new(
+        executables: Arc<Mutex<Executables>>,
+        executable_name: ExecutableName,
+    ) -> Self {
+        Self { executables, executable_name: Some(executable_name) }
+    }
+
+    /// Leaves the executable running.
+    fn disarm(mut self) {
+        self.executable_name = None;
+    }
+
+    /// Stops the executable now, rather than in the background when dropped.
+    async fn stop(mut self) {
+        let Some(executable_name) = self.executable_name.take() else {
+            return;
+        };
+        stop_executable(&self.executables, &executable_name).await;
+    }
+}
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        let Some(executable_name) = self.executable_name.take() else {
+            return;
+        };
+        let executables = self.executables.clone();
+        let _ = tokio::spawn(async move {
+            stop_executable(&executables, &executable_name).await;
+        });
+    }
+}
+
+async fn stop_executable(
+    executables: &Mutex<Executables>,
+    executable_name: &ExecutableName,
+) {
+    let mut executables = executables.lock().await;
+    if let Err(e) = executables.stop(executable_name, 0, StopPolicy::KILL).await
+    {
+        warn!("failed to stop executable that is not ready: {e:?}");
+    }
+}
+
+/// ### Mapping cgroup options to the Cell API
+///
+/// Here we *only* expose options from the CgroupBuilder
+/// as our features in Aurae need them! We do not try to
+/// "map" everything as much as we start with a few small
+/// features and add as needed.
+///
+// Example builder options can be found: https://github.com/kata-containers/cgroups-rs/blob/main/tests/builder.rs
+// Cgroup documentation: https://man7.org/linux/man-pages/man7/cgroups.7.html
+#[tonic::async_trait]
+impl cell_service_server::CellService for CellService {
+    async fn allocate(
+        &self,
+        request: Request<CellServiceAllocateRequest>,
+    ) -> std::result::Result<Response<CellServiceAllocateResponse>, Status>
+    {
+        self.audit
+            .record("allocate", request, |request| async move {
+                let (metadata, _, mut request) = request.into_parts();
+                self.config.read().await.apply_template(&mut request)?;
+
+                // We execute allocate if cell_name is a direct child
+                if matches!(&request.cell, Some(cell) if !cell.name.contains(cell_name_path::SEPARATOR))
+                {
+                    self.config.read().await.apply_defaults(&mut request);
+                    let request = ValidatedCellServiceAllocateRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    Ok(Response::new(self.allocate(request).await?))
+                } else {
+                    let validated = ValidatedCellServiceAllocateRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let (parent, cell_name) = validated
+                        .cell
+                        .name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    let mut request = request;
+                    if let Some(cell) = &mut request.cell {
+                        cell.name = cell_name.into_string();
+                    } else {
+                        unreachable!("validation should have failed")
+                    }
+
+                    self.allocate_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn update(
+        &self,
+        request: Request<CellServiceUpdateRequest>,
+    ) -> std::result::Result<Response<CellServiceUpdateResponse>, Status> {
+        self.audit
+            .record("update", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute update if cell_name is a direct child
+                if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+                    let request = ValidatedCellServiceUpdateRequest::validate(
+                        request, None,
+                    )?;
+                    Ok(Response::new(self.update(request).await?))
+                } else {
+                    let validated =
+                        ValidatedCellServiceUpdateRequest::validate(
+                            request.clone(),
+                            None,
+                        )?;
+
+                    // validation has succeeded, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.update_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn free(
+        &self,
+        request: Request<CellServiceFreeRequest>,
+    ) -> std::result::Result<Response<CellServiceFreeResponse>, Status> {
+        self.audit
+            .record("free", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute free if cell_name is a direct child
+                if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+                    let request = ValidatedCellServiceFreeRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+                    Ok(Response::new(self.free(request).await?))
+                } else {
+                    let validated = ValidatedCellServiceFreeRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeeded, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.free_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn start(
+        &self,
+        request: Request<CellServiceStartRequest>,
+    ) -> std::result::Result<Response<CellServiceStartResponse>, Status> {
+        self.audit
+            .record("start", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute start if cell_name is empty
+                if request.cell_name.is_empty() {
+                    // There is no cell to free when starting directly in auraed
+                    if request.free_cell_on_exit {
+                        return Err(ValidationError::Invalid {
+                            field: "free_cell_on_exit".into(),
+                        }
+                        .into());
+                    }
+
+                    self.start_and_keep_request(request).await
+                } else {
+                    // We are in a parent cell (or validation will fail)
+                    let validated = ValidatedCellServiceStartRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    // A cell is freed by the auraed that allocated it, which is us if the
+                    // executable starts in our child. Otherwise, the nested auraed frees it.
+                    let free_cell_on_exit = request.free_cell_on_exit
+                        && request.cell_name.is_empty();
+                    if free_cell_on_exit {
+                        request.free_cell_on_exit = false;
+
+                        let list_request = CellServiceListExecutablesRequest {
+                            cell_name: String::new(),
+                        };
+                        let executables = self
+                            .list_executables_in_cell(
+                                &parent,
+                                list_request,
+                                &metadata,
+                            )
+                            .await?
+                            .into_inner()
+                            .executables;
+                        if !executables.is_empty() {
+                            return Err(CellsError::CellHasExecutables {
+                                cell_name: parent,
+                                executables: executables
+                                    .into_iter()
+                                    .map(|executable| executable.name)
+                                    .collect(),
+                            }
+                            .into());
+                        }
+                    }
+
+                    let executable_name = validated.executable.name;
+                    let stop_request = CellServiceStopRequest {
+                        cell_name: request.cell_name.clone(),
+                        executable_name: executable_name.clone().into_inner(),
+                        return_output_tail: 0,
+                        signal: "SIGKILL".into(),
+                        grace_period_ms: 0,
+                        signal_escalation: vec![],
+                    };
+
+                    let response = start_with_timeout(
+                        validated.start_timeout_ms,
+                        self.start_in_cell(&parent, request, &metadata),
+                        || async {
+                            // The executable may have been spawned in the cell before we gave up
+                            if let Err(e) =
+                                self.stop_in_cell(&parent, stop_request, &metadata).await
+                            {
+                                if e.code() != Code::NotFound {
+                                    warn!("failed to clean up timed out start: {e:?}");
+                                }
+                            }
+                        },
+                    )
+                    .await?;
+
+                    if free_cell_on_exit && !validated.validate_only {
+                        self.free_cell_on_exit(
+                            parent,
+                            executable_name,
+                            metadata,
+                        );
+                    }
+
+                    Ok(response)
+                }
+            })
+            .await
+    }
+
+    async fn stop(
+        &self,
+        request: Request<CellServiceStopRequest>,
+    ) -> std::result::Result<Response<CellServiceStopResponse>, Status> {
+        self.audit
+            .record("stop", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute stop if cell_name is empty.
+                // Otherwise, we execute in a child
+                if request.cell_name.is_empty() {
+                    let request = ValidatedCellServiceStopRequest::validate(
+                        request, None,
+                    )?;
+                    Ok(self.stop(request).await?)
+                } else {
+                    // We are in a parent cell (or validation will fail)
+                    let validated = ValidatedCellServiceStopRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.stop_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn restart(
+        &self,
+        request: Request<CellServiceRestartRequest>,
+    ) -> std::result::Result<Response<CellServiceRestartResponse>, Status> {
+        self.audit
+            .record("restart", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute restart if cell_name is empty.
+                // Otherwise, we execute in a child
+                if request.cell_name.is_empty() {
+                    let request = ValidatedCellServiceRestartRequest::validate(
+                        request, None,
+                    )?;
+                    self.restart(request).await
+                } else {
+                    // We are in a parent cell (or validation will fail)
+                    let validated =
+                        ValidatedCellServiceRestartRequest::validate(
+                            request.clone(),
+                            None,
+                        )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.restart_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn stop_generation(
+        &self,
+        request: Request<CellServiceStopGenerationRequest>,
+    ) -> std::result::Result<Response<CellServiceStopGenerationResponse>, Status>
+    {
+        self.audit
+            .record("stop_generation", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute stop_generation if cell_name is empty.
+                // Otherwise, we execute in a child
+                if request.cell_name.is_empty() {
+                    let request =
+                        ValidatedCellServiceStopGenerationRequest::validate(
+                            request, None,
+                        )?;
+                    Ok(Response::new(self.stop_generation(request).await?))
+                } else {
+                    // We are in a parent cell (or validation will fail)
+                    let validated =
+                        ValidatedCellServiceStopGenerationRequest::validate(
+                            request.clone(),
+                            None,
+                        )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.stop_generation_in_cell(&parent, request, &metadata)
+                        .await
+                }
+            })
+            .await
+    }
+
+    async fn run(
+        &self,
+        request: Request<CellServiceRunRequest>,
+    ) -> std::result::Result<Response<CellServiceRunResponse>, Status> {
+        self.audit
+            .record("run", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute run if cell_name is empty
+                if request.cell_name.is_empty() {
+                    let request = ValidatedCellServiceRunRequest::validate(
+                        request, None,
+                    )?;
+
+                    // There is no cell to free when running directly in auraed
+                    if request.free_cell {
+                        return Err(ValidationError::Invalid {
+                            field: "free_cell".into(),
+                        }
+                        .into());
+                    }
+
+                    self.run(request).await
+                } else {
+                    // We are in a parent cell (or validation will fail)
+                    let validated = ValidatedCellServiceRunRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeed, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    // A cell is freed by the auraed that allocated it, which is us if the
+                    // executable runs in our child. Otherwise, the nested auraed frees it.
+                    let free_cell =
+                        request.free_cell && request.cell_name.is_empty();
+                    if free_cell {
+                        request.free_cell = false;
+                    }
+
+                    let res =
+                        self.run_in_cell(&parent, request, &metadata).await;
+                    if !free_cell {
+                        return res;
+                    }
+
+                    // The cell is freed even if the run failed
+                    let free_parent = parent.clone();
+                    let cell_freed = match self
+                        .with_cells_blocking(move |cells| {
+                            cells.free(&free_parent, FreeChildrenPolicy::Reject)
+                        })
+                        .await
+                    {
+                        Ok(()) => true,
+                        Err(e) => {
+                            warn!("failed to free cell after run: {e:?}");
+                            false
+                        }
+                    };
+
+                    let mut response = res?;
+                    response.get_mut().cell_freed = cell_freed;
+                    Ok(response)
+                }
+            })
+            .await
+    }
+
+    async fn free_by_selector(
+        &self,
+        request: Request<CellServiceFreeBySelectorRequest>,
+    ) -> std::result::Result<Response<CellServiceFreeBySelectorResponse>, Status>
+    {
+        self.audit
+            .record("free_by_selector", request, |request| async move {
+                let request = request.into_inner();
+                let request =
+                    ValidatedCellServiceFreeBySelectorRequest::validate(
+                        request, None,
+                    )?;
+                Ok(Response::new(self.free_by_selector(request).await?))
+            })
+            .await
+    }
+
+    async fn get_cell_by_tid(
+        &self,
+        request: Request<CellServiceGetCellByTidRequest>,
+    ) -> std::result::Result<Response<CellServiceGetCellByTidResponse>, Status>
+    {
+        let request = request.into_inner();
+        let request =
+            ValidatedCellServiceGetCellByTidRequest::validate(request, None)?;
+        Ok(Response::new(self.get_cell_by_tid(request).await?))
+    }
+
+    async fn describe(
+        &self,
+        request: Request<CellServiceDescribeRequest>,
+    ) -> std::result::Result<Response<CellServiceDescribeResponse>, Status>
+    {
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute describe if cell_name is a direct child
+        if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+            let request =
+                ValidatedCellServiceDescribeRequest::validate(request, None)?;
+            Ok(Response::new(self.describe(request).await?))
+        } else {
+            let validated = ValidatedCellServiceDescribeRequest::validate(
+                request.clone(),
+                None,
+            )?;
+
+            // validation has succeeded, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            self.describe_in_cell(&parent, request, &metadata).await
+        }
+    }
+
+    async fn stat(
+        &self,
+        request: Request<CellServiceStatRequest>,
+    ) -> std::result::Result<Response<CellServiceStatResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute stat if cell_name is a direct child
+        if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+            let request =
+                ValidatedCellServiceStatRequest::validate(request, None)?;
+            Ok(Response::new(self.stat(request).await?))
+        } else {
+            let validated = ValidatedCellServiceStatRequest::validate(
+                request.clone(),
+                None,
+            )?;
+
+            // validation has succeeded, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            self.stat_in_cell(&parent, request, &metadata).await
+        }
+    }
+
+    async fn drain(
+        &self,
+        request: Request<CellServiceDrainRequest>,
+    ) -> std::result::Result<Response<CellServiceDrainResponse>, Status> {
+        self.audit
+            .record("drain", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute drain if cell_name is a direct child
+                if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+                    let request = ValidatedCellServiceDrainRequest::validate(
+                        request, None,
+                    )?;
+                    Ok(Response::new(self.drain(request).await?))
+                } else {
+                    let validated = ValidatedCellServiceDrainRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeeded, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.drain_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn freeze(
+        &self,
+        request: Request<CellServiceFreezeRequest>,
+    ) -> std::result::Result<Response<CellServiceFreezeResponse>, Status> {
+        self.audit
+            .record("freeze", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute freeze if cell_name is a direct child
+                if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+                    let request = ValidatedCellServiceFreezeRequest::validate(
+                        request, None,
+                    )?;
+                    Ok(Response::new(self.freeze(request).await?))
+                } else {
+                    let validated =
+                        ValidatedCellServiceFreezeRequest::validate(
+                            request.clone(),
+                            None,
+                        )?;
+
+                    // validation has succeeded, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.freeze_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn thaw(
+        &self,
+        request: Request<CellServiceThawRequest>,
+    ) -> std::result::Result<Response<CellServiceThawResponse>, Status> {
+        self.audit
+            .record("thaw", request, |request| async move {
+                let (metadata, _, request) = request.into_parts();
+
+                // We execute thaw if cell_name is a direct child
+                if !request.cell_name.contains(cell_name_path::SEPARATOR) {
+                    let request = ValidatedCellServiceThawRequest::validate(
+                        request, None,
+                    )?;
+                    Ok(Response::new(self.thaw(request).await?))
+                } else {
+                    let validated = ValidatedCellServiceThawRequest::validate(
+                        request.clone(),
+                        None,
+                    )?;
+
+                    // validation has succeeded, so we can make assumptions about the request and use expect
+                    let mut request = request;
+                    let (parent, cell_name) = validated
+                        .cell_name
+                        .into_child()
+                        .expect("CellNamePath was not empty");
+
+                    request.cell_name = cell_name.into_string();
+
+                    self.thaw_in_cell(&parent, request, &metadata).await
+                }
+            })
+            .await
+    }
+
+    async fn list(
+        &self,
+        request: Request<CellServiceListRequest>,
+    ) -> std::result::Result<Response<CellServiceListResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute list if cell_name is empty.
+        // Otherwise, we execute in a child
+        if request.cell_name.is_empty() {
+            let request =
+                ValidatedCellServiceListRequest::validate(request, None)?;
+            Ok(Response::new(self.list(request, &metadata).await?))
+        } else {
+            // We are in a parent cell (or validation will fail)
+            let validated = ValidatedCellServiceListRequest::validate(
+                request.clone(),
+                None,
+            )?;
+
+            // validation has succeed, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            self.list_in_cell(&parent, request, &metadata).await
+        }
+    }
+
+    async fn list_executables(
+        &self,
+        request: Request<CellServiceListExecutablesRequest>,
+    ) -> std::result::Result<Response<CellServiceListExecutablesResponse>, Status>
+    {
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute list_executables if cell_name is empty.
+        // Otherwise, we execute in a child
+        if request.cell_name.is_empty() {
+            let request = ValidatedCellServiceListExecutablesRequest::validate(
+                request, None,
+            )?;
+            Ok(self.list_executables(request).await?)
+        } else {
+            // We are in a parent cell (or validation will fail)
+            let validated =
+                ValidatedCellServiceListExecutablesRequest::validate(
+                    request.clone(),
+                    None,
+                )?;
+
+            // validation has succeed, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            self.list_executables_in_cell(&parent, request, &metadata).await
+        }
+    }
+
+    async fn list_fds(
+        &self,
+        request: Request<CellServiceListFdsRequest>,
+    ) -> std::result::Result<Response<CellServiceListFdsResponse>, Status> {
+        self.authorize_admin("list_fds", &request).await?;
+
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute list_fds if cell_name is empty.
+        // Otherwise, we execute in a child
+        if request.cell_name.is_empty() {
+            let request =
+                ValidatedCellServiceListFdsRequest::validate(request, None)?;
+            Ok(Response::new(self.list_fds(request).await?))
+        } else {
+            // We are in a parent cell (or validation will fail)
+            let validated = ValidatedCellServiceListFdsRequest::validate(
+                request.clone(),
+                None,
+            )?;
+
+            // validation has succeeded, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            self.list_fds_in_cell(&parent, request, &metadata).await
+        }
+    }
+
+    async fn commitment(
+        &self,
+        _request: Request<CellServiceCommitmentRequest>,
+    ) -> std::result::Result<Response<CellServiceCommitmentResponse>, Status>
+    {
+        Ok(Response::new(self.commitment().await?))
+    }
+
+    async fn retry_config(
+        &self,
+        request: Request<CellServiceRetryConfigRequest>,
+    ) -> std::result::Result<Response<CellServiceRetryConfigResponse>, Status>
+    {
+        self.authorize_admin("retry_config", &request).await?;
+
+        Ok(Response::new(self.retry_config().await))
+    }
+
+    async fn list_orphans(
+        &self,
+        request: Request<CellServiceListOrphansRequest>,
+    ) -> std::result::Result<Response<CellServiceListOrphansResponse>, Status>
+    {
+        self.authorize_admin("list_orphans", &request).await?;
+
+        Ok(Response::new(self.list_orphans().await?))
+    }
+
+    type LogStreamStream = LogStream;
+
+    async fn log_stream(
+        &self,
+        request: Request<CellServiceLogStreamRequest>,
+    ) -> std::result::Result<Response<Self::LogStreamStream>, Status> {
+        let (metadata, _, request) = request.into_parts();
+
+        // We execute log_stream if cell_name is empty.
+        // Otherwise, we execute in a child
+        if request.cell_name.is_empty() {
+            let request =
+                ValidatedCellServiceLogStreamRequest::validate(request, None)?;
+            Ok(Response::new(self.log_stream(request).await?))
+        } else {
+            // We are in a parent cell (or validation will fail)
+            let validated = ValidatedCellServiceLogStreamRequest::validate(
+                request.clone(),
+                None,
+            )?;
+
+            // validation has succeeded, so we can make assumptions about the request and use expect
+            let mut request = request;
+            let (parent, cell_name) = validated
+                .cell_name
+                .into_child()
+                .expect("CellNamePath was not empty");
+
+            request.cell_name = cell_name.into_string();
+
+            let response =
+                self.log_stream_in_cell(&parent, request, &metadata).await?;
+            Ok(Response::new(Box::pin(response.into_inner())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ReloadableConfig;
+    use crate::runtime::cell_service::test_helpers::{
+        test_cell_service, test_cell_service_with_config,
+    };
+    use aurae_proto::runtime::{Cell, Executable};
+    use std::path::Path;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Identity, Server, ServerTlsConfig};
+
+    #[test]
+    fn test_nested_listed_cells_are_named_by_path() {
+        let parent = CellName::from("ae-parent");
+        let response = CellServiceListResponse {
+            cells: vec![
+                ListedCell {
+                    cell_name: "ae-child".into(),
+                    ..Default::default()
+                },
+                ListedCell {
+                    cell_name: "ae-child/ae-grandchild".into(),
+                    unreachable: true,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let cells: Vec<_> = nested_listed_cells(&parent, response).collect();
+
+        assert_eq!(cells[0].cell_name, "ae-parent/ae-child");
+        assert!(!cells[0].unreachable);
+        assert_eq!(cells[1].cell_name, "ae-parent/ae-child/ae-grandchild");
+        assert!(cells[1].unreachable);
+    }
+
+    #[test]
+    fn test_nested_listed_cells_not_below_the_parent_are_skipped() {
+        let parent = CellName::from("ae-parent");
+        let response = CellServiceListResponse {
+            cells: ["", "Not_A_Cell", "ae-child"]
+                .into_iter()
+                .map(|cell_name| ListedCell {
+                    cell_name: cell_name.into(),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        let cells: Vec<_> = nested_listed_cells(&parent, response).collect();
+
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].cell_name, "ae-parent/ae-child");
+    }
+
+    #[tokio::test]
+    async fn test_retry_config_returns_config_in_effect() {
+        let mut config = ReloadableConfig::default();
+        config.retry.initial_interval_ms = 10;
+        config.retry.multiplier = 2.0;
+        config.retry.randomization_factor = 0.1;
+        config.retry.max_interval_ms = 500;
+        config.retry.max_elapsed_ms = 5_000;
+        let service = test_cell_service_with_config(config.clone());
+
+        let retry_config = |service: CellService| async move {
+            cell_service_server::CellService::retry_config(
+                &service,
+                Request::new(CellServiceRetryConfigRequest {}),
+            )
+            .await
+        };
+
+        let e = retry_config(service.clone()).await.expect_err("not an admin");
+        assert_eq!(e.code(), Code::PermissionDenied);
+
+        config.admin.trust_all_clients = true;
+        *service.config.write().await = config;
+        let response =
+            retry_config(service).await.expect("retry config").into_inner();
+
+        assert_eq!(
+            response,
+            CellServiceRetryConfigResponse {
+                initial_interval_ms: 10,
+                multiplier: 2.0,
+                randomization_factor: 0.1,
+                max_interval_ms: 500,
+                max_elapsed_ms: 5_000,
+                disabled: false,
+            }
+        );
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mapped_cell_runs_as_root_of_the_cell() {
+        let service = test_cell_service();
+
+        let cell_name = format!("ae-test-{}", uuid::Uuid::new_v4());
+        let id_map = vec![aurae_proto::runtime::IdMapping {
+            container_id: 0,
+            host_id: 100000,
+            size: 65536,
+        }];
+        let request = ValidatedCellServiceAllocateRequest::validate(
+            CellServiceAllocateRequest {
+                cell: Some(Cell {
+                    name: cell_name.clone(),
+                    isolate_process: true,
+                    uid_map: id_map.clone(),
+                    gid_map: id_map,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .expect("valid request");
+        let _ = service.allocate(request).await.expect("allocate");
+
+        let mut request = run_request("id -u");
+        request.cell_name = cell_name.clone();
+        let request = ValidatedCellServiceRunRequest::validate(request, None)
+            .expect("valid request");
+        let response = service.run(request).await.expect("run").into_inner();
+
+        assert_eq!(response.exit_code, Some(0));
+        let lines: Vec<_> = response
+            .output_tail
+            .iter()
+            .map(|line| (line.stream.as_str(), line.line.as_str()))
+            .collect();
+        assert_eq!(lines, [("stdout", "0")]);
+
+        let request = ValidatedCellServiceFreeRequest::validate(
+            CellServiceFreeRequest {
+                cell_name,
+                return_final_stats: false,
+                children_policy: 0,
+            },
+            None,
+        )
+        .expect("valid request");
+        let _ = service.free(request).await.expect("free");
+    }
+
+    fn run_request(command: &str) -> CellServiceRunRequest {
+        CellServiceRunRequest {
+            executable: Some(Executable {
+                name: "ae-test-run".into(),
+                command: command.into(),
+                ..Default::default()
+            }),
+            return_output_tail: 10,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_collects_exit_code_and_output() {
+        let service = test_cell_service();
+
+        let request = ValidatedCellServiceRunRequest::validate(
+            run_request("echo hello; echo oops >&2; exit 3"),
+            None,
+        )
+        .expect("valid request");
+        let response = service.run(request).await.expect("run").into_inner();
+
+        assert_eq!(response.exit_code, Some(3));
+        assert_eq!(response.signal, None);
+        assert!(!response.timed_out);
+        let lines: Vec<_> = response
+            .output_tail
+            .iter()
+            .map(|line| (line.stream.as_str(), line.line.as_str()))
+            .collect();
+        assert!(lines.contains(&("stdout", "hello")));
+        assert!(lines.contains(&("stderr", "oops")));
+
+        // the executable was removed, so the name can be reused
+        assert!(service.executables.lock().await.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_kills_executable_on_timeout() {
+        let service = test_cell_service();
+
+        let mut request = run_request("sleep 10");
+        request.timeout_ms = 100;
+        let request = ValidatedCellServiceRunRequest::validate(request, None)
+            .expect("valid request");
+        let response = service.run(request).await.expect("run").into_inner();
+
+        assert!(response.timed_out);
+        assert_eq!(response.exit_code, None);
+        assert_eq!(response.signal, Some(libc::SIGKILL));
+        assert!(response.duration_ms < 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_run_free_cell_requires_cell_name() {
+        let service = test_cell_service();
+
+        let mut request = run_request("true");
+        request.free_cell = true;
+        let e = cell_service_server::CellService::run(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect_err("free_cell without a cell");
+
+        assert_eq!(e.code(), Code::InvalidArgument);
+        assert!(service.executables.lock().await.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_with_cells_blocking_returns_errors() {
+        let service = test_cell_service();
+        let cell_name = CellName::random_for_tests();
+
+        let res = service
+            .with_cells_blocking(move |cells| {
+                cells.free(&cell_name, FreeChildrenPolicy::Reject)
+            })
+            .await;
+
+        assert!(matches!(res, Err(CellsError::CellNotFound { .. })));
+        // The lock is released once done
+        assert!(service.cells.try_lock().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_start_free_cell_on_exit_requires_cell_name() {
+        let service = test_cell_service();
+
+        let mut request = start_request("ae-test-free-on-exit", "true");
+        request.free_cell_on_exit = true;
+        let e = cell_service_server::CellService::start(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect_err("free_cell_on_exit without a cell");
+
+        assert_eq!(e.code(), Code::InvalidArgument);
+        assert!(service.executables.lock().await.list().is_empty());
+    }
+
+    #[test]
+    fn test_exit_poll() {
+        let status = |name: &str, pid: i32| ExecutableStatus {
+            name: name.into(),
+            pid,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ExitPoll::new(&[status("ae-exe", 42)], "ae-exe"),
+            ExitPoll::Running
+        );
+        assert_eq!(
+            ExitPoll::new(&[status("ae-exe", 0)], "ae-exe"),
+            ExitPoll::Free
+        );
+        // Stopped executables are no longer listed
+        assert_eq!(ExitPoll::new(&[], "ae-exe"), ExitPoll::Free);
+        // Other executables were started in the cell since
+        assert_eq!(
+            ExitPoll::new(
+                &[status("ae-exe", 0), status("ae-other", 43)],
+                "ae-exe"
+            ),
+            ExitPoll::KeepForOtherExecutables
+        );
+        assert_eq!(
+            ExitPoll::new(&[status("ae-other", 0)], "ae-exe"),
+            ExitPoll::KeepForOtherExecutables
+        );
+    }
+
+    fn start_request(name: &str, command: &str) -> CellServiceStartRequest {
+        CellServiceStartRequest {
+            executable: Some(Executable {
+                name: name.into(),
+                command: command.into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_start_waits_for_ready_port() {
+        let service = test_cell_service();
+
+        // find a free port, and only listen on it once the executable is started
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind listener")
+            .local_addr()
+            .expect("local addr")
+            .port();
+
+        let mut request = start_request("ae-test-ready-port", "sleep 10");
+        request.ready_tcp_port = Some(port.into());
+        let request = ValidatedCellServiceStartRequest::validate(request, None)
+            .expect("valid request");
+        let started = tokio::spawn({
+            let service = service.clone();
+            async move { service.start(request).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!started.is_finished());
+
+        let _listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .expect("bind listener");
+        let _ = started.await.expect("join").expect("start");
+
+        let name = ExecutableName::validate(
+            Some("ae-test-ready-port".into()),
+            "name",
+            None,
+        )
+        .expect("valid name");
+        let _ = service
+            .executables
+            .lock()
+            .await
+            .stop(&name, 0, StopPolicy::KILL)
+            .await
+            .expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_start_fails_if_executable_exits_before_ready_port() {
+        let service = test_cell_service();
+
+        let mut request = start_request("ae-test-ready-port-exits", "exit 1");
+        // nothing listens on a port we just released
+        request.ready_tcp_port = Some(
+            TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind listener")
+                .local_addr()
+                .expect("local addr")
+                .port()
+                .into(),
+        );
+        let request = ValidatedCellServiceStartRequest::validate(request, None)
+            .expect("valid request");
+
+        let e = service.start(request).await.expect_err("exited");
+        assert_eq!(e.code(), Code::Aborted);
+        assert!(service.executables.lock().await.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_log_streams_receive_the_same_lines() {
+        let service = test_cell_service();
+
+        let request = ValidatedCellServiceStartRequest::validate(
+            start_request(
+                "ae-test-log-stream",
+                "sleep 0.2; for i in 1 2 3; do echo line $i; done",
+            ),
+            None,
+        )
+        .expect("valid request");
+        let _ = service.start(request).await.expect("start");
+
+        let log_stream = || async {
+            let request = ValidatedCellServiceLogStreamRequest::validate(
+                CellServiceLogStreamRequest {
+                    cell_name: String::new(),
+                    executable_name: "ae-test-log-stream".into(),
+                },
+                None,
+            )
+            .expect("valid request");
+            let lines: Vec<_> = service
+                .log_stream(request)
+                .await
+                .expect("log stream")
+                .map(|line| line.expect("line").line)
+                .collect()
+                .await;
+            lines
+        };
+
+        // the streams end once the executable exits
+        let (first, second) = tokio::join!(log_stream(), log_stream());
+        assert_eq!(first, ["line 1", "line 2", "line 3"]);
+        assert_eq!(first, second);
+
+        let name = ExecutableName::validate(
+            Some("ae-test-log-stream".into()),
+            "name",
+            None,
+        )
+        .expect("valid name");
+        let _ = service
+            .executables
+            .lock()
+            .await
+            .stop(&name, 0, StopPolicy::KILL)
+            .await
+            .expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_dependent_start_waits_for_dependency_to_be_ready() {
+        let service = test_cell_service();
+
+        let mut dependent = start_request("ae-test-dependent", "sleep 10");
+        dependent.depends_on = vec!["ae-test-dependency".into()];
+        dependent.wait_for_ready = true;
+        let dependent =
+            ValidatedCellServiceStartRequest::validate(dependent, None)
+                .expect("valid request");
+        let started_dependent = tokio::spawn({
+            let service = service.clone();
+            async move { service.start(dependent).await }
+        });
+
+        // the dependency isn't started yet
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!started_dependent.is_finished());
+
+        let mut dependency = start_request(
+            "ae-test-dependency",
+            "sleep 1; echo ready; sleep 10",
+        );
+        dependency.ready_log_pattern = "ready".into();
+        let dependency =
+            ValidatedCellServiceStartRequest::validate(dependency, None)
+                .expect("valid request");
+        let started_dependency = tokio::spawn({
+            let service = service.clone();
+            async move { service.start(dependency).await }
+        });
+
+        // the dependency is started, but not ready yet
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!started_dependent.is_finished());
+
+        let _ = started_dependency.await.expect("join").expect("start");
+        let _ = started_dependent.await.expect("join").expect("start");
+
+        let mut executables = service.executables.lock().await;
+        for name in ["ae-test-dependent", "ae-test-dependency"] {
+            let name =
+                ExecutableName::validate(Some(name.into()), "name", None)
+                    .expect("valid name");
+            let _ = executables
+                .stop(&name, 0, StopPolicy::KILL)
+                .await
+                .expect("stop");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restart_reuses_the_original_command_and_env() {
+        let service = test_cell_service();
+
+        let mut request =
+            start_request("ae-test-restart", "echo \"$GREETING\"; sleep 60");
+        let executable = request.executable.as_mut().expect("executable");
+        let _ = executable.env.insert("GREETING".into(), "hello".into());
+        executable.output_tail_capacity = 10;
+        request.ready_log_pattern = "hello".into();
+
+        let stop_request = || CellServiceStopRequest {
+            executable_name: "ae-test-restart".into(),
+            return_output_tail: 10,
+            signal: "SIGKILL".into(),
+            ..Default::default()
+        };
+        let output = |response: CellServiceStopResponse| -> Vec<String> {
+            response.output_tail.into_iter().map(|line| line.line).collect()
+        };
+
+        let _ = cell_service_server::CellService::start(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect("start");
+        let stopped = cell_service_server::CellService::stop(
+            &service,
+            Request::new(stop_request()),
+        )
+        .await
+        .expect("stop");
+        assert_eq!(output(stopped.into_inner()), vec!["hello"]);
+
+        let restarted = cell_service_server::CellService::restart(
+            &service,
+            Request::new(CellServiceRestartRequest {
+                cell_name: String::new(),
+                executable_name: "ae-test-restart".into(),
+            }),
+        )
+        .await
+        .expect("restart");
+        assert!(restarted.into_inner().pid > 0);
+
+        let stopped = cell_service_server::CellService::stop(
+            &service,
+            Request::new(stop_request()),
+        )
+        .await
+        .expect("stop");
+        assert_eq!(output(stopped.into_inner()), vec!["hello"]);
+    }
+
+    #[tokio::test]
+    async fn test_restarts_are_counted() {
+        let service = test_cell_service();
+
+        let _ = cell_service_server::CellService::start(
+            &service,
+            Request::new(start_request("ae-test-restart-count", "sleep 60")),
+        )
+        .await
+        .expect("start");
+
+        let status = || async {
+            cell_service_server::CellService::list_executables(
+                &service,
+                Request::new(CellServiceListExecutablesRequest {
+                    cell_name: String::new(),
+                }),
+            )
+            .await
+            .expect("list executables")
+            .into_inner()
+            .executables
+            .remove(0)
+        };
+        assert_eq!(status().await.restart_count, 0);
+
+        for restart_count in 1..=5 {
+            let _ = cell_service_server::CellService::stop(
+                &service,
+                Request::new(CellServiceStopRequest {
+                    executable_name: "ae-test-restart-count".into(),
+                    signal: "SIGKILL".into(),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .expect("stop");
+            let _ = cell_service_server::CellService::restart(
+                &service,
+                Request::new(CellServiceRestartRequest {
+                    cell_name: String::new(),
+                    executable_name: "ae-test-restart-count".into(),
+                }),
+            )
+            .await
+            .expect("restart");
+
+            let status = status().await;
+            assert_eq!(status.restart_count, restart_count);
+            assert!(status.last_restart_at > 0);
+            assert_eq!(status.last_exit_reason, "signal: 9 (SIGKILL)");
+            // the crash loop threshold is 5 restarts
+            assert_eq!(status.crash_looping, restart_count == 5);
+        }
+
+        let _ = service
+            .executables
+            .lock()
+            .await
+            .stop(
+                &ExecutableName::validate(
+                    Some("ae-test-restart-count".into()),
+                    "name",
+                    None,
+                )
+                .expect("valid name"),
+                0,
+                StopPolicy::KILL,
+            )
+            .await
+            .expect("stop");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_start_times_out_in_slow_pre_exec_hook() {
+        let mut service = test_cell_service();
+        service.pre_exec_delay = Some(Duration::from_secs(60));
+
+        let started_at = Instant::now();
+        let mut request = start_request("ae-slow-pre-exec", "true");
+        request.start_timeout_ms = 100;
+        let e = cell_service_server::CellService::start(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect_err("timed out");
+
+        assert_eq!(e.code(), Code::DeadlineExceeded);
+        assert!(started_at.elapsed() < Duration::from_secs(30));
+        let name = ExecutableName::validate(
+            Some("ae-slow-pre-exec".into()),
+            "name",
+            None,
+        )
+        .expect("valid name");
+        assert!(service.executables.lock().await.get(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restart_requires_a_previous_start() {
+        let service = test_cell_service();
+
+        let e = cell_service_server::CellService::restart(
+            &service,
+            Request::new(CellServiceRestartRequest {
+                cell_name: String::new(),
+                executable_name: "ae-test-never-started".into(),
+            }),
+        )
+        .await
+        .expect_err("restart without a start");
+
+        assert_eq!(e.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_start_validate_only_spawns_nothing() {
+        let service = test_cell_service();
+
+        let mut request = start_request("ae-test-validate-only", "sleep 60");
+        request.validate_only = true;
+        let response = cell_service_server::CellService::start(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect("validate only start")
+        .into_inner();
+
+        assert_eq!(response.pid, 0);
+        let plan = response.plan.expect("plan");
+        assert!(!plan.program.is_empty());
+        assert!(plan.cell_name.is_empty());
+
+        // nothing was spawned...
+        let e = cell_service_server::CellService::stop(
+            &service,
+            Request::new(CellServiceStopRequest {
+                executable_name: "ae-test-validate-only".into(),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("stop");
+        assert_eq!(e.code(), Code::NotFound);
+
+        // ...nor kept to be restarted
+        let e = cell_service_server::CellService::restart(
+            &service,
+            Request::new(CellServiceRestartRequest {
+                cell_name: String::new(),
+                executable_name: "ae-test-validate-only".into(),
+            }),
+        )
+        .await
+        .expect_err("restart");
+        assert_eq!(e.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_start_validate_only_does_not_allocate_the_cell() {
+        let service = test_cell_service();
+
+        let cell_name = format!("ae-test-{}", uuid::Uuid::new_v4());
+        let mut request = start_request("ae-test-validate-only", "sleep 60");
+        request.cell_name = cell_name.clone();
+        request.validate_only = true;
+        let e = cell_service_server::CellService::start(
+            &service,
+            Request::new(request),
+        )
+        .await
+        .expect_err("start in a cell that was never allocated");
+        assert_eq!(e.code(), Code::NotFound);
+
+        let listed = cell_service_server::CellService::list(
+            &service,
+            Request::new(CellServiceListRequest::default()),
+        )
+        .await
+        .expect("list")
+        .into_inner();
+        assert!(listed.cells.iter().all(|cell| cell.cell_name != cell_name));
+    }
+
+    #[tokio::test]
+    async fn test_list_fds_is_admin_only() {
+        let mut config = ReloadableConfig::default();
+        let service = test_cell_service_with_config(config.clone());
+
+        let request = ValidatedCellServiceStartRequest::validate(
+            start_request("ae-test-fds", "sleep 10"),
+            None,
+        )
+        .expect("valid request");
+        let _ = service.start(request).await.expect("start");
+
+        let list_fds = |service: CellService| async move {
+            cell_service_server::CellService::list_fds(
+                &service,
+                Request::new(CellServiceListFdsRequest {
+                    cell_name: "".into(),
+                    executable_name: "ae-test-fds".into(),
+                }),
+            )
+            .await
+        };
+
+        let e = list_fds(service.clone()).await.expect_err("not an admin");
+        assert_eq!(e.code(), Code::PermissionDenied);
+
+        config.admin.trust_all_clients = true;
+        *service.config.write().await = config;
+        let fds =
+            list_fds(service.clone()).await.expect("list fds").into_inner().fds;
+
+        // stdout and stderr are piped to auraed
+        for fd in [1, 2] {
+            assert!(fds
+                .iter()
+                .any(|x| x.fd == fd && x.target.starts_with("pipe:")));
+        }
+
+        let name =
+            ExecutableName::validate(Some("ae-test-fds".into()), "name", None)
+                .expect("valid name");
+        let _ = service
+            .executables
+            .lock()
+            .await
+            .stop(&name, 0, StopPolicy::KILL)
+            .await
+            .expect("stop");
+    }
+
+    /// A nested auraed that records the metadata of the requests it receives.
+    #[derive(Debug, Clone, Default)]
+    struct MockNestedAuraed {
+        metadata: Arc<std::sync::Mutex<Vec<MetadataMap>>>,
+    }
+
+    #[tonic::async_trait]
+    impl cell_service_server::CellService for MockNestedAuraed {
+        async fn allocate(
+            &self,
+            request: Request<CellServiceAllocateRequest>,
+        ) -> std::result::Result<Response<CellServiceAllocateResponse>, Status>
+        {
+            self.metadata
+                .lock()
+                .expect("lock")
+                .push(request.metadata().clone());
+            Ok(Response::new(CellServiceAllocateResponse::default()))
+        }
+
+        async fn update(
+            &self,
+            _request: Request<CellServiceUpdateRequest>,
+        ) -> std::result::Result<Response<CellServiceUpdateResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn free(
+            &self,
+            _request: Request<CellServiceFreeRequest>,
+        ) -> std::result::Result<Response<CellServiceFreeResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn start(
+            &self,
+            _request: Request<CellServiceStartRequest>,
+        ) -> std::result::Result<Response<CellServiceStartResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn stop(
+            &self,
+            _request: Request<CellServiceStopRequest>,
+        ) -> std::result::Result<Response<CellServiceStopResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn restart(
+            &self,
+            _request: Request<CellServiceRestartRequest>,
+        ) -> std::result::Result<Response<CellServiceRestartResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn stop_generation(
+            &self,
+            _request: Request<CellServiceStopGenerationRequest>,
+        ) -> std::result::Result<
+            Response<CellServiceStopGenerationResponse>,
+            Status,
+        > {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn run(
+            &self,
+            _request: Request<CellServiceRunRequest>,
+        ) -> std::result::Result<Response<CellServiceRunResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn free_by_selector(
+            &self,
+            _request: Request<CellServiceFreeBySelectorRequest>,
+        ) -> std::result::Result<
+            Response<CellServiceFreeBySelectorResponse>,
+            Status,
+        > {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn get_cell_by_tid(
+            &self,
+            _request: Request<CellServiceGetCellByTidRequest>,
+        ) -> std::result::Result<
+            Response<CellServiceGetCellByTidResponse>,
+            Status,
+        > {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn describe(
+            &self,
+            _request: Request<CellServiceDescribeRequest>,
+        ) -> std::result::Result<Response<CellServiceDescribeResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn stat(
+            &self,
+            _request: Request<CellServiceStatRequest>,
+        ) -> std::result::Result<Response<CellServiceStatResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn drain(
+            &self,
+            _request: Request<CellServiceDrainRequest>,
+        ) -> std::result::Result<Response<CellServiceDrainResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn freeze(
+            &self,
+            _request: Request<CellServiceFreezeRequest>,
+        ) -> std::result::Result<Response<CellServiceFreezeResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn thaw(
+            &self,
+            _request: Request<CellServiceThawRequest>,
+        ) -> std::result::Result<Response<CellServiceThawResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn list(
+            &self,
+            _request: Request<CellServiceListRequest>,
+        ) -> std::result::Result<Response<CellServiceListResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn list_executables(
+            &self,
+            _request: Request<CellServiceListExecutablesRequest>,
+        ) -> std::result::Result<
+            Response<CellServiceListExecutablesResponse>,
+            Status,
+        > {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn list_fds(
+            &self,
+            _request: Request<CellServiceListFdsRequest>,
+        ) -> std::result::Result<Response<CellServiceListFdsResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn commitment(
+            &self,
+            _request: Request<CellServiceCommitmentRequest>,
+        ) -> std::result::Result<Response<CellServiceCommitmentResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn retry_config(
+            &self,
+            _request: Request<CellServiceRetryConfigRequest>,
+        ) -> std::result::Result<Response<CellServiceRetryConfigResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        async fn list_orphans(
+            &self,
+            _request: Request<CellServiceListOrphansRequest>,
+        ) -> std::result::Result<Response<CellServiceListOrphansResponse>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+
+        type LogStreamStream = LogStream;
+
+        async fn log_stream(
+            &self,
+            _request: Request<CellServiceLogStreamRequest>,
+        ) -> std::result::Result<Response<Self::LogStreamStream>, Status>
+        {
+            Err(Status::unimplemented("mock"))
+        }
+    }
+
+    /// Serves a [MockNestedAuraed] in place of the nested auraed of the allocated cell,
+    /// with the certs the nested auraed is served with.
+    async fn serve_mock_nested_auraed(
+        service: &CellService,
+        cell_name: &str,
+    ) -> MockNestedAuraed {
+        let pki = Path::new("/etc/aurae/pki");
+        let identity = Identity::from_pem(
+            std::fs::read(pki.join("_signed.server.crt")).expect("server crt"),
+            std::fs::read(pki.join("server.key")).expect("server key"),
+        );
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+
+        let nested = MockNestedAuraed::default();
+        let _server = tokio::spawn(
+            Server::builder()
+                .tls_config(ServerTlsConfig::new().identity(identity))
+                .expect("tls config")
+                .add_service(cell_service_server::CellServiceServer::new(
+                    nested.clone(),
+                ))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        service
+            .cells
+            .lock()
+            .await
+            .set_socket_for_tests(
+                &CellName::from(cell_name),
+                format!("https://{addr}"),
+            )
+            .expect("cell is allocated");
+
+        nested
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_request_carries_trace_header() {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+
+        let nested = MockNestedAuraed::default();
+        let _server = tokio::spawn(
+            Server::builder()
+                .add_service(cell_service_server::CellServiceServer::new(
+                    nested.clone(),
+                ))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let traceparent =
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let mut metadata = MetadataMap::new();
+        let _ = metadata
+            .insert("traceparent", traceparent.parse().expect("valid value"));
+        let _ =
+            metadata.insert("grpc-encoding", "gzip".parse().expect("valid"));
+
+        let mut client =
+            CellServiceGrpcClient::connect(format!("http://{addr}"))
+                .await
+                .expect("connect");
+        let _ = client
+            .allocate(forwarded_request(
+                &metadata,
+                CellServiceAllocateRequest::default(),
+            ))
+            .await
+            .expect("allocate");
+
+        let received = nested.metadata.lock().expect("lock");
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].get("traceparent").expect("forwarded"),
+            traceparent
+        );
+        assert!(received[0].get("grpc-encoding").is_none());
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_allocate_in_cell_forwards_trace_header() {
+        let service = test_cell_service();
+
+        let cell_name = format!("ae-test-{}", uuid::Uuid::new_v4());
+        let _ = cell_service_server::CellService::allocate(
+            &service,
+            Request::new(CellServiceAllocateRequest {
+                cell: Some(Cell {
+                    name: cell_name.clone(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("allocate");
+
+        let nested = serve_mock_nested_auraed(&service, &cell_name).await;
+
+        let traceparent =
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let mut request = Request::new(CellServiceAllocateRequest {
+            cell: Some(Cell {
+                name: format!("{cell_name}/ae-test-child"),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let _ = request
+            .metadata_mut()
+            .insert("traceparent", traceparent.parse().expect("valid value"));
+        let _ = cell_service_server::CellService::allocate(&service, request)
+            .await
+            .expect("allocate in cell");
+
+        let received = nested.metadata.lock().expect("lock").clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].get("traceparent").expect("forwarded"),
+            traceparent
+        );
+
+        let _ = cell_service_server::CellService::free(
+            &service,
+            Request::new(CellServiceFreeRequest {
+                cell_name,
+                return_final_stats: false,
+                children_policy: 0,
+            }),
+        )
+        .await
+        .expect("free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_list_marks_cell_with_failing_nested_list_unreachable() {
+        let service = test_cell_service();
+
+        let cell_name = format!("ae-test-{}", uuid::Uuid::new_v4());
+        let request = ValidatedCellServiceAllocateRequest::validate(
+            CellServiceAllocateRequest {
+                cell: Some(Cell {
+                    name: cell_name.clone(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .expect("valid request");
+        let _ = service.allocate(request).await.expect("allocate");
+
+        // the mock fails every list
+        let _nested = serve_mock_nested_auraed(&service, &cell_name).await;
+
+        let request = ValidatedCellServiceListRequest::validate(
+            CellServiceListRequest {
+                cell_name: String::new(),
+                include_stats: false,
+                recursive: true,
+            },
+            None,
+        )
+        .expect("valid request");
+        let response = service
+            .list(request, &MetadataMap::new())
+            .await
+            .expect("list despite the failing nested auraed");
+        let cell = response
+            .cells
+            .iter()
+            .find(|cell| cell.cell_name == cell_name)
+            .expect("cell is listed");
+        assert!(cell.unreachable);
+        assert!(!cell.stale);
+        assert!(!response
+            .cells
+            .iter()
+            .any(|cell| cell.cell_name.starts_with(&format!("{cell_name}/"))));
+
+        let request = ValidatedCellServiceFreeRequest::validate(
+            CellServiceFreeRequest {
+                cell_name,
+                return_final_stats: false,
+                children_policy: 0,
+            },
+            None,
+        )
+        .expect("valid request");
+        let _ = service.free(request).await.expect("free");
+    }
 }
@@ -29,13 +29,21 @@
 \* -------------------------------------------------------------------------- */
 
 use super::{
-    cgroups::Cgroup, nested_auraed::NestedAuraed, CellName, CellSpec,
-    CellsError, Result,
+    cgroups::{
+        is_controller_delegation_blocked, is_nesting_limit_reached, Cgroup,
+        CgroupControllers, CgroupStats, FreezeState, FrozenSnapshot,
+        KillEscalation,
+    },
+    namespaces,
+    nested_auraed::NestedAuraed,
+    CellName, CellSpec, CellStatus, CellsError, CgroupSpec, Namespace, Result,
 };
 use aurae_client::AuraeConfig;
+use std::collections::HashMap;
 use std::io;
+use std::path::PathBuf;
 use std::process::ExitStatus;
-use tracing::info;
+use tracing::{info, warn};
 
 // We should not be able to change a cell after it has been created.
 // You must free the cell and create a new one if you want to change anything about the cell.
@@ -69,22 +77,218 @@ impl Cell {
             return Ok(());
         };
 
-        let mut auraed =
-            NestedAuraed::new(&self.name, self.spec.iso_ctl.clone()).map_err(
-                |e| CellsError::FailedToAllocateCell {
-                    cell_name: self.name.clone(),
-                    source: e,
-                },
-            )?;
+        self.check_swappiness(&self.spec.cgroup_spec)?;
+        self.check_client_credentials()?;
+
+        let auraed = NestedAuraed::new(
+            &self.name,
+            self.spec.iso_ctl.clone(),
+            self.spec.client_credentials.as_ref(),
+        )
+        .map_err(|e| CellsError::FailedToAllocateCell {
+            cell_name: self.name.clone(),
+            source: e,
+        })?;
 
         let pid = auraed.pid();
 
-        let cgroup: Cgroup =
-            Cgroup::new(self.name.clone(), self.spec.cgroup_spec.clone());
+        // Until the cell is allocated, the nested auraed is killed and the cgroup
+        // deleted on error
+        let mut allocation = Allocation { auraed: Some(auraed), cgroup: None };
+
+        let cgroup =
+            Cgroup::new(self.name.clone(), self.spec.cgroup_spec.clone())
+                .map_err(|e| {
+                    let cell_name = self.name.clone();
+                    if is_nesting_limit_reached(&e) {
+                        CellsError::NestingLimitReached { cell_name }
+                    } else if is_controller_delegation_blocked(&e) {
+                        CellsError::ControllerDelegationBlocked { cell_name }
+                    } else {
+                        CellsError::AbortedAllocateCell { cell_name, source: e }
+                    }
+                })?;
+        let cgroup = allocation.cgroup.insert(cgroup);
+
+        Cgroup::set_uclamp(&self.name, &self.spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToSetUclamp {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        Cgroup::set_cpu_burst(&self.name, &self.spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToSetCpuBurst {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        Cgroup::set_zswap_max(&self.name, &self.spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToSetZswapMax {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        Cgroup::set_io(&self.name, &self.spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToSetIo {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        Cgroup::set_memory_min(&self.name, &self.spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToSetMemoryMin {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        Cgroup::set_nesting_limits(&self.name, &self.spec.nesting_limits)
+            .map_err(|source| CellsError::FailedToSetNestingLimits {
+                cell_name: self.name.clone(),
+                source,
+            })?;
+
+        self.delegate_cgroup()?;
+
+        cgroup.add_task_by_tgid((pid.as_raw() as u64).into()).map_err(
+            |source| CellsError::AbortedAllocateCell {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )?;
+
+        info!(
+            "Attach nested Auraed pid {} to cgroup {}",
+            pid.clone(),
+            self.name.clone()
+        );
+
+        let (auraed, cgroup) = allocation.into_allocated();
+        self.state = CellState::Allocated { cgroup, nested_auraed: auraed };
+
+        Ok(())
+    }
+
+    /// Applies the controller values set in `cgroup_spec` to the cgroup of the allocated
+    /// [Cell] in place, and records them in the [CellSpec].
+    /// If the update fails, the cgroup is rolled back as far as possible (see [Cgroup::update])
+    /// and the [CellSpec] is left unchanged.
+    pub fn update(&mut self, cgroup_spec: CgroupSpec) -> Result<()> {
+        let CellState::Allocated { .. } = &self.state else {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        };
+
+        self.check_swappiness(&cgroup_spec)?;
+
+        Cgroup::update(&self.name, &cgroup_spec).map_err(|source| {
+            CellsError::FailedToUpdateCell {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })?;
+
+        let CgroupSpec { cpu, cpuset, memory, io, pids } = cgroup_spec;
+        if cpu.is_some() {
+            self.spec.cgroup_spec.cpu = cpu;
+        }
+        if cpuset.is_some() {
+            self.spec.cgroup_spec.cpuset = cpuset;
+        }
+        if memory.is_some() {
+            self.spec.cgroup_spec.memory = memory;
+        }
+        if io.is_some() {
+            self.spec.cgroup_spec.io = io;
+        }
+        if pids.is_some() {
+            self.spec.cgroup_spec.pids = pids;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if `cgroup_spec` sets a swappiness, which cgroup v2 does not have
+    /// (see [Cgroup::check_swappiness]), rather than leaving it unset.
+    fn check_swappiness(&self, cgroup_spec: &CgroupSpec) -> Result<()> {
+        Cgroup::check_swappiness(cgroup_spec).map_err(|source| {
+            CellsError::SwappinessUnsupported {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns an error if the [Cell] has client credentials auraed can't read, as its
+    /// nested auraed would never be reachable with them.
+    fn check_client_credentials(&self) -> Result<()> {
+        let Some(credentials) = &self.spec.client_credentials else {
+            return Ok(());
+        };
+
+        credentials.check_readable().map_err(|source| {
+            CellsError::ClientCredentialsUnreadable {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Hands the cgroup of the [Cell] over to its root, if it has a user namespace
+    /// (see [Cgroup::delegate]). Does nothing otherwise.
+    fn delegate_cgroup(&self) -> Result<()> {
+        let Some(root) = self.spec.iso_ctl.mapped_root() else {
+            return Ok(());
+        };
+
+        Cgroup::delegate(&self.name, root).map_err(|source| {
+            CellsError::FailedToDelegateCgroup {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Like [Cell::allocate], but places the [NestedAuraed] in the existing cgroup of the
+    /// [Cell] instead of creating it. Used to recover cells whose cgroup was left behind
+    /// (e.g., after auraed crashed). The caller is responsible for checking the cgroup
+    /// matches the [CellSpec].
+    /// Does nothing if [Cell] has been previously allocated.
+    pub fn adopt(&mut self) -> Result<()> {
+        let CellState::Unallocated = &self.state else {
+            return Ok(());
+        };
+
+        self.check_swappiness(&self.spec.cgroup_spec)?;
+        self.check_client_credentials()?;
+
+        let mut auraed = NestedAuraed::new(
+            &self.name,
+            self.spec.iso_ctl.clone(),
+            self.spec.client_credentials.as_ref(),
+        )
+        .map_err(|e| CellsError::FailedToAllocateCell {
+            cell_name: self.name.clone(),
+            source: e,
+        })?;
+
+        let pid = auraed.pid();
+
+        let cgroup = Cgroup::load(self.name.clone());
+
+        if let Err(e) = self.delegate_cgroup() {
+            let _best_effort = auraed.kill();
+
+            return Err(e);
+        }
 
         if let Err(e) = cgroup.add_task_by_tgid((pid.as_raw() as u64).into()) {
+            // The cgroup isn't ours to delete, as we didn't create it
             let _best_effort = auraed.kill();
-            let _best_effort = cgroup.delete();
 
             return Err(CellsError::AbortedAllocateCell {
                 cell_name: self.name.clone(),
@@ -93,7 +297,7 @@ impl Cell {
         }
 
         info!(
-            "Attach nested Auraed pid {} to cgroup {}",
+            "Attach nested Auraed pid {} to existing cgroup {}",
             pid.clone(),
             self.name.clone()
         );
@@ -110,22 +314,54 @@ impl Cell {
         // TODO https://github.com/aurae-runtime/aurae/issues/199 &&
         //      aurae.io/signals, which is more accurate
         // TODO nested auraed should proxy (bus) POSIX signals to child executables
-        self.do_free(|nested_auraed| nested_auraed.shutdown())
+        self.do_free(|nested_auraed| nested_auraed.shutdown(), false)
+            .map(|_| ())
     }
 
-    /// Sends a [SIGKILL] to the [NestedAuraed], and deletes the underlying cgroup.
-    /// The [Cell::state] will be set to [CellState::Freed] regardless of it's state prior to this call.
+    /// Like [Cell::free], but also returns the final [CgroupStats] of the cell, read after
+    /// the [NestedAuraed] has exited and just before the cgroup is deleted.
+    /// Returns [None] if the cell was not allocated or the stats could not be read.
+    pub fn free_with_stats(&mut self) -> Result<Option<CgroupStats>> {
+        self.do_free(|nested_auraed| nested_auraed.shutdown(), true)
+    }
+
+    /// Sends a [SIGKILL] to the [NestedAuraed] and any process left in the cell,
+    /// and deletes the underlying cgroup once it is empty (see [KillEscalation]).
+    /// The [Cell::state] will be set to [CellState::Freed] regardless of it's state prior to this call,
+    /// unless processes are still left in the cell after the last attempt.
     /// A [Cell] should never be reused once in the [CellState::Freed] state.
-    pub fn kill(&mut self) -> Result<()> {
-        self.do_free(|nested_auraed| nested_auraed.kill())
+    pub fn kill(&mut self, escalation: KillEscalation) -> Result<()> {
+        let cell_name = self.name.clone();
+        self.do_free(
+            |nested_auraed| {
+                // The nested auraed is in the cgroup, so this also kills the processes that
+                // would outlive it (e.g., those that left its pid namespace).
+                // It is only reaped after, so that a failed attempt can be retried.
+                Cgroup::kill_remaining(&cell_name, escalation)?;
+                nested_auraed.kill()
+            },
+            false,
+        )
+        .map(|_| ())
     }
 
-    fn do_free<F>(&mut self, f: F) -> Result<()>
+    fn do_free<F>(
+        &mut self,
+        f: F,
+        read_stats: bool,
+    ) -> Result<Option<CgroupStats>>
     where
         F: Fn(&mut NestedAuraed) -> io::Result<ExitStatus>,
     {
+        let mut stats = None;
+
         if let CellState::Allocated { cgroup, nested_auraed } = &mut self.state
         {
+            // A frozen nested auraed can't shut down (see [Cell::drain])
+            if let Err(e) = Cgroup::thaw(&self.name) {
+                warn!("failed to thaw cell {}: {e}", self.name);
+            }
+
             let _exit_status = f(nested_auraed).map_err(|e| {
                 CellsError::FailedToKillCellChildren {
                     cell_name: self.name.clone(),
@@ -133,6 +369,20 @@ impl Cell {
                 }
             })?;
 
+            // The stats are gone once the cgroup is deleted
+            if read_stats {
+                stats = match Cgroup::stats(&self.name) {
+                    Ok(stats) => Some(stats),
+                    Err(e) => {
+                        warn!(
+                            "failed to read stats of cell {}: {e}",
+                            self.name
+                        );
+                        None
+                    }
+                };
+            }
+
             cgroup.delete().map_err(|e| CellsError::FailedToFreeCell {
                 cell_name: self.name.clone(),
                 source: e,
@@ -142,7 +392,7 @@ impl Cell {
         // set cell state to freed, independent of the current state
         self.state = CellState::Freed;
 
-        Ok(())
+        Ok(stats)
     }
 
     // NOTE: Having this function return the AuraeClient means we need to make it async,
@@ -157,11 +407,200 @@ impl Cell {
         Ok(nested_auraed.client_config.clone())
     }
 
+    /// Points the client of the [NestedAuraed] at `socket` instead, e.g., a mock.
+    #[cfg(test)]
+    pub(crate) fn set_socket_for_tests(
+        &mut self,
+        socket: String,
+    ) -> Result<()> {
+        let CellState::Allocated { nested_auraed, .. } = &mut self.state else {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            })
+        };
+
+        nested_auraed.client_config.system.socket = socket;
+        Ok(())
+    }
+
+    /// Returns the first lines the [NestedAuraed] wrote to its stderr.
+    pub fn nested_auraed_stderr(&self) -> Result<Vec<String>> {
+        let CellState::Allocated { nested_auraed, .. } = &self.state else {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            })
+        };
+
+        Ok(nested_auraed.early_stderr())
+    }
+
     /// Returns the [CellName] of the [Cell]
     pub fn name(&self) -> &CellName {
         &self.name
     }
 
+    /// Returns the [CellSpec] of the [Cell]
+    pub fn spec(&self) -> &CellSpec {
+        &self.spec
+    }
+
+    /// Returns the [Cgroup] of the [Cell], if it is allocated.
+    pub fn cgroup(&self) -> Option<&Cgroup> {
+        match &self.state {
+            CellState::Allocated { cgroup, .. } => Some(cgroup),
+            CellState::Unallocated | CellState::Freed => None,
+        }
+    }
+
+    /// Returns the path of the cgroup that processes of the [Cell] are placed in
+    pub fn cgroup_path(&self) -> PathBuf {
+        Cgroup::leaf_path(&self.name)
+    }
+
+    /// Returns the current resource usage of the [Cell], including its nested cells.
+    pub fn stats(&self) -> Result<CgroupStats> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::stats(&self.name).map_err(|source| {
+            CellsError::FailedToReadCgroupStats {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns the id of the cgroup that processes of the [Cell] are placed in
+    pub fn cgroup_id(&self) -> Result<u64> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::leaf_id(&self.name).map_err(|source| {
+            CellsError::FailedToReadCgroupId {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns the controllers of the cgroup of the [Cell] (see [Cgroup::controllers]).
+    pub fn cgroup_controllers(&self) -> Result<CgroupControllers> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::controllers(&self.name).map_err(|source| {
+            CellsError::FailedToReadCgroupControllers {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Freezes the processes of the [Cell], including its [NestedAuraed], and returns a
+    /// [FrozenSnapshot] of it. The [Cell] is left frozen until it is thawed with
+    /// [Cell::thaw], or freed.
+    pub fn drain(&self, include_memory_stat: bool) -> Result<FrozenSnapshot> {
+        self.freeze()?;
+
+        Cgroup::frozen_snapshot(&self.name, include_memory_stat).map_err(
+            |source| CellsError::FailedToSnapshotCell {
+                cell_name: self.name.clone(),
+                source,
+            },
+        )
+    }
+
+    /// Freezes the processes of the [Cell], including its [NestedAuraed] and the
+    /// processes of its nested cells. The [Cell] is left frozen until it is thawed
+    /// with [Cell::thaw], or freed.
+    pub fn freeze(&self) -> Result<()> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::freeze(&self.name).map_err(|source| {
+            CellsError::FailedToFreezeCell {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Thaws the processes of the [Cell] after [Cell::freeze] or [Cell::drain].
+    /// Does nothing if the [Cell] is not frozen.
+    pub fn thaw(&self) -> Result<()> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::thaw(&self.name).map_err(|source| {
+            CellsError::FailedToThawCell {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns whether the processes of the [Cell] are frozen (e.g., by [Cell::drain]).
+    pub fn freeze_state(&self) -> Result<FreezeState> {
+        if !matches!(self.state, CellState::Allocated { .. }) {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        }
+
+        Cgroup::freeze_state(&self.name).map_err(|source| {
+            CellsError::FailedToReadFreezeState {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns the namespaces of the [NestedAuraed], which the processes of the [Cell]
+    /// share, that differ from the namespaces of this auraed.
+    pub fn isolated_namespaces(&self) -> Result<Vec<Namespace>> {
+        let CellState::Allocated { nested_auraed, .. } = &self.state else {
+            return Err(CellsError::CellNotAllocated {
+                cell_name: self.name.clone(),
+            });
+        };
+
+        namespaces::isolated(nested_auraed.pid().as_raw()).map_err(|source| {
+            CellsError::FailedToReadNamespaces {
+                cell_name: self.name.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Returns the [CellStatus] of the [Cell]
+    pub fn status(&self) -> CellStatus {
+        match &self.state {
+            CellState::Unallocated => CellStatus::Unallocated,
+            CellState::Allocated { .. } => CellStatus::Allocated,
+            CellState::Freed => CellStatus::Freed,
+        }
+    }
+
+    /// Returns the labels the [Cell] was allocated with
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.spec.labels
+    }
+
     /// Returns [None] if the [Cell] is not allocated.
     pub fn v2(&self) -> Option<bool> {
         info!("{:?}", self);
@@ -172,13 +611,41 @@ impl Cell {
     }
 }
 
+/// The nested auraed and cgroup of a [Cell] being allocated (see [Cell::allocate]).
+/// When dropped before [Allocation::into_allocated], the nested auraed is killed and the
+/// cgroup deleted.
+#[derive(Debug)]
+struct Allocation {
+    auraed: Option<NestedAuraed>,
+    cgroup: Option<Cgroup>,
+}
+
+impl Allocation {
+    fn into_allocated(mut self) -> (NestedAuraed, Cgroup) {
+        let auraed = self.auraed.take().expect("nested auraed is spawned");
+        let cgroup = self.cgroup.take().expect("cgroup is created");
+        (auraed, cgroup)
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        if let Some(auraed) = &mut self.auraed {
+            let _best_effort = auraed.kill();
+        }
+        if let Some(cgroup) = &self.cgroup {
+            let _best_effort = cgroup.delete();
+        }
+    }
+}
+
 impl Drop for Cell {
     /// During normal behavior, cells are freed before being dropped,
     /// but cache reconciliation may result in a drop in other circumstances.
     /// Here we have a chance to clean up, no matter the circumstance.   
     fn drop(&mut self) {
         // We use kill here to be aggressive in cleaning up if anything has been left behind.
-        let _best_effort = self.kill();
+        let _best_effort = self.kill(KillEscalation::default());
     }
 }
 
@@ -204,4 +671,22 @@ mod tests {
         cell.allocate().expect("failed to allocate 2");
         assert!(matches!(cell.state, CellState::Freed));
     }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_cgroup_id() {
+        let cell_name = CellName::random_for_tests();
+        let mut cell = Cell::new(cell_name, CellSpec::new_for_tests());
+        assert!(matches!(
+            cell.cgroup_id(),
+            Err(CellsError::CellNotAllocated { .. })
+        ));
+
+        cell.allocate().expect("failed to allocate");
+        let id = cell.cgroup_id().expect("failed to read cgroup id");
+        assert_ne!(id, 0);
+
+        cell.free().expect("failed to free");
+    }
 }
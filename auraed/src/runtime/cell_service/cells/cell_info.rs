@@ -0,0 +1,70 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{CellName, CellStatus};
+
+/// What the cache of [super::Cells] knows about a cell, compared with the cgroup
+/// filesystem, as returned by [super::Cells::list].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellInfo {
+    pub name: CellName,
+    pub status: CellStatus,
+    /// Whether the cgroup of the cell exists when the cell was listed.
+    pub cgroup_exists: bool,
+}
+
+impl CellInfo {
+    /// Returns true if the cache and the cgroup filesystem disagree: an allocated cell
+    /// whose cgroup is gone (e.g., removed outside of auraed), or a cell that isn't
+    /// allocated but whose cgroup exists.
+    pub fn is_stale(&self) -> bool {
+        (self.status == CellStatus::Allocated) != self.cgroup_exists
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case(CellStatus::Allocated, true, false; "allocated with cgroup")]
+    #[test_case(CellStatus::Allocated, false, true; "allocated without cgroup")]
+    #[test_case(CellStatus::Unallocated, false, false; "unallocated without cgroup")]
+    #[test_case(CellStatus::Unallocated, true, true; "unallocated with cgroup")]
+    #[test]
+    fn test_is_stale(status: CellStatus, cgroup_exists: bool, expected: bool) {
+        let info = CellInfo {
+            name: CellName::random_for_tests(),
+            status,
+            cgroup_exists,
+        };
+        assert_eq!(info.is_stale(), expected);
+    }
+}
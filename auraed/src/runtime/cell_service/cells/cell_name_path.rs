@@ -35,7 +35,7 @@ use validation::{ValidatedField, ValidationError};
 
 pub const SEPARATOR: &str = "/";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CellNamePath {
     Empty,
     CellName(CellName),
@@ -68,6 +68,56 @@ impl CellNamePath {
             CellNamePath::Path(parts) => parts.into_iter().join(SEPARATOR),
         }
     }
+
+    /// Returns the [CellName]s of the path, from the outermost to the innermost cell.
+    pub fn segments(&self) -> Vec<&CellName> {
+        match self {
+            CellNamePath::Empty => vec![],
+            CellNamePath::CellName(cell_name) => vec![cell_name],
+            CellNamePath::Path(parts) => parts.iter().collect(),
+        }
+    }
+
+    /// Returns true if the cell is nested, at any depth, in the `other` cell.
+    /// Paths are compared by [CellName], so `foo/bar` is a descendant of `foo`, but not of `fo`.
+    /// Every cell is a descendant of [CellNamePath::Empty].
+    pub fn is_descendant_of(&self, other: &CellNamePath) -> bool {
+        let other_segments = other.segments();
+        self.segments().len() > other_segments.len()
+            && self.common_prefix(other).segments() == other_segments
+    }
+
+    /// Returns true if the `other` cell is nested, at any depth, in the cell.
+    pub fn is_ancestor_of(&self, other: &CellNamePath) -> bool {
+        other.is_descendant_of(self)
+    }
+
+    /// Returns the longest path that both paths start with.
+    pub fn common_prefix(&self, other: &CellNamePath) -> CellNamePath {
+        let parts: VecDeque<CellName> = self
+            .segments()
+            .into_iter()
+            .zip(other.segments())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.clone())
+            .collect();
+
+        Self::from_parts(parts)
+    }
+
+    fn from_parts(mut parts: VecDeque<CellName>) -> Self {
+        match parts.len() {
+            0 => Self::Empty,
+            1 => Self::CellName(parts.pop_front().expect("length is 1")),
+            _ => Self::Path(parts),
+        }
+    }
+}
+
+impl From<CellName> for CellNamePath {
+    fn from(cell_name: CellName) -> Self {
+        Self::CellName(cell_name)
+    }
 }
 
 impl ValidatedField<String> for CellNamePath {
@@ -108,3 +158,61 @@ impl ValidatedField<String> for CellNamePath {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    fn path(input: &str) -> CellNamePath {
+        CellNamePath::validate(Some(input.into()), "cell_name", None)
+            .expect("valid path")
+    }
+
+    #[test_case("foo/bar", "foo"; "child")]
+    #[test_case("foo/bar/baz", "foo"; "grandchild")]
+    #[test_case("foo/bar/baz", "foo/bar"; "nested child")]
+    #[test_case("foo", ""; "any cell of empty")]
+    #[test]
+    fn test_is_descendant(descendant: &str, ancestor: &str) {
+        let descendant = path(descendant);
+        let ancestor = path(ancestor);
+        assert!(descendant.is_descendant_of(&ancestor));
+        assert!(ancestor.is_ancestor_of(&descendant));
+    }
+
+    #[test_case("foo/bar", "fo"; "prefix but not segment boundary")]
+    #[test_case("foobar", "foo"; "single segment with prefix")]
+    #[test_case("foo/bar", "foo/ba"; "nested prefix but not segment boundary")]
+    #[test_case("foo", "foo"; "same cell")]
+    #[test_case("foo", "foo/bar"; "parent")]
+    #[test_case("bar/foo", "foo"; "same name at different depth")]
+    #[test_case("", ""; "empty")]
+    #[test]
+    fn test_is_not_descendant(descendant: &str, ancestor: &str) {
+        let descendant = path(descendant);
+        let ancestor = path(ancestor);
+        assert!(!descendant.is_descendant_of(&ancestor));
+        assert!(!ancestor.is_ancestor_of(&descendant));
+    }
+
+    #[test_case("foo/bar", "foo/baz", "foo"; "siblings")]
+    #[test_case("foo/bar/baz", "foo/bar", "foo/bar"; "ancestor")]
+    #[test_case("foo/bar", "fo/bar", ""; "prefix but not segment boundary")]
+    #[test_case("foo", "bar", ""; "unrelated")]
+    #[test_case("foo/bar", "foo/bar", "foo/bar"; "same path")]
+    #[test_case("", "foo", ""; "empty")]
+    #[test]
+    fn test_common_prefix(a: &str, b: &str, expected: &str) {
+        let (a, b, expected) = (path(a), path(b), path(expected));
+        assert_eq!(a.common_prefix(&b), expected);
+        assert_eq!(b.common_prefix(&a), expected);
+    }
+
+    #[test]
+    fn test_common_prefix_of_single_segment_is_cell_name() {
+        let prefix = path("foo/bar").common_prefix(&path("foo/baz"));
+        assert!(matches!(prefix, CellNamePath::CellName(_)));
+        assert_eq!(prefix.into_string(), "foo");
+    }
+}
@@ -28,22 +28,66 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-use super::{cgroups::Cgroup, Cell, CellName, CellSpec, CellsError, Result};
-use std::collections::HashMap;
+use super::{
+    cgroups::{
+        cpuset::NumaTopology, memory, Cgroup, CgroupStats, KillEscalation,
+        OrphanedCgroup,
+    },
+    Cell, CellInfo, CellName, CellSpec, CellsError, CellsSnapshot, CgroupSpec,
+    LabelSelector, Result,
+};
+use crate::runtime::cell_service::executables::{ExecutableName, Executables};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path},
+};
 use tracing::warn;
 
 type Cache = HashMap<CellName, Cell>;
 
+const PROC: &str = "/proc";
+
 /// The in-memory cache of cells ([Cell]) created with Aurae.
 #[derive(Debug, Default)]
 pub struct Cells {
     cache: Cache,
+    /// The cells being allocated outside of the lock (see [Cells::reserve]).
+    reserved: HashMap<CellName, CellSpec>,
+}
+
+/// What happens to the children of a cell when it is freed (see [Cells::free]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FreeChildrenPolicy {
+    /// The cell is not freed if it has children.
+    #[default]
+    Reject,
+    /// The processes of the children, and of their children, are killed and their
+    /// cgroups removed before the cell is freed.
+    Recursive,
+    /// The children are moved next to the cell, with their children, and are cached
+    /// as cells. Children that are still allocated (i.e., have processes) are not
+    /// orphaned, as only the nested auraed of the cell can reach theirs.
+    Orphan,
+}
+
+impl TryFrom<i32> for FreeChildrenPolicy {
+    type Error = ();
+
+    fn try_from(value: i32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Reject),
+            1 => Ok(Self::Recursive),
+            2 => Ok(Self::Orphan),
+            _ => Err(()),
+        }
+    }
 }
 
 // TODO: add to the impl
 // [x] Get Cgroup from cell_name
-// [ ] Get Cgroup from executable_name
-// [ ] Get Cgroup from pid
+// [x] Get Cgroup from executable_name (see [Cells::get_cgroup_by_executable])
+// [x] Get Cgroup from pid (see [Cells::get_cgroup_by_pid] and [Cells::get_by_tid])
 // [ ] Get Cgroup and pids from executable_name
 
 impl Cells {
@@ -58,6 +102,27 @@ impl Cells {
         cell_name: CellName,
         cell_spec: CellSpec,
     ) -> Result<&Cell> {
+        let mut cell = self.reserve(cell_name, cell_spec)?;
+        let allocated = cell.allocate();
+        self.insert_reserved(cell, allocated)
+    }
+
+    /// Returns a new [Cell] to be allocated with [Cell::allocate], then added to the
+    /// cache with [Cells::insert_reserved], which the lock on [Cells] doesn't need to be
+    /// held in between. Until then, allocating a cell with the same name fails.
+    ///
+    /// # Errors
+    /// * If cell exists or is being allocated -> [CellsError::CellExists]
+    /// * If a cell is not in cache but cgroup exists on fs -> [CellsError::CgroupIsNotACell]
+    pub fn reserve(
+        &mut self,
+        cell_name: CellName,
+        cell_spec: CellSpec,
+    ) -> Result<Cell> {
+        if self.reserved.contains_key(&cell_name) {
+            return Err(CellsError::CellExists { cell_name });
+        }
+
         if Cgroup::exists(&cell_name) {
             return if self.cache.contains_key(&cell_name) {
                 Err(CellsError::CellExists { cell_name })
@@ -76,30 +141,380 @@ impl Cells {
             warn!("Found cached cell ('{cell_name}') without cgroup. Did you forget to call free on the cell?");
         }
 
-        let cell = self
+        let _ = self.reserved.insert(cell_name.clone(), cell_spec.clone());
+        Ok(Cell::new(cell_name, cell_spec))
+    }
+
+    /// Releases the name of a [Cell] returned by [Cells::reserve], and adds it to the
+    /// cache if it was `allocated`.
+    ///
+    /// # Errors
+    /// * If the cell failed to allocate, the error of `allocated`
+    pub fn insert_reserved(
+        &mut self,
+        cell: Cell,
+        allocated: Result<()>,
+    ) -> Result<&Cell> {
+        let cell_name = cell.name().clone();
+        let _ = self.reserved.remove(&cell_name);
+        allocated?;
+
+        let _ = self.cache.insert(cell_name.clone(), cell);
+        self.rebalance_memory_shares();
+        Ok(&self.cache[&cell_name])
+    }
+
+    /// Like [Cells::allocate], but if a cgroup exists for the cell that is not in the cache,
+    /// and its controller values match the [CellSpec], the cgroup is adopted with
+    /// [Cell::adopt] instead of returning [CellsError::CgroupIsNotACell].
+    ///
+    /// # Errors
+    /// * If cell exists -> [CellsError::CellExists]
+    /// * If a cell is not in cache but a cgroup with different controller values exists on fs
+    ///   -> [CellsError::CgroupSpecMismatch]
+    /// * If cell fails to allocate (see [Cell::allocate] and [Cell::adopt])
+    pub fn allocate_or_adopt(
+        &mut self,
+        cell_name: CellName,
+        cell_spec: CellSpec,
+    ) -> Result<&Cell> {
+        if !self.is_adoptable(&cell_name) {
+            return self.allocate(cell_name, cell_spec);
+        }
+
+        let diff = Cgroup::diff(&cell_name, &cell_spec.cgroup_spec).map_err(
+            |source| CellsError::FailedToAllocateCell {
+                cell_name: cell_name.clone(),
+                source,
+            },
+        )?;
+
+        if !diff.is_empty() {
+            return Err(CellsError::CgroupSpecMismatch { cell_name, diff });
+        }
+
+        warn!("Adopting existing cgroup ('{cell_name}') that was not in the cache");
+
+        let mut cell = Cell::new(cell_name.clone(), cell_spec);
+        cell.adopt()?;
+
+        let _ = self.cache.insert(cell_name.clone(), cell);
+        self.rebalance_memory_shares();
+        Ok(&self.cache[&cell_name])
+    }
+
+    /// Returns true if a cgroup exists for the cell that is not in the cache, which
+    /// [Cells::allocate_or_adopt] adopts.
+    pub fn is_adoptable(&self, cell_name: &CellName) -> bool {
+        Cgroup::exists(cell_name) && !self.cache.contains_key(cell_name)
+    }
+
+    /// Returns an error if allocating `cell_name` would exceed `max_cells` cached
+    /// cells, counting those being allocated. A cell that is already cached doesn't
+    /// count against the limit.
+    ///
+    /// # Errors
+    /// * If the limit would be exceeded -> [CellsError::CellLimitReached]
+    pub fn check_cell_limit(
+        &self,
+        cell_name: &CellName,
+        max_cells: Option<usize>,
+    ) -> Result<()> {
+        match max_cells {
+            Some(max_cells)
+                if !self.cache.contains_key(cell_name)
+                    && self.cache.len() + self.reserved.len() >= max_cells =>
+            {
+                Err(CellsError::CellLimitReached {
+                    cell_name: cell_name.clone(),
+                    max_cells,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the memory pinned by the cached (or reserved) cells and
+    /// `cell_spec` would exceed `budget` (see [memory::pinned_over_budget]).
+    ///
+    /// # Errors
+    /// * If the budget would be exceeded -> [CellsError::PinnedMemoryBudgetExceeded]
+    pub fn check_pinned_memory(
+        &self,
+        cell_name: &CellName,
+        cell_spec: &CellSpec,
+        budget: Option<u64>,
+    ) -> Result<()> {
+        let pinned = |spec: &CellSpec| {
+            let memory = spec.cgroup_spec.memory.as_ref()?;
+            memory.pinned.as_deref().map(|pinned| *pinned as u64)
+        };
+
+        let Some(requested) = pinned(cell_spec) else {
+            return Ok(());
+        };
+
+        let cached = self
             .cache
-            .entry(cell_name.clone())
-            .or_insert_with(|| Cell::new(cell_name, cell_spec));
+            .values()
+            .map(|cell| (cell.name(), cell.spec()))
+            .chain(self.reserved.iter())
+            .filter(|(name, _)| *name != cell_name)
+            .filter_map(|(_, spec)| pinned(spec));
 
-        cell.allocate()?;
-        Ok(cell)
+        match memory::pinned_over_budget(budget, cached.chain([requested])) {
+            Some(over) => Err(CellsError::PinnedMemoryBudgetExceeded {
+                cell_name: cell_name.clone(),
+                over,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns an error if the `cpuset.mems` of `cell_spec` do not include the NUMA
+    /// nodes local to its `cpus` (see [NumaTopology::missing_local_nodes]), as the
+    /// memory of the cell would then be allocated on nodes remote to its cpus.
+    ///
+    /// # Errors
+    /// * If a local node is missing -> [CellsError::NumaLocalityMismatch]
+    pub fn check_numa_locality(
+        cell_name: &CellName,
+        cell_spec: &CellSpec,
+        topology: &NumaTopology,
+    ) -> Result<()> {
+        let Some(cpuset) = &cell_spec.cgroup_spec.cpuset else {
+            return Ok(());
+        };
+        let (Some(cpus), Some(mems)) = (&cpuset.cpus, &cpuset.mems) else {
+            return Ok(());
+        };
+
+        let missing_nodes = topology.missing_local_nodes(cpus, mems);
+        if missing_nodes.is_empty() {
+            return Ok(());
+        }
+
+        Err(CellsError::NumaLocalityMismatch {
+            cell_name: cell_name.clone(),
+            cpus: cpus.clone(),
+            mems: mems.clone(),
+            missing_nodes,
+        })
     }
 
     /// Calls [Cell::free] on a [Cell] and removes it from the cache.
+    /// The children of the [Cell] are handled according to `children_policy`, before
+    /// the [Cell] is freed (see [FreeChildrenPolicy]).
     ///
     /// # Errors
     /// * If cell is not cached and cgroup does not exist -> [CellsError::CellNotFound]
     /// * If cell is cached and cgroup does not exist -> [CellsError::CgroupNotFound]
     ///     - note: cell will be removed from cache
     /// * If cell is not cached and cgroup exists on fs -> [CellsError::CgroupIsNotACell]
+    /// * If cell has children and the policy is [FreeChildrenPolicy::Reject]
+    ///   -> [CellsError::CellHasChildren]
+    /// * If a child would be orphaned next to a cell or cgroup of the same name
+    ///   -> [CellsError::CellExists]
+    /// * If the children fail to be freed or orphaned -> [CellsError::FailedToFreeCellChildren]
     /// * If cell fails to free (see [Cell::free])
-    pub fn free(&mut self, cell_name: &CellName) -> Result<()> {
+    pub fn free(
+        &mut self,
+        cell_name: &CellName,
+        children_policy: FreeChildrenPolicy,
+    ) -> Result<()> {
+        self.do_free(cell_name, children_policy, |cell| cell.free())
+    }
+
+    /// Like [Cells::free], but calls [Cell::free_with_stats] to also return the final
+    /// stats of the [Cell].
+    pub fn free_with_stats(
+        &mut self,
+        cell_name: &CellName,
+        children_policy: FreeChildrenPolicy,
+    ) -> Result<Option<CgroupStats>> {
+        self.do_free(cell_name, children_policy, |cell| cell.free_with_stats())
+    }
+
+    fn do_free<F, R>(
+        &mut self,
+        cell_name: &CellName,
+        children_policy: FreeChildrenPolicy,
+        f: F,
+    ) -> Result<R>
+    where
+        F: FnOnce(&mut Cell) -> Result<R>,
+    {
         self.handle_cgroup_does_not_exist(cell_name)?;
-        self.get_mut(cell_name, |cell| cell.free())?;
+        if !self.cache.contains_key(cell_name) {
+            return Err(CellsError::CgroupIsNotACell {
+                cell_name: cell_name.clone(),
+            });
+        }
+
+        let orphans = self.handle_children(cell_name, children_policy)?;
+
+        // The orphans were moved out of the cell, so they are adopted even if it fails
+        let res = self.get_mut(cell_name, f);
+        self.adopt_orphans(orphans);
+        let res = res?;
+
         let _ = self.cache.remove(cell_name);
+        self.rebalance_memory_shares();
+        Ok(res)
+    }
+
+    /// Applies `children_policy` to the children of the cell, which are the cells its
+    /// nested auraed allocated. Returns the children that were orphaned.
+    fn handle_children(
+        &mut self,
+        cell_name: &CellName,
+        children_policy: FreeChildrenPolicy,
+    ) -> Result<Vec<CellName>> {
+        let failed = |source| CellsError::FailedToFreeCellChildren {
+            cell_name: cell_name.clone(),
+            source,
+        };
+
+        let children = Cgroup::children(cell_name).map_err(failed)?;
+        if children.is_empty() {
+            return Ok(vec![]);
+        }
+
+        match children_policy {
+            FreeChildrenPolicy::Reject => Err(CellsError::CellHasChildren {
+                cell_name: cell_name.clone(),
+                children,
+            }),
+            FreeChildrenPolicy::Recursive => {
+                Cgroup::free_children(cell_name, KillEscalation::default())
+                    .map_err(failed)?;
+                Ok(vec![])
+            }
+            FreeChildrenPolicy::Orphan => {
+                if let Some(child) = children.iter().find(|child| {
+                    self.cache.contains_key(*child) || Cgroup::exists(child)
+                }) {
+                    return Err(CellsError::CellExists {
+                        cell_name: child.clone(),
+                    });
+                }
+
+                // Adopting starts a nested auraed in the cgroup of the child, next to the
+                // one the nested auraed of the cell started, which we can't reach
+                for child in &children {
+                    if Cgroup::is_child_allocated(cell_name, child)
+                        .map_err(failed)?
+                    {
+                        return Err(CellsError::ChildIsAllocated {
+                            cell_name: cell_name.clone(),
+                            child: child.clone(),
+                        });
+                    }
+                }
+
+                let mut orphans = vec![];
+                for child in children {
+                    if let Err(e) = Cgroup::orphan_child(cell_name, &child) {
+                        self.adopt_orphans(orphans);
+                        return Err(failed(e));
+                    }
+                    orphans.push(child);
+                }
+
+                Ok(orphans)
+            }
+        }
+    }
+
+    /// Adds the orphaned children of a freed cell to the cache, with a new nested auraed
+    /// in their moved cgroup (see [Cell::adopt]).
+    fn adopt_orphans(&mut self, orphans: Vec<CellName>) {
+        for cell_name in orphans {
+            let mut cell = Cell::new(cell_name.clone(), CellSpec::orphaned());
+            if let Err(e) = cell.adopt() {
+                warn!("failed to adopt orphaned cell '{cell_name}': {e}");
+                continue;
+            }
+
+            let _ = self.cache.insert(cell_name, cell);
+        }
+    }
+
+    /// Calls [Cell::update] on the cached [Cell].
+    ///
+    /// # Errors
+    /// * If cell is not cached and cgroup does not exist -> [CellsError::CellNotFound]
+    /// * If cell is cached and cgroup does not exist -> [CellsError::CgroupNotFound]
+    /// * If cell is not cached and cgroup exists on fs -> [CellsError::CgroupIsNotACell]
+    /// * If cell fails to update (see [Cell::update])
+    pub fn update(
+        &mut self,
+        cell_name: &CellName,
+        cgroup_spec: CgroupSpec,
+    ) -> Result<()> {
+        self.get_mut(cell_name, |cell| cell.update(cgroup_spec))?;
+        self.rebalance_memory_shares();
         Ok(())
     }
 
+    /// Sets `memory.low` of the cached cells with memory shares, dividing the memory
+    /// available to the cells in proportion to their shares (see [memory::memory_lows]).
+    /// This is best-effort: failures are logged, as they don't affect the cells otherwise.
+    fn rebalance_memory_shares(&self) {
+        let (cell_names, shares): (Vec<&CellName>, Vec<u64>) = self
+            .cache
+            .values()
+            .filter(|cell| Cgroup::exists(cell.name()))
+            .filter_map(|cell| {
+                let memory = cell.spec().cgroup_spec.memory.as_ref()?;
+                Some((cell.name(), memory.shares.clone()?.into_inner()))
+            })
+            .unzip();
+
+        if shares.is_empty() {
+            return;
+        }
+
+        let available = match Cgroup::available_memory() {
+            Ok(available) => available,
+            Err(e) => {
+                warn!("failed to read memory available to cells: {e}");
+                return;
+            }
+        };
+
+        let lows = memory::memory_lows(available, &shares);
+        for (cell_name, low) in cell_names.into_iter().zip(lows) {
+            if let Err(e) = Cgroup::set_memory_low(cell_name, low) {
+                warn!("failed to set memory.low of cell '{cell_name}': {e}");
+            }
+        }
+    }
+
+    /// Calls [Cells::free] on every cached [Cell] whose labels match the [LabelSelector],
+    /// rejecting cells that have children (see [FreeChildrenPolicy::Reject]).
+    /// A failure to free one cell does not prevent the remaining cells from being freed.
+    /// Returns the result of freeing each matched cell.
+    pub fn free_by_selector(
+        &mut self,
+        selector: &LabelSelector,
+    ) -> Vec<(CellName, Result<()>)> {
+        let cell_names: Vec<CellName> = self
+            .cache
+            .values()
+            .filter(|cell| selector.matches(cell.labels()))
+            .map(|cell| cell.name().clone())
+            .collect();
+
+        cell_names
+            .into_iter()
+            .map(|cell_name| {
+                let res = self.free(&cell_name, FreeChildrenPolicy::Reject);
+                (cell_name, res)
+            })
+            .collect()
+    }
+
     pub fn get<F, R>(&mut self, cell_name: &CellName, f: F) -> Result<R>
     where
         F: Fn(&Cell) -> Result<R>,
@@ -119,6 +534,173 @@ impl Cells {
         res
     }
 
+    /// Returns a [CellsSnapshot] of the cached cells, which can be read without holding
+    /// the lock on [Cells].
+    pub fn snapshot(&self) -> CellsSnapshot {
+        CellsSnapshot::new(self.cache.values())
+    }
+
+    /// Returns the name of the [Cell] that the thread `tid` belongs to (see
+    /// [CellsSnapshot::get_by_tid]).
+    pub fn get_by_tid(&self, tid: i32) -> Result<CellName> {
+        self.snapshot().get_by_tid(tid)
+    }
+
+    /// Returns a [CellInfo] for every cached cell, ordered by [CellName].
+    /// Unlike [Cells::get], cells whose cgroup is gone are reported (see
+    /// [CellInfo::is_stale]) and left in the cache.
+    pub fn list(&self) -> Vec<CellInfo> {
+        let mut cells: Vec<_> = self
+            .cache
+            .values()
+            .map(|cell| CellInfo {
+                name: cell.name().clone(),
+                status: cell.status(),
+                cgroup_exists: Cgroup::exists(cell.name()),
+            })
+            .collect();
+
+        cells.sort_by(|a, b| a.name.cmp(&b.name));
+
+        cells
+    }
+
+    /// Returns the cgroups on fs that look like cells (have a leaf cgroup), but are not
+    /// cached, e.g. cells left behind by an auraed that exited without freeing them.
+    ///
+    /// # Errors
+    /// * If reading the cgroup hierarchy fails -> [CellsError::FailedToListOrphans]
+    pub fn list_orphans(&self) -> Result<Vec<OrphanedCgroup>> {
+        Cgroup::orphans(|cell_name| self.cache.contains_key(cell_name))
+            .map_err(|source| CellsError::FailedToListOrphans { source })
+    }
+
+    /// Returns the [Cgroup] of the cached cell that the process of the executable is in.
+    /// Processes of a nested cell are reported as belonging to the top level cell.
+    ///
+    /// Executables are started in the cgroup of the auraed that starts them, which is
+    /// not one of its cells, so the process of an executable is only found in a cell
+    /// if it was moved there after it started.
+    ///
+    /// # Errors
+    /// * If the executable isn't running, or its process isn't in a cell ->
+    ///   [CellsError::ExecutableNotInCell]
+    /// * If reading the pid of the executable or a cgroup fails ->
+    ///   [CellsError::FailedToFindExecutable]
+    pub fn get_cgroup_by_executable(
+        &self,
+        executables: &Executables,
+        executable_name: &ExecutableName,
+    ) -> Result<&Cgroup> {
+        let map_err = |source| CellsError::FailedToFindExecutable {
+            executable_name: executable_name.clone(),
+            source,
+        };
+
+        let pid = executables
+            .get(executable_name)
+            .map(|executable| executable.pid())
+            .transpose()
+            .map_err(map_err)?
+            .flatten()
+            .ok_or_else(|| CellsError::ExecutableNotInCell {
+                executable_name: executable_name.clone(),
+            })?;
+
+        for cell in self.cache.values() {
+            let Some(cgroup) = cell.cgroup() else {
+                continue;
+            };
+            let has_process = Cgroup::has_process(cell.name(), pid.as_raw())
+                .map_err(map_err)?;
+            if has_process {
+                return Ok(cgroup);
+            }
+        }
+
+        Err(CellsError::ExecutableNotInCell {
+            executable_name: executable_name.clone(),
+        })
+    }
+
+    /// Returns the cached [Cell] that the process (or thread) `pid` belongs to, from the
+    /// cgroup listed in `/proc/<pid>/cgroup`. Processes of a nested cell are reported as
+    /// belonging to the top level cell.
+    ///
+    /// The pid may have exited (and even been reused) by the time the result is used.
+    ///
+    /// # Errors
+    /// * If the process isn't in a cell -> [CellsError::CellNotFound], named by the
+    ///   top level cgroup of the process (e.g., "user.slice"), or "/" for the root cgroup
+    /// * If the process doesn't exist -> [CellsError::CellNotFound], with an empty name
+    /// * If reading procfs fails -> [CellsError::FailedToFindThread]
+    pub fn get_cgroup_by_pid(&self, pid: i32) -> Result<&Cell> {
+        self.get_cgroup_by_pid_in(Path::new(PROC), pid)
+    }
+
+    fn get_cgroup_by_pid_in(&self, proc: &Path, pid: i32) -> Result<&Cell> {
+        let path = proc.join(pid.to_string()).join("cgroup");
+        let cgroup = match fs::read_to_string(path) {
+            Ok(cgroup) => cgroup,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(CellsError::CellNotFound { cell_name: "".into() });
+            }
+            Err(source) => {
+                return Err(CellsError::FailedToFindThread { tid: pid, source })
+            }
+        };
+
+        // The cgroup v2 entry is the path of the cgroup relative to the root of our
+        // cgroup namespace, where the cgroups of cells are created (e.g., "0::/ae-1/_").
+        let path = cgroup
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .map(str::trim)
+            .unwrap_or_default();
+        let top_level =
+            Path::new(path).components().nth(1).and_then(|component| {
+                match component {
+                    Component::Normal(name) => name.to_str(),
+                    _ => None,
+                }
+            });
+
+        top_level
+            .and_then(|name| {
+                self.cache.values().find(|cell| &**cell.name() == name)
+            })
+            .ok_or_else(|| CellsError::CellNotFound {
+                cell_name: top_level.unwrap_or(path).into(),
+            })
+    }
+
+    /// Adds an unallocated [Cell] to the cache
+    #[cfg(test)]
+    pub(crate) fn insert_for_tests(
+        &mut self,
+        cell_name: CellName,
+        cell_spec: CellSpec,
+    ) {
+        let _ = self
+            .cache
+            .insert(cell_name.clone(), Cell::new(cell_name, cell_spec));
+    }
+
+    #[cfg(test)]
+    pub(crate) fn remove_for_tests(&mut self, cell_name: &CellName) {
+        let _ = self.cache.remove(cell_name);
+    }
+
+    /// Points the client of an allocated [Cell] at `socket` (see [Cell::set_socket_for_tests])
+    #[cfg(test)]
+    pub(crate) fn set_socket_for_tests(
+        &mut self,
+        cell_name: &CellName,
+        socket: String,
+    ) -> Result<()> {
+        self.get_mut(cell_name, |cell| cell.set_socket_for_tests(socket))
+    }
+
     fn get_mut<F, R>(&mut self, cell_name: &CellName, f: F) -> Result<R>
     where
         F: FnOnce(&mut Cell) -> Result<R>,
@@ -167,9 +749,15 @@ impl Cells {
         }
     }
 
+    /// Returns true if no cells are cached.
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
     /// Sends a [SIGKILL] to all Cells, ignoring any errors.
-    pub fn broadcast_kill(&mut self) {
-        let killed_cells = self.do_broadcast(|cell| cell.kill());
+    /// A cell with processes left after the last attempt of `escalation` stays in the cache.
+    pub fn broadcast_kill(&mut self, escalation: KillEscalation) {
+        let killed_cells = self.do_broadcast(|cell| cell.kill(escalation));
 
         for cell_name in killed_cells {
             let _ = self.cache.remove(&cell_name);
@@ -199,6 +787,13 @@ impl Cells {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::cell_service::cells::{
+        cgroups::{cpu::CpuController, FreezeState, Limit, Weight},
+        CellStatus, ClientCredentials,
+    };
+    use crate::runtime::cell_service::executables::StopPolicy;
+    use crate::runtime::cell_service::test_helpers::{sleep_spec, temp_path};
+    use std::os::unix::fs::MetadataExt;
 
     // Ignored: requires sudo, which we don't have in CI
     #[ignore]
@@ -252,33 +847,954 @@ mod tests {
     }
 
     #[test]
-    fn test_get_missing_errors() {
+    fn test_allocate_with_unreadable_client_credentials_is_error() {
         let mut cells = Cells::default();
-        assert!(cells.cache.is_empty());
+        let cell_name = CellName::random_for_tests();
+        let mut cell = CellSpec::new_for_tests();
+        let missing = temp_path("credentials");
+        cell.client_credentials = Some(ClientCredentials {
+            ca_crt: missing.join("ca.crt"),
+            client_crt: missing.join("client.crt"),
+            client_key: missing.join("client.key"),
+        });
 
-        let cell_name_in = CellName::random_for_tests();
+        assert!(matches!(
+            cells.allocate(cell_name.clone(), cell),
+            Err(CellsError::ClientCredentialsUnreadable { .. })
+        ));
+        // Checked before anything is created
+        assert!(!Cgroup::exists(&cell_name));
+    }
+
+    #[test]
+    fn test_get_by_tid_not_in_cell_is_error() {
+        let cells = Cells::default();
+        let tid = nix::unistd::gettid().as_raw();
 
         assert!(matches!(
-            cells.get(&cell_name_in, |_cell| Ok(())),
-            Err(CellsError::CellNotFound { cell_name }) if cell_name == cell_name_in
+            cells.get_by_tid(tid),
+            Err(CellsError::ThreadNotFound { tid: t }) if t == tid
         ));
     }
 
+    #[test]
+    fn test_list() {
+        let mut cells = Cells::default();
+        assert!(cells.list().is_empty());
+
+        let cell_name = CellName::random_for_tests();
+        cells.insert_for_tests(cell_name.clone(), CellSpec::new_for_tests());
+
+        assert_eq!(
+            cells.list(),
+            vec![CellInfo {
+                name: cell_name,
+                status: CellStatus::Unallocated,
+                cgroup_exists: false,
+            }]
+        );
+    }
+
     // Ignored: requires sudo, which we don't have in CI
     #[ignore]
     #[test]
-    fn test_free() {
+    fn test_list_reports_stale_cells() {
         let mut cells = Cells::default();
-        assert!(cells.cache.is_empty());
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        // free the cgroup behind the back of `cells`
+        let mut other = Cells::default();
+        let _ = other
+            .allocate_or_adopt(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to adopt");
+        let _ = other
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+
+        let listed = cells.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].status, CellStatus::Allocated);
+        assert!(!listed[0].cgroup_exists);
+        assert!(listed[0].is_stale());
+    }
 
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_or_adopt() {
+        let mut cells = Cells::default();
         let cell_name = CellName::random_for_tests();
-        let cell = CellSpec::new_for_tests();
+
+        // a cgroup left behind without a cell, like after a crash
+        let spec = CellSpec::new_for_tests();
+        let _cgroup = Cgroup::new(cell_name.clone(), spec.cgroup_spec.clone())
+            .expect("failed to create cgroup");
+
+        assert!(matches!(
+            cells.allocate(cell_name.clone(), CellSpec::new_for_tests()),
+            Err(CellsError::CgroupIsNotACell { .. })
+        ));
+
         let _ = cells
-            .allocate(cell_name.clone(), cell)
+            .allocate_or_adopt(cell_name.clone(), spec)
+            .expect("failed to adopt");
+        assert!(cells.cache.contains_key(&cell_name));
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_or_adopt_spec_mismatch_is_error() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+
+        let mut spec = CellSpec::new_for_tests();
+        spec.cgroup_spec.cpu = Some(CpuController {
+            weight: Some(Weight::new(100)),
+            max: None,
+            period: None,
+            burst: None,
+            uclamp_min: None,
+            uclamp_max: None,
+        });
+        let cgroup = Cgroup::new(cell_name.clone(), spec.cgroup_spec.clone())
+            .expect("failed to create cgroup");
+
+        spec.cgroup_spec.cpu = Some(CpuController {
+            weight: Some(Weight::new(200)),
+            max: None,
+            period: None,
+            burst: None,
+            uclamp_min: None,
+            uclamp_max: None,
+        });
+        let Err(CellsError::CgroupSpecMismatch { diff, .. }) =
+            cells.allocate_or_adopt(cell_name.clone(), spec)
+        else {
+            panic!("expected a spec mismatch");
+        };
+        let diff = aurae_proto::runtime::CgroupSpecDiff::from(diff);
+        assert_eq!(diff.fields.len(), 1);
+        assert_eq!(diff.fields[0].file, "cpu.weight");
+        assert_eq!(diff.fields[0].requested, "200");
+        assert_eq!(diff.fields[0].actual.as_deref(), Some("100"));
+        assert!(!cells.cache.contains_key(&cell_name));
+
+        cgroup.delete().expect("failed to delete cgroup");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_drain_leaves_cell_frozen() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
             .expect("failed to allocate");
 
-        cells.free(&cell_name).expect("failed to free");
+        let snapshot = cells
+            .get(&cell_name, |cell| cell.drain(true))
+            .expect("failed to drain");
+        assert!(Cgroup::is_frozen(&cell_name).expect("failed to read events"));
+
+        // the nested auraed is in the cell, and nothing in it can run while frozen
+        assert!(!snapshot.pids.is_empty());
+        assert!(
+            snapshot.stats.pids_current >= Some(snapshot.pids.len() as u64)
+        );
+        let again = Cgroup::frozen_snapshot(&cell_name, false)
+            .expect("failed to snapshot");
+        assert_eq!(again.pids, snapshot.pids);
+        assert_eq!(again.stats.pids_current, snapshot.stats.pids_current);
+        assert_eq!(again.stats.cpu_usage_usec, snapshot.stats.cpu_usage_usec);
+
+        // freeing thaws the cell for the nested auraed to shut down
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_frozen_cell_reports_frozen() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let state = |cells: &mut Cells| {
+            cells
+                .get(&cell_name, |cell| cell.freeze_state())
+                .expect("failed to read freeze state")
+        };
+        assert_eq!(state(&mut cells), FreezeState::Thawed);
+
+        let _ = cells
+            .get(&cell_name, |cell| cell.drain(false))
+            .expect("failed to drain");
+        assert_eq!(state(&mut cells), FreezeState::Frozen);
+
+        cells.get(&cell_name, |cell| cell.thaw()).expect("failed to thaw");
+        assert_eq!(state(&mut cells), FreezeState::Thawed);
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_freeze_freezes_nested_cells() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        // the cgroup a nested auraed would create for a nested cell
+        let nested = Cgroup::leaf_path(&cell_name).join("nested");
+        std::fs::create_dir(&nested).expect("failed to create nested cgroup");
+
+        cells.get(&cell_name, |cell| cell.freeze()).expect("failed to freeze");
+        assert_eq!(
+            FreezeState::read(&nested).expect("failed to read freeze state"),
+            FreezeState::Frozen
+        );
+
+        cells.get(&cell_name, |cell| cell.thaw()).expect("failed to thaw");
+        assert_eq!(
+            FreezeState::read(&nested).expect("failed to read freeze state"),
+            FreezeState::Thawed
+        );
+
+        std::fs::remove_dir(&nested).expect("failed to remove nested cgroup");
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_with_cpu_period_and_burst() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+
+        let mut spec = CellSpec::new_for_tests();
+        spec.cgroup_spec.cpu = Some(CpuController {
+            weight: None,
+            max: Some(Limit::new(50000)),
+            period: Some(100000),
+            burst: Some(20000),
+            uclamp_min: None,
+            uclamp_max: None,
+        });
+        let _ = cells
+            .allocate(cell_name.clone(), spec)
+            .expect("failed to allocate");
+
+        let read = |file: &str| {
+            std::fs::read_to_string(Cgroup::leaf_path(&cell_name).join(file))
+                .expect("failed to read")
+        };
+        assert_eq!(read("cpu.max"), "50000 100000\n");
+        assert_eq!(read("cpu.max.burst"), "20000\n");
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_beyond_nesting_limit_is_error() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+
+        // the leaf cgroup of the cell is its only allowed descendant
+        let mut spec = CellSpec::new_for_tests();
+        spec.nesting_limits.max_descendants = Some(1);
+        let _ = cells
+            .allocate(cell_name.clone(), spec)
+            .expect("failed to allocate");
+
+        let child_name = CellName::from(format!("{cell_name}/child").as_str());
+        assert!(matches!(
+            cells.allocate(child_name.clone(), CellSpec::new_for_tests()),
+            Err(CellsError::NestingLimitReached { cell_name }) if cell_name == child_name
+        ));
+        assert!(!cells.cache.contains_key(&child_name));
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_in_cgroup_with_processes_is_delegation_blocked() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        // a process in the cgroup of the cell itself, as auraed is in the cgroup it
+        // creates cells in unless migrated. It can only be moved there once no
+        // controllers are enabled for the leaf.
+        let cgroup = Cgroup::path(&cell_name);
+        let subtree_control = cgroup.join("cgroup.subtree_control");
+        let enabled = std::fs::read_to_string(&subtree_control)
+            .expect("failed to read subtree_control");
+        for controller in enabled.split_whitespace() {
+            std::fs::write(&subtree_control, format!("-{controller}"))
+                .expect("failed to disable controller");
+        }
+        let mut sleep = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .expect("failed to spawn");
+        std::fs::write(cgroup.join("cgroup.procs"), sleep.id().to_string())
+            .expect("failed to move process into cell");
+
+        let child_name = CellName::from(format!("{cell_name}/child").as_str());
+        let mut spec = CellSpec::new_for_tests();
+        spec.cgroup_spec.cpu = Some(CpuController {
+            weight: Some(Weight::new(100)),
+            max: None,
+            period: None,
+            burst: None,
+            uclamp_min: None,
+            uclamp_max: None,
+        });
+        let res = cells.allocate(child_name.clone(), spec);
+
+        sleep.kill().expect("failed to kill");
+        let _ = sleep.wait().expect("failed to wait");
+
+        assert!(matches!(
+            res,
+            Err(CellsError::ControllerDelegationBlocked { cell_name }) if cell_name == child_name
+        ));
+        assert!(!cells.cache.contains_key(&child_name));
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Recursive)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_orphan_rejects_allocated_children() {
+        let mut cells = Cells::default();
+        let (cell_name, child_name) = allocate_with_child(&mut cells);
+
+        // a process in the child, as its nested auraed would be
+        let child_leaf =
+            Cgroup::leaf_path(&cell_name).join(&*child_name).join("_");
+        let mut sleep = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .expect("failed to spawn");
+        std::fs::write(child_leaf.join("cgroup.procs"), sleep.id().to_string())
+            .expect("failed to move process into child");
+
+        assert!(matches!(
+            cells.free(&cell_name, FreeChildrenPolicy::Orphan),
+            Err(CellsError::ChildIsAllocated { child, .. }) if child == child_name
+        ));
+        assert!(child_leaf.exists());
+        assert!(!Cgroup::exists(&child_name));
+        assert!(cells.cache.contains_key(&cell_name));
+        assert!(!cells.cache.contains_key(&child_name));
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Recursive)
+            .expect("failed to free");
+        let _ = sleep.wait().expect("failed to wait");
+    }
+
+    /// Allocates a cell with a child, as if the nested auraed of the cell allocated it.
+    fn allocate_with_child(cells: &mut Cells) -> (CellName, CellName) {
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let child_name = CellName::random_for_tests();
+        let child_path = Cgroup::leaf_path(&cell_name).join(&*child_name);
+        std::fs::create_dir_all(child_path.join("_"))
+            .expect("failed to create child cgroup");
+
+        (cell_name, child_name)
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_with_children_is_rejected() {
+        let mut cells = Cells::default();
+        let (cell_name, child_name) = allocate_with_child(&mut cells);
+
+        assert!(matches!(
+            cells.free(&cell_name, FreeChildrenPolicy::Reject),
+            Err(CellsError::CellHasChildren { children, .. })
+                if children == vec![child_name.clone()]
+        ));
+        assert!(cells.cache.contains_key(&cell_name));
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Recursive)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_recursive_frees_children_first() {
+        let mut cells = Cells::default();
+        let (cell_name, child_name) = allocate_with_child(&mut cells);
+        let child_path = Cgroup::leaf_path(&cell_name).join(&*child_name);
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Recursive)
+            .expect("failed to free");
+
+        assert!(!child_path.exists());
+        assert!(!Cgroup::exists(&cell_name));
+        assert!(!cells.cache.contains_key(&cell_name));
+        assert!(!cells.cache.contains_key(&child_name));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_orphan_moves_children_next_to_the_cell() {
+        let mut cells = Cells::default();
+        let (cell_name, child_name) = allocate_with_child(&mut cells);
+        let old_path = Cgroup::leaf_path(&cell_name).join(&*child_name);
+        let old_id = std::fs::metadata(old_path.join("_"))
+            .expect("failed to read child cgroup")
+            .ino();
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Orphan)
+            .expect("failed to free");
+
+        assert!(!Cgroup::exists(&cell_name));
+        assert!(!cells.cache.contains_key(&cell_name));
+
+        // the child is now a cell of ours, in a new cgroup
+        assert!(!old_path.exists());
+        assert!(Cgroup::exists(&child_name));
+        assert!(cells.cache.contains_key(&child_name));
+        assert_ne!(
+            Cgroup::leaf_id(&child_name).expect("failed to read cgroup id"),
+            old_id
+        );
+
+        cells
+            .free(&child_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free orphan");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_with_stats() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let stats = cells
+            .free_with_stats(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free")
+            .expect("stats");
+
+        // the nested auraed of the cell did work while starting up
+        assert!(stats.cpu_usage_usec.expect("cpu usage") > 0);
+        assert!(!cells.cache.contains_key(&cell_name));
+        assert!(!Cgroup::exists(&cell_name));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_broadcast_kill_kills_processes_left_in_cell() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        // a process that isn't a descendant of the nested auraed
+        let mut process = std::process::Command::new("sleep")
+            .arg("1000")
+            .spawn()
+            .expect("failed to spawn");
+        std::fs::write(
+            Cgroup::leaf_path(&cell_name).join("cgroup.procs"),
+            process.id().to_string(),
+        )
+        .expect("failed to move process into cell");
+
+        cells.broadcast_kill(KillEscalation::default());
+
+        let exit_status = process.wait().expect("failed to wait");
+        assert_eq!(exit_status.signal(), Some(libc::SIGKILL));
+        assert!(!cells.cache.contains_key(&cell_name));
+        assert!(!Cgroup::exists(&cell_name));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_with_pids_max() {
+        use crate::runtime::cell_service::cells::cgroups::pids::{
+            PidsController, PidsMax,
+        };
+
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let mut spec = CellSpec::new_for_tests();
+        spec.cgroup_spec.pids =
+            Some(PidsController { max: Some(PidsMax::new(Some(32))) });
+        let _ = cells
+            .allocate(cell_name.clone(), spec)
+            .expect("failed to allocate");
+
+        let pids_max = std::fs::read_to_string(
+            Cgroup::leaf_path(&cell_name).join("pids.max"),
+        )
+        .expect("failed to read pids.max");
+        assert_eq!(pids_max.trim(), "32");
+
+        let _ = cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_update_pids_max() {
+        use crate::runtime::cell_service::cells::cgroups::pids::{
+            PidsController, PidsMax,
+        };
+
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let cgroup_spec = CgroupSpec {
+            pids: Some(PidsController { max: Some(PidsMax::new(Some(16))) }),
+            ..CellSpec::new_for_tests().cgroup_spec
+        };
+        cells.update(&cell_name, cgroup_spec).expect("failed to update");
+
+        let pids_max = std::fs::read_to_string(
+            Cgroup::leaf_path(&cell_name).join("pids.max"),
+        )
+        .expect("failed to read pids.max");
+        assert_eq!(pids_max.trim(), "16");
+        cells
+            .get(&cell_name, |cell| {
+                assert!(cell.spec().cgroup_spec.pids.is_some());
+                Ok(())
+            })
+            .expect("failed to get");
+
+        let _ = cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    #[test]
+    fn test_update_not_cached_is_error() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+
+        assert!(matches!(
+            cells.update(&cell_name, CellSpec::new_for_tests().cgroup_spec),
+            Err(CellsError::CellNotFound { cell_name: name }) if name == cell_name
+        ));
+    }
+
+    /// A fake /proc with the cgroup of process 42.
+    fn fake_proc(cgroup: &str) -> std::path::PathBuf {
+        let proc = temp_path("proc");
+        fs::create_dir_all(proc.join("42")).expect("create dir");
+        fs::write(proc.join("42").join("cgroup"), cgroup)
+            .expect("write cgroup");
+        proc
+    }
+
+    #[test]
+    fn test_get_cgroup_by_pid() {
+        let mut cells = Cells::default();
+        cells.insert_for_tests("ae-1".into(), CellSpec::new_for_tests());
+
+        for cgroup in ["0::/ae-1/_\n", "0::/ae-1/ae-2/_\n"] {
+            let proc = fake_proc(cgroup);
+            let cell = cells.get_cgroup_by_pid_in(&proc, 42).expect("cell");
+            assert_eq!(&**cell.name(), "ae-1");
+            fs::remove_dir_all(proc).expect("remove fake proc");
+        }
+    }
+
+    #[test]
+    fn test_get_cgroup_by_pid_not_in_cell_is_error() {
+        let mut cells = Cells::default();
+        cells.insert_for_tests("ae-1".into(), CellSpec::new_for_tests());
+
+        for (cgroup, expected) in [
+            ("0::/\n", "/"),
+            ("0::/ae-2/_\n", "ae-2"),
+            ("0::/user.slice/session-1.scope\n", "user.slice"),
+            ("", ""),
+        ] {
+            let proc = fake_proc(cgroup);
+            assert!(matches!(
+                cells.get_cgroup_by_pid_in(&proc, 42),
+                Err(CellsError::CellNotFound { cell_name }) if &*cell_name == expected
+            ));
+            fs::remove_dir_all(proc).expect("remove fake proc");
+        }
+    }
+
+    #[test]
+    fn test_get_cgroup_by_pid_that_exited_is_error() {
+        let cells = Cells::default();
+        let proc = fake_proc("0::/ae-1/_\n");
+
+        // the pid may have exited since it was given to us
+        assert!(matches!(
+            cells.get_cgroup_by_pid_in(&proc, 43),
+            Err(CellsError::CellNotFound { cell_name }) if cell_name.is_empty()
+        ));
+        fs::remove_dir_all(proc).expect("remove fake proc");
+    }
+
+    #[tokio::test]
+    async fn test_get_cgroup_by_executable_not_in_cell() {
+        let cells = Cells::default();
+        let mut executables = Executables::default();
+        let executable_name =
+            executables.start(sleep_spec("ae-sleep")).expect("start");
+
+        // executables start in the cgroup of auraed, which isn't a cell
+        assert!(matches!(
+            cells.get_cgroup_by_executable(&executables, &executable_name),
+            Err(CellsError::ExecutableNotInCell { .. })
+        ));
+
+        let _ = executables
+            .stop(&executable_name, 0, StopPolicy::KILL)
+            .await
+            .expect("failed to stop");
+        assert!(matches!(
+            cells.get_cgroup_by_executable(&executables, &executable_name),
+            Err(CellsError::ExecutableNotInCell { .. })
+        ));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[tokio::test]
+    async fn test_get_cgroup_by_executable() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let mut executables = Executables::default();
+        let executable_name =
+            executables.start(sleep_spec("ae-sleep")).expect("start");
+        let pid = executables
+            .get(&executable_name)
+            .and_then(|executable| executable.pid().expect("pid"))
+            .expect("running");
+        std::fs::write(
+            Cgroup::leaf_path(&cell_name).join("cgroup.procs"),
+            pid.to_string(),
+        )
+        .expect("failed to move process into cell");
+
+        let cgroup = cells
+            .get_cgroup_by_executable(&executables, &executable_name)
+            .expect("cgroup of executable");
+        assert_eq!(cgroup.cell_name(), &cell_name);
+
+        let _ = executables
+            .stop(&executable_name, 0, StopPolicy::KILL)
+            .await
+            .expect("failed to stop");
+        let _ = cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+    }
+
+    #[test]
+    fn test_pinned_memory_budget() {
+        use crate::runtime::cell_service::cells::cgroups::{
+            memory::{MemoryController, MemoryPinning},
+            Limit,
+        };
+
+        const MIB: i64 = 1024 * 1024;
+        const BUDGET: Option<u64> = Some(1024 * MIB as u64);
+
+        let pinning = |pinned: i64| {
+            let mut spec = CellSpec::new_for_tests();
+            spec.cgroup_spec.memory = Some(MemoryController {
+                max: None,
+                shares: None,
+                zswap_max: None,
+                pinned: Some(Limit::new(pinned)),
+                pinning: MemoryPinning::Reserve,
+                swappiness: None,
+            });
+            spec
+        };
+
+        // cells that are cached, but not allocated, so no cgroup is created
+        let mut cells = Cells::default();
+        for pinned in [256 * MIB, 512 * MIB] {
+            let cell_name = CellName::random_for_tests();
+            let cell = Cell::new(cell_name.clone(), pinning(pinned));
+            let _ = cells.cache.insert(cell_name, cell);
+        }
+
+        let cell_name = CellName::random_for_tests();
+        cells
+            .check_pinned_memory(&cell_name, &pinning(256 * MIB), BUDGET)
+            .expect("within budget");
+        cells
+            .check_pinned_memory(
+                &cell_name,
+                &CellSpec::new_for_tests(),
+                Some(0),
+            )
+            .expect("pins no memory");
+        cells
+            .check_pinned_memory(&cell_name, &pinning(1024 * MIB), None)
+            .expect("no budget");
+
+        assert!(matches!(
+            cells.check_pinned_memory(&cell_name, &pinning(512 * MIB), BUDGET),
+            Err(CellsError::PinnedMemoryBudgetExceeded { over, .. })
+                if over == 256 * MIB as u64
+        ));
+    }
+
+    #[test]
+    fn test_check_cell_limit() {
+        // cells that are cached, but not allocated, so no cgroup is created
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let cell = Cell::new(cell_name.clone(), CellSpec::new_for_tests());
+        let _ = cells.cache.insert(cell_name.clone(), cell);
+
+        let other_name = CellName::random_for_tests();
+        cells.check_cell_limit(&other_name, None).expect("no limit");
+        cells.check_cell_limit(&other_name, Some(2)).expect("within limit");
+        cells.check_cell_limit(&cell_name, Some(1)).expect("already cached");
+
+        assert!(matches!(
+            cells.check_cell_limit(&other_name, Some(1)),
+            Err(CellsError::CellLimitReached { cell_name, max_cells: 1 })
+                if cell_name == other_name
+        ));
+    }
+
+    #[test]
+    fn test_reserved_cell_name_is_taken_until_inserted() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        let cell = cells
+            .reserve(cell_name.clone(), CellSpec::new_for_tests())
+            .expect("reserve");
+
+        assert!(matches!(
+            cells.reserve(cell_name.clone(), CellSpec::new_for_tests()),
+            Err(CellsError::CellExists { cell_name: name }) if name == cell_name
+        ));
+        // the cell counts against the limit while it is allocated
+        let other_name = CellName::random_for_tests();
+        assert!(matches!(
+            cells.check_cell_limit(&other_name, Some(1)),
+            Err(CellsError::CellLimitReached { .. })
+        ));
+
+        // a cell that failed to allocate is not cached, but releases its name
+        let failed =
+            Err(CellsError::CellNotAllocated { cell_name: cell_name.clone() });
+        assert!(matches!(
+            cells.insert_reserved(cell, failed),
+            Err(CellsError::CellNotAllocated { .. })
+        ));
+        assert!(cells.is_empty());
+        cells.check_cell_limit(&other_name, Some(1)).expect("within limit");
+        let _ = cells
+            .reserve(cell_name, CellSpec::new_for_tests())
+            .expect("name is released");
+    }
+
+    /// A fake /sys/devices/system/node with two nodes of 4 cpus each.
+    fn fake_node_dir() -> std::path::PathBuf {
+        let dir = temp_path("node");
+        for (node, cpulist) in [("node0", "0-3\n"), ("node1", "4-7\n")] {
+            fs::create_dir_all(dir.join(node)).expect("create dir");
+            fs::write(dir.join(node).join("cpulist"), cpulist)
+                .expect("write cpulist");
+        }
+        // not a node
+        fs::create_dir_all(dir.join("power")).expect("create dir");
+        dir
+    }
+
+    #[test]
+    fn test_numa_locality() {
+        use crate::runtime::cell_service::cells::cgroups::cpuset::{
+            Cpus, CpusetController, Mems,
+        };
+
+        let cpuset = |cpus: Option<&str>, mems: Option<&str>| {
+            let mut spec = CellSpec::new_for_tests();
+            spec.cgroup_spec.cpuset = Some(CpusetController {
+                cpus: cpus.map(|cpus| Cpus::new(cpus.into())),
+                mems: mems.map(|mems| Mems::new(mems.into())),
+            });
+            spec
+        };
+
+        let dir = fake_node_dir();
+        let topology = NumaTopology::read(&dir).expect("read topology");
+        fs::remove_dir_all(dir).expect("remove fake node dir");
+
+        let cell_name = CellName::random_for_tests();
+        for (cpus, mems) in [
+            (Some("0-3"), Some("0")),
+            (Some("2-5"), Some("0-1")),
+            (Some("4"), Some("0,1")),
+            (Some("0-7"), None),
+            (None, Some("1")),
+            (Some(""), Some("1")),
+        ] {
+            Cells::check_numa_locality(
+                &cell_name,
+                &cpuset(cpus, mems),
+                &topology,
+            )
+            .expect("local mems");
+        }
+
+        assert!(matches!(
+            Cells::check_numa_locality(
+                &cell_name,
+                &cpuset(Some("2-5"), Some("1")),
+                &topology,
+            ),
+            Err(CellsError::NumaLocalityMismatch { missing_nodes, .. })
+                if missing_nodes == vec![0]
+        ));
+        assert!(matches!(
+            Cells::check_numa_locality(
+                &cell_name,
+                &cpuset(Some("4-7"), Some("0")),
+                &topology,
+            ),
+            Err(CellsError::NumaLocalityMismatch { missing_nodes, .. })
+                if missing_nodes == vec![1]
+        ));
+    }
+
+    #[test]
+    fn test_get_missing_errors() {
+        let mut cells = Cells::default();
+        assert!(cells.cache.is_empty());
+
+        let cell_name_in = CellName::random_for_tests();
+
+        assert!(matches!(
+            cells.get(&cell_name_in, |_cell| Ok(())),
+            Err(CellsError::CellNotFound { cell_name }) if cell_name == cell_name_in
+        ));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free() {
+        let mut cells = Cells::default();
+        assert!(cells.cache.is_empty());
+
+        let cell_name = CellName::random_for_tests();
+        let cell = CellSpec::new_for_tests();
+        let _ = cells
+            .allocate(cell_name.clone(), cell)
+            .expect("failed to allocate");
+
+        cells
+            .free(&cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
+        assert!(cells.cache.is_empty());
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_free_by_selector() {
+        let mut cells = Cells::default();
         assert!(cells.cache.is_empty());
+
+        let matching_cell_name = CellName::random_for_tests();
+        let mut matching_cell = CellSpec::new_for_tests();
+        let _ = matching_cell.labels.insert("job".into(), "build".into());
+        let _ = cells
+            .allocate(matching_cell_name.clone(), matching_cell)
+            .expect("failed to allocate");
+
+        let other_cell_name = CellName::random_for_tests();
+        let _ = cells
+            .allocate(other_cell_name.clone(), CellSpec::new_for_tests())
+            .expect("failed to allocate");
+
+        let selector = LabelSelector::new(
+            [("job".to_string(), "build".to_string())].into_iter().collect(),
+        );
+        let results = cells.free_by_selector(&selector);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, matching_cell_name);
+        assert!(results[0].1.is_ok());
+        assert!(!cells.cache.contains_key(&matching_cell_name));
+        assert!(cells.cache.contains_key(&other_cell_name));
+
+        cells
+            .free(&other_cell_name, FreeChildrenPolicy::Reject)
+            .expect("failed to free");
     }
 
     #[test]
@@ -289,7 +1805,7 @@ mod tests {
         let cell_name_in = CellName::random_for_tests();
 
         assert!(matches!(
-            cells.free(&cell_name_in),
+            cells.free(&cell_name_in, FreeChildrenPolicy::Reject),
             Err(CellsError::CellNotFound { cell_name }) if cell_name == cell_name_in
         ));
     }
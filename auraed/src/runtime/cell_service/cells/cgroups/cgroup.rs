@@ -28,19 +28,43 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
+use super::{
+    children, freezer, kill, orphans,
+    update::{self, CgroupDir, UpdateError},
+    CgroupControllers, CgroupSpecDiff, CgroupStats, FreezeState,
+    FrozenSnapshot, KillEscalation, NestingLimits, OrphanedCgroup,
+};
 use crate::runtime::cell_service::cells::{
-    cgroups::{CpuController, CpusetController},
+    cgroups::{memory, CpusetController, PidsController},
+    nested_auraed::MappedRoot,
     CellName, CgroupSpec,
 };
 use cgroups_rs::{cgroup_builder::CgroupBuilder, hierarchies, Hierarchy};
 use std::{
+    collections::HashMap,
+    io,
     ops::{Deref, DerefMut},
-    path::PathBuf,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
 };
+use walkdir::WalkDir;
+
+/// The mount point of the cgroup v2 hierarchy.
+pub(super) const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// This is used as the default denominator for the CPU quota/period configuration.  This allows
+/// users to set the quota as if it was in the unit "µs/s" without worrying about also setting the
+/// period.
+pub(super) const MICROSECONDS_PER_SECOND: u64 = 1000000;
+
+/// How long the processes of a cell are given to freeze (see [Cgroup::freeze]).
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// This is used as the denominator for the CPU quota/period configuration.  This allows users to
-/// set the quota as if it was in the unit "µs/s" without worrying about also setting the period.
-const MICROSECONDS_PER_SECOND: u64 = 1000000;
+/// The locks on the parents of the cgroups of cells (see [parent_lock]).
+static PARENT_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> =
+    OnceLock::new();
 
 #[derive(Debug)]
 pub struct Cgroup {
@@ -49,8 +73,16 @@ pub struct Cgroup {
 }
 
 impl Cgroup {
-    pub fn new(cell_name: CellName, spec: CgroupSpec) -> Self {
-        let CgroupSpec { cpu, cpuset } = spec;
+    /// Creates the cgroup of the cell. Fails if an ancestor's `cgroup.max.depth` or
+    /// `cgroup.max.descendants` would be exceeded (see [super::is_nesting_limit_reached]),
+    /// or if the cgroup cells are created in has processes, which prevents enabling
+    /// controllers (see [super::is_controller_delegation_blocked]).
+    pub fn new(
+        cell_name: CellName,
+        spec: CgroupSpec,
+    ) -> cgroups_rs::error::Result<Self> {
+        // io is written on its own, after the cgroup is created (see [Cgroup::set_io])
+        let CgroupSpec { cpu, cpuset, memory, io: _, pids } = spec;
 
         // NOTE: v2 cgroups can either have nested cgroups or processes, not both (leaf workaround)
         // NOTE: '_' is a disallowed character in cell name, so won't collide
@@ -59,17 +91,20 @@ impl Cgroup {
         let builder = CgroupBuilder::new(&name);
 
         // cpu controller
-        let builder = if let Some(CpuController { weight, max }) = cpu {
+        // uclamp is not supported by cgroups_rs (see [Cgroup::set_uclamp])
+        // cpu.max.burst is not supported by cgroups_rs (see [Cgroup::set_cpu_burst])
+        let builder = if let Some(cpu) = cpu {
             let builder = builder.cpu();
+            let period = cpu.period();
 
-            let builder = if let Some(weight) = weight {
+            let builder = if let Some(weight) = cpu.weight {
                 builder.shares(weight.into_inner())
             } else {
                 builder
             };
 
-            let builder = if let Some(max) = max {
-                builder.quota(max.into_inner()).period(MICROSECONDS_PER_SECOND) // microseconds in a second
+            let builder = if let Some(max) = cpu.max {
+                builder.quota(max.into_inner()).period(period)
             } else {
                 builder
             };
@@ -100,11 +135,60 @@ impl Cgroup {
             builder
         };
 
-        let inner = builder.build(hierarchy()).expect("valid cgroup");
+        // memory controller
+        // shares are set as memory.low by [crate::runtime::cell_service::cells::Cells]
+        // memory.min is not supported by cgroups_rs (see [Cgroup::set_memory_min])
+        // zswap is not supported by cgroups_rs (see [Cgroup::set_zswap_max])
+        let builder = if let Some(memory) = memory {
+            let builder = builder.memory();
+
+            let builder = if let Some(max) = memory.effective_max() {
+                builder.memory_hard_limit(max.into_inner())
+            } else {
+                builder
+            };
+
+            builder.done()
+        } else {
+            builder
+        };
+
+        // pids controller
+        let builder = if let Some(PidsController { max: Some(max) }) = pids {
+            builder.pid().maximum_number_of_processes(max.into()).done()
+        } else {
+            builder
+        };
+
+        // Creating the cgroup enables the controllers it requests in the
+        // cgroup.subtree_control of its parent, which its siblings write to as well
+        let parent = parent_lock(&cell_name);
+        let _parent = parent.lock().expect("parent cgroup lock");
+        let inner = builder.build(hierarchy())?;
+
+        Ok(Self { cell_name, inner })
+    }
 
+    /// Loads the existing cgroup of the cell.
+    pub fn load(cell_name: CellName) -> Self {
+        let inner =
+            cgroups_rs::Cgroup::load(hierarchy(), format!("{cell_name}/_"));
         Self { cell_name, inner }
     }
 
+    /// Returns the cgroups that look like cells (see [super::orphans]), for which
+    /// `is_cached` is false.
+    pub fn orphans(
+        is_cached: impl Fn(&CellName) -> bool,
+    ) -> io::Result<Vec<OrphanedCgroup>> {
+        orphans::find(Path::new(CGROUP_ROOT), is_cached)
+    }
+
+    /// Returns the name of the cell the cgroup belongs to.
+    pub fn cell_name(&self) -> &CellName {
+        &self.cell_name
+    }
+
     pub fn delete(&self) -> cgroups_rs::error::Result<()> {
         self.inner.delete()?;
 
@@ -114,11 +198,295 @@ impl Cgroup {
         cgroups_rs::Cgroup::load(hierarchy(), &*self.cell_name).delete()
     }
 
+    /// Writes the controller values set in `spec` to the cgroup in place. Values that are
+    /// not set are left unchanged. If a write fails, the values already written are rolled
+    /// back (see [update] for the limitations).
+    pub fn update(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        // The reservation of the leaf is limited by that of the cell (see [Cgroup::set_memory_min])
+        let writes = update::memory_min_writes(spec);
+        update::apply(&mut CgroupDir(Self::path(cell_name)), &writes)?;
+
+        let writes = update::writes(spec);
+        update::apply(&mut CgroupDir(Self::leaf_path(cell_name)), &writes)
+    }
+
+    /// Writes the utilization clamps set in `spec` to the cgroup of the cell.
+    /// Clamps are skipped if the kernel does not support uclamp.
+    pub fn set_uclamp(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        let writes = update::uclamp_writes(spec);
+        update::apply(&mut CgroupDir(Self::leaf_path(cell_name)), &writes)
+    }
+
+    /// Writes the burst of the cpu quota set in `spec` to the cgroup of the cell.
+    pub fn set_cpu_burst(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        let writes = update::cpu_burst_writes(spec);
+        update::apply(&mut CgroupDir(Self::leaf_path(cell_name)), &writes)
+    }
+
+    /// Writes the zswap limit set in `spec` to the cgroup of the cell.
+    /// The limit is skipped if zswap is not configured.
+    pub fn set_zswap_max(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        let writes = update::zswap_writes(spec);
+        update::apply(&mut CgroupDir(Self::leaf_path(cell_name)), &writes)
+    }
+
+    /// Writes the io weight and per-device limits set in `spec` to the cgroup of the cell.
+    /// If the limits of a device can't be written, those already written are rolled back.
+    pub fn set_io(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        let writes = update::io_writes(spec);
+        update::apply(&mut CgroupDir(Self::leaf_path(cell_name)), &writes)
+    }
+
+    /// Writes the memory reservation set in `spec` to the cgroup of the cell, and to the
+    /// cgroup its processes are placed in, as the protection of a cgroup is limited by
+    /// that of its parent.
+    pub fn set_memory_min(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> Result<(), UpdateError> {
+        let writes = update::memory_min_writes(spec);
+        for path in [Self::path(cell_name), Self::leaf_path(cell_name)] {
+            update::apply(&mut CgroupDir(path), &writes)?;
+        }
+        Ok(())
+    }
+
+    /// Sets `memory.low` of the cgroup of the cell, and of the cgroup its processes are
+    /// placed in, as the protection of a cgroup is limited by that of its parent.
+    pub fn set_memory_low(cell_name: &CellName, low: u64) -> io::Result<()> {
+        for path in [Self::path(cell_name), Self::leaf_path(cell_name)] {
+            std::fs::write(path.join("memory.low"), low.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the nesting limits to the cgroup of the cell, so they also cover the
+    /// cgroups of its nested cells.
+    pub fn set_nesting_limits(
+        cell_name: &CellName,
+        limits: &NestingLimits,
+    ) -> io::Result<()> {
+        limits.write(&Self::path(cell_name))
+    }
+
+    /// Returns an error if `spec` sets a swappiness, which cgroup v2 does not have
+    /// (see [memory::check_swappiness]).
+    pub fn check_swappiness(
+        spec: &CgroupSpec,
+    ) -> Result<(), memory::SwappinessUnsupported> {
+        memory::check_swappiness(
+            spec.memory.as_ref().and_then(|x| x.swappiness),
+        )
+    }
+
+    /// Returns the memory available to the cells (see [memory::available_memory]).
+    pub fn available_memory() -> io::Result<u64> {
+        memory::available_memory(Path::new(CGROUP_ROOT))
+    }
+
+    /// Compares the controller values set in `spec` with the values of the existing
+    /// cgroup of the cell.
+    pub fn diff(
+        cell_name: &CellName,
+        spec: &CgroupSpec,
+    ) -> io::Result<CgroupSpecDiff> {
+        CgroupSpecDiff::new(&CgroupDir(Self::leaf_path(cell_name)), spec)
+    }
+
+    /// Returns the resource usage of the cell, including its nested cells.
+    pub fn stats(cell_name: &CellName) -> io::Result<CgroupStats> {
+        CgroupStats::read(&Self::path(cell_name))
+    }
+
+    /// Returns the controllers available to the cell, and enabled for its leaf cgroup.
+    pub fn controllers(cell_name: &CellName) -> io::Result<CgroupControllers> {
+        CgroupControllers::read(&Self::path(cell_name))
+    }
+
+    /// Freezes the processes of the cell, including those of its nested cells
+    /// (see [freezer::freeze]).
+    pub fn freeze(cell_name: &CellName) -> io::Result<()> {
+        freezer::freeze(&Self::path(cell_name), FREEZE_TIMEOUT)
+    }
+
+    /// Thaws the processes of the cell. Does nothing if the cell is not frozen.
+    pub fn thaw(cell_name: &CellName) -> io::Result<()> {
+        freezer::thaw(&Self::path(cell_name))
+    }
+
+    /// Returns whether the processes of the cell are frozen (see [FreezeState::read]).
+    pub fn freeze_state(cell_name: &CellName) -> io::Result<FreezeState> {
+        FreezeState::read(&Self::path(cell_name))
+    }
+
+    /// Returns true if the processes of the cell are frozen.
+    #[cfg(test)]
+    pub fn is_frozen(cell_name: &CellName) -> io::Result<bool> {
+        freezer::is_frozen(&Self::path(cell_name))
+    }
+
+    /// Reads the state of the frozen cell (see [FrozenSnapshot::read]).
+    pub fn frozen_snapshot(
+        cell_name: &CellName,
+        include_memory_stat: bool,
+    ) -> io::Result<FrozenSnapshot> {
+        FrozenSnapshot::read(&Self::path(cell_name), include_memory_stat)
+    }
+
+    /// Kills the processes left in the cgroup of the cell (see [kill::kill_until_empty]).
+    pub fn kill_remaining(
+        cell_name: &CellName,
+        escalation: KillEscalation,
+    ) -> io::Result<()> {
+        kill::kill_until_empty(&Self::path(cell_name), escalation)
+    }
+
+    /// Returns the names of the cells allocated in the cell by its nested auraed.
+    pub fn children(cell_name: &CellName) -> io::Result<Vec<CellName>> {
+        children::child_cells(&Self::leaf_path(cell_name))
+    }
+
+    /// Kills the processes of the children of the cell, and of their children, and
+    /// removes their cgroups (see [children::remove_tree]).
+    pub fn free_children(
+        cell_name: &CellName,
+        escalation: KillEscalation,
+    ) -> io::Result<()> {
+        for child in Self::children(cell_name)? {
+            let path = Self::leaf_path(cell_name).join(&*child);
+            children::remove_tree(&path, escalation)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `child`, a child of the cell, or one of its children, has processes,
+    /// such as the nested auraed it was allocated with.
+    pub fn is_child_allocated(
+        cell_name: &CellName,
+        child: &CellName,
+    ) -> io::Result<bool> {
+        kill::is_populated(&Self::leaf_path(cell_name).join(&**child))
+    }
+
+    /// Moves the cgroup of `child`, a child of the cell, next to the cell, so that it is
+    /// the cgroup of a cell named `child` (see [children::reparent]).
+    pub fn orphan_child(
+        cell_name: &CellName,
+        child: &CellName,
+    ) -> io::Result<()> {
+        let path = Self::leaf_path(cell_name).join(&**child);
+        children::reparent(&path, &Self::path(child))
+    }
+
+    /// Hands the cgroup of the cell over to root of a cell with a user namespace, so its
+    /// nested auraed can create the cgroups of nested cells and move processes into them.
+    /// Docs: https://docs.kernel.org/admin-guide/cgroup-v2.html#model-of-delegation
+    pub fn delegate(cell_name: &CellName, root: MappedRoot) -> io::Result<()> {
+        for path in [Self::path(cell_name), Self::leaf_path(cell_name)] {
+            root.chown(&path)?;
+            for file in
+                ["cgroup.procs", "cgroup.subtree_control", "cgroup.threads"]
+            {
+                root.chown(&path.join(file))?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn exists(cell_name: &CellName) -> bool {
-        let mut path = PathBuf::from("/sys/fs/cgroup");
+        Self::path(cell_name).exists()
+    }
+
+    /// Returns the path of the cgroup of the cell.
+    pub fn path(cell_name: &CellName) -> PathBuf {
+        let mut path = PathBuf::from(CGROUP_ROOT);
         path.push(cell_name.deref());
-        path.exists()
+        path
+    }
+
+    /// Returns the path of the cgroup that processes of the cell are placed in.
+    pub fn leaf_path(cell_name: &CellName) -> PathBuf {
+        let mut path = Self::path(cell_name);
+        path.push("_");
+        path
     }
+
+    /// Returns the id of the cgroup that processes of the cell are placed in.
+    /// This is the inode number of the cgroup directory, which is the id the
+    /// kernel uses for the cgroup (e.g., in `bpf_get_current_cgroup_id`).
+    pub fn leaf_id(cell_name: &CellName) -> io::Result<u64> {
+        Ok(std::fs::metadata(Self::leaf_path(cell_name))?.ino())
+    }
+
+    /// Returns true if the thread is listed in `cgroup.threads` of the cell's cgroup,
+    /// or of any cgroup below it.
+    pub fn has_thread(cell_name: &CellName, tid: i32) -> io::Result<bool> {
+        contains_id(&Self::path(cell_name), "cgroup.threads", tid)
+    }
+
+    /// Returns true if the process is listed in `cgroup.procs` of the cell's cgroup,
+    /// or of any cgroup below it.
+    pub fn has_process(cell_name: &CellName, pid: i32) -> io::Result<bool> {
+        contains_id(&Self::path(cell_name), "cgroup.procs", pid)
+    }
+}
+
+/// Walks the cgroup at `path` and its descendants, looking for `id` in the interface file
+/// named `file`. Cgroups where the file is missing or can't be read in their current mode
+/// (e.g., `cgroup.threads` when threaded mode isn't enabled) are skipped.
+fn contains_id(path: &Path, file: &str, id: i32) -> io::Result<bool> {
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let ids = match std::fs::read_to_string(entry.path().join(file)) {
+            Ok(ids) => ids,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::Unsupported
+                ) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+
+        if ids.lines().any(|line| line.trim().parse() == Ok(id)) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns the lock on the parent of the cgroup of `cell_name`, which serializes
+/// creating the cgroups of sibling cells. Locks are kept for the lifetime of auraed,
+/// as there is at most one per cell that has had children.
+fn parent_lock(cell_name: &CellName) -> Arc<Mutex<()>> {
+    let path = Cgroup::path(cell_name);
+    let parent = path.parent().unwrap_or(&path).to_path_buf();
+    let locks = PARENT_LOCKS.get_or_init(Default::default);
+    let mut locks = locks.lock().expect("parent cgroup locks");
+    locks.entry(parent).or_default().clone()
 }
 
 impl Deref for Cgroup {
@@ -145,3 +513,49 @@ fn hierarchy() -> Box<dyn Hierarchy> {
     // hierarchies::V2
     Box::new(hierarchies::V2::new())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+
+    #[test]
+    fn test_contains_id_distinguishes_threads_from_processes() {
+        let root = temp_path("cgroup");
+
+        // a process (pid 100) in the leaf cgroup, with one of its threads (tid 101)
+        // moved into a threaded cgroup below it
+        let leaf = root.join("_");
+        let threaded = leaf.join("worker");
+        std::fs::create_dir_all(&threaded).expect("create cgroup dirs");
+        std::fs::write(leaf.join("cgroup.procs"), "100\n").expect("write");
+        std::fs::write(leaf.join("cgroup.threads"), "100\n").expect("write");
+        std::fs::write(threaded.join("cgroup.threads"), "101\n")
+            .expect("write");
+
+        assert!(contains_id(&root, "cgroup.threads", 101).expect("threads"));
+        assert!(!contains_id(&root, "cgroup.procs", 101).expect("procs"));
+        assert!(contains_id(&root, "cgroup.procs", 100).expect("procs"));
+        assert!(!contains_id(&root, "cgroup.threads", 1010).expect("threads"));
+
+        std::fs::remove_dir_all(&root).expect("remove cgroup dirs");
+    }
+
+    #[test]
+    fn test_siblings_share_the_lock_on_their_parent() {
+        let parent = CellName::random_for_tests();
+        let child =
+            |name: &str| CellName::from(format!("{parent}/{name}").as_str());
+
+        let lock = parent_lock(&child("a"));
+        assert!(Arc::ptr_eq(&lock, &parent_lock(&child("b"))));
+        // the parent is a sibling of the other cells
+        let other = parent_lock(&CellName::random_for_tests());
+        assert!(Arc::ptr_eq(&other, &parent_lock(&parent)));
+        assert!(!Arc::ptr_eq(&lock, &other));
+
+        // creating a cgroup next to a sibling waits for it
+        let _sibling = lock.lock().expect("lock");
+        assert!(parent_lock(&child("c")).try_lock().is_err());
+    }
+}
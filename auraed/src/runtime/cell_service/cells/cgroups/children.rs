@@ -0,0 +1,300 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The cgroups of the children of a cell: the cells allocated in it by its nested auraed,
+//! which are below the leaf cgroup of the cell (`{cell}/_/{child}`).
+
+use super::{kill::kill_until_empty, KillEscalation};
+use crate::runtime::cell_service::cells::CellName;
+use nix::errno::Errno;
+use std::{fs, io, path::Path};
+use validation::ValidatedField;
+use walkdir::WalkDir;
+
+/// The interface files holding the controller values of a cell, copied when its cgroup is
+/// moved (see [reparent]). Files of controllers that are not enabled are skipped.
+/// Also reported for cgroups that look like cells (see [super::orphans]).
+pub(super) const CONTROLLER_FILES: &[&str] = &[
+    "cgroup.max.depth",
+    "cgroup.max.descendants",
+    "cpu.weight",
+    "cpu.max",
+    "cpu.uclamp.min",
+    "cpu.uclamp.max",
+    "cpuset.cpus",
+    "cpuset.mems",
+    "memory.min",
+    "memory.low",
+    "memory.high",
+    "memory.max",
+    "memory.swap.max",
+    "memory.zswap.max",
+    "pids.max",
+];
+
+/// Returns the names of the cells below the leaf cgroup at `leaf`, sorted.
+/// Other cgroups (e.g., [super::DaemonCgroup::Migrated]) are skipped, as their names are
+/// not valid cell names.
+pub fn child_cells(leaf: &Path) -> io::Result<Vec<CellName>> {
+    let mut children = vec![];
+    for entry in fs::read_dir(leaf)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        if let Ok(child) =
+            CellName::validate_for_creation(Some(name), "cell_name", None)
+        {
+            children.push(child);
+        }
+    }
+
+    children.sort();
+    Ok(children)
+}
+
+/// Kills the processes in the cgroup at `path` and the cgroups below it
+/// (see [kill_until_empty]), and removes the cgroups.
+pub fn remove_tree(path: &Path, escalation: KillEscalation) -> io::Result<()> {
+    kill_until_empty(path, escalation)?;
+    remove_dirs(path)
+}
+
+/// Moves the cgroup at `from`, with its processes and the cgroups below it, to `to`.
+///
+/// A cgroup v2 can't be renamed to another parent, so the tree is recreated at `to`:
+/// each new cgroup gets the enabled controllers and [CONTROLLER_FILES] values of the
+/// cgroup it replaces, and the processes are migrated before the old tree is removed.
+/// The moved cgroups have a new path and a new id (the inode of their directory).
+pub fn reparent(from: &Path, to: &Path) -> io::Result<()> {
+    copy_tree(from, to)?;
+    remove_dirs(from)
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    // The controllers of a cgroup are enabled in its parent
+    if let (Some(from_parent), Some(to_parent)) = (from.parent(), to.parent()) {
+        copy_subtree_control(from_parent, to_parent)?;
+    }
+
+    // Parents first, as the controllers they enable decide the files of their children
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let dest =
+            to.join(entry.path().strip_prefix(from).expect("below from"));
+        fs::create_dir(&dest)?;
+        copy_subtree_control(entry.path(), &dest)?;
+        for file in CONTROLLER_FILES {
+            copy_value(&entry.path().join(file), &dest.join(file))?;
+        }
+    }
+
+    // Processes last, as controllers can't be enabled in a cgroup that has processes
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let dest =
+            to.join(entry.path().strip_prefix(from).expect("below from"));
+        migrate_procs(entry.path(), &dest)?;
+    }
+
+    Ok(())
+}
+
+fn copy_subtree_control(from: &Path, to: &Path) -> io::Result<()> {
+    let controllers =
+        match fs::read_to_string(from.join("cgroup.subtree_control")) {
+            Ok(controllers) => controllers,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+    // Read as "cpu memory", but enabled as "+cpu +memory"
+    let controllers = controllers
+        .split_whitespace()
+        .map(|controller| format!("+{controller}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if controllers.is_empty() {
+        return Ok(());
+    }
+
+    fs::write(to.join("cgroup.subtree_control"), controllers)
+}
+
+fn copy_value(from: &Path, to: &Path) -> io::Result<()> {
+    let value = match fs::read_to_string(from) {
+        Ok(value) => value,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let value = value.trim();
+    if value.is_empty() || !to.exists() {
+        return Ok(());
+    }
+
+    fs::write(to, value)
+}
+
+fn migrate_procs(from: &Path, to: &Path) -> io::Result<()> {
+    let procs = match fs::read_to_string(from.join("cgroup.procs")) {
+        Ok(procs) => procs,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    // Writing the pid moves all threads of the process
+    for pid in procs.lines().filter(|line| !line.is_empty()) {
+        match fs::write(to.join("cgroup.procs"), pid) {
+            Ok(()) => {}
+            // exited since we read cgroup.procs
+            Err(e) if e.raw_os_error() == Some(Errno::ESRCH as i32) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the cgroup at `path` and the cgroups below it, deepest first.
+/// The cgroups must be empty. Their interface files are removed with them by the kernel.
+fn remove_dirs(path: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(path).contents_first(true) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_child_cells_skips_cgroups_that_are_not_cells() {
+        let leaf = temp_dir("children");
+        for dir in ["web", "_auraed", "db"] {
+            fs::create_dir(leaf.join(dir)).expect("create dir");
+        }
+        fs::write(leaf.join("cgroup.procs"), "").expect("write");
+
+        let children = child_cells(&leaf);
+        fs::remove_dir_all(&leaf).expect("remove dir");
+
+        assert_eq!(
+            children.expect("list children"),
+            vec![CellName::from("db"), CellName::from("web")]
+        );
+    }
+
+    #[test]
+    fn test_copy_tree_moves_the_cgroups_and_their_processes() {
+        // a child cell, with its leaf cgroup and a process, in the leaf of its parent
+        let root = temp_dir("children");
+        let from = root.join("parent/_/child");
+        fs::create_dir_all(from.join("_")).expect("create dir");
+        fs::write(root.join("parent/_/cgroup.subtree_control"), "cpu memory\n")
+            .expect("write");
+        fs::write(from.join("cgroup.subtree_control"), "memory\n")
+            .expect("write");
+        fs::write(from.join("_/cgroup.procs"), "100\n").expect("write");
+
+        let to = root.join("child");
+        let res = copy_tree(&from, &to);
+
+        let read = |path: &str| fs::read_to_string(root.join(path));
+        let old_id = fs::metadata(from.join("_")).map(|m| m.ino());
+        let moved_id = fs::metadata(to.join("_")).map(|m| m.ino());
+        let subtree_control = read("cgroup.subtree_control");
+        let child_subtree_control = read("child/cgroup.subtree_control");
+        let procs = read("child/_/cgroup.procs");
+        fs::remove_dir_all(&root).expect("remove dir");
+
+        res.expect("copy tree");
+        assert_eq!(subtree_control.expect("read"), "+cpu +memory");
+        assert_eq!(child_subtree_control.expect("read"), "+memory");
+        assert_eq!(procs.expect("read"), "100");
+        assert_ne!(moved_id.expect("moved"), old_id.expect("old"));
+    }
+
+    #[test]
+    fn test_copy_value_skips_controllers_that_are_not_enabled() {
+        let dir = temp_dir("children");
+        fs::write(dir.join("memory.max"), "1048576\n").expect("write");
+        fs::write(dir.join("cpu.max"), "max 100000\n").expect("write");
+        // the kernel only creates the files of the enabled controllers
+        let to = dir.join("to");
+        fs::create_dir(&to).expect("create dir");
+        fs::write(to.join("memory.max"), "max\n").expect("write");
+
+        let res = ["memory.max", "cpu.max", "pids.max"]
+            .into_iter()
+            .try_for_each(|file| copy_value(&dir.join(file), &to.join(file)));
+        let memory_max = fs::read_to_string(to.join("memory.max"));
+        let cpu_max = to.join("cpu.max").exists();
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        res.expect("copy values");
+        assert_eq!(memory_max.expect("read"), "1048576");
+        assert!(!cpu_max);
+    }
+
+    #[test]
+    fn test_remove_dirs_removes_deepest_first() {
+        let root = temp_dir("children");
+        fs::create_dir_all(root.join("child/_/grandchild/_")).expect("create");
+
+        let res = remove_dirs(&root.join("child"));
+        let removed = !root.join("child").exists();
+        fs::remove_dir_all(&root).expect("remove dir");
+
+        res.expect("remove dirs");
+        assert!(removed);
+    }
+}
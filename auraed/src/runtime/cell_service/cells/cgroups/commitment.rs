@@ -0,0 +1,179 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{cgroup::MICROSECONDS_PER_SECOND, Cgroup, CgroupSpec};
+use std::io;
+
+/// The capacity of the host that the resources committed to cells are compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostCapacity {
+    /// The cpus auraed may run on, in µs/s (the unit of `cpu.max`).
+    pub cpu: u64,
+    /// The memory available to the cells (see [Cgroup::available_memory]).
+    pub memory_bytes: u64,
+}
+
+impl HostCapacity {
+    pub fn detect() -> io::Result<Self> {
+        let cpus = std::thread::available_parallelism()?.get() as u64;
+        Ok(Self {
+            cpu: cpus * MICROSECONDS_PER_SECOND,
+            memory_bytes: Cgroup::available_memory()?,
+        })
+    }
+}
+
+/// The sum of the resources committed to a set of cells, as set in their [CgroupSpec]
+/// rather than their usage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceCommitment {
+    pub cpu_weight: u64,
+    /// The sum of `cpu.max`, in µs/s.
+    pub cpu_max: u64,
+    /// The number of cells without a `cpu.max`, which are not included in `cpu_max`.
+    pub cpu_unlimited_cells: u32,
+    /// The sum of `memory.max` (see [super::memory::MemoryController::effective_max]).
+    pub memory_max_bytes: u64,
+    /// The number of cells without a `memory.max`, which are not included in
+    /// `memory_max_bytes`.
+    pub memory_unlimited_cells: u32,
+}
+
+impl ResourceCommitment {
+    pub fn sum<'a>(specs: impl IntoIterator<Item = &'a CgroupSpec>) -> Self {
+        let mut commitment = Self::default();
+
+        for spec in specs {
+            let cpu = spec.cpu.as_ref();
+            if let Some(weight) = cpu.and_then(|cpu| cpu.weight.clone()) {
+                commitment.cpu_weight += weight.into_inner();
+            }
+            match cpu.and_then(|cpu| cpu.max_per_second()) {
+                Some(max) => commitment.cpu_max += max,
+                None => commitment.cpu_unlimited_cells += 1,
+            }
+
+            let memory = spec.memory.as_ref();
+            match memory.and_then(|memory| memory.effective_max()) {
+                Some(max) => commitment.memory_max_bytes += *max as u64,
+                None => commitment.memory_unlimited_cells += 1,
+            }
+        }
+
+        commitment
+    }
+
+    /// Returns true if more cpu time is committed than the host has.
+    /// Cells without a `cpu.max` are not considered.
+    pub fn cpu_over_committed(&self, capacity: &HostCapacity) -> bool {
+        self.cpu_max > capacity.cpu
+    }
+
+    /// Returns true if more memory is committed than is available to the cells.
+    /// Cells without a `memory.max` are not considered.
+    pub fn memory_over_committed(&self, capacity: &HostCapacity) -> bool {
+        self.memory_max_bytes > capacity.memory_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::cells::cgroups::{
+        cpu::CpuController,
+        memory::{MemoryController, MemoryPinning},
+        Limit, Weight,
+    };
+
+    fn spec(weight: u64, cpu_max: Option<i64>, memory_max: i64) -> CgroupSpec {
+        CgroupSpec {
+            cpu: Some(CpuController {
+                weight: Some(Weight::new(weight)),
+                max: cpu_max.map(Limit::new),
+                period: None,
+                burst: None,
+                uclamp_min: None,
+                uclamp_max: None,
+            }),
+            cpuset: None,
+            memory: Some(MemoryController {
+                max: Some(Limit::new(memory_max)),
+                shares: None,
+                zswap_max: None,
+                pinned: None,
+                pinning: MemoryPinning::default(),
+                swappiness: None,
+            }),
+            io: None,
+            pids: None,
+        }
+    }
+
+    #[test]
+    fn test_sum_of_cells() {
+        let specs = [
+            spec(100, Some(1_500_000), 3 << 30),
+            spec(200, None, 2 << 30),
+            CgroupSpec {
+                cpu: None,
+                cpuset: None,
+                memory: None,
+                io: None,
+                pids: None,
+            },
+        ];
+
+        let commitment = ResourceCommitment::sum(&specs);
+        assert_eq!(
+            commitment,
+            ResourceCommitment {
+                cpu_weight: 300,
+                cpu_max: 1_500_000,
+                cpu_unlimited_cells: 2,
+                memory_max_bytes: 5 << 30,
+                memory_unlimited_cells: 1,
+            }
+        );
+
+        let capacity = HostCapacity {
+            cpu: 2 * MICROSECONDS_PER_SECOND,
+            memory_bytes: 4 << 30,
+        };
+        assert!(!commitment.cpu_over_committed(&capacity));
+        assert!(commitment.memory_over_committed(&capacity));
+
+        let capacity = HostCapacity {
+            cpu: MICROSECONDS_PER_SECOND,
+            memory_bytes: 8 << 30,
+        };
+        assert!(commitment.cpu_over_committed(&capacity));
+        assert!(!commitment.memory_over_committed(&capacity));
+    }
+}
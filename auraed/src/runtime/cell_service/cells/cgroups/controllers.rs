@@ -0,0 +1,91 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{fs, io, path::Path};
+
+/// The controllers of a cgroup, read from its interface files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CgroupControllers {
+    /// Controllers the cgroup can use (`cgroup.controllers`), which are those its
+    /// parent enabled.
+    pub available: Vec<String>,
+    /// Controllers the cgroup enabled for its children (`cgroup.subtree_control`).
+    pub enabled: Vec<String>,
+}
+
+impl CgroupControllers {
+    /// Reads the controllers of the cgroup at `path`.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let available = fs::read_to_string(path.join("cgroup.controllers"))?;
+        let enabled = fs::read_to_string(path.join("cgroup.subtree_control"))?;
+        Ok(Self { available: parse(&available), enabled: parse(&enabled) })
+    }
+}
+
+/// Parses the space separated controllers of `cgroup.controllers` or
+/// `cgroup.subtree_control` (e.g., "cpuset cpu io memory pids\n").
+fn parse(contents: &str) -> Vec<String> {
+    contents.split_whitespace().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+
+    #[test]
+    fn test_read_controllers() {
+        let dir = temp_dir("controllers");
+        fs::write(
+            dir.join("cgroup.controllers"),
+            "cpuset cpu io memory pids\n",
+        )
+        .expect("write");
+        // no controller enabled for the children
+        fs::write(dir.join("cgroup.subtree_control"), "\n").expect("write");
+
+        let controllers = CgroupControllers::read(&dir);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            controllers.expect("read controllers"),
+            CgroupControllers {
+                available: vec![
+                    "cpuset".into(),
+                    "cpu".into(),
+                    "io".into(),
+                    "memory".into(),
+                    "pids".into(),
+                ],
+                enabled: vec![],
+            }
+        );
+    }
+}
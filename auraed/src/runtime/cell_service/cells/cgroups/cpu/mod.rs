@@ -28,10 +28,112 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-use super::{Limit, Weight};
+use super::{cgroup::MICROSECONDS_PER_SECOND, Limit, Weight};
+pub use uclamp::Uclamp;
+
+mod uclamp;
+
+/// The period of `cpu.max` if not set, in µs.
+pub const DEFAULT_PERIOD: u64 = MICROSECONDS_PER_SECOND;
+/// The shortest period of `cpu.max` accepted by the kernel, in µs.
+pub const MIN_PERIOD: u64 = 1000;
+/// The longest period of `cpu.max` accepted by the kernel, in µs.
+pub const MAX_PERIOD: u64 = MICROSECONDS_PER_SECOND;
+
+/// Returns the `cpu.max` quota of `percent` of a CPU in each `period`, e.g., 1.5 CPUs
+/// for 150%.
+pub fn max_from_percent(percent: f64, period: u64) -> Limit {
+    let quota = percent / 100.0 * period as f64;
+    Limit::new(quota.round() as i64)
+}
 
 #[derive(Debug, Clone)]
 pub struct CpuController {
     pub weight: Option<Weight>,
+    /// The quota of `cpu.max`, in µs per period.
     pub max: Option<Limit>,
+    /// The period of `cpu.max`, in µs (see [CpuController::period]).
+    pub period: Option<u64>,
+    /// `cpu.max.burst`, in µs: how much of the quota left unused in previous periods
+    /// can be used on top of the quota of a period.
+    pub burst: Option<u64>,
+    pub uclamp_min: Option<Uclamp>,
+    pub uclamp_max: Option<Uclamp>,
+}
+
+impl CpuController {
+    /// Returns the period of `cpu.max`, in µs.
+    pub fn period(&self) -> u64 {
+        self.period.unwrap_or(DEFAULT_PERIOD)
+    }
+
+    /// Returns the quota of `cpu.max` in µs/s, whatever its period, so that quotas with
+    /// different periods can be added up. Returns [None] without a quota.
+    pub fn max_per_second(&self) -> Option<u64> {
+        let max = self.max.as_deref()?;
+        Some(
+            (*max as u128 * MICROSECONDS_PER_SECOND as u128
+                / self.period() as u128) as u64,
+        )
+    }
+}
+
+impl From<CpuController> for aurae_proto::runtime::CpuController {
+    fn from(value: CpuController) -> Self {
+        let CpuController {
+            weight,
+            max,
+            period,
+            burst,
+            uclamp_min,
+            uclamp_max,
+        } = value;
+        Self {
+            weight: weight.map(|x| x.into_inner()),
+            max: max.map(|x| x.into_inner()),
+            period,
+            burst,
+            uclamp_min: uclamp_min.map(|x| x.into_inner()),
+            uclamp_max: uclamp_max.map(|x| x.into_inner()),
+            cpu_percent: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case(50.0, 1000000, 500000; "half a cpu")]
+    #[test_case(100.0, 1000000, 1000000; "one cpu")]
+    #[test_case(150.0, 1000000, 1500000; "over one cpu")]
+    #[test_case(400.0, 1000000, 4000000; "four cpus")]
+    #[test_case(0.01, 1000000, 100; "fraction of a percent")]
+    #[test_case(50.0, 100000, 50000; "half a cpu in a shorter period")]
+    #[test]
+    fn test_max_from_percent(percent: f64, period: u64, expected: i64) {
+        assert_eq!(max_from_percent(percent, period).into_inner(), expected);
+    }
+
+    #[test_case(Some(500000), None, Some(500000); "default period")]
+    #[test_case(Some(50000), Some(100000), Some(500000); "shorter period")]
+    #[test_case(Some(2000), Some(1000), Some(2000000); "two cpus")]
+    #[test_case(None, Some(100000), None; "no quota")]
+    #[test]
+    fn test_max_per_second(
+        max: Option<i64>,
+        period: Option<u64>,
+        expected: Option<u64>,
+    ) {
+        let cpu = CpuController {
+            weight: None,
+            max: max.map(Limit::new),
+            period,
+            burst: None,
+            uclamp_min: None,
+            uclamp_max: None,
+        };
+        assert_eq!(cpu.max_per_second(), expected);
+    }
 }
@@ -0,0 +1,133 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{
+    fmt::{Display, Formatter},
+    ops::Deref,
+};
+use validation::{ValidatedField, ValidationError};
+
+/// A utilization clamp (`cpu.uclamp.min` or `cpu.uclamp.max`), as a percentage
+/// of the CPU capacity.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Uclamp(u32);
+
+impl Uclamp {
+    pub fn new(percent: u32) -> Self {
+        Self(percent)
+    }
+
+    pub fn into_inner(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if `value`, as read back from the interface file, is this clamp.
+    /// The kernel stores clamps in units of 1/1024 of the capacity, and reads them
+    /// back as a percentage with two decimals, or "max" for 100% of `cpu.uclamp.max`.
+    pub fn matches(&self, value: &str) -> bool {
+        let percent = match value {
+            "max" => 100.0,
+            value => match value.parse::<f64>() {
+                Ok(percent) => percent,
+                Err(_) => return false,
+            },
+        };
+
+        (percent - f64::from(self.0)).abs() < 0.5
+    }
+}
+
+impl ValidatedField<u32> for Uclamp {
+    fn validate(
+        input: Option<u32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        let input = validation::required(input, field_name, parent_name)?;
+
+        validation::maximum_value(
+            input,
+            100,
+            "percent",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(Self(input))
+    }
+}
+
+impl Deref for Uclamp {
+    type Target = u32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for Uclamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test]
+    fn test_validation() {
+        assert!(Uclamp::validate(Some(0), "uclamp_min", None).is_ok());
+        assert!(Uclamp::validate(Some(100), "uclamp_max", None).is_ok());
+        assert!(matches!(
+            Uclamp::validate(Some(101), "uclamp_max", None),
+            Err(ValidationError::Maximum { .. })
+        ));
+    }
+
+    #[test]
+    fn test_serialized_as_percent() {
+        assert_eq!(Uclamp::new(0).to_string(), "0");
+        assert_eq!(Uclamp::new(42).to_string(), "42");
+        assert_eq!(Uclamp::new(100).to_string(), "100");
+    }
+
+    #[test_case(50, "50.00", true; "exact")]
+    #[test_case(33, "33.01", true; "rounded by the kernel")]
+    #[test_case(100, "max", true; "max")]
+    #[test_case(100, "100.00", true; "max of uclamp min")]
+    #[test_case(20, "30.00", false; "different")]
+    #[test_case(20, "garbage", false; "unparsable")]
+    #[test]
+    fn test_matches(percent: u32, value: &str, expected: bool) {
+        assert_eq!(Uclamp::new(percent).matches(value), expected);
+    }
+}
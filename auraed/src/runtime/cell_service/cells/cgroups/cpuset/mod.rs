@@ -30,12 +30,24 @@
 
 pub use cpus::Cpus;
 pub use mems::Mems;
+pub use numa::NumaTopology;
 
 mod cpus;
 mod mems;
+mod numa;
 
 #[derive(Debug, Clone)]
 pub struct CpusetController {
     pub cpus: Option<Cpus>,
     pub mems: Option<Mems>,
 }
+
+impl From<CpusetController> for aurae_proto::runtime::CpusetController {
+    fn from(value: CpusetController) -> Self {
+        let CpusetController { cpus, mems } = value;
+        Self {
+            cpus: cpus.map(|x| x.into_inner()),
+            mems: mems.map(|x| x.into_inner()),
+        }
+    }
+}
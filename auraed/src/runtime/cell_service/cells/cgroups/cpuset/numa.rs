@@ -0,0 +1,150 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{Cpus, Mems};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Where the kernel describes the NUMA nodes of the host.
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+/// The NUMA nodes of the host, and the cpus local to each of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumaTopology {
+    cpus_by_node: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+impl NumaTopology {
+    /// Reads the topology of the host.
+    /// A host whose kernel doesn't support NUMA has no nodes.
+    pub fn detect() -> io::Result<Self> {
+        Self::read(Path::new(NODE_DIR))
+    }
+
+    /// Reads the topology from `dir`, laid out as `/sys/devices/system/node`
+    /// (a `node<N>` directory with a `cpulist` per node).
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut cpus_by_node = BTreeMap::new();
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(node) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|node| node.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            let cpulist = fs::read_to_string(entry.path().join("cpulist"))?;
+            let cpus = parse_list(cpulist.trim()).ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid cpulist of node{node}: '{cpulist}'"),
+                )
+            })?;
+            let _ = cpus_by_node.insert(node, cpus);
+        }
+
+        Ok(Self { cpus_by_node })
+    }
+
+    /// Returns the nodes local to `cpus` that are not included in `mems`.
+    /// Nothing is missing if either is empty (i.e., inherited from the parent).
+    pub fn missing_local_nodes(&self, cpus: &Cpus, mems: &Mems) -> Vec<u32> {
+        let (Some(cpus), Some(mems)) = (parse_list(cpus), parse_list(mems))
+        else {
+            return vec![];
+        };
+        if cpus.is_empty() || mems.is_empty() {
+            return vec![];
+        }
+
+        self.cpus_by_node
+            .iter()
+            .filter(|(_, node_cpus)| !node_cpus.is_disjoint(&cpus))
+            .map(|(node, _)| *node)
+            .filter(|node| !mems.contains(node))
+            .collect()
+    }
+}
+
+/// Parses a list in the format of `cpuset.cpus` and `cpuset.mems` (e.g., "0-3,8").
+fn parse_list(list: &str) -> Option<BTreeSet<u32>> {
+    let mut ids = BTreeSet::new();
+    for part in list.split(',').filter(|part| !part.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None => {
+                let id = part.parse().ok()?;
+                (id, id)
+            }
+        };
+        ids.extend(start..=end);
+    }
+    Some(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use simple_test_case::test_case;
+
+    #[test_case("", &[]; "empty")]
+    #[test_case("2", &[2]; "single")]
+    #[test_case("0-3,8", &[0, 1, 2, 3, 8]; "range and single")]
+    #[test_case("1,2,", &[1, 2]; "trailing comma")]
+    #[test]
+    fn test_parse_list(list: &str, expected: &[u32]) {
+        assert_eq!(parse_list(list), Some(expected.iter().copied().collect()));
+    }
+
+    #[test]
+    fn test_parse_invalid_list() {
+        assert_eq!(parse_list("0-foo"), None);
+    }
+
+    #[test]
+    fn test_missing_node_dir_has_no_nodes() {
+        let dir = temp_path("node");
+        assert_eq!(NumaTopology::read(&dir).expect("read"), Default::default());
+    }
+}
@@ -0,0 +1,264 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Checks that auraed does not share a cgroup with the cells it creates.
+//!
+//! Cells are created as children of the cgroup at [CGROUP_ROOT]. Except for the root of
+//! the hierarchy, a cgroup v2 can either have processes or child cgroups with controllers
+//! enabled, not both (the "no internal processes" rule). When auraed runs in that cgroup
+//! (e.g., a nested auraed in the cgroup namespace of its cell), allocating cells fails in
+//! ways that are hard to attribute to auraed's own placement.
+//!
+//! The fix is to move auraed into a dedicated leaf cgroup ([DAEMON_CGROUP]) at startup.
+//! As processes inherit the cgroup of auraed, executables started outside of a cell will
+//! then be placed in that cgroup too.
+
+use super::cgroup::CGROUP_ROOT;
+use nix::errno::Errno;
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The cgroup auraed moves itself into.
+/// '_' is a disallowed character in cell names, so won't collide.
+pub const DAEMON_CGROUP: &str = "_auraed";
+
+const PROC_SELF_CGROUP: &str = "/proc/self/cgroup";
+
+/// Where auraed was found at startup, relative to the cgroup cells are created in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonCgroup {
+    /// Auraed is in the root of the hierarchy, which is exempt from the rule.
+    Root,
+    /// Auraed is not in the cgroup cells are created in.
+    Separate(String),
+    /// Auraed is in the cgroup cells are created in, and was left there.
+    Shared,
+    /// Auraed was in the cgroup cells are created in, and moved itself to the path.
+    Migrated(PathBuf),
+}
+
+/// Checks the cgroup of auraed, moving it into [DAEMON_CGROUP] if it shares the
+/// cgroup cells are created in and `migrate` is true.
+pub fn ensure_daemon_cgroup(migrate: bool) -> io::Result<DaemonCgroup> {
+    ensure_daemon_cgroup_in(
+        Path::new(CGROUP_ROOT),
+        Path::new(PROC_SELF_CGROUP),
+        std::process::id(),
+        migrate,
+    )
+}
+
+fn ensure_daemon_cgroup_in(
+    cgroup_root: &Path,
+    proc_self_cgroup: &Path,
+    pid: u32,
+    migrate: bool,
+) -> io::Result<DaemonCgroup> {
+    let cgroup = fs::read_to_string(proc_self_cgroup)?;
+    let cgroup = cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim().to_string())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no cgroup v2 entry")
+        })?;
+
+    // The path is relative to our cgroup namespace, whose root is mounted at cgroup_root
+    if cgroup != "/" {
+        return Ok(DaemonCgroup::Separate(cgroup));
+    }
+
+    // cgroup.type exists on every cgroup except the root of the hierarchy
+    if !cgroup_root.join("cgroup.type").exists() {
+        return Ok(DaemonCgroup::Root);
+    }
+
+    if !migrate {
+        return Ok(DaemonCgroup::Shared);
+    }
+
+    let daemon_cgroup = cgroup_root.join(DAEMON_CGROUP);
+    match fs::create_dir(&daemon_cgroup) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+
+    // Writing the pid moves all threads of the process
+    fs::write(daemon_cgroup.join("cgroup.procs"), pid.to_string())?;
+
+    Ok(DaemonCgroup::Migrated(daemon_cgroup))
+}
+
+/// Returns true if `error` was caused by enabling controllers in the
+/// `cgroup.subtree_control` of a cgroup with resident processes, for which the
+/// kernel fails the write with [Errno::EBUSY]. When creating the cgroup of a
+/// cell, this is the cgroup at [CGROUP_ROOT] (e.g., auraed itself, when not
+/// moved into [DAEMON_CGROUP]).
+pub fn is_controller_delegation_blocked(error: &(dyn Error + 'static)) -> bool {
+    let mut error = Some(error);
+    while let Some(e) = error {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if e.raw_os_error() == Some(Errno::EBUSY as i32) {
+                return true;
+            }
+        }
+        error = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+
+    /// A fake cgroup hierarchy and /proc/self/cgroup, with auraed in `cgroup`.
+    fn fake_backend(cgroup: &str, is_root: bool) -> (PathBuf, PathBuf) {
+        let dir = temp_path("daemon-cgroup");
+        let cgroup_root = dir.join("cgroup");
+        fs::create_dir_all(&cgroup_root).expect("create dir");
+        if !is_root {
+            fs::write(cgroup_root.join("cgroup.type"), "domain\n")
+                .expect("write cgroup.type");
+        }
+
+        let proc_self_cgroup = dir.join("cgroup-self");
+        fs::write(&proc_self_cgroup, format!("0::{cgroup}\n"))
+            .expect("write cgroup");
+
+        (dir, cgroup_root)
+    }
+
+    #[test]
+    fn test_shared_cgroup_is_migrated() {
+        let (dir, cgroup_root) = fake_backend("/", false);
+        let res = ensure_daemon_cgroup_in(
+            &cgroup_root,
+            &dir.join("cgroup-self"),
+            42,
+            true,
+        );
+        let procs = fs::read_to_string(
+            cgroup_root.join(DAEMON_CGROUP).join("cgroup.procs"),
+        );
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            res.expect("migrated"),
+            DaemonCgroup::Migrated(cgroup_root.join(DAEMON_CGROUP))
+        );
+        assert_eq!(procs.expect("cgroup.procs"), "42");
+    }
+
+    #[test]
+    fn test_migration_reuses_existing_cgroup() {
+        let (dir, cgroup_root) = fake_backend("/", false);
+        fs::create_dir(cgroup_root.join(DAEMON_CGROUP)).expect("create dir");
+        let res = ensure_daemon_cgroup_in(
+            &cgroup_root,
+            &dir.join("cgroup-self"),
+            42,
+            true,
+        );
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert!(matches!(res, Ok(DaemonCgroup::Migrated(_))));
+    }
+
+    #[test]
+    fn test_shared_cgroup_is_reported_without_migrate() {
+        let (dir, cgroup_root) = fake_backend("/", false);
+        let res = ensure_daemon_cgroup_in(
+            &cgroup_root,
+            &dir.join("cgroup-self"),
+            42,
+            false,
+        );
+        let created = cgroup_root.join(DAEMON_CGROUP).exists();
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(res.expect("checked"), DaemonCgroup::Shared);
+        assert!(!created);
+    }
+
+    #[test]
+    fn test_root_cgroup_is_left_alone() {
+        let (dir, cgroup_root) = fake_backend("/", true);
+        let res = ensure_daemon_cgroup_in(
+            &cgroup_root,
+            &dir.join("cgroup-self"),
+            42,
+            true,
+        );
+        let created = cgroup_root.join(DAEMON_CGROUP).exists();
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(res.expect("checked"), DaemonCgroup::Root);
+        assert!(!created);
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("failed to write cgroup.subtree_control")]
+    struct SubtreeControlError(#[source] io::Error);
+
+    #[test]
+    fn test_busy_subtree_control_is_delegation_blocked() {
+        let ebusy = || io::Error::from_raw_os_error(Errno::EBUSY as i32);
+        assert!(is_controller_delegation_blocked(&ebusy()));
+        assert!(is_controller_delegation_blocked(
+            &SubtreeControlError(ebusy())
+        ));
+
+        let eacces = io::Error::from_raw_os_error(Errno::EACCES as i32);
+        assert!(!is_controller_delegation_blocked(&SubtreeControlError(
+            eacces
+        )));
+    }
+
+    #[test]
+    fn test_separate_cgroup_is_left_alone() {
+        let (dir, cgroup_root) = fake_backend("/system.slice/auraed", true);
+        let res = ensure_daemon_cgroup_in(
+            &cgroup_root,
+            &dir.join("cgroup-self"),
+            42,
+            true,
+        );
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            res.expect("checked"),
+            DaemonCgroup::Separate("/system.slice/auraed".into())
+        );
+    }
+}
@@ -0,0 +1,345 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{
+    cpu::Uclamp,
+    update::{self, InterfaceFiles},
+    CgroupSpec,
+};
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Formatter},
+    io,
+};
+
+/// A controller value of a cgroup that differs from the requested value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub file: &'static str,
+    pub requested: String,
+    /// [None] if the value could not be read (e.g., the controller is not enabled).
+    pub actual: Option<String>,
+}
+
+/// The controller values of a cgroup that differ from a requested [CgroupSpec].
+/// Only the values set in the [CgroupSpec] are compared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CgroupSpecDiff(Vec<FieldDiff>);
+
+impl CgroupSpecDiff {
+    /// Reads back the values set in `spec` and returns those that differ.
+    pub fn new<F: InterfaceFiles>(
+        files: &F,
+        spec: &CgroupSpec,
+    ) -> io::Result<Self> {
+        let mut diffs = vec![];
+
+        for write in update::writes(spec) {
+            // Only the line of the device is compared for files with a line per device
+            let actual = files
+                .read(write.file)?
+                .and_then(|contents| update::replaced_value(&write, &contents));
+            if !is_same_value(write.file, &write.value, actual.as_deref()) {
+                diffs.push(FieldDiff {
+                    file: write.file,
+                    requested: write.value,
+                    actual,
+                });
+            }
+        }
+
+        Ok(Self(diffs))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FieldDiff> {
+        self.0.iter()
+    }
+}
+
+impl From<FieldDiff> for aurae_proto::runtime::CgroupFieldDiff {
+    fn from(value: FieldDiff) -> Self {
+        let FieldDiff { file, requested, actual } = value;
+        Self { file: file.into(), requested, actual }
+    }
+}
+
+impl From<CgroupSpecDiff> for aurae_proto::runtime::CgroupSpecDiff {
+    fn from(value: CgroupSpecDiff) -> Self {
+        Self { fields: value.0.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl Display for CgroupSpecDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, diff) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+
+            let actual = diff.actual.as_deref().unwrap_or("<unreadable>");
+            write!(
+                f,
+                "{}: requested '{}', found '{}'",
+                diff.file, diff.requested, actual
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_same_value(file: &str, requested: &str, actual: Option<&str>) -> bool {
+    let Some(actual) = actual else {
+        // Absent because the kernel doesn't support it, which is also skipped on allocate
+        return update::is_optional(file);
+    };
+
+    match file {
+        // The kernel normalizes cpu and memory node lists (e.g., "1,2,3" reads back as "1-3")
+        "cpuset.cpus" | "cpuset.mems" => {
+            match (parse_list(requested), parse_list(actual)) {
+                (Some(requested), Some(actual)) => requested == actual,
+                _ => false,
+            }
+        }
+        // The kernel reads clamps back with two decimals (e.g., "50" reads back as "50.00")
+        "cpu.uclamp.min" | "cpu.uclamp.max" => match requested.parse() {
+            Ok(requested) => Uclamp::new(requested).matches(actual),
+            Err(_) => false,
+        },
+        _ => requested == actual,
+    }
+}
+
+/// Parses a list in the format of `cpuset.cpus` (e.g., "0-2,4") into the set of its members.
+fn parse_list(list: &str) -> Option<BTreeSet<u32>> {
+    let mut members = BTreeSet::new();
+
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None => {
+                let member = part.parse().ok()?;
+                (member, member)
+            }
+        };
+
+        members.extend(start..=end);
+    }
+
+    Some(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::cells::cgroups::{
+        cpu::CpuController,
+        cpuset::{Cpus, CpusetController},
+        io::{IoController, IoMax},
+        update::CgroupDir,
+        Limit, Weight,
+    };
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+
+    fn cgroup_dir(files: &[(&str, &str)]) -> CgroupDir {
+        let path = temp_dir("cgroup");
+        for (file, value) in files {
+            std::fs::write(path.join(file), format!("{value}\n"))
+                .expect("write");
+        }
+        CgroupDir(path)
+    }
+
+    fn remove(dir: CgroupDir) {
+        std::fs::remove_dir_all(dir.0).expect("remove cgroup dir");
+    }
+
+    fn spec(weight: u64, cpus: &str) -> CgroupSpec {
+        CgroupSpec {
+            cpu: Some(CpuController {
+                weight: Some(Weight::new(weight)),
+                max: None,
+                period: None,
+                burst: None,
+                uclamp_min: None,
+                uclamp_max: None,
+            }),
+            cpuset: Some(CpusetController {
+                cpus: Some(Cpus::new(cpus.into())),
+                mems: None,
+            }),
+            memory: None,
+            io: None,
+            pids: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_cgroup_has_empty_diff() {
+        let dir = cgroup_dir(&[("cpu.weight", "200"), ("cpuset.cpus", "1-3")]);
+
+        let diff = CgroupSpecDiff::new(&dir, &spec(200, "1,2,3"))
+            .expect("failed to diff");
+        assert!(diff.is_empty(), "{diff}");
+
+        remove(dir);
+    }
+
+    #[test]
+    fn test_diff_names_differing_fields() {
+        let dir = cgroup_dir(&[("cpu.weight", "100")]);
+
+        let diff =
+            CgroupSpecDiff::new(&dir, &spec(200, "1")).expect("failed to diff");
+        assert_eq!(
+            diff.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                FieldDiff {
+                    file: "cpu.weight",
+                    requested: "200".into(),
+                    actual: Some("100".into()),
+                },
+                FieldDiff {
+                    file: "cpuset.cpus",
+                    requested: "1".into(),
+                    actual: None,
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "cpu.weight: requested '200', found '100'; cpuset.cpus: requested '1', found '<unreadable>'"
+        );
+
+        remove(dir);
+    }
+
+    #[test]
+    fn test_diff_into_proto() {
+        let dir = cgroup_dir(&[("cpu.weight", "100")]);
+
+        let diff =
+            CgroupSpecDiff::new(&dir, &spec(200, "1")).expect("failed to diff");
+        let diff = aurae_proto::runtime::CgroupSpecDiff::from(diff);
+        assert_eq!(diff.fields.len(), 2);
+        assert_eq!(diff.fields[0].file, "cpu.weight");
+        assert_eq!(diff.fields[0].requested, "200");
+        assert_eq!(diff.fields[0].actual.as_deref(), Some("100"));
+        assert_eq!(diff.fields[1].actual, None);
+
+        remove(dir);
+    }
+
+    #[test]
+    fn test_uclamp_compared_as_percent() {
+        let spec = |uclamp_max| CgroupSpec {
+            cpu: Some(CpuController {
+                weight: None,
+                max: None,
+                period: None,
+                burst: None,
+                uclamp_min: Some(Uclamp::new(20)),
+                uclamp_max: Some(Uclamp::new(uclamp_max)),
+            }),
+            cpuset: None,
+            memory: None,
+            io: None,
+            pids: None,
+        };
+
+        let dir = cgroup_dir(&[
+            ("cpu.uclamp.min", "20.00"),
+            ("cpu.uclamp.max", "max"),
+        ]);
+        let diff = CgroupSpecDiff::new(&dir, &spec(100)).expect("diff");
+        assert!(diff.is_empty(), "{diff}");
+        let diff = CgroupSpecDiff::new(&dir, &spec(80)).expect("diff");
+        assert_eq!(diff.iter().count(), 1);
+        remove(dir);
+
+        // a kernel without uclamp has no cpu.uclamp.* files
+        let dir = cgroup_dir(&[]);
+        let diff = CgroupSpecDiff::new(&dir, &spec(80)).expect("diff");
+        assert!(diff.is_empty(), "{diff}");
+        remove(dir);
+    }
+
+    #[test]
+    fn test_io_compared_per_device() {
+        let spec = |wiops| CgroupSpec {
+            cpu: None,
+            cpuset: None,
+            memory: None,
+            io: Some(IoController {
+                weight: None,
+                max: ["8:0", "8:16"]
+                    .iter()
+                    .map(|device| IoMax {
+                        device: device.parse().expect("device number"),
+                        rbps: None,
+                        wbps: None,
+                        riops: None,
+                        wiops: wiops.map(Limit::new),
+                    })
+                    .collect(),
+            }),
+            pids: None,
+        };
+
+        let dir = cgroup_dir(&[(
+            "io.max",
+            "8:0 rbps=max wbps=max riops=max wiops=120\n8:16 rbps=max wbps=max riops=max wiops=120",
+        )]);
+        let diff = CgroupSpecDiff::new(&dir, &spec(Some(120))).expect("diff");
+        assert!(diff.is_empty(), "{diff}");
+
+        // devices without limits are not listed
+        let diff = CgroupSpecDiff::new(&dir, &spec(None)).expect("diff");
+        assert_eq!(diff.iter().count(), 2);
+        remove(dir);
+
+        let dir = cgroup_dir(&[("io.max", "")]);
+        let diff = CgroupSpecDiff::new(&dir, &spec(None)).expect("diff");
+        assert!(diff.is_empty(), "{diff}");
+        remove(dir);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse_list(""), Some(BTreeSet::new()));
+        assert_eq!(parse_list("0-2,4"), Some(BTreeSet::from([0, 1, 2, 4])));
+        assert_eq!(parse_list("1:2"), None);
+    }
+}
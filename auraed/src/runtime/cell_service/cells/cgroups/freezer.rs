@@ -0,0 +1,318 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::CgroupStats;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+use walkdir::WalkDir;
+
+/// How often `cgroup.events` is checked while waiting for a cgroup to freeze.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Freezes the processes in the cgroup at `path` and the cgroups below it, and waits
+/// until `cgroup.events` reports the cgroup as frozen.
+///
+/// Returns a [io::ErrorKind::TimedOut] error if the cgroup is not frozen within
+/// `timeout`, in which case the cgroup is thawed again.
+pub fn freeze(path: &Path, timeout: Duration) -> io::Result<()> {
+    fs::write(path.join("cgroup.freeze"), "1")?;
+
+    let start = Instant::now();
+    while !is_frozen(path)? {
+        if start.elapsed() >= timeout {
+            thaw(path)?;
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(())
+}
+
+/// Thaws the cgroup at `path`. Does nothing if the cgroup is not frozen.
+pub fn thaw(path: &Path) -> io::Result<()> {
+    fs::write(path.join("cgroup.freeze"), "0")
+}
+
+/// Returns true if the processes in the cgroup at `path`, and below it, are frozen.
+pub fn is_frozen(path: &Path) -> io::Result<bool> {
+    let events = fs::read_to_string(path.join("cgroup.events"))?;
+    Ok(events.lines().any(|line| line == "frozen 1"))
+}
+
+/// Whether the processes of a cgroup are frozen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezeState {
+    Thawed,
+    /// The cgroup was asked to freeze, but some of its processes, or those of the
+    /// cgroups below it, are still running (e.g., in uninterruptible sleep).
+    Freezing,
+    Frozen,
+}
+
+impl FreezeState {
+    /// Reads the freeze state of the cgroup at `path` from `cgroup.freeze` and
+    /// `cgroup.events`.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let freeze = fs::read_to_string(path.join("cgroup.freeze"))?;
+        let events = fs::read_to_string(path.join("cgroup.events"))?;
+        Ok(Self::parse(&freeze, &events))
+    }
+
+    /// `cgroup.events` only reports `frozen 1` once every process has stopped, so a
+    /// cgroup with `cgroup.freeze` set that isn't frozen yet is freezing. Thawing takes
+    /// effect immediately, so a cgroup with `cgroup.freeze` unset is thawed.
+    fn parse(freeze: &str, events: &str) -> Self {
+        if freeze.trim() != "1" {
+            return Self::Thawed;
+        }
+
+        if events.lines().any(|line| line.trim() == "frozen 1") {
+            Self::Frozen
+        } else {
+            Self::Freezing
+        }
+    }
+}
+
+impl From<FreezeState> for aurae_proto::runtime::FreezeState {
+    fn from(value: FreezeState) -> Self {
+        match value {
+            FreezeState::Thawed => Self::Thawed,
+            FreezeState::Freezing => Self::Freezing,
+            FreezeState::Frozen => Self::Frozen,
+        }
+    }
+}
+
+/// The state of a frozen cgroup. As its processes can't run, the values are read
+/// without them changing underneath.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrozenSnapshot {
+    pub stats: CgroupStats,
+    /// The processes in the cgroup and the cgroups below it, in ascending order.
+    pub pids: Vec<i32>,
+    /// The values of `memory.stat`, if requested. Empty if the memory controller is not
+    /// enabled.
+    pub memory_stat: BTreeMap<String, u64>,
+}
+
+impl FrozenSnapshot {
+    /// Reads the state of the frozen cgroup at `path`.
+    ///
+    /// Returns a [io::ErrorKind::InvalidInput] error if the cgroup is not frozen, or was
+    /// thawed while reading, as the values would not be consistent.
+    pub fn read(path: &Path, include_memory_stat: bool) -> io::Result<Self> {
+        if !is_frozen(path)? {
+            return Err(not_frozen());
+        }
+
+        let stats = CgroupStats::read(path)?;
+        let pids = pids(path)?;
+        let memory_stat = if include_memory_stat {
+            memory_stat(path)?
+        } else {
+            BTreeMap::new()
+        };
+
+        if !is_frozen(path)? {
+            return Err(not_frozen());
+        }
+
+        Ok(Self { stats, pids, memory_stat })
+    }
+}
+
+impl From<FrozenSnapshot> for aurae_proto::runtime::CellServiceDrainResponse {
+    fn from(value: FrozenSnapshot) -> Self {
+        let FrozenSnapshot { stats, pids, memory_stat } = value;
+        Self {
+            stats: Some(stats.into()),
+            pids,
+            memory_stat: memory_stat.into_iter().collect(),
+        }
+    }
+}
+
+fn not_frozen() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "cgroup is not frozen")
+}
+
+fn pids(path: &Path) -> io::Result<Vec<i32>> {
+    let mut pids = vec![];
+
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let procs = match fs::read_to_string(entry.path().join("cgroup.procs"))
+        {
+            Ok(procs) => procs,
+            // removed since we listed it
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        pids.extend(procs.lines().filter_map(|line| line.parse::<i32>().ok()));
+    }
+
+    pids.sort_unstable();
+    Ok(pids)
+}
+
+fn memory_stat(path: &Path) -> io::Result<BTreeMap<String, u64>> {
+    let stat = match fs::read_to_string(path.join("memory.stat")) {
+        Ok(stat) => stat,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(BTreeMap::new())
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(stat
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key.to_string(), value.trim().parse().ok()?))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use std::path::PathBuf;
+
+    fn fake_cgroup(frozen: bool) -> PathBuf {
+        let dir = temp_path("freezer");
+        fs::create_dir_all(dir.join("_")).expect("create dir");
+        fs::write(
+            dir.join("cgroup.events"),
+            format!("populated 1\nfrozen {}\n", u8::from(frozen)),
+        )
+        .expect("write cgroup.events");
+        fs::write(dir.join("cgroup.procs"), "").expect("write");
+        fs::write(dir.join("_/cgroup.procs"), "42\n7\n").expect("write");
+        fs::write(dir.join("pids.current"), "3\n").expect("write");
+        fs::write(dir.join("memory.stat"), "anon 4096\nfile 8192\n")
+            .expect("write");
+        dir
+    }
+
+    #[test]
+    fn test_freeze_waits_for_frozen() {
+        let dir = fake_cgroup(true);
+
+        let res = freeze(&dir, Duration::from_millis(100));
+        let freeze = fs::read_to_string(dir.join("cgroup.freeze"));
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        res.expect("cgroup is frozen");
+        assert_eq!(freeze.expect("read cgroup.freeze"), "1");
+    }
+
+    #[test]
+    fn test_freeze_timeout_thaws() {
+        let dir = fake_cgroup(false);
+
+        let res = freeze(&dir, Duration::from_millis(20));
+        let freeze = fs::read_to_string(dir.join("cgroup.freeze"));
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            res.expect_err("cgroup never freezes").kind(),
+            io::ErrorKind::TimedOut
+        );
+        assert_eq!(freeze.expect("read cgroup.freeze"), "0");
+    }
+
+    #[test]
+    fn test_parse_freeze_state() {
+        let events = |frozen| format!("populated 1\nfrozen {frozen}\n");
+
+        assert_eq!(FreezeState::parse("0\n", &events(0)), FreezeState::Thawed);
+        assert_eq!(
+            FreezeState::parse("1\n", &events(0)),
+            FreezeState::Freezing
+        );
+        assert_eq!(FreezeState::parse("1\n", &events(1)), FreezeState::Frozen);
+        // cgroup.events may not have caught up with a thaw yet
+        assert_eq!(FreezeState::parse("0\n", &events(1)), FreezeState::Thawed);
+    }
+
+    #[test]
+    fn test_read_freeze_state() {
+        let dir = fake_cgroup(true);
+        fs::write(dir.join("cgroup.freeze"), "1\n").expect("write");
+
+        let state = FreezeState::read(&dir);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(state.expect("read freeze state"), FreezeState::Frozen);
+    }
+
+    #[test]
+    fn test_snapshot_of_frozen_cgroup() {
+        let dir = fake_cgroup(true);
+
+        let snapshot = FrozenSnapshot::read(&dir, true);
+        let without_memory_stat = FrozenSnapshot::read(&dir, false);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        let snapshot = snapshot.expect("snapshot");
+        assert_eq!(snapshot.pids, vec![7, 42]);
+        assert_eq!(snapshot.stats.pids_current, Some(3));
+        assert_eq!(
+            snapshot.memory_stat,
+            BTreeMap::from([("anon".into(), 4096), ("file".into(), 8192)])
+        );
+        assert!(without_memory_stat.expect("snapshot").memory_stat.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_of_thawed_cgroup_is_error() {
+        let dir = fake_cgroup(false);
+
+        let res = FrozenSnapshot::read(&dir, false);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            res.expect_err("cgroup is not frozen").kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,115 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use validation::{ValidatedField, ValidationError};
+
+/// The number of a block device, as "MAJOR:MINOR" in the interface files of the
+/// io controller.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct DeviceNumber {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl DeviceNumber {
+    #[cfg(test)]
+    pub fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl FromStr for DeviceNumber {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once(':').ok_or(())?;
+        let parse = |x: &str| match x.bytes().all(|b| b.is_ascii_digit()) {
+            true => x.parse().map_err(|_| ()),
+            false => Err(()),
+        };
+        Ok(Self { major: parse(major)?, minor: parse(minor)? })
+    }
+}
+
+impl ValidatedField<String> for DeviceNumber {
+    fn validate(
+        input: Option<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        let input = validation::required(input, field_name, parent_name)?;
+
+        input.parse().map_err(|_| ValidationError::Invalid {
+            field: validation::field_name(field_name, parent_name),
+        })
+    }
+}
+
+impl Display for DeviceNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test]
+    fn test_validation_success() {
+        let device =
+            DeviceNumber::validate(Some("8:16".into()), "device", None)
+                .expect("valid device");
+        assert_eq!(device, DeviceNumber::new(8, 16));
+        assert_eq!(device.to_string(), "8:16");
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("8"; "no minor")]
+    #[test_case("8:"; "empty minor")]
+    #[test_case(":16"; "empty major")]
+    #[test_case("8:16:0"; "extra number")]
+    #[test_case("8,16"; "comma seperation")]
+    #[test_case("+8:16"; "sign")]
+    #[test_case("sda"; "device name")]
+    #[test_case("8:4294967296"; "overflow")]
+    #[test]
+    fn test_validation_failure(input: &str) {
+        assert!(matches!(
+            DeviceNumber::validate(Some(input.into()), "device", None),
+            Err(ValidationError::Invalid { .. })
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{Limit, Weight};
+pub use device_number::DeviceNumber;
+use std::fmt::{Display, Formatter};
+
+mod device_number;
+
+#[derive(Debug, Clone)]
+pub struct IoController {
+    /// Written to `io.weight` as the default weight of the devices.
+    pub weight: Option<Weight>,
+    /// Written to `io.max`, one device at a time. Devices are unique.
+    pub max: Vec<IoMax>,
+}
+
+impl From<IoController> for aurae_proto::runtime::IoController {
+    fn from(value: IoController) -> Self {
+        let IoController { weight, max } = value;
+        Self {
+            weight: weight.map(|x| x.into_inner()),
+            max: max.into_iter().map(|x| x.into()).collect(),
+        }
+    }
+}
+
+/// The throttling limits of a device, a line of `io.max`.
+/// Limits that are not set are unlimited ("max").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoMax {
+    pub device: DeviceNumber,
+    pub rbps: Option<Limit>,
+    pub wbps: Option<Limit>,
+    pub riops: Option<Limit>,
+    pub wiops: Option<Limit>,
+}
+
+impl IoMax {
+    /// Returns the line of `io.max` of a device without limits, which is not listed
+    /// when reading the file.
+    pub fn unlimited(device: &str) -> String {
+        format!("{device} rbps=max wbps=max riops=max wiops=max")
+    }
+}
+
+impl Display for IoMax {
+    /// Formats the limits as a line of `io.max`, with every key, so that the line
+    /// reads back the same (e.g., "8:16 rbps=2097152 wbps=max riops=max wiops=120").
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Self { device, rbps, wbps, riops, wiops } = self;
+        write!(f, "{device}")?;
+        for (key, limit) in
+            [("rbps", rbps), ("wbps", wbps), ("riops", riops), ("wiops", wiops)]
+        {
+            match limit {
+                Some(limit) => write!(f, " {key}={limit}")?,
+                None => write!(f, " {key}=max")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<IoMax> for aurae_proto::runtime::IoMax {
+    fn from(value: IoMax) -> Self {
+        let IoMax { device, rbps, wbps, riops, wiops } = value;
+        Self {
+            device: device.to_string(),
+            rbps: rbps.map(|x| x.into_inner()),
+            wbps: wbps.map(|x| x.into_inner()),
+            riops: riops.map(|x| x.into_inner()),
+            wiops: wiops.map(|x| x.into_inner()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_max_line() {
+        let io_max = IoMax {
+            device: DeviceNumber::new(8, 16),
+            rbps: Some(Limit::new(2097152)),
+            wbps: None,
+            riops: None,
+            wiops: Some(Limit::new(120)),
+        };
+
+        assert_eq!(
+            io_max.to_string(),
+            "8:16 rbps=2097152 wbps=max riops=max wiops=120"
+        );
+        assert_eq!(
+            IoMax::unlimited("8:16"),
+            IoMax { rbps: None, wiops: None, ..io_max }.to_string()
+        );
+    }
+}
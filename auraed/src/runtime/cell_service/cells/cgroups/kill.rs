@@ -0,0 +1,222 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use nix::{errno::Errno, sys::signal::Signal, unistd::Pid};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+use walkdir::WalkDir;
+
+/// How hard we try to kill the processes left in a cgroup (see [kill_until_empty]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KillEscalation {
+    /// Number of times the processes are sent a SIGKILL before giving up.
+    pub attempts: u32,
+    /// Time given to the processes to exit after each SIGKILL.
+    pub delay: Duration,
+}
+
+impl Default for KillEscalation {
+    fn default() -> Self {
+        Self { attempts: 5, delay: Duration::from_millis(100) }
+    }
+}
+
+/// Sends a SIGKILL to the processes in the cgroup at `path` and the cgroups below it,
+/// until `cgroup.events` no longer reports the cgroup as populated.
+/// Blocks for up to `attempts * delay` of [KillEscalation].
+///
+/// Returns an [Errno::EBUSY] error if processes are left after the last attempt
+/// (e.g., a process stuck in uninterruptible sleep).
+pub fn kill_until_empty(
+    path: &Path,
+    escalation: KillEscalation,
+) -> io::Result<()> {
+    for _ in 0..escalation.attempts {
+        if !is_populated(path)? {
+            return Ok(());
+        }
+
+        kill_all(path)?;
+        thread::sleep(escalation.delay);
+    }
+
+    if is_populated(path)? {
+        return Err(io::Error::from_raw_os_error(Errno::EBUSY as i32));
+    }
+
+    Ok(())
+}
+
+/// Returns true if there are live processes in the cgroup at `path`, or below it.
+pub(super) fn is_populated(path: &Path) -> io::Result<bool> {
+    let events = fs::read_to_string(path.join("cgroup.events"))?;
+    Ok(events.lines().any(|line| line == "populated 1"))
+}
+
+/// Sends a SIGKILL to the processes in the cgroup at `path` and the cgroups below it.
+///
+/// Writes to `cgroup.kill` (since Linux 5.14), which kills every process of the
+/// hierarchy at once, including those forked while killing. On older kernels, the
+/// processes listed in the `cgroup.procs` of each cgroup are signaled one by one, which
+/// misses the processes forked after their cgroup was listed (e.g., by a process
+/// forking in a tight loop); [kill_until_empty] tries again for those.
+fn kill_all(path: &Path) -> io::Result<()> {
+    // Files can't be created in cgroupfs, so the file is opened without creating it:
+    // writing to a missing file would fail with EACCES rather than ENOENT.
+    match OpenOptions::new()
+        .write(true)
+        .open(path.join("cgroup.kill"))
+        .and_then(|mut file| file.write_all(b"1"))
+    {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let procs = match fs::read_to_string(entry.path().join("cgroup.procs"))
+        {
+            Ok(procs) => procs,
+            // removed since we listed it
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        for pid in procs.lines().filter_map(|line| line.parse().ok()) {
+            match nix::sys::signal::kill(Pid::from_raw(pid), Signal::SIGKILL) {
+                // exited since we read cgroup.procs
+                Ok(()) | Err(Errno::ESRCH) => {}
+                Err(e) => return Err(io::Error::from_raw_os_error(e as i32)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+
+    fn fake_cgroup(populated: bool) -> std::path::PathBuf {
+        let dir = temp_dir("kill");
+        fs::write(
+            dir.join("cgroup.events"),
+            format!("populated {}\nfrozen 0\n", u8::from(populated)),
+        )
+        .expect("write cgroup.events");
+        fs::write(dir.join("cgroup.kill"), "").expect("write cgroup.kill");
+        dir
+    }
+
+    const ESCALATION: KillEscalation =
+        KillEscalation { attempts: 3, delay: Duration::from_millis(1) };
+
+    #[test]
+    fn test_empty_cgroup_is_not_killed() {
+        let dir = fake_cgroup(false);
+
+        let res = kill_until_empty(&dir, ESCALATION);
+        let killed = fs::read_to_string(dir.join("cgroup.kill"));
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        res.expect("cgroup is empty");
+        assert_eq!(killed.expect("read cgroup.kill"), "");
+    }
+
+    #[test]
+    fn test_cgroup_that_stays_populated_is_an_error() {
+        let dir = fake_cgroup(true);
+
+        let res = kill_until_empty(&dir, ESCALATION);
+        let killed = fs::read_to_string(dir.join("cgroup.kill"));
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            res.expect_err("cgroup is populated").raw_os_error(),
+            Some(Errno::EBUSY as i32)
+        );
+        assert_eq!(killed.expect("read cgroup.kill"), "1");
+    }
+
+    #[test]
+    fn test_failure_to_kill_is_not_retried() {
+        let dir = fake_cgroup(true);
+        fs::remove_file(dir.join("cgroup.kill")).expect("remove cgroup.kill");
+        fs::create_dir(dir.join("cgroup.kill")).expect("create dir");
+        let escalation =
+            KillEscalation { attempts: 3, delay: Duration::from_secs(10) };
+
+        let started = std::time::Instant::now();
+        let res = kill_until_empty(&dir, escalation);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        let e = res.expect_err("cgroup.kill can't be written");
+        assert_ne!(e.raw_os_error(), Some(Errno::EBUSY as i32));
+        assert!(started.elapsed() < escalation.delay);
+    }
+
+    #[test]
+    fn test_processes_are_signaled_without_cgroup_kill() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = fake_cgroup(true);
+        fs::remove_file(dir.join("cgroup.kill")).expect("remove cgroup.kill");
+        let nested = dir.join("nested");
+        fs::create_dir(&nested).expect("create nested cgroup");
+
+        let mut process = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .expect("failed to spawn");
+        fs::write(nested.join("cgroup.procs"), format!("{}\n", process.id()))
+            .expect("write cgroup.procs");
+
+        let res = kill_all(&dir);
+        let killed = dir.join("cgroup.kill").exists();
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        res.expect("processes are killed");
+        assert!(!killed);
+        let exit_status = process.wait().expect("failed to wait");
+        assert_eq!(exit_status.signal(), Some(libc::SIGKILL));
+    }
+}
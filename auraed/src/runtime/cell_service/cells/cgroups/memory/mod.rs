@@ -0,0 +1,201 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{Limit, Weight};
+pub use shares::{available_memory, memory_lows};
+pub use swappiness::{check_swappiness, SwappinessUnsupported};
+
+mod shares;
+mod swappiness;
+
+#[derive(Debug, Clone)]
+pub struct MemoryController {
+    pub max: Option<Limit>,
+    /// Not a cgroup value, translated into `memory.low` across sibling cells
+    /// (see [memory_lows]).
+    pub shares: Option<Weight>,
+    /// Written to `memory.zswap.max`, which only exists if zswap is configured.
+    /// Not supported by cgroups_rs (see [super::Cgroup::set_zswap_max]).
+    pub zswap_max: Option<Limit>,
+    /// Memory kept in RAM for the cell, as chosen by `pinning`.
+    pub pinned: Option<Limit>,
+    pub pinning: MemoryPinning,
+    /// `memory.swappiness`, which only exists in cgroup v1, so it is always rejected
+    /// (see [check_swappiness]).
+    pub swappiness: Option<u8>,
+}
+
+impl MemoryController {
+    /// Returns the value of `memory.max`, which defaults to the pinned memory.
+    pub fn effective_max(&self) -> Option<Limit> {
+        self.max.clone().or_else(|| self.pinned.clone())
+    }
+
+    /// Returns the value of `memory.min`, which is the pinned memory if it is reserved.
+    /// Not supported by cgroups_rs (see [super::Cgroup::set_memory_min]).
+    pub fn min(&self) -> Option<Limit> {
+        self.pinned.clone().filter(|_| self.pinning.reserves())
+    }
+
+    /// Returns the `RLIMIT_MEMLOCK` of the processes of the cell,
+    /// which is the pinned memory if it is locked.
+    pub fn memlock_limit(&self) -> Option<u64> {
+        let pinned = self.pinned.as_ref().filter(|_| self.pinning.locks())?;
+        Some(**pinned as u64)
+    }
+}
+
+impl From<MemoryController> for aurae_proto::runtime::MemoryController {
+    fn from(value: MemoryController) -> Self {
+        let MemoryController {
+            max,
+            shares,
+            zswap_max,
+            pinned,
+            pinning,
+            swappiness,
+        } = value;
+        Self {
+            max: max.map(|x| x.into_inner()),
+            shares: shares.map(|x| x.into_inner()),
+            zswap_max: zswap_max.map(|x| x.into_inner()),
+            pinned: pinned.map(|x| x.into_inner()),
+            pinning: pinning as i32,
+            swappiness: swappiness.map(u32::from),
+        }
+    }
+}
+
+/// How the pinned memory of a cell is kept in RAM.
+/// The values match the `MemoryPinning` enum of the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryPinning {
+    /// `memory.min` of the cell is set to the pinned memory.
+    #[default]
+    Reserve = 0,
+    /// `RLIMIT_MEMLOCK` of the processes of the cell is raised to the pinned memory,
+    /// for them to `mlockall`. Locks are dropped on exec, so we can't lock for them.
+    Mlock = 1,
+    ReserveAndMlock = 2,
+}
+
+impl TryFrom<i32> for MemoryPinning {
+    type Error = ();
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Reserve),
+            1 => Ok(Self::Mlock),
+            2 => Ok(Self::ReserveAndMlock),
+            _ => Err(()),
+        }
+    }
+}
+
+impl MemoryPinning {
+    fn reserves(self) -> bool {
+        matches!(self, Self::Reserve | Self::ReserveAndMlock)
+    }
+
+    fn locks(self) -> bool {
+        matches!(self, Self::Mlock | Self::ReserveAndMlock)
+    }
+}
+
+/// Returns the pinned memory of the cells that exceeds `budget`, if any.
+pub fn pinned_over_budget<I>(budget: Option<u64>, pinned: I) -> Option<u64>
+where
+    I: IntoIterator<Item = u64>,
+{
+    let budget = budget?;
+    let total = pinned.into_iter().fold(0u64, |x, y| x.saturating_add(y));
+    total.checked_sub(budget).filter(|over| *over > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_pinned_within_budget() {
+        assert_eq!(pinned_over_budget(Some(1024 * MIB), [512 * MIB]), None);
+        assert_eq!(
+            pinned_over_budget(Some(1024 * MIB), [512 * MIB, 512 * MIB]),
+            None
+        );
+        assert_eq!(pinned_over_budget(Some(1024 * MIB), []), None);
+    }
+
+    #[test]
+    fn test_pinned_over_budget() {
+        assert_eq!(
+            pinned_over_budget(Some(1024 * MIB), [512 * MIB, 768 * MIB]),
+            Some(256 * MIB)
+        );
+        assert_eq!(pinned_over_budget(Some(0), [1]), Some(1));
+        assert_eq!(
+            pinned_over_budget(Some(1024 * MIB), [u64::MAX, u64::MAX]),
+            Some(u64::MAX - 1024 * MIB)
+        );
+    }
+
+    #[test]
+    fn test_pinned_without_budget() {
+        assert_eq!(pinned_over_budget(None, [u64::MAX]), None);
+    }
+
+    #[test]
+    fn test_pinning_mechanisms() {
+        let memory = |pinning| MemoryController {
+            max: None,
+            shares: None,
+            zswap_max: None,
+            pinned: Some(Limit::new(64)),
+            pinning,
+            swappiness: None,
+        };
+
+        let reserved = memory(MemoryPinning::Reserve);
+        assert_eq!(reserved.min(), Some(Limit::new(64)));
+        assert_eq!(reserved.effective_max(), Some(Limit::new(64)));
+        assert_eq!(reserved.memlock_limit(), None);
+
+        let locked = memory(MemoryPinning::Mlock);
+        assert_eq!(locked.min(), None);
+        assert_eq!(locked.effective_max(), Some(Limit::new(64)));
+        assert_eq!(locked.memlock_limit(), Some(64));
+
+        let both = memory(MemoryPinning::ReserveAndMlock);
+        assert_eq!(both.min(), Some(Limit::new(64)));
+        assert_eq!(both.memlock_limit(), Some(64));
+    }
+}
@@ -0,0 +1,127 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Memory shares: an approximation of a memory weight, which cgroup v2 does not have.
+//!
+//! The memory available to a set of sibling cells is divided between the siblings that
+//! have shares, in proportion to their shares, and each sibling's portion is set as its
+//! `memory.low`. Under memory pressure, the kernel then reclaims from siblings in roughly
+//! that proportion. Unlike a real weight, this is a protection of a fixed amount: it is
+//! recomputed when siblings with shares are allocated or freed, but not when the memory
+//! available to the siblings changes.
+
+use std::{fs, io, path::Path};
+
+/// Returns the `memory.low` of each share in `shares`, dividing `available` bytes
+/// in proportion to the shares. Rounds down, so the sum never exceeds `available`.
+pub fn memory_lows(available: u64, shares: &[u64]) -> Vec<u64> {
+    let total: u128 = shares.iter().map(|&share| u128::from(share)).sum();
+    if total == 0 {
+        return vec![0; shares.len()];
+    }
+
+    shares
+        .iter()
+        .map(|&share| {
+            let low = u128::from(available) * u128::from(share) / total;
+            // low <= available, as share <= total
+            low as u64
+        })
+        .collect()
+}
+
+/// Returns the memory available to the cells under the cgroup at `parent`: its
+/// `memory.max` if it has a limit, otherwise the memory of the host.
+pub fn available_memory(parent: &Path) -> io::Result<u64> {
+    match fs::read_to_string(parent.join("memory.max")) {
+        Ok(max) if max.trim() != "max" => max
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        // The root cgroup has no memory.max
+        Ok(_) => host_memory(Path::new("/proc/meminfo")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            host_memory(Path::new("/proc/meminfo"))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns MemTotal of `meminfo` in bytes.
+fn host_memory(meminfo: &Path) -> io::Result<u64> {
+    fs::read_to_string(meminfo)?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|total| total.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no MemTotal in meminfo")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+    use simple_test_case::test_case;
+
+    #[test_case(1000, &[1, 1], &[500, 500]; "equal shares")]
+    #[test_case(1000, &[1, 3], &[250, 750]; "proportional")]
+    #[test_case(1000, &[1, 1, 1], &[333, 333, 333]; "rounds down")]
+    #[test_case(1000, &[5], &[1000]; "single sibling")]
+    #[test_case(1000, &[], &[]; "no siblings")]
+    #[test_case(u64::MAX, &[10000, 10000], &[u64::MAX / 2, u64::MAX / 2]; "no overflow")]
+    #[test]
+    fn test_memory_lows(available: u64, shares: &[u64], expected: &[u64]) {
+        assert_eq!(memory_lows(available, shares), expected);
+    }
+
+    #[test]
+    fn test_memory_lows_never_exceed_available() {
+        let lows = memory_lows(1 << 30, &[7, 13, 1, 9999]);
+        assert!(lows.iter().sum::<u64>() <= 1 << 30);
+    }
+
+    #[test]
+    fn test_available_memory() {
+        let dir = temp_dir("memory");
+        std::fs::write(dir.join("memory.max"), "1073741824\n").expect("write");
+        std::fs::write(dir.join("meminfo"), "MemTotal:       16318480 kB\n")
+            .expect("write");
+
+        let limited = available_memory(&dir);
+        let host = host_memory(&dir.join("meminfo"));
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(limited.expect("available"), 1 << 30);
+        assert_eq!(host.expect("host memory"), 16318480 * 1024);
+    }
+}
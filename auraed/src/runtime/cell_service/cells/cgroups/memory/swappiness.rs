@@ -0,0 +1,67 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Swappiness: how readily the kernel swaps out the anonymous memory of a cell, rather than
+//! reclaiming its page cache, from 0 to 100.
+//!
+//! Per-cgroup swappiness only exists in cgroup v1 (`memory.swappiness`). cgroup v2 has no
+//! such interface file: the system-wide `vm.swappiness` applies to every cgroup. auraed
+//! only uses the cgroup v2 hierarchy (see [super::super::Cgroup]), so a cell requesting a
+//! swappiness is rejected rather than allocated with the system-wide value.
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+    "swappiness is unsupported on cgroup v2, where only the system-wide vm.swappiness applies"
+)]
+pub struct SwappinessUnsupported;
+
+/// Returns an error if `swappiness` is set, as cgroup v2 has no per-cgroup swappiness.
+pub fn check_swappiness(
+    swappiness: Option<u8>,
+) -> Result<(), SwappinessUnsupported> {
+    match swappiness {
+        Some(_) => Err(SwappinessUnsupported),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swappiness_is_unsupported() {
+        let err = check_swappiness(Some(10)).expect_err("unsupported");
+        assert!(err.to_string().contains("unsupported on cgroup v2"));
+        assert_eq!(check_swappiness(None), Ok(()));
+    }
+}
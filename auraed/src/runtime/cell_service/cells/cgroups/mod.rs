@@ -29,19 +29,51 @@
 \* -------------------------------------------------------------------------- */
 
 pub use cgroup::Cgroup;
+pub use commitment::{HostCapacity, ResourceCommitment};
+pub use controllers::CgroupControllers;
 use cpu::CpuController;
 use cpuset::CpusetController;
+pub use daemon_cgroup::{
+    ensure_daemon_cgroup, is_controller_delegation_blocked, DaemonCgroup,
+};
+pub use diff::CgroupSpecDiff;
+pub use freezer::{FreezeState, FrozenSnapshot};
+use io::IoController;
+pub use kill::KillEscalation;
 pub use limit::Limit;
+use memory::MemoryController;
+pub use nesting::{is_nesting_limit_reached, NestingLimits};
+pub use orphans::OrphanedCgroup;
+use pids::PidsController;
+pub use stats::CgroupStats;
+pub use update::UpdateError;
 pub use weight::Weight;
 
 mod cgroup;
+mod children;
+mod commitment;
+mod controllers;
 pub mod cpu;
 pub mod cpuset;
+mod daemon_cgroup;
+mod diff;
+mod freezer;
+pub mod io;
+mod kill;
 mod limit;
+pub mod memory;
+mod nesting;
+mod orphans;
+pub mod pids;
+mod stats;
+mod update;
 mod weight;
 
 #[derive(Debug, Clone)]
 pub struct CgroupSpec {
     pub cpu: Option<CpuController>,
     pub cpuset: Option<CpusetController>,
+    pub memory: Option<MemoryController>,
+    pub io: Option<IoController>,
+    pub pids: Option<PidsController>,
 }
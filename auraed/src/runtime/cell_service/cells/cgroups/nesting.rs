@@ -0,0 +1,111 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use nix::errno::Errno;
+use std::{error::Error, fs, io, path::Path};
+
+/// Limits on the cgroups that may be created below the cgroup of a cell.
+/// The `_` leaf the processes of the cell are placed in counts as one level and
+/// one descendant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NestingLimits {
+    /// Written to `cgroup.max.depth`. Unlimited if [None].
+    pub max_depth: Option<u32>,
+    /// Written to `cgroup.max.descendants`. Unlimited if [None].
+    pub max_descendants: Option<u32>,
+}
+
+impl NestingLimits {
+    /// Writes the limits that are set to the cgroup at `path`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(max_depth) = self.max_depth {
+            fs::write(path.join("cgroup.max.depth"), max_depth.to_string())?;
+        }
+        if let Some(max_descendants) = self.max_descendants {
+            fs::write(
+                path.join("cgroup.max.descendants"),
+                max_descendants.to_string(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `error` was caused by creating a cgroup beyond the
+/// `cgroup.max.depth` or `cgroup.max.descendants` of an ancestor, for which the
+/// kernel fails `mkdir` with [Errno::EAGAIN].
+pub fn is_nesting_limit_reached(error: &(dyn Error + 'static)) -> bool {
+    let mut error = Some(error);
+    while let Some(e) = error {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if e.raw_os_error() == Some(Errno::EAGAIN as i32) {
+                return true;
+            }
+        }
+        error = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+
+    #[test]
+    fn test_write_nesting_limits() {
+        let dir = temp_dir("nesting");
+
+        let limits =
+            NestingLimits { max_depth: Some(2), max_descendants: None };
+        let written = limits.write(&dir);
+        let max_depth = fs::read_to_string(dir.join("cgroup.max.depth"));
+        let has_max_descendants = dir.join("cgroup.max.descendants").exists();
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        written.expect("write limits");
+        assert_eq!(max_depth.expect("read cgroup.max.depth"), "2");
+        assert!(!has_max_descendants);
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("failed to create cgroup")]
+    struct CreateError(#[source] io::Error);
+
+    #[test]
+    fn test_is_nesting_limit_reached() {
+        let eagain = || io::Error::from_raw_os_error(Errno::EAGAIN as i32);
+        assert!(is_nesting_limit_reached(&eagain()));
+        assert!(is_nesting_limit_reached(&CreateError(eagain())));
+
+        let eacces = io::Error::from_raw_os_error(Errno::EACCES as i32);
+        assert!(!is_nesting_limit_reached(&CreateError(eacces)));
+    }
+}
@@ -0,0 +1,123 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Cgroups that look like cells, but are not cells of auraed, e.g., left behind when
+//! auraed crashed without freeing its cells. Allocating a cell with the name of one fails
+//! with [crate::runtime::cell_service::cells::CellsError::CgroupIsNotACell].
+//!
+//! auraed doesn't persist the specs of its cells, so what marks a cgroup as a cell is the
+//! leaf cgroup (`{cell}/_`) every cell has. Instead of the spec, the controller values are
+//! read back from the leaf cgroup, for an operator to decide whether to adopt the cgroup
+//! (with a matching spec) or to remove it.
+
+use super::{
+    children::{child_cells, CONTROLLER_FILES},
+    update::{CgroupDir, InterfaceFiles},
+};
+use crate::runtime::cell_service::cells::CellName;
+use std::{io, path::Path, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedCgroup {
+    pub cell_name: CellName,
+    /// The path of the cgroup on the cgroup filesystem.
+    pub path: PathBuf,
+    /// The controller values of the leaf cgroup, by interface file.
+    /// Files of controllers that are not enabled are skipped.
+    pub controller_values: Vec<(&'static str, String)>,
+}
+
+/// Returns the cgroups at `root` that look like cells, and for which `is_cached` is false,
+/// ordered by name.
+pub fn find(
+    root: &Path,
+    is_cached: impl Fn(&CellName) -> bool,
+) -> io::Result<Vec<OrphanedCgroup>> {
+    let mut orphans = vec![];
+
+    for cell_name in child_cells(root)? {
+        if is_cached(&cell_name) {
+            continue;
+        }
+
+        let path = root.join(&*cell_name);
+        let leaf = CgroupDir(path.join("_"));
+        if !leaf.0.is_dir() {
+            continue;
+        }
+
+        let mut controller_values = vec![];
+        for file in CONTROLLER_FILES {
+            if let Some(value) = leaf.read(file)? {
+                controller_values.push((*file, value));
+            }
+        }
+
+        orphans.push(OrphanedCgroup { cell_name, path, controller_values });
+    }
+
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use std::fs;
+
+    #[test]
+    fn test_find_orphans() {
+        let root = temp_path("orphans");
+        // an orphaned cell, a cached cell, and cgroups that are not cells
+        for dir in
+            ["ae-orphan/_", "ae-cached/_", "ae-no-leaf", "system.slice/_"]
+        {
+            fs::create_dir_all(root.join(dir)).expect("create dir");
+        }
+        fs::write(root.join("ae-orphan/_/cpu.max"), "50000 1000000\n")
+            .expect("write");
+        fs::write(root.join("ae-orphan/_/pids.max"), "max\n").expect("write");
+
+        let orphans = find(&root, |cell_name| &**cell_name == "ae-cached");
+        fs::remove_dir_all(&root).expect("remove dir");
+
+        assert_eq!(
+            orphans.expect("find orphans"),
+            vec![OrphanedCgroup {
+                cell_name: CellName::from("ae-orphan"),
+                path: root.join("ae-orphan"),
+                controller_values: vec![
+                    ("cpu.max", "50000 1000000".into()),
+                    ("pids.max", "max".into()),
+                ],
+            }]
+        );
+    }
+}
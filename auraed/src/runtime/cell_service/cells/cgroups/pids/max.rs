@@ -0,0 +1,131 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use cgroups_rs::MaxValue;
+use std::fmt::{Display, Formatter};
+use validation::{ValidatedField, ValidationError};
+
+/// The maximum number of processes (and threads) of a cgroup (`pids.max`).
+/// [None] is no limit, written as "max".
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct PidsMax(Option<i64>);
+
+impl PidsMax {
+    #[cfg(test)]
+    pub fn new(max: Option<i64>) -> Self {
+        Self(max)
+    }
+
+    pub fn into_inner(self) -> Option<i64> {
+        self.0
+    }
+}
+
+impl ValidatedField<String> for PidsMax {
+    fn validate(
+        input: Option<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        let input = validation::required(input, field_name, parent_name)?;
+
+        if input == "max" {
+            return Ok(Self(None));
+        }
+
+        let input: i64 =
+            input.parse().map_err(|_| ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            })?;
+
+        validation::minimum_value(
+            input,
+            1,
+            "process",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(Self(Some(input)))
+    }
+}
+
+impl From<PidsMax> for MaxValue {
+    fn from(value: PidsMax) -> Self {
+        match value.0 {
+            Some(max) => MaxValue::Value(max),
+            None => MaxValue::Max,
+        }
+    }
+}
+
+impl Display for PidsMax {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(max) => max.fmt(f),
+            None => f.write_str("max"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case("32", Some(32); "limit")]
+    #[test_case("max", None; "no limit")]
+    #[test]
+    fn test_validation_success(input: &str, expected: Option<i64>) {
+        let max = PidsMax::validate(Some(input.into()), "max", None)
+            .expect("valid pids.max");
+        assert_eq!(max.into_inner(), expected);
+        assert_eq!(max.to_string(), input);
+    }
+
+    #[test]
+    fn test_validation_failure() {
+        assert!(matches!(
+            PidsMax::validate(Some("0".into()), "max", None),
+            Err(ValidationError::Minimum { .. })
+        ));
+        assert!(matches!(
+            PidsMax::validate(Some("-1".into()), "max", None),
+            Err(ValidationError::Minimum { .. })
+        ));
+
+        for input in ["", "Max", "unlimited", "1.5"] {
+            assert!(matches!(
+                PidsMax::validate(Some(input.into()), "max", None),
+                Err(ValidationError::Invalid { .. })
+            ));
+        }
+    }
+}
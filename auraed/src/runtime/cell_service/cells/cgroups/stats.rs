@@ -0,0 +1,155 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{fs, io, path::Path};
+
+/// Resource usage of a cgroup, read from its interface files.
+/// Values whose file is missing (e.g., the controller is not enabled) are [None].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CgroupStats {
+    /// Total CPU time consumed, in microseconds (`usage_usec` of `cpu.stat`).
+    pub cpu_usage_usec: Option<u64>,
+    /// Memory currently used, in bytes (`memory.current`).
+    pub memory_current: Option<u64>,
+    /// Highest memory used, in bytes (`memory.peak`, since Linux 5.19).
+    pub memory_peak: Option<u64>,
+    /// Number of processes and threads (`pids.current`).
+    pub pids_current: Option<u64>,
+    /// Number of processes killed by the OOM killer (`oom_kill` of `memory.events`).
+    pub oom_kills: Option<u64>,
+}
+
+impl CgroupStats {
+    /// Reads the stats of the cgroup at `path`.
+    /// Returns a [io::ErrorKind::NotFound] error if the cgroup does not exist.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        if !path.is_dir() {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let read_key = |file: &str, key: &str| {
+            read_optional(&path.join(file))?
+                .map(|stat| {
+                    stat.lines()
+                        .find_map(|line| line.strip_prefix(key))
+                        .map(parse)
+                        .transpose()
+                })
+                .transpose()
+                .map(Option::flatten)
+        };
+
+        let read_value = |file: &str| {
+            read_optional(&path.join(file))?
+                .map(|value| parse(&value))
+                .transpose()
+        };
+
+        Ok(Self {
+            cpu_usage_usec: read_key("cpu.stat", "usage_usec ")?,
+            memory_current: read_value("memory.current")?,
+            memory_peak: read_value("memory.peak")?,
+            pids_current: read_value("pids.current")?,
+            oom_kills: read_key("memory.events", "oom_kill ")?,
+        })
+    }
+}
+
+impl From<CgroupStats> for aurae_proto::runtime::CellStats {
+    fn from(value: CgroupStats) -> Self {
+        let CgroupStats {
+            cpu_usage_usec,
+            memory_current,
+            memory_peak,
+            pids_current,
+            oom_kills,
+        } = value;
+        Self {
+            cpu_usage_usec,
+            memory_peak_bytes: memory_peak,
+            oom_kills,
+            memory_current_bytes: memory_current,
+            pids_current,
+        }
+    }
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn parse(value: &str) -> io::Result<u64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+
+    #[test]
+    fn test_read_stats() {
+        let dir = temp_dir("stats");
+        fs::write(
+            dir.join("cpu.stat"),
+            "usage_usec 1500000\nuser_usec 1000000\nsystem_usec 500000\n",
+        )
+        .expect("write cpu.stat");
+        fs::write(dir.join("memory.current"), "4096\n").expect("write");
+        fs::write(dir.join("pids.current"), "3\n").expect("write");
+        fs::write(
+            dir.join("memory.events"),
+            "low 0\nhigh 0\nmax 2\noom 1\noom_kill 1\noom_group_kill 0\n",
+        )
+        .expect("write memory.events");
+
+        let stats = CgroupStats::read(&dir);
+        fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(
+            stats.expect("stats"),
+            CgroupStats {
+                cpu_usage_usec: Some(1500000),
+                memory_current: Some(4096),
+                // memory.peak is missing on older kernels
+                memory_peak: None,
+                pids_current: Some(3),
+                oom_kills: Some(1),
+            }
+        );
+    }
+}
@@ -0,0 +1,723 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! In-place updates of the controller values of an existing cgroup.
+//!
+//! An update may consist of several interface file writes. To keep the cgroup from being
+//! left in a mixed state, the current values are read before anything is written, and if
+//! a write fails, the writes that already succeeded are rolled back to those values.
+//!
+//! Rollback is best-effort: a value that can't be read back before the update (e.g., the
+//! controller is not enabled for the cgroup) can't be restored, and a rollback write may
+//! itself fail. Such files are reported in [UpdateError::not_restored].
+//!
+//! `io.weight` and `io.max` hold a line per device, and a write only replaces the line of
+//! the device it names, so only that line is read before the update and restored. The
+//! limits of several devices are written one device at a time, and rolled back as a group.
+//!
+//! The `cpu.uclamp.*` files only exist on kernels built with uclamp support, and
+//! `memory.zswap.max` only when zswap is configured. Writes to them are skipped with a
+//! warning when they are absent.
+
+use super::{
+    cpu::CpuController,
+    cpuset::CpusetController,
+    io::{IoController, IoMax},
+    memory::MemoryController,
+    pids::PidsController,
+    CgroupSpec,
+};
+use std::{fs, io, path::PathBuf};
+use thiserror::Error;
+use tracing::{error, warn};
+
+/// Read and write access to the interface files of a single cgroup.
+pub trait InterfaceFiles {
+    /// Returns the current value of `file`, or [None] if the value can't be read back.
+    fn read(&self, file: &str) -> io::Result<Option<String>>;
+
+    fn write(&mut self, file: &str, value: &str) -> io::Result<()>;
+}
+
+/// The interface files of a cgroup directory on the cgroup filesystem.
+#[derive(Debug)]
+pub struct CgroupDir(pub PathBuf);
+
+impl InterfaceFiles for CgroupDir {
+    fn read(&self, file: &str) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.0.join(file)) {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&mut self, file: &str, value: &str) -> io::Result<()> {
+        fs::write(self.0.join(file), value)
+    }
+}
+
+/// A single interface file write of an update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControllerWrite {
+    pub file: &'static str,
+    pub value: String,
+}
+
+impl ControllerWrite {
+    fn new(file: &'static str, value: impl ToString) -> Self {
+        Self { file, value: value.to_string() }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "failed to write '{file}': {source}{}",
+    not_restored_note(not_restored)
+)]
+pub struct UpdateError {
+    pub file: &'static str,
+    pub source: io::Error,
+    /// Files that were written before the failure, but could not be restored.
+    pub not_restored: Vec<&'static str>,
+}
+
+fn not_restored_note(not_restored: &[&str]) -> String {
+    if not_restored.is_empty() {
+        return String::new();
+    }
+    format!(" (could not restore {})", not_restored.join(", "))
+}
+
+/// Returns the interface file writes for the controller values set in `spec`,
+/// in the order they are applied.
+pub fn writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(cpu) = &spec.cpu {
+        if let Some(weight) = &cpu.weight {
+            writes.push(ControllerWrite::new("cpu.weight", weight));
+        }
+
+        if let Some(max) = &cpu.max {
+            writes.push(ControllerWrite::new(
+                "cpu.max",
+                format!("{max} {}", cpu.period()),
+            ));
+        }
+
+        writes.extend(cpu_burst_writes(spec));
+        writes.extend(uclamp_writes(spec));
+    }
+
+    if let Some(CpusetController { cpus, mems }) = &spec.cpuset {
+        if let Some(cpus) = cpus {
+            writes.push(ControllerWrite::new("cpuset.cpus", cpus));
+        }
+
+        if let Some(mems) = mems {
+            writes.push(ControllerWrite::new("cpuset.mems", mems));
+        }
+    }
+
+    if let Some(memory) = &spec.memory {
+        if let Some(max) = memory.effective_max() {
+            writes.push(ControllerWrite::new("memory.max", max));
+        }
+
+        writes.extend(memory_min_writes(spec));
+        writes.extend(zswap_writes(spec));
+    }
+
+    if let Some(PidsController { max: Some(max) }) = &spec.pids {
+        writes.push(ControllerWrite::new("pids.max", max));
+    }
+
+    writes.extend(io_writes(spec));
+
+    writes
+}
+
+/// Returns the interface file writes for the burst of the cpu quota set in `spec`.
+/// cgroups_rs has no support for cpu.max.burst, so these are also written on their own
+/// when a cgroup is created. The burst is written after `cpu.max`, as the kernel rejects
+/// a burst greater than the quota.
+pub fn cpu_burst_writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(CpuController { burst: Some(burst), .. }) = &spec.cpu {
+        writes.push(ControllerWrite::new("cpu.max.burst", burst));
+    }
+
+    writes
+}
+
+/// Returns the interface file writes for the utilization clamps set in `spec`.
+/// cgroups_rs has no support for uclamp, so these are also written on their own
+/// when a cgroup is created.
+pub fn uclamp_writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(CpuController { uclamp_min, uclamp_max, .. }) = &spec.cpu {
+        if let Some(uclamp_min) = uclamp_min {
+            writes.push(ControllerWrite::new("cpu.uclamp.min", uclamp_min));
+        }
+
+        if let Some(uclamp_max) = uclamp_max {
+            writes.push(ControllerWrite::new("cpu.uclamp.max", uclamp_max));
+        }
+    }
+
+    writes
+}
+
+/// Returns the interface file writes for the memory reservation set in `spec`.
+/// cgroups_rs has no support for memory.min, so these are also written on their own
+/// when a cgroup is created.
+pub fn memory_min_writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(min) = spec.memory.as_ref().and_then(|x| x.min()) {
+        writes.push(ControllerWrite::new("memory.min", min));
+    }
+
+    writes
+}
+
+/// Returns the interface file writes for the zswap limit set in `spec`.
+/// cgroups_rs has no support for zswap, so these are also written on their own
+/// when a cgroup is created.
+pub fn zswap_writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(MemoryController { zswap_max: Some(zswap_max), .. }) =
+        &spec.memory
+    {
+        writes.push(ControllerWrite::new("memory.zswap.max", zswap_max));
+    }
+
+    writes
+}
+
+/// Returns the interface file writes for the io controller values set in `spec`, with a
+/// write per device for `io.max`. The io controller is not configured through
+/// cgroups_rs, so these are also written on their own when a cgroup is created.
+pub fn io_writes(spec: &CgroupSpec) -> Vec<ControllerWrite> {
+    let mut writes = vec![];
+
+    if let Some(IoController { weight, max }) = &spec.io {
+        if let Some(weight) = weight {
+            writes.push(ControllerWrite::new(
+                "io.weight",
+                format!("default {weight}"),
+            ));
+        }
+
+        for io_max in max {
+            writes.push(ControllerWrite::new("io.max", io_max));
+        }
+    }
+
+    writes
+}
+
+/// Returns the value `write` replaces, given the `contents` of its file. Files with a line
+/// per device only have the line named by the write replaced (see [IoMax::unlimited] for
+/// devices missing from `io.max`). Returns [None] if the value is not in `contents`.
+pub(super) fn replaced_value(
+    write: &ControllerWrite,
+    contents: &str,
+) -> Option<String> {
+    if !matches!(write.file, "io.weight" | "io.max") {
+        return Some(contents.to_string());
+    }
+
+    let key = write.value.split_whitespace().next()?;
+    let line = contents
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some(key))
+        .map(str::to_string);
+
+    match write.file {
+        "io.max" => Some(line.unwrap_or_else(|| IoMax::unlimited(key))),
+        _ => line,
+    }
+}
+
+/// Returns true if `file` may be absent because the kernel was built without the feature
+/// (or, for zswap, the feature is not configured).
+pub(super) fn is_optional(file: &str) -> bool {
+    file.starts_with("cpu.uclamp.") || file == "memory.zswap.max"
+}
+
+/// Applies `writes` in order, rolling back the already applied writes if one fails.
+pub fn apply<F: InterfaceFiles>(
+    files: &mut F,
+    writes: &[ControllerWrite],
+) -> Result<(), UpdateError> {
+    let snapshot: Vec<Option<String>> = writes
+        .iter()
+        .map(|write| match files.read(write.file) {
+            Ok(contents) => {
+                contents.and_then(|contents| replaced_value(write, &contents))
+            }
+            Err(e) => {
+                warn!("failed to read '{}' before update: {e}", write.file);
+                None
+            }
+        })
+        .collect();
+
+    for (i, write) in writes.iter().enumerate() {
+        let Err(source) = files.write(write.file, &write.value) else {
+            continue;
+        };
+
+        if source.kind() == io::ErrorKind::NotFound && is_optional(write.file) {
+            warn!("'{}' is not supported by the kernel, skipping", write.file);
+            continue;
+        }
+
+        let mut not_restored = vec![];
+        for (applied, previous) in writes[..i].iter().zip(&snapshot).rev() {
+            // Skipped above, so there is nothing to restore
+            if previous.is_none() && is_optional(applied.file) {
+                continue;
+            }
+
+            let Some(previous) = previous else {
+                not_restored.push(applied.file);
+                continue;
+            };
+
+            if let Err(e) = files.write(applied.file, previous) {
+                error!("failed to restore '{}': {e}", applied.file);
+                not_restored.push(applied.file);
+            }
+        }
+
+        return Err(UpdateError { file: write.file, source, not_restored });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::cells::cgroups::{
+        cpu::Uclamp, cpuset::Cpus, memory::MemoryPinning, Limit, Weight,
+    };
+    use std::collections::{HashMap, HashSet};
+
+    /// Interface files kept in memory, with writes to `failing` files returning an error.
+    #[derive(Debug, Default)]
+    struct MockFiles {
+        values: HashMap<&'static str, String>,
+        unreadable: HashSet<&'static str>,
+        failing: HashSet<&'static str>,
+    }
+
+    impl InterfaceFiles for MockFiles {
+        fn read(&self, file: &str) -> io::Result<Option<String>> {
+            if self.unreadable.contains(file) {
+                return Ok(None);
+            }
+            Ok(self.values.get(file).cloned())
+        }
+
+        fn write(&mut self, file: &str, value: &str) -> io::Result<()> {
+            let Some((file, _)) = self.values.get_key_value(file) else {
+                return Err(io::ErrorKind::NotFound.into());
+            };
+            let file = *file;
+
+            if self.failing.contains(file) {
+                return Err(io::ErrorKind::InvalidInput.into());
+            }
+
+            if file == "io.max" {
+                // The kernel rejects devices that don't exist (ENODEV)
+                if value.starts_with("8:32 ") {
+                    return Err(io::ErrorKind::InvalidInput.into());
+                }
+
+                // Only the line of the device is replaced, and devices without
+                // limits are not listed
+                let key = value.split_whitespace().next();
+                let mut lines: Vec<String> = self.values[file]
+                    .lines()
+                    .filter(|line| line.split_whitespace().next() != key)
+                    .map(str::to_string)
+                    .collect();
+                if !value.ends_with("rbps=max wbps=max riops=max wiops=max") {
+                    lines.push(value.to_string());
+                }
+                let _ = self.values.insert(file, lines.join("\n"));
+                return Ok(());
+            }
+
+            let _ = self.values.insert(file, value.to_string());
+            Ok(())
+        }
+    }
+
+    fn mock_files() -> MockFiles {
+        MockFiles {
+            values: HashMap::from([
+                ("cpu.weight", "100".to_string()),
+                ("cpu.max", "max 100000".to_string()),
+                ("cpuset.cpus", "0-3".to_string()),
+                ("cpuset.mems", "0".to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn spec() -> CgroupSpec {
+        CgroupSpec {
+            cpu: Some(CpuController {
+                weight: Some(Weight::new(200)),
+                max: Some(Limit::new(500000)),
+                period: None,
+                burst: None,
+                uclamp_min: None,
+                uclamp_max: None,
+            }),
+            cpuset: Some(CpusetController {
+                cpus: Some(Cpus::new("1".into())),
+                mems: None,
+            }),
+            memory: None,
+            io: None,
+            pids: None,
+        }
+    }
+
+    #[test]
+    fn test_writes_from_spec() {
+        assert_eq!(
+            writes(&spec()),
+            vec![
+                ControllerWrite::new("cpu.weight", "200"),
+                ControllerWrite::new("cpu.max", "500000 1000000"),
+                ControllerWrite::new("cpuset.cpus", "1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_writes_cpu_period_and_burst() {
+        let mut spec = spec();
+        if let Some(cpu) = &mut spec.cpu {
+            cpu.max = Some(Limit::new(50000));
+            cpu.period = Some(100000);
+            cpu.burst = Some(20000);
+        }
+
+        assert_eq!(
+            writes(&spec),
+            vec![
+                ControllerWrite::new("cpu.weight", "200"),
+                ControllerWrite::new("cpu.max", "50000 100000"),
+                ControllerWrite::new("cpu.max.burst", "20000"),
+                ControllerWrite::new("cpuset.cpus", "1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut files = mock_files();
+        apply(&mut files, &writes(&spec())).expect("failed to apply");
+
+        assert_eq!(files.values["cpu.weight"], "200");
+        assert_eq!(files.values["cpu.max"], "500000 1000000");
+        assert_eq!(files.values["cpuset.cpus"], "1");
+        assert_eq!(files.values["cpuset.mems"], "0");
+    }
+
+    #[test]
+    fn test_apply_rolls_back_on_failure() {
+        let mut files = mock_files();
+        let _ = files.failing.insert("cpuset.cpus");
+
+        let err = apply(&mut files, &writes(&spec()))
+            .expect_err("write to cpuset.cpus should fail");
+
+        assert_eq!(err.file, "cpuset.cpus");
+        assert!(err.not_restored.is_empty());
+        assert_eq!(files.values, mock_files().values);
+    }
+
+    #[test]
+    fn test_apply_reports_unreadable_files_as_not_restored() {
+        let mut files = mock_files();
+        let _ = files.unreadable.insert("cpu.weight");
+        let _ = files.failing.insert("cpuset.cpus");
+
+        let err = apply(&mut files, &writes(&spec()))
+            .expect_err("write to cpuset.cpus should fail");
+
+        assert_eq!(err.not_restored, vec!["cpu.weight"]);
+        assert!(err.to_string().ends_with("(could not restore cpu.weight)"));
+        assert_eq!(files.values["cpu.weight"], "200");
+        assert_eq!(files.values["cpu.max"], "max 100000");
+    }
+
+    fn uclamp_spec() -> CgroupSpec {
+        CgroupSpec {
+            cpu: Some(CpuController {
+                weight: Some(Weight::new(200)),
+                max: None,
+                period: None,
+                burst: None,
+                uclamp_min: Some(Uclamp::new(10)),
+                uclamp_max: Some(Uclamp::new(80)),
+            }),
+            cpuset: None,
+            memory: None,
+            io: None,
+            pids: None,
+        }
+    }
+
+    #[test]
+    fn test_writes_uclamp() {
+        assert_eq!(
+            writes(&uclamp_spec()),
+            vec![
+                ControllerWrite::new("cpu.weight", "200"),
+                ControllerWrite::new("cpu.uclamp.min", "10"),
+                ControllerWrite::new("cpu.uclamp.max", "80"),
+            ]
+        );
+        assert_eq!(
+            uclamp_writes(&uclamp_spec()),
+            vec![
+                ControllerWrite::new("cpu.uclamp.min", "10"),
+                ControllerWrite::new("cpu.uclamp.max", "80"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_skips_absent_uclamp_files() {
+        // mock_files has no cpu.uclamp.* files, as on a kernel without uclamp
+        let mut files = mock_files();
+        apply(&mut files, &writes(&uclamp_spec())).expect("failed to apply");
+        assert_eq!(files.values["cpu.weight"], "200");
+        assert!(!files.values.contains_key("cpu.uclamp.min"));
+
+        // the skipped files are not reported as not restored on rollback
+        let mut files = mock_files();
+        let _ = files.failing.insert("cpuset.cpus");
+        let mut spec = uclamp_spec();
+        spec.cpuset = Some(CpusetController {
+            cpus: Some(Cpus::new("1".into())),
+            mems: None,
+        });
+        let err = apply(&mut files, &writes(&spec))
+            .expect_err("write to cpuset.cpus should fail");
+        assert!(err.not_restored.is_empty());
+        assert_eq!(files.values, mock_files().values);
+    }
+
+    fn zswap_spec() -> CgroupSpec {
+        CgroupSpec {
+            cpu: None,
+            cpuset: None,
+            memory: Some(MemoryController {
+                max: Some(Limit::new(1 << 30)),
+                shares: None,
+                zswap_max: Some(Limit::new(0)),
+                pinned: None,
+                pinning: MemoryPinning::default(),
+                swappiness: None,
+            }),
+            io: None,
+            pids: None,
+        }
+    }
+
+    #[test]
+    fn test_writes_zswap() {
+        assert_eq!(
+            writes(&zswap_spec()),
+            vec![
+                ControllerWrite::new("memory.max", "1073741824"),
+                ControllerWrite::new("memory.zswap.max", "0"),
+            ]
+        );
+        assert_eq!(
+            zswap_writes(&zswap_spec()),
+            vec![ControllerWrite::new("memory.zswap.max", "0")]
+        );
+    }
+
+    #[test]
+    fn test_writes_pinned_memory() {
+        let spec = |pinning| CgroupSpec {
+            cpu: None,
+            cpuset: None,
+            memory: Some(MemoryController {
+                max: None,
+                shares: None,
+                zswap_max: None,
+                pinned: Some(Limit::new(1 << 30)),
+                pinning,
+                swappiness: None,
+            }),
+            io: None,
+            pids: None,
+        };
+
+        assert_eq!(
+            writes(&spec(MemoryPinning::Reserve)),
+            vec![
+                ControllerWrite::new("memory.max", "1073741824"),
+                ControllerWrite::new("memory.min", "1073741824"),
+            ]
+        );
+        assert_eq!(
+            writes(&spec(MemoryPinning::Mlock)),
+            vec![ControllerWrite::new("memory.max", "1073741824")]
+        );
+    }
+
+    #[test]
+    fn test_apply_skips_absent_zswap_file() {
+        // mock_files has no memory.zswap.max, as when zswap is not configured
+        let mut files = mock_files();
+        let _ = files.values.insert("memory.max", "max".to_string());
+
+        apply(&mut files, &writes(&zswap_spec())).expect("failed to apply");
+
+        assert_eq!(files.values["memory.max"], "1073741824");
+        assert!(!files.values.contains_key("memory.zswap.max"));
+    }
+
+    fn io_spec(devices: &[&str]) -> CgroupSpec {
+        let io_max = |device: &&str| IoMax {
+            device: device.parse().expect("device number"),
+            rbps: Some(Limit::new(1 << 20)),
+            wbps: None,
+            riops: None,
+            wiops: Some(Limit::new(120)),
+        };
+
+        CgroupSpec {
+            cpu: None,
+            cpuset: None,
+            memory: None,
+            io: Some(IoController {
+                weight: Some(Weight::new(200)),
+                max: devices.iter().map(io_max).collect(),
+            }),
+            pids: None,
+        }
+    }
+
+    fn io_files() -> MockFiles {
+        MockFiles {
+            values: HashMap::from([
+                ("io.weight", "default 100".to_string()),
+                (
+                    "io.max",
+                    "8:16 rbps=max wbps=max riops=1000 wiops=max".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_writes_io() {
+        assert_eq!(
+            writes(&io_spec(&["8:0", "8:16"])),
+            vec![
+                ControllerWrite::new("io.weight", "default 200"),
+                ControllerWrite::new(
+                    "io.max",
+                    "8:0 rbps=1048576 wbps=max riops=max wiops=120"
+                ),
+                ControllerWrite::new(
+                    "io.max",
+                    "8:16 rbps=1048576 wbps=max riops=max wiops=120"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replaced_value_of_device() {
+        let contents = "8:16 rbps=max wbps=max riops=1000 wiops=max";
+        let writes = writes(&io_spec(&["8:0", "8:16"]));
+        let [weight, first, second] = &writes[..] else {
+            panic!("expected 3 writes");
+        };
+
+        assert_eq!(
+            replaced_value(weight, "default 100\n8:16 50").as_deref(),
+            Some("default 100")
+        );
+        assert_eq!(
+            replaced_value(first, contents).as_deref(),
+            Some("8:0 rbps=max wbps=max riops=max wiops=max")
+        );
+        assert_eq!(replaced_value(second, contents).as_deref(), Some(contents));
+    }
+
+    #[test]
+    fn test_apply_io_devices() {
+        let mut files = io_files();
+        apply(&mut files, &writes(&io_spec(&["8:0", "8:16"])))
+            .expect("failed to apply");
+
+        assert_eq!(files.values["io.weight"], "default 200");
+        assert_eq!(
+            files.values["io.max"],
+            "8:0 rbps=1048576 wbps=max riops=max wiops=120\n8:16 rbps=1048576 wbps=max riops=max wiops=120"
+        );
+    }
+
+    #[test]
+    fn test_apply_rolls_back_io_devices_as_a_group() {
+        // 8:32 is rejected, after 8:0 and 8:16 were written
+        let mut files = io_files();
+        let err =
+            apply(&mut files, &writes(&io_spec(&["8:0", "8:16", "8:32"])))
+                .expect_err("write of 8:32 should fail");
+
+        assert_eq!(err.file, "io.max");
+        assert!(err.not_restored.is_empty());
+        assert_eq!(files.values, io_files().values);
+    }
+}
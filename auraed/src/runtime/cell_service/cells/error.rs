@@ -28,7 +28,15 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-use super::CellName;
+use super::{
+    cgroups::{
+        cpuset::{Cpus, Mems},
+        memory, CgroupSpecDiff, UpdateError,
+    },
+    CellName,
+};
+use crate::runtime::cell_service::executables::ExecutableName;
+use aurae_client::AuraeClientError;
 use std::io;
 use thiserror::Error;
 use tracing::error;
@@ -54,10 +62,123 @@ pub enum CellsError {
     FailedToKillCellChildren { cell_name: CellName, source: io::Error },
     #[error("cell '{cell_name}' could not be freed: {source}")]
     FailedToFreeCell { cell_name: CellName, source: cgroups_rs::error::Error },
+    #[error(
+        "cell '{cell_name}' has children ({})",
+        .children.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    CellHasChildren { cell_name: CellName, children: Vec<CellName> },
+    #[error(
+        "cell '{cell_name}' can't orphan its child '{child}', which is still allocated"
+    )]
+    ChildIsAllocated { cell_name: CellName, child: CellName },
+    #[error(
+        "cell '{cell_name}' has executables ({}), and can't be freed when one exits",
+        .executables.join(", ")
+    )]
+    CellHasExecutables { cell_name: CellName, executables: Vec<String> },
+    #[error("cell '{cell_name}' could not free its children: {source}")]
+    FailedToFreeCellChildren { cell_name: CellName, source: io::Error },
     #[error(
         "cgroup '{cell_name}' exists on host, but is not controlled by auraed"
     )]
     CgroupIsNotACell { cell_name: CellName },
+    #[error(
+        "cgroup '{cell_name}' exists on host, but does not match the requested spec: {diff}"
+    )]
+    CgroupSpecMismatch { cell_name: CellName, diff: CgroupSpecDiff },
+    #[error(
+        "cell '{cell_name}' could not reach its nested auraed: {source}; stderr of the nested auraed: {stderr:?}"
+    )]
+    NestedAuraedUnreachable {
+        cell_name: CellName,
+        source: AuraeClientError,
+        stderr: Vec<String>,
+    },
     #[error("cgroup '{cell_name}` not found on host")]
     CgroupNotFound { cell_name: CellName },
+    #[error("cell '{cell_name}' could not set uclamp: {source}")]
+    FailedToSetUclamp { cell_name: CellName, source: UpdateError },
+    #[error("cell '{cell_name}' could not set cpu burst: {source}")]
+    FailedToSetCpuBurst { cell_name: CellName, source: UpdateError },
+    #[error("cell '{cell_name}' could not set zswap limit: {source}")]
+    FailedToSetZswapMax { cell_name: CellName, source: UpdateError },
+    #[error("cell '{cell_name}' could not set io limits: {source}")]
+    FailedToSetIo { cell_name: CellName, source: UpdateError },
+    #[error("cell '{cell_name}' could not set swappiness: {source}")]
+    SwappinessUnsupported {
+        cell_name: CellName,
+        source: memory::SwappinessUnsupported,
+    },
+    #[error("cell '{cell_name}' could not reserve memory: {source}")]
+    FailedToSetMemoryMin { cell_name: CellName, source: UpdateError },
+    #[error(
+        "cell '{cell_name}' would exceed the pinned memory budget by {over} bytes"
+    )]
+    PinnedMemoryBudgetExceeded { cell_name: CellName, over: u64 },
+    #[error("cell '{cell_name}' would exceed the limit of {max_cells} cells")]
+    CellLimitReached { cell_name: CellName, max_cells: usize },
+    #[error(
+        "cell '{cell_name}' cpuset.mems '{mems}' does not include the NUMA nodes {missing_nodes:?} local to cpuset.cpus '{cpus}'"
+    )]
+    NumaLocalityMismatch {
+        cell_name: CellName,
+        cpus: Cpus,
+        mems: Mems,
+        missing_nodes: Vec<u32>,
+    },
+    #[error("cell '{cell_name}' could not set nesting limits: {source}")]
+    FailedToSetNestingLimits { cell_name: CellName, source: io::Error },
+    #[error(
+        "cell '{cell_name}' could not read its client credentials: {source}"
+    )]
+    ClientCredentialsUnreadable { cell_name: CellName, source: io::Error },
+    #[error(
+        "cell '{cell_name}' could not hand its cgroup over to its root: {source}"
+    )]
+    FailedToDelegateCgroup { cell_name: CellName, source: io::Error },
+    #[error(
+        "cell '{cell_name}' would exceed the cgroup.max.depth or cgroup.max.descendants of a parent cell"
+    )]
+    NestingLimitReached { cell_name: CellName },
+    #[error(
+        "cell '{cell_name}' could not be allocated: the Aurae base cgroup has resident processes, which prevents enabling controllers for cells. Run auraed with --migrate-to-leaf-cgroup, or move the processes out of the cgroup"
+    )]
+    ControllerDelegationBlocked { cell_name: CellName },
+    #[error("cell '{cell_name}' could not be updated: {source}")]
+    FailedToUpdateCell { cell_name: CellName, source: UpdateError },
+    #[error(
+        "failed to read cgroup controllers of cell '{cell_name}': {source}"
+    )]
+    FailedToReadCgroupControllers { cell_name: CellName, source: io::Error },
+    #[error("cell '{cell_name}' could not be frozen: {source}")]
+    FailedToFreezeCell { cell_name: CellName, source: io::Error },
+    #[error("failed to snapshot frozen cell '{cell_name}': {source}")]
+    FailedToSnapshotCell { cell_name: CellName, source: io::Error },
+    #[error("cell '{cell_name}' could not be thawed: {source}")]
+    FailedToThawCell { cell_name: CellName, source: io::Error },
+    #[error("failed to read freeze state of cell '{cell_name}': {source}")]
+    FailedToReadFreezeState { cell_name: CellName, source: io::Error },
+    #[error("failed to read namespaces of cell '{cell_name}': {source}")]
+    FailedToReadNamespaces { cell_name: CellName, source: io::Error },
+    #[error("failed to read cgroup id of cell '{cell_name}': {source}")]
+    FailedToReadCgroupId { cell_name: CellName, source: io::Error },
+    #[error("failed to read cgroup stats of cell '{cell_name}': {source}")]
+    FailedToReadCgroupStats { cell_name: CellName, source: io::Error },
+    #[error("thread '{tid}' not found in any cell")]
+    ThreadNotFound { tid: i32 },
+    #[error("failed to find cell of thread '{tid}': {source}")]
+    FailedToFindThread { tid: i32, source: io::Error },
+    #[error("executable '{executable_name}' is not running in any cell")]
+    ExecutableNotInCell { executable_name: ExecutableName },
+    #[error(
+        "executable '{executable_name}' is running in cell '{cell_name}', and must be addressed through it"
+    )]
+    ExecutableInCell { executable_name: ExecutableName, cell_name: CellName },
+    #[error("failed to find cell of executable '{executable_name}': {source}")]
+    FailedToFindExecutable {
+        executable_name: ExecutableName,
+        source: io::Error,
+    },
+    #[error("failed to list orphaned cgroups: {source}")]
+    FailedToListOrphans { source: io::Error },
 }
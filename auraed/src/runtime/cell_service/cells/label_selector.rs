@@ -0,0 +1,119 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::collections::HashMap;
+use validation::{ValidatedField, ValidationError};
+
+/// A set of label requirements used to select [Cell]s.
+/// A cell matches the selector when its labels contain every key/value pair of the selector.
+///
+/// [Cell]: super::Cell
+#[derive(Debug, Clone)]
+pub struct LabelSelector(HashMap<String, String>);
+
+impl LabelSelector {
+    #[cfg(test)]
+    pub fn new(selector: HashMap<String, String>) -> Self {
+        Self(selector)
+    }
+
+    /// Returns true if every key/value pair of the selector is present in `labels`.
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.0.iter().all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+impl ValidatedField<HashMap<String, String>> for LabelSelector {
+    fn validate(
+        input: Option<HashMap<String, String>>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        // An empty selector matches every cell. We never want a bulk operation
+        // to target everything by accident, so an empty selector is an error.
+        let input =
+            validation::required_not_empty(input, field_name, parent_name)?;
+
+        if input.keys().any(|key| key.is_empty()) {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Self(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_matches() {
+        let selector = LabelSelector::new(labels(&[("job", "build")]));
+
+        assert!(selector.matches(&labels(&[("job", "build")])));
+        assert!(selector.matches(&labels(&[("job", "build"), ("team", "a")])));
+        assert!(!selector.matches(&labels(&[("job", "test")])));
+        assert!(!selector.matches(&labels(&[("team", "a")])));
+        assert!(!selector.matches(&labels(&[])));
+    }
+
+    #[test]
+    fn test_validation_success() {
+        assert!(LabelSelector::validate(
+            Some(labels(&[("job", "build")])),
+            "selector",
+            None
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validation_failure() {
+        assert!(matches!(
+            LabelSelector::validate(Some(labels(&[])), "selector", None),
+            Err(ValidationError::Required { .. })
+        ));
+
+        assert!(matches!(
+            LabelSelector::validate(
+                Some(labels(&[("", "build")])),
+                "selector",
+                None
+            ),
+            Err(ValidationError::Invalid { .. })
+        ));
+    }
+}
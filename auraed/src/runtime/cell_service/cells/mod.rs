@@ -28,38 +28,94 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-use cell::Cell;
+pub use cell::Cell;
+pub use cell_info::CellInfo;
 pub use cell_name::CellName;
 pub use cell_name_path::CellNamePath;
-pub use cells::Cells;
-use cgroups::CgroupSpec;
+pub use cells::{Cells, FreeChildrenPolicy};
+use cgroups::{CgroupSpec, NestingLimits};
 pub use error::{CellsError, Result};
-pub use nested_auraed::IsolationControls;
+pub use label_selector::LabelSelector;
+pub use namespaces::Namespace;
+pub use nested_auraed::{
+    Architecture, BandwidthLimit, BandwidthLimits, BridgeConfig,
+    ClientCredentials, DenyAction, DeviceMapping, DnsConfig, IdMapping,
+    IsolationControls, Mount, NetworkMode, SeccompControls, MAX_ID_MAPPINGS,
+};
+pub use snapshot::{CellSnapshot, CellStatus, CellsSnapshot};
+use std::collections::HashMap;
 
 mod cell;
+mod cell_info;
 mod cell_name;
 pub mod cell_name_path;
 #[allow(clippy::module_inception)]
 mod cells;
 pub mod cgroups;
 mod error;
+mod label_selector;
+mod namespaces;
 mod nested_auraed;
+mod snapshot;
 
 #[derive(Debug, Clone)]
 pub struct CellSpec {
     pub cgroup_spec: CgroupSpec,
+    pub nesting_limits: NestingLimits,
     pub iso_ctl: IsolationControls,
+    /// Used to connect to the nested auraed of the cell, instead of the credentials of
+    /// the client config of auraed.
+    pub client_credentials: Option<ClientCredentials>,
+    pub labels: HashMap<String, String>,
 }
 
 impl CellSpec {
+    /// The spec of a child of a freed cell that was moved next to it
+    /// (see [FreeChildrenPolicy::Orphan]). Its cgroup keeps the values it was moved with,
+    /// but the spec it was allocated with was only known to the nested auraed of the
+    /// freed cell.
+    pub fn orphaned() -> Self {
+        Self {
+            cgroup_spec: CgroupSpec {
+                cpu: None,
+                cpuset: None,
+                memory: None,
+                io: None,
+                pids: None,
+            },
+            nesting_limits: NestingLimits::default(),
+            iso_ctl: IsolationControls::default(),
+            client_credentials: None,
+            labels: HashMap::new(),
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn new_for_tests() -> Self {
         Self {
-            cgroup_spec: CgroupSpec { cpu: None, cpuset: None },
+            cgroup_spec: CgroupSpec {
+                cpu: None,
+                cpuset: None,
+                memory: None,
+                io: None,
+                pids: None,
+            },
+            nesting_limits: NestingLimits::default(),
             iso_ctl: IsolationControls {
-                isolate_network: false,
+                network: NetworkMode::None,
                 isolate_process: false,
+                seccomp: SeccompControls::default(),
+                ephemeral_root: false,
+                mounts: vec![],
+                devices: vec![],
+                oom_score_adj: None,
+                memlock_limit: None,
+                dns: None,
+                uid_map: vec![],
+                gid_map: vec![],
             },
+            client_credentials: None,
+            labels: HashMap::new(),
         }
     }
 }
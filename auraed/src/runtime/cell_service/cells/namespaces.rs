@@ -0,0 +1,189 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Reads back the namespaces a process is in, to verify isolation took effect.
+
+use std::{
+    fmt::{Display, Formatter},
+    io,
+    path::Path,
+};
+
+/// A Linux namespace type, named as in `/proc/<pid>/ns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Cgroup,
+    Ipc,
+    Mnt,
+    Net,
+    Pid,
+    User,
+    Uts,
+}
+
+impl Namespace {
+    const ALL: [Namespace; 7] = [
+        Namespace::Cgroup,
+        Namespace::Ipc,
+        Namespace::Mnt,
+        Namespace::Net,
+        Namespace::Pid,
+        Namespace::User,
+        Namespace::Uts,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Namespace::Cgroup => "cgroup",
+            Namespace::Ipc => "ipc",
+            Namespace::Mnt => "mnt",
+            Namespace::Net => "net",
+            Namespace::Pid => "pid",
+            Namespace::User => "user",
+            Namespace::Uts => "uts",
+        }
+    }
+}
+
+impl Display for Namespace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the namespaces of the process `pid` that differ from the namespaces
+/// of the calling process.
+pub fn isolated(pid: i32) -> io::Result<Vec<Namespace>> {
+    isolated_from(
+        &Path::new("/proc").join(pid.to_string()),
+        Path::new("/proc/self"),
+    )
+}
+
+/// Compares the namespaces in `<proc_dir>/ns` with those in `<reference>/ns`.
+fn isolated_from(
+    proc_dir: &Path,
+    reference: &Path,
+) -> io::Result<Vec<Namespace>> {
+    let mut isolated = vec![];
+
+    for namespace in Namespace::ALL {
+        if inode(proc_dir, namespace)? != inode(reference, namespace)? {
+            isolated.push(namespace);
+        }
+    }
+
+    Ok(isolated)
+}
+
+/// Returns the inode number identifying the namespace, read from its link
+/// in `<proc_dir>/ns` (e.g., `net:[4026531840]`).
+fn inode(proc_dir: &Path, namespace: Namespace) -> io::Result<u64> {
+    let link =
+        std::fs::read_link(proc_dir.join("ns").join(namespace.as_str()))?;
+
+    parse_link(namespace, &link.to_string_lossy()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected namespace link '{}'", link.display()),
+        )
+    })
+}
+
+fn parse_link(namespace: Namespace, link: &str) -> Option<u64> {
+    link.strip_prefix(namespace.as_str())?
+        .strip_prefix(":[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use simple_test_case::test_case;
+    use std::path::PathBuf;
+
+    fn proc_dir(inodes: [u64; 7]) -> PathBuf {
+        let dir = temp_path("proc");
+        std::fs::create_dir_all(dir.join("ns")).expect("create ns dir");
+
+        for (namespace, inode) in Namespace::ALL.into_iter().zip(inodes) {
+            std::os::unix::fs::symlink(
+                format!("{namespace}:[{inode}]"),
+                dir.join("ns").join(namespace.as_str()),
+            )
+            .expect("create ns link");
+        }
+
+        dir
+    }
+
+    #[test_case(Namespace::Net, "net:[4026531840]", Some(4026531840); "valid link")]
+    #[test_case(Namespace::Net, "pid:[4026531840]", None; "other namespace")]
+    #[test_case(Namespace::Net, "net:4026531840", None; "missing brackets")]
+    #[test_case(Namespace::Net, "net:[x]", None; "not a number")]
+    #[test]
+    fn test_parse_link(
+        namespace: Namespace,
+        link: &str,
+        expected: Option<u64>,
+    ) {
+        assert_eq!(parse_link(namespace, link), expected);
+    }
+
+    #[test]
+    fn test_isolated_from_reports_differing_namespaces() {
+        //                       cgroup ipc mnt net pid user uts
+        let reference = proc_dir([1, 2, 3, 4, 5, 6, 7]);
+        let cell = proc_dir([11, 12, 13, 4, 15, 6, 17]);
+
+        assert_eq!(
+            isolated_from(&cell, &reference).expect("compare namespaces"),
+            vec![
+                Namespace::Cgroup,
+                Namespace::Ipc,
+                Namespace::Mnt,
+                Namespace::Pid,
+                Namespace::Uts,
+            ]
+        );
+
+        std::fs::remove_dir_all(reference).expect("remove proc dir");
+        std::fs::remove_dir_all(cell).expect("remove proc dir");
+    }
+
+    #[test]
+    fn test_own_process_is_not_isolated() {
+        let pid = std::process::id() as i32;
+        assert_eq!(isolated(pid).expect("compare namespaces"), vec![]);
+    }
+}
@@ -0,0 +1,337 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Network bandwidth limits of a cell, applied with `tc`.
+//!
+//! Egress is shaped by a token bucket filter (tbf) as the root qdisc of the interface.
+//! Ingress can't be shaped without redirecting it through another interface, so it is
+//! policed instead: packets above the rate are dropped, and the sender backs off.
+//!
+//! The limits are applied to the host end of the veth pair of a bridged cell (see
+//! [super::veth::Veth]), where the traffic of the cell goes the other way: what the cell
+//! receives leaves the host end, and is shaped there.
+
+use std::{fmt, io, process::Command, str::FromStr};
+
+/// How long a packet may wait in the token bucket filter before it is dropped.
+const TBF_LATENCY: &str = "50ms";
+
+/// A rate in bits per second, parsed from the rate format of `tc` (e.g., "10mbit").
+/// Unlike `tc`, a unit is required, as a bare number means bytes per second to `tc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRate(u64);
+
+impl BitRate {
+    pub fn bits_per_second(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for BitRate {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = split_unit(s)?;
+        let multiplier: u64 = match unit {
+            "bit" => 1,
+            "kbit" => 1_000,
+            "mbit" => 1_000_000,
+            "gbit" => 1_000_000_000,
+            "tbit" => 1_000_000_000_000,
+            "kibit" => 1 << 10,
+            "mibit" => 1 << 20,
+            "gibit" => 1 << 30,
+            "tibit" => 1 << 40,
+            // bytes per second
+            "bps" => 8,
+            "kbps" => 8_000,
+            "mbps" => 8_000_000,
+            "gbps" => 8_000_000_000,
+            "tbps" => 8_000_000_000_000,
+            _ => return Err(()),
+        };
+        Ok(Self(scale(value, multiplier)?))
+    }
+}
+
+impl fmt::Display for BitRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}bit", self.0)
+    }
+}
+
+/// A size in bytes, parsed from the size format of `tc` (e.g., "32kb").
+/// A bare number is a number of bytes, and "k", "m" and "g" are powers of 1024.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = split_unit(s)?;
+        let multiplier: u64 = match unit {
+            "" | "b" => 1,
+            "k" | "kb" => 1 << 10,
+            "m" | "mb" => 1 << 20,
+            "g" | "gb" => 1 << 30,
+            _ => return Err(()),
+        };
+        Ok(Self(scale(value, multiplier)?))
+    }
+}
+
+/// Splits `s` into its number and its lowercased unit.
+fn split_unit(s: &str) -> Result<(f64, String), ()> {
+    let s = s.trim();
+    let split =
+        s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().map_err(|_| ())?;
+    Ok((value, unit.to_ascii_lowercase()))
+}
+
+/// Returns `value` times `multiplier`, rounded, if it is a positive integer.
+fn scale(value: f64, multiplier: u64) -> Result<u64, ()> {
+    let scaled = (value * multiplier as f64).round();
+    if !scaled.is_finite() || scaled < 1.0 || scaled > u64::MAX as f64 {
+        return Err(());
+    }
+    Ok(scaled as u64)
+}
+
+/// The rate traffic is limited to, and the burst allowed above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthLimit {
+    pub rate: BitRate,
+    /// How much can be sent at once at full speed, before the rate applies.
+    pub burst: ByteSize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BandwidthLimits {
+    /// Traffic received by the cell.
+    pub ingress: Option<BandwidthLimit>,
+    /// Traffic sent by the cell.
+    pub egress: Option<BandwidthLimit>,
+}
+
+impl BandwidthLimits {
+    pub fn is_empty(&self) -> bool {
+        self.ingress.is_none() && self.egress.is_none()
+    }
+
+    /// Returns the limits as seen from the other end of a veth pair, where the traffic
+    /// received by the cell is sent, and the other way around.
+    pub fn reversed(&self) -> Self {
+        Self { ingress: self.egress, egress: self.ingress }
+    }
+
+    /// Returns the arguments of the `tc` commands applying the limits to `device`.
+    /// The commands replace existing limits, so they can be run again.
+    pub fn tc_commands(&self, device: &str) -> Vec<Vec<String>> {
+        let mut commands = vec![];
+
+        if let Some(BandwidthLimit { rate, burst }) = self.egress {
+            commands.push(args(format!(
+                "qdisc replace dev {device} root tbf rate {rate} burst {} latency {TBF_LATENCY}",
+                burst.bytes()
+            )));
+        }
+
+        if let Some(BandwidthLimit { rate, burst }) = self.ingress {
+            commands.push(args(format!(
+                "qdisc replace dev {device} handle ffff: ingress"
+            )));
+            commands.push(args(format!(
+                "filter replace dev {device} parent ffff: protocol all prio 1 u32 match u32 0 0 police rate {rate} burst {} drop",
+                burst.bytes()
+            )));
+        }
+
+        commands
+    }
+
+    /// Returns the arguments of the `tc` commands removing the limits from `device`.
+    pub fn tc_teardown_commands(&self, device: &str) -> Vec<Vec<String>> {
+        let mut commands = vec![];
+
+        if self.egress.is_some() {
+            commands.push(args(format!("qdisc del dev {device} root")));
+        }
+        if self.ingress.is_some() {
+            commands.push(args(format!("qdisc del dev {device} ingress")));
+        }
+
+        commands
+    }
+
+    /// Applies the limits to `device` (see [BandwidthLimits::tc_commands]).
+    pub fn apply(&self, device: &str) -> io::Result<()> {
+        run_tc(self.tc_commands(device))
+    }
+
+    /// Removes the limits from `device`. Removing the device (e.g., when the network
+    /// namespace of the cell is destroyed) removes them as well.
+    pub fn remove(&self, device: &str) -> io::Result<()> {
+        run_tc(self.tc_teardown_commands(device))
+    }
+}
+
+/// Interface names can't contain whitespace, so splitting on it is safe.
+fn args(command: String) -> Vec<String> {
+    command.split_whitespace().map(String::from).collect()
+}
+
+fn run_tc(commands: Vec<Vec<String>>) -> io::Result<()> {
+    for args in commands {
+        let output = Command::new("tc").args(&args).output()?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "tc {} failed: {}",
+                    args.join(" "),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case("1bit", 1; "bits")]
+    #[test_case("10mbit", 10_000_000; "megabits")]
+    #[test_case("1.5Gbit", 1_500_000_000; "fractional gigabits")]
+    #[test_case("1mibit", 1_048_576; "mebibits")]
+    #[test_case("100kbps", 800_000; "kilobytes")]
+    fn test_parse_rate(input: &str, bits_per_second: u64) {
+        let rate: BitRate = input.parse().expect("valid rate");
+        assert_eq!(rate.bits_per_second(), bits_per_second);
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("100"; "no unit")]
+    #[test_case("mbit"; "no number")]
+    #[test_case("10 furlongs"; "unknown unit")]
+    #[test_case("0mbit"; "zero")]
+    #[test_case("-1mbit"; "negative")]
+    #[test_case("1.2.3mbit"; "malformed number")]
+    fn test_parse_invalid_rate(input: &str) {
+        assert_eq!(input.parse::<BitRate>(), Err(()));
+    }
+
+    #[test_case("1500", 1500; "bytes")]
+    #[test_case("32kb", 32_768; "kilobytes")]
+    #[test_case("1m", 1_048_576; "megabytes")]
+    fn test_parse_size(input: &str, bytes: u64) {
+        let size: ByteSize = input.parse().expect("valid size");
+        assert_eq!(size.bytes(), bytes);
+    }
+
+    #[test]
+    fn test_tc_commands() {
+        let limits = BandwidthLimits {
+            ingress: Some(BandwidthLimit {
+                rate: "1mbit".parse().expect("rate"),
+                burst: "10kb".parse().expect("burst"),
+            }),
+            egress: Some(BandwidthLimit {
+                rate: "2mbit".parse().expect("rate"),
+                burst: "32kb".parse().expect("burst"),
+            }),
+        };
+
+        let commands: Vec<_> = limits
+            .tc_commands("veth0")
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                "qdisc replace dev veth0 root tbf rate 2000000bit burst 32768 latency 50ms",
+                "qdisc replace dev veth0 handle ffff: ingress",
+                "filter replace dev veth0 parent ffff: protocol all prio 1 u32 match u32 0 0 police rate 1000000bit burst 10240 drop",
+            ]
+        );
+
+        let teardown: Vec<_> = limits
+            .tc_teardown_commands("veth0")
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect();
+        assert_eq!(
+            teardown,
+            ["qdisc del dev veth0 root", "qdisc del dev veth0 ingress"]
+        );
+    }
+
+    #[test]
+    fn test_reversed_limits_shape_what_the_cell_receives() {
+        let limit = |rate: &str| BandwidthLimit {
+            rate: rate.parse().expect("rate"),
+            burst: "32kb".parse().expect("burst"),
+        };
+        let limits =
+            BandwidthLimits { ingress: Some(limit("1mbit")), egress: None };
+
+        let commands: Vec<_> = limits
+            .reversed()
+            .tc_commands("aeveth1")
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect();
+        assert_eq!(
+            commands,
+            ["qdisc replace dev aeveth1 root tbf rate 1000000bit burst 32768 latency 50ms"]
+        );
+        assert_eq!(limits.reversed().reversed(), limits);
+    }
+
+    #[test]
+    fn test_no_limits_have_no_commands() {
+        let limits = BandwidthLimits::default();
+        assert!(limits.is_empty());
+        assert!(limits.tc_commands("veth0").is_empty());
+        assert!(limits.tc_teardown_commands("veth0").is_empty());
+    }
+}
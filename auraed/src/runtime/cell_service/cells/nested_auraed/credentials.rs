@@ -0,0 +1,220 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The client credentials auraed uses to connect to the nested auraed of a cell.
+//!
+//! By default, auraed connects with the credentials of its own client config. A cell can
+//! be given credentials of its own, so that its nested auraed can tell auraed's
+//! connections to it apart from those to other cells: the nested auraed only accepts
+//! client certificates signed by the CA of the cell.
+
+use aurae_client::AuraeConfig;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use x509_certificate::X509Certificate;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCredentials {
+    /// The CA certificate the nested auraed verifies client certificates with.
+    pub ca_crt: PathBuf,
+    /// The client certificate presented to the nested auraed.
+    pub client_crt: PathBuf,
+    /// The secret key of the client certificate.
+    pub client_key: PathBuf,
+}
+
+impl ClientCredentials {
+    /// Returns an error naming the first of the files that is not a file auraed can read.
+    /// The files are on the host, so they are checked when the cell is allocated rather
+    /// than when the request is validated.
+    pub fn check_readable(&self) -> io::Result<()> {
+        for path in [&self.ca_crt, &self.client_crt, &self.client_key] {
+            check_readable(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn check_readable(path: &Path) -> io::Result<()> {
+    let metadata = std::fs::File::open(path)?.metadata()?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the config to connect to the nested auraed listening on `socket`: the
+/// `default` config, with the client certificate of the cell if it has credentials.
+/// The server certificate of the nested auraed is the one of auraed, so it is still
+/// verified with the CA of the `default` config.
+pub(crate) fn client_config(
+    default: AuraeConfig,
+    socket: String,
+    credentials: Option<&ClientCredentials>,
+) -> AuraeConfig {
+    let mut config = default;
+    config.system.socket = socket;
+
+    if let Some(credentials) = credentials {
+        config.auth.client_crt =
+            credentials.client_crt.to_string_lossy().into_owned();
+        config.auth.client_key =
+            credentials.client_key.to_string_lossy().into_owned();
+    }
+
+    config
+}
+
+/// Returns the common name of the client certificate of `config`, which the nested
+/// auraed trusts as an admin, as it is the identity auraed forwards calls with.
+pub(crate) fn client_common_name(config: &AuraeConfig) -> io::Result<String> {
+    let pem = std::fs::read(&config.auth.client_crt).map_err(|e| {
+        io::Error::new(e.kind(), format!("{}: {e}", config.auth.client_crt))
+    })?;
+    let cert = X509Certificate::from_pem(pem).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", config.auth.client_crt),
+        )
+    })?;
+    cert.subject_common_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: no common name", config.auth.client_crt),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+    use aurae_client::{AuthConfig, SystemConfig};
+
+    fn default_config() -> AuraeConfig {
+        AuraeConfig {
+            auth: AuthConfig {
+                ca_crt: "/etc/aurae/pki/ca.crt".into(),
+                client_crt: "/etc/aurae/pki/_signed.client.nova.crt".into(),
+                client_key: "/etc/aurae/pki/client.nova.key".into(),
+            },
+            system: SystemConfig { socket: "/var/run/aurae/aurae.sock".into() },
+        }
+    }
+
+    #[test]
+    fn test_client_config_uses_the_credentials_of_the_cell() {
+        let credentials = ClientCredentials {
+            ca_crt: "/etc/aurae/cells/ae-1/ca.crt".into(),
+            client_crt: "/etc/aurae/cells/ae-1/client.crt".into(),
+            client_key: "/etc/aurae/cells/ae-1/client.key".into(),
+        };
+
+        let config = client_config(
+            default_config(),
+            "/var/run/aurae/aurae-1.sock".into(),
+            Some(&credentials),
+        );
+
+        assert_eq!(config.system.socket, "/var/run/aurae/aurae-1.sock");
+        assert_eq!(config.auth.ca_crt, "/etc/aurae/pki/ca.crt");
+        assert_eq!(config.auth.client_crt, "/etc/aurae/cells/ae-1/client.crt");
+        assert_eq!(config.auth.client_key, "/etc/aurae/cells/ae-1/client.key");
+    }
+
+    #[test]
+    fn test_check_readable() {
+        let dir = temp_dir("credentials");
+        for file in ["ca.crt", "client.crt", "client.key"] {
+            std::fs::write(dir.join(file), "").expect("write credential");
+        }
+        let mut credentials = ClientCredentials {
+            ca_crt: dir.join("ca.crt"),
+            client_crt: dir.join("client.crt"),
+            client_key: dir.join("client.key"),
+        };
+        credentials.check_readable().expect("readable credentials");
+
+        credentials.client_key = dir.join("missing.key");
+        let e = credentials.check_readable().expect_err("missing key");
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains("missing.key"));
+
+        credentials.client_key = dir.join("client.key");
+        credentials.ca_crt = dir.clone();
+        let e = credentials.check_readable().expect_err("directory");
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+    }
+
+    #[test]
+    fn test_client_common_name_of_an_invalid_certificate() {
+        let dir = temp_dir("client-common-name");
+        let mut config = default_config();
+
+        config.auth.client_crt = dir.join("missing.crt").display().to_string();
+        let e = client_common_name(&config).expect_err("missing certificate");
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains("missing.crt"));
+
+        std::fs::write(dir.join("client.crt"), "not a certificate")
+            .expect("write certificate");
+        config.auth.client_crt = dir.join("client.crt").display().to_string();
+        let e = client_common_name(&config).expect_err("invalid certificate");
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+    }
+
+    #[test]
+    fn test_client_config_falls_back_to_the_default_credentials() {
+        let config = client_config(
+            default_config(),
+            "/var/run/aurae/aurae-1.sock".into(),
+            None,
+        );
+
+        assert_eq!(config.system.socket, "/var/run/aurae/aurae-1.sock");
+        assert_eq!(config.auth.ca_crt, "/etc/aurae/pki/ca.crt");
+        assert_eq!(
+            config.auth.client_crt,
+            "/etc/aurae/pki/_signed.client.nova.crt"
+        );
+        assert_eq!(config.auth.client_key, "/etc/aurae/pki/client.nova.key");
+    }
+}
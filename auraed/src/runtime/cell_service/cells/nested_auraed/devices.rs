@@ -0,0 +1,304 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Host devices made accessible in the mount namespace of a cell.
+//!
+//! Device nodes are created after the mounts of the cell, in whatever filesystem is
+//! mounted at their path. Unless the cell mounts a tmpfs on /dev, that is the /dev of the
+//! host, where the device usually exists already. Existing nodes of the same device are
+//! left as is.
+//!
+//! cgroup v2 has no device controller interface files: access to devices is controlled
+//! by BPF programs attached to the cgroup. auraed doesn't attach any, so the processes of
+//! a cell can access every device node they can open, and mapped devices need no cgroup
+//! setup.
+
+use nix::{
+    errno::Errno,
+    sys::stat::{self, makedev, mknod, Mode, SFlag},
+};
+use std::{
+    fs,
+    os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Char,
+    Block,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DeviceError {
+    #[error("device path '{0}' is not an absolute path in /dev")]
+    InvalidPath(String),
+    #[error("device '{0}' not found on host")]
+    NotFound(String),
+    #[error("'{0}' is not a device")]
+    NotADevice(String),
+    #[error("major {requested} does not match the device on host ({actual})")]
+    MajorMismatch { requested: u64, actual: u64 },
+    #[error("minor {requested} does not match the device on host ({actual})")]
+    MinorMismatch { requested: u64, actual: u64 },
+    #[error("permissions '{0}' are not a combination of 'r' and 'w'")]
+    InvalidPermissions(String),
+}
+
+impl DeviceError {
+    /// The field of the device mapping the error is about.
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::InvalidPath(_) | Self::NotFound(_) | Self::NotADevice(_) => {
+                "path"
+            }
+            Self::MajorMismatch { .. } => "major",
+            Self::MinorMismatch { .. } => "minor",
+            Self::InvalidPermissions(_) => "permissions",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceMapping {
+    /// The path of the device on the host, and of its node in the cell.
+    pub path: PathBuf,
+    pub kind: DeviceKind,
+    pub major: u64,
+    pub minor: u64,
+    /// The permissions of the node, for its owner, group and others.
+    pub mode: u32,
+}
+
+impl DeviceMapping {
+    /// Validates a mapping of the device at `path` on the host, which must be a
+    /// character or block device with the given `major` and `minor` numbers.
+    /// `permissions` is a combination of "r" (read) and "w" (write).
+    pub fn new(
+        path: &str,
+        major: u32,
+        minor: u32,
+        permissions: &str,
+    ) -> Result<Self, DeviceError> {
+        let path = PathBuf::from(path);
+        if !path.is_absolute() || !path.starts_with("/dev") {
+            return Err(DeviceError::InvalidPath(path.display().to_string()));
+        }
+
+        let mode = parse_permissions(permissions).ok_or_else(|| {
+            DeviceError::InvalidPermissions(permissions.into())
+        })?;
+
+        let metadata = fs::metadata(&path)
+            .map_err(|_| DeviceError::NotFound(path.display().to_string()))?;
+        let kind = if metadata.file_type().is_char_device() {
+            DeviceKind::Char
+        } else if metadata.file_type().is_block_device() {
+            DeviceKind::Block
+        } else {
+            return Err(DeviceError::NotADevice(path.display().to_string()));
+        };
+
+        let (major, minor) = (u64::from(major), u64::from(minor));
+        let actual = stat::major(metadata.rdev());
+        if actual != major {
+            return Err(DeviceError::MajorMismatch {
+                requested: major,
+                actual,
+            });
+        }
+        let actual = stat::minor(metadata.rdev());
+        if actual != minor {
+            return Err(DeviceError::MinorMismatch {
+                requested: minor,
+                actual,
+            });
+        }
+
+        Ok(Self { path, kind, major, minor, mode })
+    }
+
+    /// Runs in the child, before exec, in the mount namespace of the cell.
+    pub fn map(&self) -> nix::Result<()> {
+        self.map_in(Path::new("/"))
+    }
+
+    /// Creates the node of the device at its path under `root`, along with its parent
+    /// directories. Does nothing if a node for the same device already exists.
+    fn map_in(&self, root: &Path) -> nix::Result<()> {
+        let node = root.join(self.path.strip_prefix("/").unwrap_or(&self.path));
+        let dev = makedev(self.major, self.minor);
+
+        if let Ok(metadata) = fs::symlink_metadata(&node) {
+            return if metadata.rdev() == dev && self.is_kind(&metadata) {
+                Ok(())
+            } else {
+                Err(Errno::EEXIST)
+            };
+        }
+
+        if let Some(parent) = node.parent() {
+            fs::create_dir_all(parent).map_err(io_errno)?;
+        }
+
+        let kind = match self.kind {
+            DeviceKind::Char => SFlag::S_IFCHR,
+            DeviceKind::Block => SFlag::S_IFBLK,
+        };
+        mknod(&node, kind, Mode::from_bits_truncate(self.mode), dev)?;
+
+        // The mode passed to mknod is masked by the umask
+        fs::set_permissions(&node, fs::Permissions::from_mode(self.mode))
+            .map_err(io_errno)
+    }
+
+    fn is_kind(&self, metadata: &fs::Metadata) -> bool {
+        match self.kind {
+            DeviceKind::Char => metadata.file_type().is_char_device(),
+            DeviceKind::Block => metadata.file_type().is_block_device(),
+        }
+    }
+}
+
+/// Returns the mode of a node with `permissions` for its owner, group and others.
+fn parse_permissions(permissions: &str) -> Option<u32> {
+    let (mut read, mut write) = (false, false);
+    for c in permissions.chars() {
+        let seen = match c {
+            'r' => std::mem::replace(&mut read, true),
+            'w' => std::mem::replace(&mut write, true),
+            _ => return None,
+        };
+        if seen {
+            return None;
+        }
+    }
+
+    match (read, write) {
+        (false, false) => None,
+        (read, write) => {
+            Some(if read { 0o444 } else { 0 } | if write { 0o222 } else { 0 })
+        }
+    }
+}
+
+fn io_errno(e: std::io::Error) -> Errno {
+    Errno::from_i32(e.raw_os_error().unwrap_or(libc::EIO))
+}
+
+/// Maps `devices` in order. Nodes that were already created are left in place on error,
+/// as they are removed along with the mounts of the cell.
+pub fn map_all(devices: &[DeviceMapping]) -> nix::Result<()> {
+    for device in devices {
+        device.map()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use simple_test_case::test_case;
+
+    #[test]
+    fn test_null_device() {
+        let device = DeviceMapping::new("/dev/null", 1, 3, "rw")
+            .expect("valid device mapping");
+
+        assert_eq!(device.kind, DeviceKind::Char);
+        assert_eq!(device.mode, 0o666);
+    }
+
+    #[test_case("dev/null", 1, 3, "rw", "path"; "relative path")]
+    #[test_case("/tmp/null", 1, 3, "rw", "path"; "outside of dev")]
+    #[test_case("/dev/aurae-does-not-exist", 1, 3, "rw", "path"; "missing device")]
+    #[test_case("/dev", 1, 3, "rw", "path"; "not a device")]
+    #[test_case("/dev/null", 2, 3, "rw", "major"; "wrong major")]
+    #[test_case("/dev/null", 1, 5, "rw", "minor"; "wrong minor")]
+    #[test_case("/dev/null", 1, 3, "rwm", "permissions"; "unknown permission")]
+    #[test_case("/dev/null", 1, 3, "rr", "permissions"; "repeated permission")]
+    #[test_case("/dev/null", 1, 3, "", "permissions"; "no permissions")]
+    #[test]
+    fn test_invalid_device_mapping(
+        path: &str,
+        major: u32,
+        minor: u32,
+        permissions: &str,
+        field: &str,
+    ) {
+        let e = DeviceMapping::new(path, major, minor, permissions)
+            .expect_err("invalid device mapping");
+        assert_eq!(e.field(), field);
+    }
+
+    #[test]
+    fn test_parse_permissions() {
+        assert_eq!(parse_permissions("r"), Some(0o444));
+        assert_eq!(parse_permissions("w"), Some(0o222));
+        assert_eq!(parse_permissions("wr"), Some(0o666));
+    }
+
+    #[test]
+    fn test_existing_node_is_left_as_is() {
+        let device = DeviceMapping::new("/dev/null", 1, 3, "r")
+            .expect("valid device mapping");
+
+        // /dev/null exists on the host, so nothing is created (or changed)
+        device.map_in(Path::new("/")).expect("existing node");
+        let mode = fs::metadata("/dev/null").expect("stat").mode() & 0o777;
+        assert_eq!(mode, 0o666);
+
+        // a node of another device is in the way
+        let zero = DeviceMapping { minor: 5, ..device };
+        assert_eq!(zero.map_in(Path::new("/")), Err(Errno::EEXIST));
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_map_creates_node() {
+        let root = temp_path("devices");
+        let device = DeviceMapping::new("/dev/null", 1, 3, "r")
+            .expect("valid device mapping");
+
+        let res = device.map_in(&root);
+        let metadata = fs::metadata(root.join("dev/null"));
+        let _ = fs::remove_dir_all(&root);
+
+        res.expect("failed to map device");
+        let metadata = metadata.expect("node created");
+        assert!(metadata.file_type().is_char_device());
+        assert_eq!(metadata.rdev(), makedev(1, 3));
+        assert_eq!(metadata.mode() & 0o777, 0o444);
+    }
+}
@@ -0,0 +1,144 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The /etc/resolv.conf of a cell.
+//!
+//! Without an ephemeral root, the generated file is written on the host before the clone,
+//! and bind mounted over /etc/resolv.conf in the mount namespace of the cell, so the
+//! host's file is left as is. auraed removes the generated file once the nested auraed
+//! has started; the bind mount keeps it alive for as long as the cell.
+//!
+//! With an ephemeral root, /etc/resolv.conf is replaced in the overlay instead, as the
+//! generated file on the host isn't reachable from the new root.
+
+use super::isolation_controls::retry_on_eintr;
+use nix::mount::MsFlags;
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsConfig {
+    pub nameservers: Vec<IpAddr>,
+    /// Search domains, in the order they are tried.
+    pub search: Vec<String>,
+}
+
+impl DnsConfig {
+    /// Returns the contents of the resolv.conf of the cell.
+    pub fn resolv_conf(&self) -> String {
+        let mut contents = String::from("# Generated by auraed\n");
+        for nameserver in &self.nameservers {
+            contents.push_str(&format!("nameserver {nameserver}\n"));
+        }
+        if !self.search.is_empty() {
+            contents.push_str(&format!("search {}\n", self.search.join(" ")));
+        }
+        contents
+    }
+}
+
+/// Runs in auraed, before the clone.
+/// Writes the resolv.conf of the cell in the runtime directory of aurae, and returns its path.
+pub(crate) fn write_resolv_conf(dns: &DnsConfig) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!(
+        "/var/run/aurae/resolv-{}.conf",
+        uuid::Uuid::new_v4()
+    ));
+    fs::write(&path, dns.resolv_conf())?;
+    Ok(path)
+}
+
+/// Runs in the child, before exec, in the mount namespace of the cell.
+/// Bind mounts the file written by [write_resolv_conf] over /etc/resolv.conf.
+pub(crate) fn bind_resolv_conf(source: &Path) -> nix::Result<()> {
+    retry_on_eintr(|| {
+        nix::mount::mount(
+            Some(source),
+            RESOLV_CONF,
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        )
+    })
+}
+
+/// Runs in the child, before exec, in the ephemeral root of the cell.
+/// /etc/resolv.conf is often a symlink to a file outside of /etc, so it is replaced rather
+/// than written through.
+pub(crate) fn replace_resolv_conf(dns: &DnsConfig) -> io::Result<()> {
+    match fs::remove_file(RESOLV_CONF) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    fs::write(RESOLV_CONF, dns.resolv_conf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolv_conf() {
+        let dns = DnsConfig {
+            nameservers: vec![
+                "10.0.0.53".parse().unwrap(),
+                "2001:db8::53".parse().unwrap(),
+            ],
+            search: vec!["cell.local".into(), "aurae.local".into()],
+        };
+
+        assert_eq!(
+            dns.resolv_conf(),
+            "# Generated by auraed\n\
+             nameserver 10.0.0.53\n\
+             nameserver 2001:db8::53\n\
+             search cell.local aurae.local\n"
+        );
+    }
+
+    #[test]
+    fn test_resolv_conf_without_search_domains() {
+        let dns = DnsConfig {
+            nameservers: vec!["1.1.1.1".parse().unwrap()],
+            search: vec![],
+        };
+
+        assert_eq!(
+            dns.resolv_conf(),
+            "# Generated by auraed\nnameserver 1.1.1.1\n"
+        );
+    }
+}
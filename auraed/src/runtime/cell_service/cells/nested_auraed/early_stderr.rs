@@ -0,0 +1,157 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Captures the stderr of a nested auraed, so that when it fails to come up (e.g., it
+//! can't bind its socket), we can say why rather than only that it can't be reached.
+
+use nix::errno::Errno;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    os::unix::io::FromRawFd,
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+
+/// How many lines from the start of the stderr of a nested auraed are kept.
+/// The lines after those are only logged.
+pub const EARLY_STDERR_LINES: usize = 50;
+
+/// The first lines a nested auraed wrote to its stderr.
+#[derive(Debug, Clone, Default)]
+pub struct EarlyStderr {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl EarlyStderr {
+    /// Returns the [EarlyStderr] of the process given the returned [Stdio] as its
+    /// stderr. The stderr is read in a new thread, which logs every line as the output
+    /// of the nested auraed of `cell_name`, until all its writers are closed.
+    pub fn capture(cell_name: &str) -> io::Result<(Self, Stdio)> {
+        let mut fds = [-1; 2];
+        Errno::result(unsafe {
+            libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC)
+        })
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+        let [read, write] = fds;
+
+        // SAFETY: the fds were just created, and are only owned here
+        let (read, write) =
+            unsafe { (File::from_raw_fd(read), Stdio::from_raw_fd(write)) };
+
+        let early_stderr = Self::default();
+        let lines = early_stderr.lines.clone();
+        let cell_name = cell_name.to_string();
+        let _ = std::thread::spawn(move || {
+            for line in BufReader::new(read).split(b'\n') {
+                let Ok(line) = line else {
+                    break;
+                };
+                let line = String::from_utf8_lossy(&line).into_owned();
+                info!(cell_name, "nested auraed: {line}");
+
+                let mut lines = lines.lock().expect("lock");
+                if lines.len() < EARLY_STDERR_LINES {
+                    lines.push(line);
+                }
+            }
+        });
+
+        Ok((early_stderr, write))
+    }
+
+    /// Returns the lines read so far, up to [EARLY_STDERR_LINES].
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().expect("lock").clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        process::Command,
+        time::{Duration, Instant},
+    };
+
+    fn wait_for_lines(stderr: &EarlyStderr, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let lines = stderr.lines();
+            if lines.len() >= count || Instant::now() > deadline {
+                return lines;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_captures_the_stderr_of_a_failing_process() {
+        let (stderr, stdio) =
+            EarlyStderr::capture("ae-test").expect("capture stderr");
+
+        // a stub of a nested auraed that fails to come up
+        let status = Command::new("sh")
+            .args(["-c", "echo 'failed to bind socket' >&2; echo out; exit 1"])
+            .stdout(Stdio::null())
+            .stderr(stdio)
+            .status()
+            .expect("run stub");
+        assert!(!status.success());
+
+        assert_eq!(wait_for_lines(&stderr, 1), ["failed to bind socket"]);
+    }
+
+    #[test]
+    fn test_keeps_the_first_lines() {
+        let (stderr, stdio) =
+            EarlyStderr::capture("ae-test").expect("capture stderr");
+
+        let script = format!(
+            "for i in $(seq 1 {}); do echo $i >&2; done",
+            EARLY_STDERR_LINES + 10
+        );
+        let status = Command::new("sh")
+            .args(["-c", &script])
+            .stderr(stdio)
+            .status()
+            .expect("run stub");
+        assert!(status.success());
+
+        let lines = wait_for_lines(&stderr, EARLY_STDERR_LINES);
+        assert_eq!(lines.len(), EARLY_STDERR_LINES);
+        assert_eq!(lines[0], "1");
+        assert_eq!(
+            lines[EARLY_STDERR_LINES - 1],
+            EARLY_STDERR_LINES.to_string()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! An ephemeral, writable root for a cell.
+//!
+//! The root of the host is mounted as the lower layer of an overlay, with the upper
+//! layer on a tmpfs. The cell then pivots into the overlay, so its writes land in the
+//! tmpfs and the host's root is never modified. The tmpfs only exists in the mount
+//! namespace of the cell, so the writes are discarded by the kernel once the last
+//! process of the cell exits (i.e., when the cell is freed).
+//!
+//! Only the root filesystem is in the lower layer, not the filesystems mounted below it.
+//! /dev and /sys are bind mounted into the overlay; /proc is mounted afterwards by
+//! [super::isolation_controls::Isolation::isolate_process].
+
+use nix::{
+    mount::{MntFlags, MsFlags},
+    sys::stat::Mode,
+};
+use std::path::{Path, PathBuf};
+
+/// Where the tmpfs holding the overlay is mounted, before pivoting into the overlay.
+/// Only shadowed in the mount namespace of the cell.
+const SCRATCH: &str = "/tmp";
+
+/// Where the old root is put by pivot_root, relative to the new root. It is detached
+/// right after.
+const OLD_ROOT: &str = ".aurae-old-root";
+
+/// Pseudo filesystems of the host that are bind mounted into the overlay.
+const HOST_BINDS: [&str; 2] = ["/dev", "/sys"];
+
+/// The syscalls an ephemeral root (and the mounts of a cell, see [super::mounts]) is
+/// set up with, so the order of the steps can be tested.
+pub trait MountOps {
+    fn mount(
+        &mut self,
+        source: Option<&Path>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: MsFlags,
+        data: Option<&str>,
+    ) -> nix::Result<()>;
+
+    fn unmount(&mut self, target: &Path, flags: MntFlags) -> nix::Result<()>;
+
+    fn create_dir(&mut self, path: &Path) -> nix::Result<()>;
+
+    fn pivot_root(
+        &mut self,
+        new_root: &Path,
+        put_old: &Path,
+    ) -> nix::Result<()>;
+
+    fn chdir(&mut self, path: &Path) -> nix::Result<()>;
+}
+
+/// Mounts in the mount namespace of the calling process.
+pub struct HostMounts;
+
+impl MountOps for HostMounts {
+    fn mount(
+        &mut self,
+        source: Option<&Path>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: MsFlags,
+        data: Option<&str>,
+    ) -> nix::Result<()> {
+        super::isolation_controls::retry_on_eintr(|| {
+            nix::mount::mount(source, target, fstype, flags, data)
+        })
+    }
+
+    fn unmount(&mut self, target: &Path, flags: MntFlags) -> nix::Result<()> {
+        super::isolation_controls::retry_on_eintr(|| {
+            nix::mount::umount2(target, flags)
+        })
+    }
+
+    fn create_dir(&mut self, path: &Path) -> nix::Result<()> {
+        match nix::unistd::mkdir(path, Mode::from_bits_truncate(0o755)) {
+            Err(nix::errno::Errno::EEXIST) => Ok(()),
+            res => res,
+        }
+    }
+
+    fn pivot_root(
+        &mut self,
+        new_root: &Path,
+        put_old: &Path,
+    ) -> nix::Result<()> {
+        nix::unistd::pivot_root(new_root, put_old)
+    }
+
+    fn chdir(&mut self, path: &Path) -> nix::Result<()> {
+        nix::unistd::chdir(path)
+    }
+}
+
+/// Runs in the child, before exec, in the mount namespace of the cell.
+/// If a step before the pivot fails, the mounts that were already made are unmounted
+/// (in reverse order) before returning.
+pub fn setup(ops: &mut impl MountOps) -> nix::Result<()> {
+    let scratch = Path::new(SCRATCH);
+    let upper = scratch.join("upper");
+    let work = scratch.join("work");
+    let root = scratch.join("root");
+
+    let mut mounted: Vec<PathBuf> = vec![];
+    let res = (|| {
+        ops.mount(
+            Some(Path::new("tmpfs")),
+            scratch,
+            Some("tmpfs"),
+            MsFlags::empty(),
+            Some("mode=0755"),
+        )?;
+        mounted.push(scratch.into());
+
+        for dir in [&upper, &work, &root] {
+            ops.create_dir(dir)?;
+        }
+
+        let data = format!(
+            "lowerdir=/,upperdir={},workdir={}",
+            upper.display(),
+            work.display()
+        );
+        ops.mount(
+            Some(Path::new("overlay")),
+            &root,
+            Some("overlay"),
+            MsFlags::empty(),
+            Some(&data),
+        )?;
+        mounted.push(root.clone());
+
+        for host in HOST_BINDS {
+            let target = root.join(host.trim_start_matches('/'));
+            ops.mount(
+                Some(Path::new(host)),
+                &target,
+                None,
+                MsFlags::MS_BIND | MsFlags::MS_REC,
+                None,
+            )?;
+            mounted.push(target);
+        }
+
+        ops.create_dir(&root.join(OLD_ROOT))?;
+        ops.pivot_root(&root, &root.join(OLD_ROOT))
+    })();
+
+    if let Err(e) = res {
+        for target in mounted.iter().rev() {
+            let _best_effort = ops.unmount(target, MntFlags::MNT_DETACH);
+        }
+        return Err(e);
+    }
+
+    ops.chdir(Path::new("/"))?;
+    ops.unmount(&Path::new("/").join(OLD_ROOT), MntFlags::MNT_DETACH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::errno::Errno;
+
+    /// Records the calls, failing the call at index `fail_at`, if set.
+    #[derive(Default)]
+    struct MockMounts {
+        calls: Vec<String>,
+        fail_at: Option<usize>,
+    }
+
+    impl MockMounts {
+        fn record(&mut self, call: String) -> nix::Result<()> {
+            let failed = self.fail_at == Some(self.calls.len());
+            self.calls.push(call);
+            if failed {
+                Err(Errno::EPERM)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl MountOps for MockMounts {
+        fn mount(
+            &mut self,
+            source: Option<&Path>,
+            target: &Path,
+            fstype: Option<&str>,
+            flags: MsFlags,
+            data: Option<&str>,
+        ) -> nix::Result<()> {
+            self.record(format!(
+                "mount {} {} {} {:#x} {}",
+                source.map_or("-".into(), |x| x.display().to_string()),
+                target.display(),
+                fstype.unwrap_or("-"),
+                flags.bits(),
+                data.unwrap_or("-")
+            ))
+        }
+
+        fn unmount(
+            &mut self,
+            target: &Path,
+            flags: MntFlags,
+        ) -> nix::Result<()> {
+            let call = if flags.contains(MntFlags::MNT_DETACH) {
+                "detach"
+            } else {
+                "unmount"
+            };
+            self.record(format!("{call} {}", target.display()))
+        }
+
+        fn create_dir(&mut self, path: &Path) -> nix::Result<()> {
+            self.record(format!("mkdir {}", path.display()))
+        }
+
+        fn pivot_root(
+            &mut self,
+            new_root: &Path,
+            put_old: &Path,
+        ) -> nix::Result<()> {
+            self.record(format!(
+                "pivot_root {} {}",
+                new_root.display(),
+                put_old.display()
+            ))
+        }
+
+        fn chdir(&mut self, path: &Path) -> nix::Result<()> {
+            self.record(format!("chdir {}", path.display()))
+        }
+    }
+
+    #[test]
+    fn test_setup_sequence() {
+        let mut mounts = MockMounts::default();
+        setup(&mut mounts).expect("setup");
+
+        // MS_BIND | MS_REC is 0x5000
+        assert_eq!(
+            mounts.calls,
+            [
+                "mount tmpfs /tmp tmpfs 0x0 mode=0755",
+                "mkdir /tmp/upper",
+                "mkdir /tmp/work",
+                "mkdir /tmp/root",
+                "mount overlay /tmp/root overlay 0x0 lowerdir=/,upperdir=/tmp/upper,workdir=/tmp/work",
+                "mount /dev /tmp/root/dev - 0x5000 -",
+                "mount /sys /tmp/root/sys - 0x5000 -",
+                "mkdir /tmp/root/.aurae-old-root",
+                "pivot_root /tmp/root /tmp/root/.aurae-old-root",
+                "chdir /",
+                "detach /.aurae-old-root",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_setup_undoes_mounts_on_failure() {
+        // fail the bind mount of /sys
+        let mut mounts = MockMounts { fail_at: Some(6), ..Default::default() };
+        assert_eq!(setup(&mut mounts), Err(Errno::EPERM));
+
+        assert_eq!(
+            mounts.calls[7..],
+            ["detach /tmp/root/dev", "detach /tmp/root", "detach /tmp"]
+        );
+    }
+}
@@ -28,26 +28,115 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
+use super::{
+    devices, dns,
+    ephemeral_root::{self, HostMounts},
+    mounts,
+    user_namespace::{MappedRoot, UserNamespace},
+    BridgeConfig, DeviceMapping, DnsConfig, IdMapping, Mount, SeccompControls,
+};
+use crate::runtime::cell_service::pre_exec::PreExecHooks;
 use libc::c_char;
+use nix::errno::Errno;
+use std::ffi::CString;
 use std::io::{self};
-use std::path::PathBuf;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
 use tracing::info;
 
+/// The longest hostname (and domainname) accepted by the kernel, in bytes.
+const HOST_NAME_MAX: usize = 64;
+
+/// How a cell is connected to the network.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Shares the network namespace of the host.
+    #[default]
+    None,
+    /// Has a network namespace of its own, with only a loopback interface.
+    Isolated,
+    /// Like [NetworkMode::Isolated], with a veth pair attached to a bridge of the host.
+    Bridged(BridgeConfig),
+}
+
+impl NetworkMode {
+    /// Whether the cell has a network namespace of its own.
+    pub fn is_isolated(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct IsolationControls {
     pub isolate_process: bool,
-    pub isolate_network: bool,
+    pub network: NetworkMode,
+    pub seccomp: SeccompControls,
+    /// Pivots the cell into an overlay of the host's root, whose writes go to a tmpfs
+    /// that is discarded with the cell. Requires isolate_process.
+    pub ephemeral_root: bool,
+    /// Applied in the mount namespace of the cell. Requires isolate_process.
+    pub mounts: Vec<Mount>,
+    /// Host devices created in the mount namespace of the cell, after the mounts.
+    /// Requires isolate_process.
+    pub devices: Vec<DeviceMapping>,
+    /// Written to the oom_score_adj of the nested auraed. Processes inherit it when
+    /// forked, so it applies to every process started in the cell.
+    pub oom_score_adj: Option<i16>,
+    /// Set as the RLIMIT_MEMLOCK of the nested auraed, which the processes started in the
+    /// cell inherit, so they can lock that much memory (e.g., with mlockall).
+    pub memlock_limit: Option<u64>,
+    /// Written as the /etc/resolv.conf of the cell, without changing the one of the host.
+    /// Requires isolate_process and an isolated network.
+    pub dns: Option<DnsConfig>,
+    /// Map the ids of a user namespace of the cell to ids of the host, so the nested
+    /// auraed runs as root of the cell without being root on the host. Empty to share
+    /// the user namespace of the host. Requires isolate_process.
+    pub uid_map: Vec<IdMapping>,
+    pub gid_map: Vec<IdMapping>,
 }
 
-#[derive(Default)]
+impl IsolationControls {
+    /// Whether the cell has a user namespace of its own.
+    pub fn has_user_namespace(&self) -> bool {
+        !self.uid_map.is_empty()
+    }
+
+    /// The ids of the host root of the cell is mapped to, if it has a user namespace.
+    pub fn mapped_root(&self) -> Option<MappedRoot> {
+        MappedRoot::new(&self.uid_map, &self.gid_map)
+    }
+}
+
+#[derive(Default, Clone)]
 pub(crate) struct Isolation {
     name: String,
+    /// The resolv.conf written on the host by [Isolation::setup], if any.
+    resolv_conf: Option<PathBuf>,
+    /// Created by [Isolation::setup] for a cell with a user namespace.
+    user_namespace: Option<UserNamespace>,
 }
 
 impl Isolation {
     pub fn new(name: &str) -> Isolation {
-        Isolation { name: name.to_string() }
+        Isolation {
+            name: name.to_string(),
+            resolv_conf: None,
+            user_namespace: None,
+        }
+    }
+
+    /// The resolv.conf written on the host by [Isolation::setup], which can be removed
+    /// once the child has bind mounted it.
+    pub fn resolv_conf(&self) -> Option<&Path> {
+        self.resolv_conf.as_deref()
+    }
+
+    /// Set up by [Isolation::setup] for auraed to write the id maps of the child
+    /// (see [UserNamespace::map_ids]).
+    pub fn user_namespace(&self) -> Option<UserNamespace> {
+        self.user_namespace
     }
+
     pub fn setup(&mut self, iso_ctl: &IsolationControls) -> io::Result<()> {
         // The only setup we will need to do is for isolate_process at this time.
         // We can exit quickly if we are sharing the process controls with the host.
@@ -55,20 +144,109 @@ impl Isolation {
             return Ok(());
         }
 
+        // The child is cloned into the user namespace, and waits for us to write its
+        // id maps before anything else.
+        if iso_ctl.has_user_namespace() {
+            self.user_namespace = Some(UserNamespace::new()?);
+        }
+
         // Bind mount root:root with MS_REC and MS_PRIVATE flags
         // We are not sharing the mounts at this point (in other words we are in a new mount namespace)
-        nix::mount::mount(
-            None::<&str>, // ignored
-            "/",
-            None::<&str>, // ignored
-            nix::mount::MsFlags::MS_PRIVATE | nix::mount::MsFlags::MS_REC,
-            None::<&str>, // ignored
-        )
+        retry_on_eintr(|| {
+            nix::mount::mount(
+                None::<&str>, // ignored
+                "/",
+                None::<&str>, // ignored
+                nix::mount::MsFlags::MS_PRIVATE | nix::mount::MsFlags::MS_REC,
+                None::<&str>, // ignored
+            )
+        })
         .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
         info!("Isolation: Mounted root dir (/) in cell");
+
+        // With an ephemeral root, the child writes the resolv.conf in the overlay itself.
+        if let Some(dns) = &iso_ctl.dns {
+            if !iso_ctl.ephemeral_root {
+                self.resolv_conf = Some(dns::write_resolv_conf(dns)?);
+            }
+        }
         Ok(())
     }
 
+    /// Returns the isolation steps to run in the child, before exec, in order.
+    /// Seccomp is applied last, as the filter may deny the syscalls of the other steps.
+    pub fn into_pre_exec_hooks(
+        self,
+        iso_ctl: IsolationControls,
+    ) -> PreExecHooks {
+        let mut hooks = PreExecHooks::default();
+
+        let isolation = self.clone();
+        hooks.push("enter_user_namespace", move || {
+            isolation.enter_user_namespace()
+        });
+
+        let (mut isolation, ctl) = (self.clone(), iso_ctl.clone());
+        hooks.push("set_oom_score_adj", move || {
+            isolation.set_oom_score_adj(&ctl)
+        });
+
+        let (mut isolation, ctl) = (self.clone(), iso_ctl.clone());
+        hooks.push("set_memlock_limit", move || {
+            isolation.set_memlock_limit(&ctl)
+        });
+
+        let (mut isolation, ctl) = (self.clone(), iso_ctl.clone());
+        hooks.push("isolate_process", move || isolation.isolate_process(&ctl));
+
+        let (mut isolation, ctl) = (self.clone(), iso_ctl.clone());
+        hooks.push("isolate_network", move || isolation.isolate_network(&ctl));
+
+        let mut isolation = self;
+        hooks.push("apply_seccomp", move || isolation.apply_seccomp(&iso_ctl));
+
+        hooks
+    }
+
+    /// Runs in the child, before exec, before any other isolation step, so the other
+    /// steps (and the nested auraed) run as root of the user namespace of the cell.
+    pub fn enter_user_namespace(&self) -> io::Result<()> {
+        let Some(user_namespace) = self.user_namespace else {
+            return Ok(());
+        };
+
+        user_namespace.wait_for_id_maps()
+    }
+
+    /// Runs in the child, before exec.
+    /// Runs before isolate_process, which mounts a new /proc.
+    pub fn set_oom_score_adj(
+        &mut self,
+        iso_ctl: &IsolationControls,
+    ) -> io::Result<()> {
+        let Some(oom_score_adj) = iso_ctl.oom_score_adj else {
+            return Ok(());
+        };
+
+        write_oom_score_adj(oom_score_adj)
+    }
+
+    /// Runs in the child, before exec.
+    /// Memory locks are dropped on exec, so we can only allow the processes to lock.
+    pub fn set_memlock_limit(
+        &mut self,
+        iso_ctl: &IsolationControls,
+    ) -> io::Result<()> {
+        let Some(memlock_limit) = iso_ctl.memlock_limit else {
+            return Ok(());
+        };
+
+        write_memlock_limit(memlock_limit)
+    }
+
+    /// Runs in the child, before exec.
+    /// If any step fails, the steps that have already run are undone before returning.
+    /// The namespaces themselves are torn down by the kernel once the child exits.
     pub fn isolate_process(
         &mut self,
         iso_ctl: &IsolationControls,
@@ -77,52 +255,353 @@ impl Isolation {
             return Ok(());
         }
 
+        // Checked before any other step, so there is nothing to undo if the name is invalid.
+        let uts_name = uts_name(&self.name)?;
+
+        // The root is replaced first, so /proc and the mounts are made in the new root.
+        // The pivot can't be undone, but the overlay only exists in the mount namespace
+        // of the child.
+        if iso_ctl.ephemeral_root {
+            ephemeral_root::setup(&mut HostMounts)
+                .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+        }
+
         //Mount proc in the new pid and mount namespace
         let target = PathBuf::from("/proc");
-        nix::mount::mount(
-            Some("/proc"),
-            &target,
-            Some("proc"),
-            nix::mount::MsFlags::empty(),
-            None::<&str>,
-        )
+        retry_on_eintr(|| {
+            nix::mount::mount(
+                Some("/proc"),
+                &target,
+                Some("proc"),
+                nix::mount::MsFlags::empty(),
+                None::<&str>,
+            )
+        })
         .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
 
+        let proc_mount = OnError::new(|| {
+            let _best_effort = mounts::unmount(&mut HostMounts, &target);
+        });
+
+        // Mounts are applied here rather than in [Isolation::setup], as setup runs in
+        // auraed before the clone, where we are still in the mount namespace of the host.
+        mounts::mount_all(&iso_ctl.mounts)
+            .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+        let cell_mounts = OnError::new(|| {
+            mounts::unmount_all(&mut HostMounts, &iso_ctl.mounts)
+        });
+
+        devices::map_all(&iso_ctl.devices)
+            .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+
         // We are in a new UTS namespace so we manage hostname and domainname.
-        // hostname and domainname both allow null bytes and are not required to be null terminated.
-        if unsafe {
-            #[allow(trivial_casts)]
-            libc::sethostname(
-                self.name.as_ptr() as *const c_char,
-                self.name.len(),
-            )
-        } == -1
-        {
-            return Err(io::Error::last_os_error());
-        }
+        retry_on_eintr(|| {
+            Errno::result(unsafe {
+                libc::sethostname(uts_name.as_ptr(), uts_name.as_bytes().len())
+            })
+        })
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
 
         // Set domainname
-        if unsafe {
-            #[allow(trivial_casts)]
-            libc::setdomainname(
-                self.name.as_ptr() as *const c_char,
-                self.name.len(),
-            )
-        } == -1
-        {
-            return Err(io::Error::last_os_error());
-        }
+        retry_on_eintr(|| {
+            Errno::result(unsafe {
+                libc::setdomainname(
+                    uts_name.as_ptr(),
+                    uts_name.as_bytes().len(),
+                )
+            })
+        })
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+
+        cell_mounts.succeeded();
+        proc_mount.succeeded();
         Ok(())
     }
 
+    /// Runs in the child, before exec, after isolate_process, so the resolv.conf of the
+    /// cell is set up in its mount namespace.
+    /// The child is cloned into a new network namespace (see [NestedAuraed::new]), whose
+    /// only interface is a loopback that is down until we bring it up. With a bridge, the
+    /// veth pair is added by auraed once the child has started.
+    ///
+    /// [NestedAuraed::new]: super::NestedAuraed::new
     pub fn isolate_network(
         &mut self,
         iso_ctl: &IsolationControls,
     ) -> io::Result<()> {
-        if !iso_ctl.isolate_network {
+        if !iso_ctl.network.is_isolated() {
             return Ok(());
         }
-        // Insert pre_exec network logic here
+
+        set_loopback_up()?;
+
+        if let Some(dns) = &iso_ctl.dns {
+            match &self.resolv_conf {
+                Some(resolv_conf) => dns::bind_resolv_conf(resolv_conf)
+                    .map_err(|e| io::Error::from_raw_os_error(e as i32))?,
+                None => dns::replace_resolv_conf(dns)?,
+            }
+        }
         Ok(())
     }
+
+    /// Runs in the child, before exec, after all other isolation steps.
+    pub fn apply_seccomp(
+        &mut self,
+        iso_ctl: &IsolationControls,
+    ) -> io::Result<()> {
+        iso_ctl.seccomp.apply()
+    }
+}
+
+/// Returns `name` as the NUL-terminated hostname and domainname of a cell.
+/// Fails if `name` contains a NUL byte or is longer than [HOST_NAME_MAX].
+fn uts_name(name: &str) -> io::Result<CString> {
+    if name.len() > HOST_NAME_MAX {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "cell name {name:?} is longer than the {HOST_NAME_MAX} bytes allowed for a hostname"
+            ),
+        ));
+    }
+
+    CString::new(name)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Brings up the loopback interface of the network namespace of the calling thread.
+/// Once it is up, the kernel assigns it 127.0.0.1 and ::1.
+/// Uses an ioctl rather than netlink, as it runs in the child before exec.
+pub(crate) fn set_loopback_up() -> io::Result<()> {
+    let fd = Errno::result(unsafe {
+        libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0)
+    })
+    .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+    // Closes the socket when returning
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut ifreq: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifreq.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as c_char;
+    }
+
+    Errno::result(unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS, &mut ifreq)
+    })
+    .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+
+    unsafe { ifreq.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+
+    Errno::result(unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS, &ifreq)
+    })
+    .map(|_| ())
+    .map_err(|e| io::Error::from_raw_os_error(e as i32))
+}
+
+/// Sets the oom_score_adj of the calling process, which its children inherit.
+/// Lowering it below the lowest value set so far requires CAP_SYS_RESOURCE.
+pub(crate) fn write_oom_score_adj(oom_score_adj: i16) -> io::Result<()> {
+    std::fs::write("/proc/self/oom_score_adj", oom_score_adj.to_string())
+}
+
+/// Sets the soft and hard RLIMIT_MEMLOCK of the calling process, which its children inherit.
+/// Raising the hard limit requires CAP_SYS_RESOURCE.
+pub(crate) fn write_memlock_limit(memlock_limit: u64) -> io::Result<()> {
+    let limit =
+        libc::rlimit { rlim_cur: memlock_limit, rlim_max: memlock_limit };
+    Errno::result(unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit) })
+        .map(|_| ())
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))
+}
+
+/// Calls `f` until it does not fail with [Errno::EINTR].
+/// Use for syscalls that can be interrupted by a signal before completing.
+pub(crate) fn retry_on_eintr<T, F>(mut f: F) -> nix::Result<T>
+where
+    F: FnMut() -> nix::Result<T>,
+{
+    loop {
+        match f() {
+            Err(Errno::EINTR) => continue,
+            res => return res,
+        }
+    }
+}
+
+/// Runs the undo step of a partially completed setup when dropped,
+/// unless [OnError::succeeded] was called.
+/// Returning early with `?` drops the guard, so the undo step can't be forgotten.
+pub(crate) struct OnError<F: FnOnce()> {
+    undo: Option<F>,
+}
+
+impl<F: FnOnce()> OnError<F> {
+    pub fn new(undo: F) -> Self {
+        Self { undo: Some(undo) }
+    }
+
+    /// Marks the setup as successful, so the undo step doesn't run.
+    pub fn succeeded(mut self) {
+        let _ = self.undo.take();
+    }
+}
+
+impl<F: FnOnce()> Drop for OnError<F> {
+    fn drop(&mut self) {
+        if let Some(undo) = self.undo.take() {
+            undo();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_retry_on_eintr() {
+        let calls = Cell::new(0);
+
+        let res = retry_on_eintr(|| {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err(Errno::EINTR)
+            } else {
+                Ok(calls.get())
+            }
+        });
+
+        assert_eq!(res, Ok(3));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_on_eintr_returns_other_errors() {
+        let calls = Cell::new(0);
+
+        let res: nix::Result<()> = retry_on_eintr(|| {
+            calls.set(calls.get() + 1);
+            Err(Errno::EPERM)
+        });
+
+        assert_eq!(res, Err(Errno::EPERM));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_on_error_undoes_on_early_return() {
+        let undone = Cell::new(false);
+
+        let setup = |fail: bool| -> io::Result<()> {
+            let guard = OnError::new(|| undone.set(true));
+            if fail {
+                return Err(io::Error::from_raw_os_error(libc::EPERM));
+            }
+            guard.succeeded();
+            Ok(())
+        };
+
+        assert!(setup(false).is_ok());
+        assert!(!undone.get());
+
+        assert!(setup(true).is_err());
+        assert!(undone.get());
+    }
+
+    #[test]
+    fn test_uts_name() {
+        let name = uts_name("ae-1").expect("valid name");
+        assert_eq!(name.as_bytes_with_nul(), b"ae-1\0");
+        assert_eq!(name.as_bytes().len(), 4);
+
+        let longest = "a".repeat(HOST_NAME_MAX);
+        assert!(uts_name(&longest).is_ok());
+
+        let too_long = "a".repeat(HOST_NAME_MAX + 1);
+        let err = uts_name(&too_long).expect_err("name is too long");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = uts_name("ae\01").expect_err("name contains a NUL byte");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_seccomp_is_the_last_pre_exec_hook() {
+        let hooks = Isolation::new("ae-1")
+            .into_pre_exec_hooks(IsolationControls::default());
+
+        assert_eq!(
+            format!("{hooks:?}"),
+            r#"["enter_user_namespace", "set_oom_score_adj", "set_memlock_limit", "isolate_process", "isolate_network", "apply_seccomp"]"#
+        );
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_isolated_network_only_has_loopback() {
+        // Network namespaces are per thread, so the test doesn't leave its own
+        std::thread::spawn(|| {
+            nix::sched::unshare(nix::sched::CloneFlags::CLONE_NEWNET)
+                .expect("unshare");
+            set_loopback_up().expect("set loopback up");
+
+            let dev = std::fs::read_to_string("/proc/thread-self/net/dev")
+                .expect("read net/dev");
+            // The first two lines are headers
+            let interfaces: Vec<_> = dev
+                .lines()
+                .skip(2)
+                .filter_map(|line| line.split(':').next())
+                .map(str::trim)
+                .collect();
+            assert_eq!(interfaces, ["lo"]);
+
+            // Binding to 127.0.0.1 fails unless the loopback is up
+            let listener =
+                std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+            let addr = listener.local_addr().expect("local addr");
+            let _ = std::net::TcpStream::connect(addr).expect("connect");
+        })
+        .join()
+        .expect("thread panicked");
+    }
+
+    #[test]
+    fn test_oom_score_adj_is_inherited() {
+        let mut command = std::process::Command::new("cat");
+        let _ = command.arg("/proc/self/oom_score_adj");
+        // Raising the adjustment doesn't require privileges
+        let output = unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+                write_oom_score_adj(500)
+            })
+        }
+        .output()
+        .expect("run cat");
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "500\n");
+    }
+
+    #[test]
+    fn test_memlock_limit_is_inherited() {
+        let mut command = std::process::Command::new("sh");
+        let _ = command.args(["-c", "ulimit -l"]);
+        // Lowering the limit doesn't require privileges
+        let output = unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+                write_memlock_limit(64 * 1024)
+            })
+        }
+        .output()
+        .expect("run sh");
+
+        assert!(output.status.success());
+        // ulimit reports the limit in KiB
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "64\n");
+    }
 }
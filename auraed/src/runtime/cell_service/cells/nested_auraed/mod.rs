@@ -28,9 +28,27 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-pub use isolation_controls::IsolationControls;
+pub use bandwidth::{BandwidthLimit, BandwidthLimits};
+pub use credentials::ClientCredentials;
+pub use devices::DeviceMapping;
+pub use dns::DnsConfig;
+pub use isolation_controls::{IsolationControls, NetworkMode};
+pub use mounts::Mount;
 pub use nested_auraed::NestedAuraed;
+pub use seccomp::{Architecture, DenyAction, SeccompControls};
+pub use user_namespace::{IdMapping, MappedRoot, MAX_ID_MAPPINGS};
+pub use veth::BridgeConfig;
 
+mod bandwidth;
+mod credentials;
+mod devices;
+mod dns;
+mod early_stderr;
+mod ephemeral_root;
 mod isolation_controls;
+mod mounts;
 #[allow(clippy::module_inception)]
 mod nested_auraed;
+mod seccomp;
+mod user_namespace;
+mod veth;
@@ -0,0 +1,497 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Mounts in the format of the OCI runtime-spec, applied in the mount namespace of a cell.
+//!
+//! Only a subset of mount types and options is supported. Mounts are applied in order,
+//! so a mount can be placed on top of an earlier one. Destinations must already exist.
+//! They are unmounted in reverse order, so a mount is never unmounted before the
+//! mounts placed on top of it.
+//!
+//! Docs: https://github.com/opencontainers/runtime-spec/blob/main/config.md#mounts
+
+use super::ephemeral_root::{HostMounts, MountOps};
+use nix::{
+    errno::Errno,
+    mount::{MntFlags, MsFlags},
+};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// A supported mount type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountType {
+    Bind,
+    Tmpfs,
+    Proc,
+    Sysfs,
+    Mqueue,
+}
+
+impl MountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bind => "bind",
+            Self::Tmpfs => "tmpfs",
+            Self::Proc => "proc",
+            Self::Sysfs => "sysfs",
+            Self::Mqueue => "mqueue",
+        }
+    }
+
+    /// Filesystem options that are passed on to the filesystem (as mount data).
+    fn data_options(&self) -> &'static [&'static str] {
+        match self {
+            Self::Tmpfs => &["size", "mode", "nr_inodes", "uid", "gid"],
+            Self::Bind | Self::Proc | Self::Sysfs | Self::Mqueue => &[],
+        }
+    }
+}
+
+impl FromStr for MountType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bind" => Ok(Self::Bind),
+            "tmpfs" => Ok(Self::Tmpfs),
+            "proc" => Ok(Self::Proc),
+            "sysfs" => Ok(Self::Sysfs),
+            "mqueue" => Ok(Self::Mqueue),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MountError {
+    #[error("destination '{0}' is not an absolute path")]
+    RelativeDestination(String),
+    #[error("mount type '{0}' is not supported")]
+    UnsupportedType(String),
+    #[error("mount option '{option}' is not supported for type '{kind}'")]
+    UnsupportedOption { kind: MountType, option: String },
+    #[error("bind mounts require a source")]
+    MissingSource,
+}
+
+impl MountError {
+    /// The field of the mount the error is about.
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::RelativeDestination(_) => "destination",
+            Self::UnsupportedType(_) => "type",
+            Self::UnsupportedOption { .. } => "options",
+            Self::MissingSource => "source",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub destination: PathBuf,
+    pub kind: MountType,
+    pub source: Option<PathBuf>,
+    pub flags: MsFlags,
+    /// Bind mounts the submounts of the source as well ("rbind").
+    pub recursive: bool,
+    /// Comma-separated filesystem options (e.g., "size=64m" for tmpfs).
+    pub data: Option<String>,
+}
+
+impl Mount {
+    /// Parses a mount of the OCI runtime-spec. As in the spec, an empty `kind` with a
+    /// "bind" or "rbind" option is a bind mount.
+    pub fn new(
+        destination: &str,
+        kind: &str,
+        source: &str,
+        options: &[String],
+    ) -> Result<Self, MountError> {
+        let destination = PathBuf::from(destination);
+        if !destination.is_absolute() {
+            return Err(MountError::RelativeDestination(
+                destination.display().to_string(),
+            ));
+        }
+
+        let is_bind = |o: &String| o == "bind" || o == "rbind";
+        let kind = match kind {
+            "" | "none" if options.iter().any(is_bind) => MountType::Bind,
+            kind => kind
+                .parse()
+                .map_err(|_| MountError::UnsupportedType(kind.into()))?,
+        };
+
+        let source = match source {
+            "" => None,
+            source => Some(PathBuf::from(source)),
+        };
+        if kind == MountType::Bind && source.is_none() {
+            return Err(MountError::MissingSource);
+        }
+
+        let mut flags = MsFlags::empty();
+        let mut recursive = false;
+        let mut data = vec![];
+
+        for option in options {
+            match option.as_str() {
+                "ro" => flags.insert(MsFlags::MS_RDONLY),
+                "rw" => flags.remove(MsFlags::MS_RDONLY),
+                "nosuid" => flags.insert(MsFlags::MS_NOSUID),
+                "suid" => flags.remove(MsFlags::MS_NOSUID),
+                "nodev" => flags.insert(MsFlags::MS_NODEV),
+                "dev" => flags.remove(MsFlags::MS_NODEV),
+                "noexec" => flags.insert(MsFlags::MS_NOEXEC),
+                "exec" => flags.remove(MsFlags::MS_NOEXEC),
+                "noatime" => flags.insert(MsFlags::MS_NOATIME),
+                "nodiratime" => flags.insert(MsFlags::MS_NODIRATIME),
+                "relatime" => flags.insert(MsFlags::MS_RELATIME),
+                "strictatime" => flags.insert(MsFlags::MS_STRICTATIME),
+                "bind" if kind == MountType::Bind => {}
+                "rbind" if kind == MountType::Bind => recursive = true,
+                option
+                    if option.split_once('=').map_or(false, |(key, _)| {
+                        kind.data_options().contains(&key)
+                    }) =>
+                {
+                    data.push(option)
+                }
+                option => {
+                    return Err(MountError::UnsupportedOption {
+                        kind,
+                        option: option.into(),
+                    })
+                }
+            }
+        }
+
+        let data = match data.is_empty() {
+            true => None,
+            false => Some(data.join(",")),
+        };
+
+        Ok(Self { destination, kind, source, flags, recursive, data })
+    }
+
+    /// Runs in the child, before exec, in the mount namespace of the cell.
+    /// Flags of a bind mount only take effect on a remount, which is done as a second step.
+    pub fn mount(&self) -> nix::Result<()> {
+        let Self { destination, kind, source, flags, recursive, data } = self;
+
+        if *kind == MountType::Bind {
+            let mut bind_flags = MsFlags::MS_BIND;
+            if *recursive {
+                bind_flags.insert(MsFlags::MS_REC);
+            }
+            HostMounts.mount(
+                source.as_deref(),
+                destination,
+                None,
+                bind_flags,
+                None,
+            )?;
+
+            if flags.is_empty() {
+                return Ok(());
+            }
+
+            let remount_flags = MsFlags::MS_BIND | MsFlags::MS_REMOUNT | *flags;
+            if let Err(e) =
+                HostMounts.mount(None, destination, None, remount_flags, None)
+            {
+                let _best_effort = self.unmount();
+                return Err(e);
+            }
+
+            return Ok(());
+        }
+
+        // The source of a pseudo filesystem is only informational, use the type like mount(8)
+        let source = source.as_deref().unwrap_or(Path::new(kind.as_str()));
+        HostMounts.mount(
+            Some(source),
+            destination,
+            Some(kind.as_str()),
+            *flags,
+            data.as_deref(),
+        )
+    }
+
+    pub fn unmount(&self) -> nix::Result<()> {
+        unmount(&mut HostMounts, &self.destination)
+    }
+}
+
+/// Unmounts `target`. If it is busy (e.g., a process still has a file open below it),
+/// it is detached instead, and released by the kernel once it is no longer in use.
+pub fn unmount(ops: &mut impl MountOps, target: &Path) -> nix::Result<()> {
+    match ops.unmount(target, MntFlags::empty()) {
+        Err(Errno::EBUSY) => ops.unmount(target, MntFlags::MNT_DETACH),
+        res => res,
+    }
+}
+
+/// Applies `mounts` in order. If a mount fails, the mounts that were already
+/// applied are unmounted (in reverse order) before returning.
+pub fn mount_all(mounts: &[Mount]) -> nix::Result<()> {
+    for (i, mount) in mounts.iter().enumerate() {
+        if let Err(e) = mount.mount() {
+            unmount_all(&mut HostMounts, &mounts[..i]);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Unmounts `mounts` in reverse order (see [unmount]), ignoring errors.
+pub fn unmount_all(ops: &mut impl MountOps, mounts: &[Mount]) {
+    for mount in mounts.iter().rev() {
+        let _best_effort = unmount(ops, &mount.destination);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_dir;
+    use simple_test_case::test_case;
+
+    fn options(options: &[&str]) -> Vec<String> {
+        options.iter().map(|option| option.to_string()).collect()
+    }
+
+    /// Records the unmounts, failing those of the `busy` targets with [Errno::EBUSY]
+    /// unless they are detached, and those of the `denied` targets with [Errno::EPERM].
+    /// Only unmounts are expected.
+    #[derive(Default)]
+    struct MockUnmount {
+        calls: Vec<String>,
+        busy: Vec<&'static str>,
+        denied: Vec<&'static str>,
+    }
+
+    impl MountOps for MockUnmount {
+        fn mount(
+            &mut self,
+            _: Option<&Path>,
+            target: &Path,
+            _: Option<&str>,
+            _: MsFlags,
+            _: Option<&str>,
+        ) -> nix::Result<()> {
+            unreachable!("mount {}", target.display())
+        }
+
+        fn create_dir(&mut self, path: &Path) -> nix::Result<()> {
+            unreachable!("mkdir {}", path.display())
+        }
+
+        fn pivot_root(&mut self, new_root: &Path, _: &Path) -> nix::Result<()> {
+            unreachable!("pivot_root {}", new_root.display())
+        }
+
+        fn chdir(&mut self, path: &Path) -> nix::Result<()> {
+            unreachable!("chdir {}", path.display())
+        }
+
+        fn unmount(
+            &mut self,
+            target: &Path,
+            flags: MntFlags,
+        ) -> nix::Result<()> {
+            let detach = flags.contains(MntFlags::MNT_DETACH);
+            let call = if detach { "detach" } else { "unmount" };
+            self.calls.push(format!("{call} {}", target.display()));
+
+            let busy = self.busy.iter().any(|busy| Path::new(busy) == target);
+            let denied =
+                self.denied.iter().any(|denied| Path::new(denied) == target);
+            if denied {
+                Err(Errno::EPERM)
+            } else if busy && !detach {
+                Err(Errno::EBUSY)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_bind_mount() {
+        let mount = Mount::new(
+            "/data",
+            "bind",
+            "/srv/data",
+            &options(&["rbind", "ro", "nosuid"]),
+        )
+        .expect("valid mount");
+
+        assert_eq!(mount.kind, MountType::Bind);
+        assert_eq!(mount.source, Some(PathBuf::from("/srv/data")));
+        assert!(mount.recursive);
+        assert_eq!(mount.flags, MsFlags::MS_RDONLY | MsFlags::MS_NOSUID);
+        assert_eq!(mount.data, None);
+    }
+
+    #[test]
+    fn test_empty_type_with_bind_option_is_bind_mount() {
+        let mount = Mount::new("/data", "", "/srv/data", &options(&["bind"]))
+            .expect("valid mount");
+
+        assert_eq!(mount.kind, MountType::Bind);
+        assert!(!mount.recursive);
+        assert!(mount.flags.is_empty());
+    }
+
+    #[test]
+    fn test_tmpfs_mount() {
+        let mount = Mount::new(
+            "/tmp",
+            "tmpfs",
+            "tmpfs",
+            &options(&["nosuid", "nodev", "size=64m", "mode=1777"]),
+        )
+        .expect("valid mount");
+
+        assert_eq!(mount.kind, MountType::Tmpfs);
+        assert_eq!(mount.flags, MsFlags::MS_NOSUID | MsFlags::MS_NODEV);
+        assert_eq!(mount.data.as_deref(), Some("size=64m,mode=1777"));
+    }
+
+    #[test]
+    fn test_later_options_win() {
+        let mount = Mount::new("/tmp", "tmpfs", "", &options(&["ro", "rw"]))
+            .expect("valid mount");
+
+        assert!(mount.flags.is_empty());
+    }
+
+    #[test_case("tmp", "tmpfs", "", &[], "destination"; "relative destination")]
+    #[test_case("/mnt", "nfs", "host:/export", &[], "type"; "unsupported type")]
+    #[test_case("/mnt", "", "/srv", &[], "type"; "empty type without bind")]
+    #[test_case("/mnt", "bind", "", &["bind"], "source"; "bind without source")]
+    #[test_case("/mnt", "tmpfs", "", &["shared"], "options"; "propagation")]
+    #[test_case("/mnt", "tmpfs", "", &["bind"], "options"; "bind option on tmpfs")]
+    #[test_case("/mnt", "proc", "", &["size=1m"], "options"; "data on proc")]
+    #[test_case("/mnt", "tmpfs", "", &["size"], "options"; "data without value")]
+    #[test]
+    fn test_invalid_mounts(
+        destination: &str,
+        kind: &str,
+        source: &str,
+        mount_options: &[&str],
+        field: &str,
+    ) {
+        let err =
+            Mount::new(destination, kind, source, &options(mount_options))
+                .expect_err("invalid mount");
+
+        assert_eq!(err.field(), field);
+    }
+
+    #[test]
+    fn test_unmount_all_in_reverse_order() {
+        let mounts = vec![
+            Mount::new("/tmp", "tmpfs", "", &[]).expect("valid mount"),
+            Mount::new("/tmp/data", "bind", "/srv/data", &options(&["bind"]))
+                .expect("valid mount"),
+            Mount::new("/tmp/data/cache", "tmpfs", "", &[])
+                .expect("valid mount"),
+        ];
+
+        let mut ops = MockUnmount::default();
+        unmount_all(&mut ops, &mounts);
+
+        assert_eq!(
+            ops.calls,
+            ["unmount /tmp/data/cache", "unmount /tmp/data", "unmount /tmp"]
+        );
+    }
+
+    #[test]
+    fn test_busy_mount_is_detached() {
+        let mounts = vec![
+            Mount::new("/tmp", "tmpfs", "", &[]).expect("valid mount"),
+            Mount::new("/var/cache", "tmpfs", "", &[]).expect("valid mount"),
+        ];
+
+        let mut ops = MockUnmount { busy: vec!["/tmp"], ..Default::default() };
+        unmount_all(&mut ops, &mounts);
+
+        assert_eq!(
+            ops.calls,
+            ["unmount /var/cache", "unmount /tmp", "detach /tmp"]
+        );
+    }
+
+    #[test]
+    fn test_unmount_returns_other_errors() {
+        let mut ops =
+            MockUnmount { denied: vec!["/tmp"], ..Default::default() };
+        assert_eq!(unmount(&mut ops, Path::new("/tmp")), Err(Errno::EPERM));
+        // only a busy mount is detached
+        assert_eq!(ops.calls, ["unmount /tmp"]);
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_mount_all_undoes_on_failure() {
+        let dir = temp_dir("mounts");
+
+        let mounts = vec![
+            Mount::new(dir.to_str().expect("utf8"), "tmpfs", "", &[])
+                .expect("valid mount"),
+            Mount::new("/aurae-missing-destination", "tmpfs", "", &[])
+                .expect("valid mount"),
+        ];
+
+        assert!(mount_all(&mounts).is_err());
+
+        let mountinfo =
+            std::fs::read_to_string("/proc/self/mountinfo").expect("mountinfo");
+        assert!(!mountinfo.contains(dir.to_str().expect("utf8")));
+
+        std::fs::remove_dir(&dir).expect("remove dir");
+    }
+}
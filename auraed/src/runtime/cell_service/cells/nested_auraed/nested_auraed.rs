@@ -28,7 +28,14 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-use super::isolation_controls::{Isolation, IsolationControls};
+use super::credentials::{self, ClientCredentials};
+use super::early_stderr::EarlyStderr;
+use super::isolation_controls::{
+    retry_on_eintr, Isolation, IsolationControls, NetworkMode,
+};
+use super::user_namespace::MappedRoot;
+use super::veth::Veth;
+use crate::{AURAE_RUNTIME_DIR, AURAE_SERVER_KEY};
 use aurae_client::AuraeConfig;
 use clone3::Flags;
 use nix::{
@@ -37,11 +44,14 @@ use nix::{
     unistd::Pid,
 };
 use std::{
+    fs::{DirBuilder, Permissions},
     io::{self, ErrorKind},
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
     os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
     process::{Command, ExitStatus},
 };
-use tracing::{error, info, trace};
+use tracing::{error, info, trace, warn};
 
 #[derive(Debug)]
 pub struct NestedAuraed {
@@ -49,22 +59,48 @@ pub struct NestedAuraed {
     #[allow(unused)]
     pidfd: i32,
     iso_ctl: IsolationControls,
+    /// Removed once the nested auraed has been reaped.
+    veth: Option<Veth>,
+    /// Removed once the nested auraed has been reaped (see [mapped_runtime_dir]).
+    runtime_dir: Option<PathBuf>,
+    stderr: EarlyStderr,
     pub client_config: AuraeConfig,
 }
 
 impl NestedAuraed {
-    pub fn new(name: &str, iso_ctl: IsolationControls) -> io::Result<Self> {
+    pub fn new(
+        name: &str,
+        iso_ctl: IsolationControls,
+        credentials: Option<&ClientCredentials>,
+    ) -> io::Result<Self> {
         // Here we launch a nested auraed with the --nested flag
         // which is used our way of "hooking" into the newly created
         // aurae isolation zone.
 
         let random = uuid::Uuid::new_v4();
 
+        let runtime_dir = match iso_ctl.mapped_root() {
+            Some(root) => Some(mapped_runtime_dir(&random, root)?),
+            None => None,
+        };
+        let remove_runtime_dir = || {
+            if let Some(runtime_dir) = &runtime_dir {
+                let _best_effort = std::fs::remove_dir_all(runtime_dir);
+            }
+        };
+
+        let socket = match &runtime_dir {
+            Some(runtime_dir) => runtime_dir.join("aurae.sock"),
+            None => Path::new(AURAE_RUNTIME_DIR)
+                .join(format!("aurae-{random}.sock")),
+        };
+
         // TODO: handle expect
-        let mut client_config =
-            AuraeConfig::try_default().expect("file based config");
-        client_config.system.socket =
-            format!("/var/run/aurae/aurae-{}.sock", random);
+        let client_config = credentials::client_config(
+            AuraeConfig::try_default().expect("file based config"),
+            socket.display().to_string(),
+            credentials,
+        );
 
         let mut command = Command::new("auraed");
         let _ = command.current_dir("/").args([
@@ -79,6 +115,47 @@ impl NestedAuraed {
         // to command.args, whose return value we ignored above.
         assert_eq!(command.get_args().len(), 3);
 
+        // The nested auraed trusts the calls we forward as admin calls, as we authorized
+        // them, but no other client of the cell
+        match credentials::client_common_name(&client_config) {
+            Ok(common_name) => {
+                let _ = command.arg("--parent-client").arg(common_name);
+            }
+            Err(e) => warn!(
+                cell_name = name,
+                "admin calls will not be forwarded to the nested auraed, as the common name of the client certificate is unknown: {e}"
+            ),
+        }
+
+        // Only clients signed by the CA of the cell are accepted
+        if let Some(credentials) = credentials {
+            let _ = command.arg("--ca-crt").arg(&credentials.ca_crt);
+        }
+
+        if let Some(runtime_dir) = &runtime_dir {
+            let _ = command
+                .arg("--runtime-dir")
+                .arg(runtime_dir)
+                .arg("--server-key")
+                .arg(runtime_dir.join("server.key"));
+        }
+
+        // The nested auraed can't tell a client why it failed to come up, so we keep what
+        // it printed on the way down (see [NestedAuraed::early_stderr])
+        let (stderr, stderr_stdio) =
+            EarlyStderr::capture(name).map_err(|e| {
+                remove_runtime_dir();
+                e
+            })?;
+        let _ = command.stderr(stderr_stdio);
+
+        info!(
+            cell_name = name,
+            socket = %client_config.system.socket,
+            ?command,
+            "spawning nested auraed"
+        );
+
         // *****************************************************************
         // ██████╗██╗      ██████╗ ███╗   ██╗███████╗██████╗
         // ██╔════╝██║     ██╔═══██╗████╗  ██║██╔════╝╚════██╗
@@ -104,8 +181,12 @@ impl NestedAuraed {
         // We check that the clone we kept has set the first flag we set above.
         assert_eq!(clone.as_clone_args().flags, Flags::PIDFD.bits());
 
-        // Freeze the parent until the child calls execvp
-        let _ = clone.flag_vfork();
+        // Freeze the parent until the child calls execvp.
+        // The child of a cell with a user namespace waits for us to write its id maps
+        // first, so we wait for its exec ourselves (see UserNamespace::map_ids).
+        if !iso_ctl.has_user_namespace() {
+            let _ = clone.flag_vfork();
+        }
 
         // Manage SIGCHLD for the nested process
         // Define SIGCHLD for signal handler
@@ -114,13 +195,25 @@ impl NestedAuraed {
         // [ Namespaces and Isolation ]
 
         let mut isolation = Isolation::new(name);
-        isolation.setup(&iso_ctl)?;
+        isolation.setup(&iso_ctl).map_err(|e| {
+            remove_runtime_dir();
+            e
+        })?;
+        let resolv_conf = isolation.resolv_conf().map(|p| p.to_path_buf());
+        let user_namespace = isolation.user_namespace();
+        let mut pre_exec_hooks = isolation.into_pre_exec_hooks(iso_ctl.clone());
 
         // Always unshare the Cgroup namespace
         let _ = clone.flag_newcgroup();
 
+        // Isolate Users
+        // The other namespaces are created along with it, so they are owned by it
+        if user_namespace.is_some() {
+            let _ = clone.flag_newuser();
+        }
+
         // Isolate Network
-        if iso_ctl.isolate_network {
+        if iso_ctl.network.is_isolated() {
             let _ = clone.flag_newnet();
         }
 
@@ -133,32 +226,96 @@ impl NestedAuraed {
         }
 
         // Execute the clone system call and create the new process with the relevant namespaces.
-        match unsafe { clone.call() }
-            .map_err(|e| io::Error::from_raw_os_error(e.0))?
-        {
+        let res = unsafe { clone.call() };
+
+        let remove_resolv_conf = || {
+            if let Some(resolv_conf) = &resolv_conf {
+                let _best_effort = std::fs::remove_file(resolv_conf);
+            }
+        };
+
+        if res.is_err() {
+            remove_resolv_conf();
+            remove_runtime_dir();
+            if let Some(user_namespace) = user_namespace {
+                user_namespace.close();
+            }
+        }
+
+        match res.map_err(|e| io::Error::from_raw_os_error(e.0))? {
             0 => {
                 // child
                 let command = {
-                    unsafe {
-                        command.pre_exec(move || {
-                            isolation.isolate_process(&iso_ctl)?;
-                            isolation.isolate_network(&iso_ctl)?;
-                            Ok(())
-                        })
-                    }
+                    unsafe { command.pre_exec(move || pre_exec_hooks.run()) }
                 };
 
                 let e = command.exec();
                 error!("Unexpected exit from child command: {e:#?}");
-                Err(e)
+
+                // Exit instead of returning, so the child doesn't continue as a copy of
+                // the parent. The namespaces and mounts of the child are torn down with it.
+                unsafe { libc::_exit(1) }
             }
             pid => {
                 // parent
-                info!("Nested auraed running with host pid {}", pid.clone());
-                let process = procfs::process::Process::new(pid)
-                    .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+                info!(
+                    cell_name = name,
+                    "Nested auraed running with host pid {}",
+                    pid.clone()
+                );
+
+                // Close our copy of the write end of the stderr pipe, so it is closed
+                // once the nested auraed exits
+                drop(command);
+
+                // We can't manage the child, so don't leave it running
+                let kill_child = |e: io::Error| {
+                    let pid = Pid::from_raw(pid);
+                    let _best_effort = nix::sys::signal::kill(pid, SIGKILL);
+                    let _best_effort =
+                        retry_on_eintr(|| nix::sys::wait::waitpid(pid, None));
+                    remove_runtime_dir();
+                    e
+                };
+
+                // The parent is frozen until the child calls execvp, by which time the
+                // child has bind mounted the resolv.conf, or failed to. With a user
+                // namespace, map_ids returns once the child called execvp instead.
+                let mapped = match user_namespace {
+                    Some(user_namespace) => user_namespace.map_ids(
+                        Pid::from_raw(pid),
+                        &iso_ctl.uid_map,
+                        &iso_ctl.gid_map,
+                    ),
+                    None => Ok(()),
+                };
+                remove_resolv_conf();
+                mapped.map_err(kill_child)?;
+
+                let process =
+                    procfs::process::Process::new(pid).map_err(|e| {
+                        kill_child(io::Error::new(ErrorKind::Other, e))
+                    })?;
+
+                // The child has exec'd, so its network namespace is set up, except for
+                // the veth pair, which is added from the host.
+                let veth = match &iso_ctl.network {
+                    NetworkMode::Bridged(config) => Some(
+                        Veth::create(Pid::from_raw(pid), config)
+                            .map_err(kill_child)?,
+                    ),
+                    NetworkMode::None | NetworkMode::Isolated => None,
+                };
 
-                Ok(Self { process, pidfd, iso_ctl, client_config })
+                Ok(Self {
+                    process,
+                    pidfd,
+                    iso_ctl,
+                    veth,
+                    runtime_dir,
+                    stderr,
+                    client_config,
+                })
             }
         }
     }
@@ -212,10 +369,58 @@ impl NestedAuraed {
 
         trace!("Pid {pid} exited with status {exit_status}");
 
+        if let Some(veth) = self.veth.take() {
+            if let Err(e) = veth.remove() {
+                warn!("failed to remove the veth of pid {pid}: {e}");
+            }
+        }
+
+        if let Some(runtime_dir) = self.runtime_dir.take() {
+            if let Err(e) = std::fs::remove_dir_all(&runtime_dir) {
+                warn!(
+                    "failed to remove the runtime directory of pid {pid}: {e}"
+                );
+            }
+        }
+
         Ok(exit_status)
     }
 
     pub fn pid(&self) -> Pid {
         Pid::from_raw(self.process.pid)
     }
+
+    /// Returns the first lines the nested auraed wrote to its stderr, which say why it
+    /// failed to come up, if it did.
+    pub fn early_stderr(&self) -> Vec<String> {
+        self.stderr.lines()
+    }
+}
+
+/// Creates a runtime directory for the nested auraed of a cell with a user namespace,
+/// owned by root of the cell, with a copy of the server key. Root of the cell can
+/// neither bind its socket in the runtime directory of the host, nor read the key of
+/// the host, and we don't hand those over to it.
+fn mapped_runtime_dir(
+    random: &uuid::Uuid,
+    root: MappedRoot,
+) -> io::Result<PathBuf> {
+    let runtime_dir =
+        Path::new(AURAE_RUNTIME_DIR).join(format!("aurae-{random}"));
+    DirBuilder::new().recursive(true).mode(0o700).create(&runtime_dir)?;
+
+    let res = (|| {
+        let server_key = runtime_dir.join("server.key");
+        let _ = std::fs::copy(AURAE_SERVER_KEY, &server_key)?;
+        std::fs::set_permissions(&server_key, Permissions::from_mode(0o600))?;
+        root.chown(&server_key)?;
+        root.chown(&runtime_dir)
+    })();
+
+    if let Err(e) = res {
+        let _best_effort = std::fs::remove_dir_all(&runtime_dir);
+        return Err(e);
+    }
+
+    Ok(runtime_dir)
 }
@@ -0,0 +1,274 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Seccomp filtering of the syscall architectures (ABIs) processes in a cell can use.
+//!
+//! Docs: https://docs.kernel.org/userspace-api/seccomp_filter.html
+
+use crate::runtime::cell_service::seccomp_bpf::{
+    jump, load, ret, AUDIT_ARCH_AARCH64, AUDIT_ARCH_ARM, AUDIT_ARCH_I386,
+    AUDIT_ARCH_X86_64, SECCOMP_DATA_ARCH_OFFSET, SECCOMP_DATA_NR_OFFSET,
+    X32_SYSCALL_BIT,
+};
+use std::{fmt, io, str::FromStr};
+
+/// A syscall ABI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    X86_64,
+    X86,
+    X32,
+    Aarch64,
+    Arm,
+}
+
+#[cfg(target_arch = "x86_64")]
+const NATIVE: Architecture = Architecture::X86_64;
+#[cfg(target_arch = "x86")]
+const NATIVE: Architecture = Architecture::X86;
+#[cfg(target_arch = "aarch64")]
+const NATIVE: Architecture = Architecture::Aarch64;
+#[cfg(target_arch = "arm")]
+const NATIVE: Architecture = Architecture::Arm;
+
+impl Architecture {
+    /// The architecture auraed was built for.
+    pub fn native() -> Self {
+        NATIVE
+    }
+
+    fn audit_arch(&self) -> u32 {
+        match self {
+            Self::X86_64 | Self::X32 => AUDIT_ARCH_X86_64,
+            Self::X86 => AUDIT_ARCH_I386,
+            Self::Aarch64 => AUDIT_ARCH_AARCH64,
+            Self::Arm => AUDIT_ARCH_ARM,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownArchitecture(String);
+
+impl fmt::Display for UnknownArchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown architecture '{}'", self.0)
+    }
+}
+
+impl FromStr for Architecture {
+    type Err = UnknownArchitecture;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "native" => Self::native(),
+            "x86_64" => Self::X86_64,
+            "x86" => Self::X86,
+            "x32" => Self::X32,
+            "aarch64" => Self::Aarch64,
+            "arm" => Self::Arm,
+            _ => return Err(UnknownArchitecture(s.into())),
+        })
+    }
+}
+
+/// What happens to a syscall made with an architecture that is not allowed.
+/// The values match the `SeccompDenyAction` enum of the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DenyAction {
+    /// The syscall fails with ENOSYS, as if it doesn't exist.
+    #[default]
+    Enosys,
+    /// The process is killed.
+    Kill,
+}
+
+impl TryFrom<i32> for DenyAction {
+    type Error = ();
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Enosys),
+            1 => Ok(Self::Kill),
+            _ => Err(()),
+        }
+    }
+}
+
+impl DenyAction {
+    fn seccomp_ret(&self) -> u32 {
+        match self {
+            Self::Enosys => libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+            Self::Kill => libc::SECCOMP_RET_KILL_PROCESS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeccompControls {
+    pub allowed_architectures: Vec<Architecture>,
+    pub deny_action: DenyAction,
+}
+
+impl Default for SeccompControls {
+    fn default() -> Self {
+        Self {
+            allowed_architectures: vec![Architecture::native()],
+            deny_action: DenyAction::default(),
+        }
+    }
+}
+
+impl SeccompControls {
+    /// Installs the filter on the calling thread. The filter is inherited by all
+    /// children and can't be removed.
+    ///
+    /// Requires CAP_SYS_ADMIN (or no_new_privs to be set).
+    pub fn apply(&self) -> io::Result<()> {
+        let mut filter = self.filter();
+        let prog = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+
+        if unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                std::ptr::addr_of!(prog),
+            )
+        } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Builds a BPF program which allows syscalls made with an allowed architecture,
+    /// and returns the [DenyAction] for all others.
+    fn filter(&self) -> Vec<libc::sock_filter> {
+        let allowed = |arch| self.allowed_architectures.contains(&arch);
+        let allow = libc::SECCOMP_RET_ALLOW;
+        let deny = self.deny_action.seccomp_ret();
+
+        let mut audit_archs: Vec<u32> = self
+            .allowed_architectures
+            .iter()
+            .map(|arch| arch.audit_arch())
+            .collect();
+        audit_archs.sort_unstable();
+        audit_archs.dedup();
+
+        let mut filter = vec![];
+        for audit_arch in audit_archs {
+            filter.push(load(SECCOMP_DATA_ARCH_OFFSET));
+
+            // x86_64 and x32 share an audit arch, so the syscall number tells them apart
+            let x86_64 = allowed(Architecture::X86_64);
+            let x32 = allowed(Architecture::X32);
+            if audit_arch != AUDIT_ARCH_X86_64 || (x86_64 && x32) {
+                filter.push(jump(libc::BPF_JEQ, audit_arch, 0, 1));
+                filter.push(ret(allow));
+            } else {
+                let (is_x32, not_x32) =
+                    if x32 { (allow, deny) } else { (deny, allow) };
+                filter.push(jump(libc::BPF_JEQ, audit_arch, 0, 4));
+                filter.push(load(SECCOMP_DATA_NR_OFFSET));
+                filter.push(jump(libc::BPF_JGE, X32_SYSCALL_BIT, 0, 1));
+                filter.push(ret(is_x32));
+                filter.push(ret(not_x32));
+            }
+        }
+        filter.push(ret(deny));
+
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::seccomp_bpf::run;
+    use simple_test_case::test_case;
+
+    fn controls(
+        archs: &[Architecture],
+        deny_action: DenyAction,
+    ) -> SeccompControls {
+        SeccompControls { allowed_architectures: archs.to_vec(), deny_action }
+    }
+
+    const ENOSYS: u32 = libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32;
+    const X32_NR: u32 = X32_SYSCALL_BIT | 1;
+
+    #[test_case(&[Architecture::X86_64], AUDIT_ARCH_X86_64, 1, libc::SECCOMP_RET_ALLOW; "x86_64 allowed")]
+    #[test_case(&[Architecture::X86_64], AUDIT_ARCH_X86_64, X32_NR, ENOSYS; "x32 denied")]
+    #[test_case(&[Architecture::X86_64], AUDIT_ARCH_I386, 1, ENOSYS; "x86 denied")]
+    #[test_case(&[Architecture::X32], AUDIT_ARCH_X86_64, X32_NR, libc::SECCOMP_RET_ALLOW; "only x32 allowed")]
+    #[test_case(&[Architecture::X32], AUDIT_ARCH_X86_64, 1, ENOSYS; "x86_64 denied when only x32 allowed")]
+    #[test_case(&[Architecture::X86_64, Architecture::X32], AUDIT_ARCH_X86_64, X32_NR, libc::SECCOMP_RET_ALLOW; "x86_64 and x32 allowed")]
+    #[test_case(&[Architecture::X86_64, Architecture::X86], AUDIT_ARCH_I386, 1, libc::SECCOMP_RET_ALLOW; "x86 compat allowed")]
+    #[test_case(&[Architecture::Aarch64], AUDIT_ARCH_ARM, 1, ENOSYS; "arm denied")]
+    #[test_case(&[Architecture::Aarch64], AUDIT_ARCH_AARCH64, 1, libc::SECCOMP_RET_ALLOW; "aarch64 allowed")]
+    #[test]
+    fn test_filter(archs: &[Architecture], arch: u32, nr: u32, expected: u32) {
+        let filter = controls(archs, DenyAction::Enosys).filter();
+        assert_eq!(run(&filter, arch, nr), expected);
+    }
+
+    #[test]
+    fn test_filter_kill() {
+        let filter =
+            controls(&[Architecture::X86_64], DenyAction::Kill).filter();
+        assert_eq!(
+            run(&filter, AUDIT_ARCH_I386, 1),
+            libc::SECCOMP_RET_KILL_PROCESS
+        );
+    }
+
+    #[test]
+    fn test_default_is_native_only() {
+        let controls = SeccompControls::default();
+        assert_eq!(
+            controls.allowed_architectures,
+            vec![Architecture::native()]
+        );
+        assert_eq!(controls.deny_action, DenyAction::Enosys);
+    }
+
+    #[test]
+    fn test_parse_architecture() {
+        assert_eq!("native".parse(), Ok(Architecture::native()));
+        assert_eq!("x32".parse(), Ok(Architecture::X32));
+        assert!("mips".parse::<Architecture>().is_err());
+        assert!("X86_64".parse::<Architecture>().is_err());
+    }
+}
@@ -0,0 +1,309 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::isolation_controls::retry_on_eintr;
+use nix::errno::Errno;
+use nix::unistd::{Gid, Pid, Uid};
+use std::io;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+/// The most lines the kernel accepts in a uid_map or gid_map (since Linux 4.15).
+pub const MAX_ID_MAPPINGS: usize = 340;
+
+/// A range of ids of the cell mapped to a range of ids of the host, in the format of
+/// the OCI runtime-spec.
+/// Docs: https://github.com/opencontainers/runtime-spec/blob/main/config-linux.md#user-namespace-mappings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdMapping {
+    /// First id of the range in the cell.
+    pub container_id: u32,
+    /// First id of the range on the host.
+    pub host_id: u32,
+    /// Number of ids in the range.
+    pub size: u32,
+}
+
+impl IdMapping {
+    /// Whether `id` of the cell is in the range.
+    pub fn contains(&self, id: u32) -> bool {
+        id >= self.container_id
+            && (id as u64) < self.container_id as u64 + self.size as u64
+    }
+
+    /// Whether the ranges of the cell, or of the host, of both mappings overlap.
+    pub fn overlaps(&self, other: &IdMapping) -> bool {
+        let overlap = |a: u32, b: u32| {
+            (a as u64) < b as u64 + other.size as u64
+                && (b as u64) < a as u64 + self.size as u64
+        };
+        overlap(self.container_id, other.container_id)
+            || overlap(self.host_id, other.host_id)
+    }
+}
+
+/// The ids of the host root of a cell is mapped to. Whatever root of the cell has to
+/// write to, or read from, on the host (e.g., its cgroup) has to be owned by them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedRoot {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl MappedRoot {
+    /// Returns None if root of the cell isn't mapped by both maps.
+    pub fn new(uid_map: &[IdMapping], gid_map: &[IdMapping]) -> Option<Self> {
+        let host_root = |map: &[IdMapping]| {
+            map.iter().find(|x| x.contains(0)).map(|x| x.host_id)
+        };
+        Some(Self { uid: host_root(uid_map)?, gid: host_root(gid_map)? })
+    }
+
+    /// Hands `path` over to root of the cell.
+    pub fn chown(&self, path: &Path) -> io::Result<()> {
+        nix::unistd::chown(
+            path,
+            Some(Uid::from_raw(self.uid)),
+            Some(Gid::from_raw(self.gid)),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))
+    }
+}
+
+/// Lets auraed write the id maps of a child cloned into a new user namespace before
+/// the child goes on. Only a process with CAP_SETUID (CAP_SETGID) in the parent user
+/// namespace can map more than its own id, so the child can't write its maps itself.
+///
+/// As the parent writes the maps while the child waits, the child can't be cloned with
+/// CLONE_VFORK. Instead, [UserNamespace::map_ids] returns once the child has exec'd.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UserNamespace {
+    /// Closed by the parent once the maps are written.
+    ids_mapped: [RawFd; 2],
+    /// Closed in the child by its exec (or exit).
+    exec: [RawFd; 2],
+}
+
+impl UserNamespace {
+    /// Runs in the parent, before the clone.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self { ids_mapped: pipe()?, exec: pipe()? })
+    }
+
+    /// Runs in the parent, after the clone. Writes the maps of the child, lets it go
+    /// on, and waits for it to exec.
+    pub fn map_ids(
+        self,
+        pid: Pid,
+        uid_map: &[IdMapping],
+        gid_map: &[IdMapping],
+    ) -> io::Result<()> {
+        let [ids_mapped_read, ids_mapped_write] = self.ids_mapped;
+        let [exec_read, exec_write] = self.exec;
+        close(ids_mapped_read);
+        close(exec_write);
+
+        let res = write_id_map(pid, "uid_map", uid_map)
+            .and_then(|_| write_id_map(pid, "gid_map", gid_map));
+
+        // Lets the child go on even if the maps weren't written, in which case it
+        // fails to switch to root of its user namespace and exits.
+        close(ids_mapped_write);
+
+        // The read returns once the write end is closed in the child.
+        let _ = read_until_closed(exec_read);
+        close(exec_read);
+
+        res
+    }
+
+    /// Runs in the child, before exec, before any other isolation step. Waits for the
+    /// parent to write the maps, and switches to root of the user namespace.
+    pub fn wait_for_id_maps(self) -> io::Result<()> {
+        let [ids_mapped_read, ids_mapped_write] = self.ids_mapped;
+        close(ids_mapped_write);
+        // The write end of `exec` is close-on-exec
+        close(self.exec[0]);
+
+        read_until_closed(ids_mapped_read)?;
+        close(ids_mapped_read);
+
+        Errno::result(unsafe { libc::setgroups(0, std::ptr::null()) })
+            .and_then(|_| Errno::result(unsafe { libc::setresgid(0, 0, 0) }))
+            .and_then(|_| Errno::result(unsafe { libc::setresuid(0, 0, 0) }))
+            .map(|_| ())
+            .map_err(|e| io::Error::from_raw_os_error(e as i32))
+    }
+
+    /// Runs in the parent, if the clone failed.
+    pub fn close(self) {
+        for fd in self.ids_mapped.into_iter().chain(self.exec) {
+            close(fd);
+        }
+    }
+}
+
+/// Writes the map of `pid` in a single write, as required by the kernel.
+fn write_id_map(pid: Pid, file: &str, map: &[IdMapping]) -> io::Result<()> {
+    let contents: String = map
+        .iter()
+        .map(|x| format!("{} {} {}\n", x.container_id, x.host_id, x.size))
+        .collect();
+    std::fs::write(format!("/proc/{pid}/{file}"), contents)
+}
+
+fn pipe() -> io::Result<[RawFd; 2]> {
+    let mut fds = [-1; 2];
+    Errno::result(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+    Ok(fds)
+}
+
+fn close(fd: RawFd) {
+    let _best_effort = unsafe { libc::close(fd) };
+}
+
+/// Reads from `fd` until every write end of the pipe is closed.
+fn read_until_closed(fd: RawFd) -> io::Result<()> {
+    let mut buf = [0u8; 1];
+    loop {
+        let read = retry_on_eintr(|| {
+            Errno::result(unsafe {
+                libc::read(fd, buf.as_mut_ptr().cast(), buf.len())
+            })
+        })
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+
+        if read == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    fn mapping(container_id: u32, host_id: u32, size: u32) -> IdMapping {
+        IdMapping { container_id, host_id, size }
+    }
+
+    #[test]
+    fn test_contains() {
+        let x = mapping(0, 100000, 65536);
+        assert!(x.contains(0));
+        assert!(x.contains(65535));
+        assert!(!x.contains(65536));
+
+        assert!(mapping(u32::MAX, 0, 1).contains(u32::MAX));
+    }
+
+    #[test]
+    fn test_mapped_root() {
+        let uid_map = [mapping(1, 200001, 10), mapping(0, 100000, 1)];
+        let gid_map = [mapping(0, 300000, 65536)];
+
+        assert_eq!(
+            MappedRoot::new(&uid_map, &gid_map),
+            Some(MappedRoot { uid: 100000, gid: 300000 })
+        );
+        assert_eq!(MappedRoot::new(&uid_map[..1], &gid_map), None);
+        assert_eq!(MappedRoot::new(&[], &[]), None);
+    }
+
+    #[test_case(mapping(0, 100000, 1000), mapping(1000, 101000, 1000), false; "adjacent")]
+    #[test_case(mapping(0, 100000, 1000), mapping(999, 200000, 1), true; "container ids overlap")]
+    #[test_case(mapping(0, 100000, 1000), mapping(5000, 100500, 10), true; "host ids overlap")]
+    #[test_case(mapping(10, 100010, 1), mapping(0, 100000, 1000), true; "contained")]
+    #[test]
+    fn test_overlaps(a: IdMapping, b: IdMapping, overlaps: bool) {
+        assert_eq!(a.overlaps(&b), overlaps);
+        assert_eq!(b.overlaps(&a), overlaps);
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_mapped_child_is_root_inside_and_unprivileged_on_host() {
+        use std::os::unix::process::CommandExt;
+
+        let uid_map = [mapping(0, 100000, 65536)];
+        let user_namespace = UserNamespace::new().expect("user namespace");
+
+        let mut clone = clone3::Clone3::default();
+        let _ = clone.flag_newuser();
+        let _ = clone.exit_signal(libc::SIGCHLD as u64);
+
+        match unsafe { clone.call() }.expect("clone") {
+            0 => {
+                let res = user_namespace.wait_for_id_maps();
+                if res.is_err() || unsafe { libc::getuid() } != 0 {
+                    unsafe { libc::_exit(1) }
+                }
+                let _ = std::process::Command::new("sleep").arg("10").exec();
+                unsafe { libc::_exit(1) }
+            }
+            pid => {
+                let pid = Pid::from_raw(pid);
+                user_namespace
+                    .map_ids(pid, &uid_map, &uid_map)
+                    .expect("map ids");
+
+                // Uids are shown as seen from the user namespace of the reader
+                let status =
+                    std::fs::read_to_string(format!("/proc/{pid}/status"))
+                        .expect("read status");
+                let uids = status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Uid:"))
+                    .expect("uid line");
+                assert_eq!(
+                    uids.split_whitespace().collect::<Vec<_>>(),
+                    ["100000"; 4]
+                );
+
+                // The child only execs once it is root in its user namespace
+                nix::sys::signal::kill(pid, nix::sys::signal::SIGKILL)
+                    .expect("kill");
+                let status =
+                    nix::sys::wait::waitpid(pid, None).expect("waitpid");
+                assert_eq!(
+                    status,
+                    nix::sys::wait::WaitStatus::Signaled(
+                        pid,
+                        nix::sys::signal::SIGKILL,
+                        false
+                    )
+                );
+            }
+        }
+    }
+}
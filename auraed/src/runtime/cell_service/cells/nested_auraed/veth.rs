@@ -0,0 +1,385 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! A veth pair connecting the network namespace of a cell to a bridge on the host.
+//!
+//! The host end is attached to the bridge. The other end is moved into the network
+//! namespace of the cell, where it is renamed to eth0 and given the address of the cell.
+//! Removing the host end removes the end in the cell as well. The bandwidth limits of the
+//! cell are applied to the host end (see [BandwidthLimits]).
+//!
+//! Netlink is async, while cells are allocated and freed synchronously, so each step runs
+//! on a thread of its own, with a runtime of its own. That thread is also the one that
+//! enters the network namespace of the cell, leaving the other threads of auraed in the
+//! network namespace of the host.
+
+use super::bandwidth::BandwidthLimits;
+use futures::stream::TryStreamExt;
+use ipnetwork::IpNetwork;
+use nix::{sched::CloneFlags, unistd::Pid};
+use rtnetlink::Handle;
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, ErrorKind},
+    net::IpAddr,
+    os::unix::io::AsRawFd,
+};
+use tracing::warn;
+
+/// The name of the end of the pair in the network namespace of the cell.
+const CELL_DEV: &str = "eth0";
+
+/// Connects a cell to a bridge on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgeConfig {
+    /// Name of an existing bridge on the host.
+    pub bridge: String,
+    /// Address of the cell, with the prefix length of its subnet.
+    pub address: IpNetwork,
+    /// Default gateway of the cell, if any. Of the same family as the address.
+    pub gateway: Option<IpAddr>,
+    /// Limits of the traffic of the cell, as seen from the cell.
+    pub bandwidth: BandwidthLimits,
+}
+
+/// The host end of the veth pair of a cell.
+#[derive(Debug)]
+pub struct Veth {
+    host_dev: String,
+    /// Applied to the host end, so reversed from the limits of the cell.
+    bandwidth: BandwidthLimits,
+}
+
+impl Veth {
+    /// Runs in auraed, once the nested auraed with `pid` has started in its own network
+    /// namespace. If a step fails, the pair is removed before returning.
+    pub fn create(pid: Pid, config: &BridgeConfig) -> io::Result<Self> {
+        // Interface names are limited to 15 bytes, and pids to 7 digits
+        let veth = Self {
+            host_dev: format!("aeveth{pid}"),
+            bandwidth: config.bandwidth.reversed(),
+        };
+        let peer_dev = format!("aepeer{pid}");
+        let (host_dev, peer_dev) = (&veth.host_dev, &peer_dev);
+
+        let res = run(None, |handle| async move {
+            handle
+                .link()
+                .add()
+                .veth(host_dev.clone(), peer_dev.clone())
+                .execute()
+                .await
+                .map_err(netlink_error)?;
+
+            let bridge = required_link_index(&handle, &config.bridge).await?;
+            let host = required_link_index(&handle, host_dev).await?;
+            handle
+                .link()
+                .set(host)
+                .master(bridge)
+                .execute()
+                .await
+                .map_err(netlink_error)?;
+            handle
+                .link()
+                .set(host)
+                .up()
+                .execute()
+                .await
+                .map_err(netlink_error)?;
+
+            let peer = required_link_index(&handle, peer_dev).await?;
+            handle
+                .link()
+                .set(peer)
+                .setns_by_pid(pid.as_raw() as u32)
+                .execute()
+                .await
+                .map_err(netlink_error)
+        })
+        .and_then(|()| {
+            run(Some(pid), |handle| {
+                configure_cell_dev(handle, peer_dev, config)
+            })
+        })
+        .and_then(|()| veth.bandwidth.apply(host_dev));
+
+        if let Err(e) = res {
+            let _best_effort = veth.remove();
+            return Err(e);
+        }
+
+        Ok(veth)
+    }
+
+    /// Removes the pair, if it still exists. The kernel removes it on its own once the
+    /// network namespace of the cell is gone, but a process outside of the cell may
+    /// still hold on to the namespace.
+    pub fn remove(&self) -> io::Result<()> {
+        run(None, |handle| async move {
+            let Some(host) = link_index(&handle, &self.host_dev).await? else {
+                return Ok(());
+            };
+
+            // Removing the link removes its limits as well, unless that fails
+            if let Err(e) = self.bandwidth.remove(&self.host_dev) {
+                warn!("failed to remove the limits of {}: {e}", self.host_dev);
+            }
+
+            handle.link().del(host).execute().await.map_err(netlink_error)
+        })
+    }
+}
+
+/// Runs in the network namespace of the cell.
+async fn configure_cell_dev(
+    handle: Handle,
+    peer_dev: &str,
+    config: &BridgeConfig,
+) -> io::Result<()> {
+    let index = required_link_index(&handle, peer_dev).await?;
+    handle
+        .link()
+        .set(index)
+        .name(CELL_DEV.into())
+        .execute()
+        .await
+        .map_err(netlink_error)?;
+    handle
+        .address()
+        .add(index, config.address.ip(), config.address.prefix())
+        .execute()
+        .await
+        .map_err(netlink_error)?;
+    handle.link().set(index).up().execute().await.map_err(netlink_error)?;
+
+    match config.gateway {
+        Some(IpAddr::V4(gateway)) => {
+            handle.route().add().v4().gateway(gateway).execute().await
+        }
+        Some(IpAddr::V6(gateway)) => {
+            handle.route().add().v6().gateway(gateway).execute().await
+        }
+        None => Ok(()),
+    }
+    .map_err(netlink_error)
+}
+
+/// Runs `f` with a netlink connection, on a thread of its own. With `netns`, the thread
+/// enters the network namespace of that process first, so the connection is made there.
+fn run<'a, F, Fut>(netns: Option<Pid>, f: F) -> io::Result<()>
+where
+    F: FnOnce(Handle) -> Fut + Send + 'a,
+    Fut: Future<Output = io::Result<()>>,
+{
+    std::thread::scope(|scope| {
+        scope
+            .spawn(move || {
+                if let Some(pid) = netns {
+                    let netns = File::open(format!("/proc/{pid}/ns/net"))?;
+                    nix::sched::setns(
+                        netns.as_raw_fd(),
+                        CloneFlags::CLONE_NEWNET,
+                    )
+                    .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+                }
+
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_io()
+                    .build()?;
+                runtime.block_on(async move {
+                    let (connection, handle, _) = rtnetlink::new_connection()?;
+                    let _ = tokio::spawn(connection);
+                    f(handle).await
+                })
+            })
+            .join()
+            .unwrap_or_else(|_| {
+                Err(io::Error::new(ErrorKind::Other, "netlink thread panicked"))
+            })
+    })
+}
+
+async fn link_index(handle: &Handle, name: &str) -> io::Result<Option<u32>> {
+    let mut links = handle.link().get().match_name(name.into()).execute();
+    match links.try_next().await {
+        Ok(link) => Ok(link.map(|link| link.header.index)),
+        // The kernel reports a missing link as ENODEV
+        Err(rtnetlink::Error::NetlinkError(e)) if e.code == -libc::ENODEV => {
+            Ok(None)
+        }
+        Err(e) => Err(netlink_error(e)),
+    }
+}
+
+async fn required_link_index(handle: &Handle, name: &str) -> io::Result<u32> {
+    link_index(handle, name).await?.ok_or_else(|| {
+        io::Error::new(ErrorKind::NotFound, format!("link '{name}' not found"))
+    })
+}
+
+fn netlink_error(e: rtnetlink::Error) -> io::Error {
+    io::Error::new(ErrorKind::Other, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::cells::{
+        CellName, CellSpec, Cells, FreeChildrenPolicy, NetworkMode,
+    };
+    use std::{ffi::OsStr, os::unix::process::CommandExt, process::Command};
+
+    /// Adds a bridge with a random name, and returns the name.
+    fn add_bridge() -> String {
+        let name =
+            format!("aebr{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let bridge = &name;
+        run(None, |handle| async move {
+            handle
+                .link()
+                .add()
+                .bridge(bridge.clone())
+                .execute()
+                .await
+                .map_err(netlink_error)
+        })
+        .expect("add bridge");
+        name
+    }
+
+    fn remove_bridge(name: &str) {
+        run(None, |handle| async move {
+            let index = required_link_index(&handle, name).await?;
+            handle.link().del(index).execute().await.map_err(netlink_error)
+        })
+        .expect("remove bridge");
+    }
+
+    /// The names of the links in the network namespace of the host.
+    fn host_links() -> Vec<String> {
+        std::fs::read_dir("/sys/class/net")
+            .expect("read /sys/class/net")
+            .map(|entry| {
+                entry.expect("link").file_name().to_string_lossy().into()
+            })
+            .collect()
+    }
+
+    fn bridge_config(bridge: &str, address: &str) -> BridgeConfig {
+        BridgeConfig {
+            bridge: bridge.into(),
+            address: address.parse().expect("valid address"),
+            gateway: None,
+            bandwidth: BandwidthLimits::default(),
+        }
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_create_and_remove() {
+        let bridge = add_bridge();
+
+        // a process in a network namespace of its own stands in for the nested auraed
+        let mut command = Command::new("sleep");
+        let _ = command.arg("60");
+        let mut process = unsafe {
+            command.pre_exec(|| {
+                nix::sched::unshare(CloneFlags::CLONE_NEWNET)
+                    .map_err(|e| io::Error::from_raw_os_error(e as i32))
+            })
+        }
+        .spawn()
+        .expect("spawn sleep");
+        let pid = Pid::from_raw(process.id() as i32);
+
+        let veth = Veth::create(pid, &bridge_config(&bridge, "10.200.0.2/24"))
+            .expect("create");
+
+        let host_dev = format!("aeveth{pid}");
+        assert!(host_links().contains(&host_dev));
+        let master =
+            std::fs::read_link(format!("/sys/class/net/{host_dev}/master"))
+                .expect("host end attached to a bridge");
+        assert_eq!(master.file_name(), Some(OsStr::new(&bridge)));
+
+        let mut cell_dev = None;
+        let found = &mut cell_dev;
+        run(Some(pid), |handle| async move {
+            *found = link_index(&handle, CELL_DEV).await?;
+            Ok(())
+        })
+        .expect("find the end in the cell");
+        assert!(cell_dev.is_some());
+
+        veth.remove().expect("remove");
+        assert!(!host_links().contains(&host_dev));
+        // removing a pair that is gone is not an error
+        veth.remove().expect("remove again");
+
+        process.kill().expect("kill sleep");
+        let _ = process.wait().expect("wait for sleep");
+        remove_bridge(&bridge);
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_allocate_and_free_do_not_leak_veths() {
+        let bridge = add_bridge();
+        let veths = || {
+            host_links()
+                .iter()
+                .filter(|link| link.starts_with("aeveth"))
+                .count()
+        };
+        let before = veths();
+
+        let mut cells = Cells::default();
+        for i in 2..5 {
+            let cell_name = CellName::random_for_tests();
+            let mut cell = CellSpec::new_for_tests();
+            cell.iso_ctl.network = NetworkMode::Bridged(bridge_config(
+                &bridge,
+                &format!("10.200.0.{i}/24"),
+            ));
+
+            let _ = cells.allocate(cell_name.clone(), cell).expect("allocate");
+            assert_eq!(veths(), before + 1);
+
+            cells.free(&cell_name, FreeChildrenPolicy::Reject).expect("free");
+            assert_eq!(veths(), before);
+        }
+
+        remove_bridge(&bridge);
+    }
+}
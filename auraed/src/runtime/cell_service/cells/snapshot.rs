@@ -0,0 +1,220 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{
+    cgroups::{Cgroup, CgroupStats, FreezeState},
+    Cell, CellName, CellSpec, CellsError, Result,
+};
+use procfs::ProcError;
+use std::io;
+
+/// The lifecycle state of a [Cell], without the resources it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellStatus {
+    Unallocated,
+    Allocated,
+    Freed,
+}
+
+/// A copy of what is known about a [Cell] at the time the snapshot was taken.
+#[derive(Debug, Clone)]
+pub struct CellSnapshot {
+    pub name: CellName,
+    pub spec: CellSpec,
+    pub status: CellStatus,
+}
+
+/// An immutable view of the cells in [super::Cells], taken with [super::Cells::snapshot].
+///
+/// Taking a snapshot only requires a brief lock on the cells. Long running reads (e.g.,
+/// reading the stats of every cell from the cgroup filesystem) can then iterate the
+/// snapshot without holding the lock, and without blocking allocating or freeing cells.
+///
+/// The snapshot is not updated: by the time it is consumed, cells in it may have been
+/// freed and new cells may have been allocated.
+#[derive(Debug, Clone, Default)]
+pub struct CellsSnapshot {
+    cells: Vec<CellSnapshot>,
+}
+
+impl CellSnapshot {
+    /// Reads the resource usage of the cell from its cgroup.
+    /// Returns a [io::ErrorKind::NotFound] error if the cell has since been freed.
+    pub fn stats(&self) -> io::Result<CgroupStats> {
+        Cgroup::stats(&self.name)
+    }
+
+    /// Reads whether the processes of the cell are frozen from its cgroup.
+    /// Returns a [io::ErrorKind::NotFound] error if the cell has since been freed.
+    pub fn freeze_state(&self) -> io::Result<FreezeState> {
+        Cgroup::freeze_state(&self.name)
+    }
+}
+
+impl CellsSnapshot {
+    pub(super) fn new<'a>(cells: impl Iterator<Item = &'a Cell>) -> Self {
+        let mut cells: Vec<_> = cells
+            .map(|cell| CellSnapshot {
+                name: cell.name().clone(),
+                spec: cell.spec().clone(),
+                status: cell.status(),
+            })
+            .collect();
+
+        cells.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self { cells }
+    }
+
+    /// Returns the cells of the snapshot, ordered by [CellName].
+    pub fn iter(&self) -> impl Iterator<Item = &CellSnapshot> {
+        self.cells.iter()
+    }
+
+    pub fn get(&self, cell_name: &CellName) -> Option<&CellSnapshot> {
+        self.cells
+            .binary_search_by(|cell| cell.name.cmp(cell_name))
+            .ok()
+            .map(|i| &self.cells[i])
+    }
+
+    /// Returns the name of the [Cell] that the thread `tid` belongs to.
+    /// Threads of a nested cell are reported as belonging to the top level cell.
+    ///
+    /// `cgroup.threads` is checked first, so a thread placed in a threaded cgroup is found
+    /// even when its process lives elsewhere. If no cell lists the thread (e.g., threaded
+    /// mode isn't enabled), we fall back to finding the cell of the thread's process (TGID).
+    ///
+    /// # Errors
+    /// * If the thread doesn't exist or isn't in a cell -> [CellsError::ThreadNotFound]
+    /// * If reading procfs or a cgroup fails -> [CellsError::FailedToFindThread]
+    pub fn get_by_tid(&self, tid: i32) -> Result<CellName> {
+        let map_err = |source| CellsError::FailedToFindThread { tid, source };
+
+        for CellSnapshot { name, .. } in self.iter() {
+            if Cgroup::has_thread(name, tid).map_err(map_err)? {
+                return Ok(name.clone());
+            }
+        }
+
+        let tgid = thread_group_id(tid)?;
+        for CellSnapshot { name, .. } in self.iter() {
+            if Cgroup::has_process(name, tgid).map_err(map_err)? {
+                return Ok(name.clone());
+            }
+        }
+
+        Err(CellsError::ThreadNotFound { tid })
+    }
+}
+
+/// Returns the id of the thread group (i.e., the pid of the process) the thread belongs to.
+fn thread_group_id(tid: i32) -> Result<i32> {
+    let status = procfs::process::Process::new(tid)
+        .and_then(|thread| thread.status())
+        .map_err(|e| match e {
+            ProcError::NotFound(_) => CellsError::ThreadNotFound { tid },
+            e => CellsError::FailedToFindThread {
+                tid,
+                source: io::Error::new(io::ErrorKind::Other, e),
+            },
+        })?;
+
+    Ok(status.tgid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::cells::Cells;
+
+    #[test]
+    fn test_snapshot_is_not_updated() {
+        let mut cells = Cells::default();
+        let cell_name = CellName::random_for_tests();
+        cells.insert_for_tests(cell_name.clone(), CellSpec::new_for_tests());
+
+        let snapshot = cells.snapshot();
+        cells.remove_for_tests(&cell_name);
+
+        assert_eq!(snapshot.iter().count(), 1);
+        let cell = snapshot.get(&cell_name).expect("cell in snapshot");
+        assert_eq!(cell.name, cell_name);
+        assert_eq!(cell.status, CellStatus::Unallocated);
+
+        assert_eq!(cells.snapshot().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_snapshot_is_ordered_by_name() {
+        let mut cells = Cells::default();
+        for name in ["c", "a", "b"] {
+            cells.insert_for_tests(name.into(), CellSpec::new_for_tests());
+        }
+
+        let snapshot = cells.snapshot();
+        let names: Vec<_> = snapshot.iter().map(|cell| &*cell.name).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert!(snapshot.get(&"b".into()).is_some());
+        assert!(snapshot.get(&"d".into()).is_none());
+    }
+
+    #[test]
+    fn test_thread_group_id_distinguishes_tid_from_pid() {
+        let pid = std::process::id() as i32;
+
+        // keep the thread alive until we are done looking it up
+        let (tid_tx, tid_rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            tid_tx.send(nix::unistd::gettid().as_raw()).expect("send tid");
+            let _ = done_rx.recv();
+        });
+        let tid = tid_rx.recv().expect("tid");
+
+        assert_ne!(tid, pid);
+        assert_eq!(thread_group_id(tid).expect("tgid of thread"), pid);
+        assert_eq!(thread_group_id(pid).expect("tgid of process"), pid);
+
+        drop(done_tx);
+        thread.join().expect("thread");
+    }
+
+    #[test]
+    fn test_get_by_tid_not_in_cell_is_error() {
+        let snapshot = CellsSnapshot::default();
+        let tid = nix::unistd::gettid().as_raw();
+
+        assert!(matches!(
+            snapshot.get_by_tid(tid),
+            Err(CellsError::ThreadNotFound { tid: t }) if t == tid
+        ));
+    }
+}
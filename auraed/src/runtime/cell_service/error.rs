@@ -30,8 +30,10 @@
 
 use super::{cells::CellsError, executables::ExecutablesError};
 use aurae_client::AuraeClientError;
+use prost::Message;
+use std::time::Duration;
 use thiserror::Error;
-use tonic::Status;
+use tonic::{Code, Status};
 use tracing::error;
 
 pub(crate) type Result<T> = std::result::Result<T, CellsServiceError>;
@@ -46,24 +48,98 @@ pub(crate) enum CellsServiceError {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     AuraeClientError(#[from] AuraeClientError),
+    #[error("start did not complete within {timeout:?}")]
+    StartTimedOut { timeout: Duration },
+    #[error("'{method}' is restricted to admin clients")]
+    AdminOnly { method: &'static str },
 }
 
+/// Maps each error to the [Code] that tells the client what to do about it:
+///
+/// - `NotFound`: the cell, cgroup, thread, executable or command does not exist.
+/// - `AlreadyExists`: a cell or executable with the same name exists.
+/// - `FailedPrecondition`: the request conflicts with the current state (e.g., freeing a
+///   cell that still has children, or stopping an executable that is not running).
+/// - `ResourceExhausted`: a limit was reached (nesting, pinned memory, cells, pids.max).
+/// - `DeadlineExceeded`: a start, or the readiness of an executable, timed out.
+/// - `Aborted`: the executable exited or closed its output before it was ready.
+/// - `PermissionDenied`: the method is restricted to admins, or auraed lacks the
+///   permissions to inspect the executable.
+/// - `Unimplemented`: the host does not support the requested setting.
+/// - `Unavailable`: the nested auraed of the cell could not be reached.
+/// - `Internal`: a syscall or cgroup operation failed (including [std::io::Error]).
+///
+/// Requests that fail validation are rejected with `InvalidArgument` before any of
+/// these errors can occur (see [validation::ValidationError]).
 impl From<CellsServiceError> for Status {
     fn from(err: CellsServiceError) -> Self {
         let msg = err.to_string();
         error!("{msg}");
         match err {
             CellsServiceError::CellsError(e) => match e {
-                CellsError::CgroupIsNotACell { .. } => {
+                CellsError::CgroupIsNotACell { .. }
+                | CellsError::CellHasChildren { .. }
+                | CellsError::ChildIsAllocated { .. }
+                | CellsError::CellHasExecutables { .. }
+                | CellsError::ClientCredentialsUnreadable { .. }
+                | CellsError::ControllerDelegationBlocked { .. }
+                | CellsError::ExecutableInCell { .. }
+                | CellsError::NumaLocalityMismatch { .. } => {
                     Status::failed_precondition(msg)
                 }
+                CellsError::CgroupSpecMismatch { diff, .. } => {
+                    // clients can decode the differing values from the details
+                    let diff = aurae_proto::runtime::CgroupSpecDiff::from(diff);
+                    Status::with_details(
+                        Code::FailedPrecondition,
+                        msg,
+                        diff.encode_to_vec().into(),
+                    )
+                }
                 CellsError::CellExists { .. } => Status::already_exists(msg),
+                CellsError::NestedAuraedUnreachable { .. } => {
+                    Status::unavailable(msg)
+                }
+                CellsError::SwappinessUnsupported { .. } => {
+                    Status::unimplemented(msg)
+                }
+                CellsError::NestingLimitReached { .. }
+                | CellsError::PinnedMemoryBudgetExceeded { .. }
+                | CellsError::CellLimitReached { .. } => {
+                    Status::resource_exhausted(msg)
+                }
                 CellsError::CellNotFound { .. }
-                | CellsError::CgroupNotFound { .. } => Status::not_found(msg),
+                | CellsError::CgroupNotFound { .. }
+                | CellsError::ThreadNotFound { .. }
+                | CellsError::ExecutableNotInCell { .. } => {
+                    Status::not_found(msg)
+                }
                 CellsError::FailedToAllocateCell { .. }
                 | CellsError::AbortedAllocateCell { .. }
                 | CellsError::FailedToKillCellChildren { .. }
-                | CellsError::FailedToFreeCell { .. } => Status::internal(msg),
+                | CellsError::FailedToFreeCell { .. }
+                | CellsError::FailedToFreeCellChildren { .. }
+                | CellsError::FailedToSetUclamp { .. }
+                | CellsError::FailedToSetCpuBurst { .. }
+                | CellsError::FailedToSetZswapMax { .. }
+                | CellsError::FailedToSetIo { .. }
+                | CellsError::FailedToSetMemoryMin { .. }
+                | CellsError::FailedToSetNestingLimits { .. }
+                | CellsError::FailedToDelegateCgroup { .. }
+                | CellsError::FailedToUpdateCell { .. }
+                | CellsError::FailedToReadCgroupId { .. }
+                | CellsError::FailedToReadCgroupStats { .. }
+                | CellsError::FailedToReadCgroupControllers { .. }
+                | CellsError::FailedToReadNamespaces { .. }
+                | CellsError::FailedToFreezeCell { .. }
+                | CellsError::FailedToSnapshotCell { .. }
+                | CellsError::FailedToThawCell { .. }
+                | CellsError::FailedToReadFreezeState { .. }
+                | CellsError::FailedToFindThread { .. }
+                | CellsError::FailedToFindExecutable { .. }
+                | CellsError::FailedToListOrphans { .. } => {
+                    Status::internal(msg)
+                }
                 CellsError::CellNotAllocated { cell_name } => {
                     CellsServiceError::CellsError(CellsError::CellNotFound {
                         cell_name,
@@ -75,11 +151,43 @@ impl From<CellsServiceError> for Status {
                 ExecutablesError::ExecutableExists { .. } => {
                     Status::already_exists(msg)
                 }
-                ExecutablesError::ExecutableNotFound { .. } => {
+                ExecutablesError::ExecutableNotFound { .. }
+                | ExecutablesError::CommandNotFound { .. } => {
                     Status::not_found(msg)
                 }
+                ExecutablesError::FailedToLoadEnvFile { .. }
+                | ExecutablesError::FailedToLoadSeccompProfile { .. }
+                | ExecutablesError::FailedToOpenWorkingDir { .. }
+                | ExecutablesError::DependencyCycle { .. }
+                | ExecutablesError::ExecutableNotRunning { .. }
+                | ExecutablesError::NoStartToRestart { .. } => {
+                    Status::failed_precondition(msg)
+                }
+                ExecutablesError::FailedToListOpenFds { source, .. }
+                    if source.kind()
+                        == std::io::ErrorKind::PermissionDenied =>
+                {
+                    Status::permission_denied(msg)
+                }
+                ExecutablesError::ReadyLogTimedOut { .. }
+                | ExecutablesError::ReadyPortTimedOut { .. }
+                | ExecutablesError::DependenciesTimedOut { .. } => {
+                    Status::deadline_exceeded(msg)
+                }
+                ExecutablesError::OutputClosedBeforeReady { .. }
+                | ExecutablesError::ExitedBeforeReady { .. }
+                | ExecutablesError::DependencyNotReady { .. } => {
+                    Status::aborted(msg)
+                }
+                ExecutablesError::ProcessLimitReached { .. } => {
+                    Status::resource_exhausted(msg)
+                }
                 ExecutablesError::FailedToStartExecutable { .. }
-                | ExecutablesError::FailedToStopExecutable { .. } => {
+                | ExecutablesError::ProcessNotInCell { .. }
+                | ExecutablesError::FailedToVerifyPlacement { .. }
+                | ExecutablesError::FailedToStopExecutable { .. }
+                | ExecutablesError::FailedToWaitForExecutable { .. }
+                | ExecutablesError::FailedToListOpenFds { .. } => {
                     Status::internal(msg)
                 }
             },
@@ -90,6 +198,92 @@ impl From<CellsServiceError> for Status {
                 }
                 AuraeClientError::Other(_) => Status::unknown(msg),
             },
+            CellsServiceError::StartTimedOut { .. } => {
+                Status::deadline_exceeded(msg)
+            }
+            CellsServiceError::AdminOnly { .. } => {
+                Status::permission_denied(msg)
+            }
         }
     }
 }
+
+impl From<CellsError> for Status {
+    fn from(err: CellsError) -> Self {
+        CellsServiceError::from(err).into()
+    }
+}
+
+impl From<ExecutablesError> for Status {
+    fn from(err: ExecutablesError) -> Self {
+        CellsServiceError::from(err).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::{
+        cells::CellName, executables::ExecutableName,
+    };
+    use simple_test_case::test_case;
+    use validation::ValidatedField;
+
+    fn executable_name() -> ExecutableName {
+        ExecutableName::validate(Some("ae-exe".into()), "name", None)
+            .expect("valid name")
+    }
+
+    #[test_case(CellsError::CellNotFound { cell_name: CellName::random_for_tests() }, Code::NotFound; "cell not found")]
+    #[test_case(CellsError::CellNotAllocated { cell_name: CellName::random_for_tests() }, Code::NotFound; "cell not allocated")]
+    #[test_case(CellsError::CellExists { cell_name: CellName::random_for_tests() }, Code::AlreadyExists; "cell exists")]
+    #[test_case(CellsError::CellHasChildren { cell_name: CellName::random_for_tests(), children: vec![] }, Code::FailedPrecondition; "cell has children")]
+    #[test_case(CellsError::CellHasExecutables { cell_name: CellName::random_for_tests(), executables: vec![] }, Code::FailedPrecondition; "cell has executables")]
+    #[test_case(CellsError::ClientCredentialsUnreadable { cell_name: CellName::random_for_tests(), source: std::io::ErrorKind::NotFound.into() }, Code::FailedPrecondition; "client credentials unreadable")]
+    #[test_case(CellsError::CellLimitReached { cell_name: CellName::random_for_tests(), max_cells: 1 }, Code::ResourceExhausted; "cell limit reached")]
+    #[test_case(CellsError::FailedToAllocateCell { cell_name: CellName::random_for_tests(), source: std::io::ErrorKind::Other.into() }, Code::Internal; "failed to allocate")]
+    #[test]
+    fn test_cells_error_code(err: CellsError, code: Code) {
+        assert_eq!(Status::from(err).code(), code);
+    }
+
+    #[test]
+    fn test_nested_auraed_unreachable_includes_stderr() {
+        let err = CellsError::NestedAuraedUnreachable {
+            cell_name: CellName::random_for_tests(),
+            source: AuraeClientError::Other(anyhow::anyhow!("no socket")),
+            stderr: vec!["Error: failed to bind socket".into()],
+        };
+
+        let status = Status::from(err);
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(status.message().contains("failed to bind socket"));
+    }
+
+    #[test_case(ExecutablesError::ExecutableNotFound { executable_name: executable_name() }, Code::NotFound; "executable not found")]
+    #[test_case(ExecutablesError::ExecutableExists { executable_name: executable_name() }, Code::AlreadyExists; "executable exists")]
+    #[test_case(ExecutablesError::ExecutableNotRunning { executable_name: executable_name() }, Code::FailedPrecondition; "executable not running")]
+    #[test_case(ExecutablesError::NoStartToRestart { executable_name: executable_name() }, Code::FailedPrecondition; "no start to restart")]
+    #[test_case(ExecutablesError::FailedToLoadSeccompProfile { executable_name: executable_name(), source: std::io::ErrorKind::InvalidData.into() }, Code::FailedPrecondition; "invalid seccomp profile")]
+    #[test_case(ExecutablesError::FailedToListOpenFds { executable_name: executable_name(), source: std::io::ErrorKind::PermissionDenied.into() }, Code::PermissionDenied; "fds permission denied")]
+    #[test_case(ExecutablesError::FailedToListOpenFds { executable_name: executable_name(), source: std::io::ErrorKind::Other.into() }, Code::Internal; "fds other error")]
+    #[test]
+    fn test_executables_error_code(err: ExecutablesError, code: Code) {
+        assert_eq!(Status::from(err).code(), code);
+    }
+
+    #[test_case(CellsServiceError::StartTimedOut { timeout: Duration::from_secs(1) }, Code::DeadlineExceeded; "start timed out")]
+    #[test_case(CellsServiceError::AdminOnly { method: "free" }, Code::PermissionDenied; "admin only")]
+    #[test_case(CellsServiceError::Io(std::io::ErrorKind::NotFound.into()), Code::Internal; "io")]
+    #[test]
+    fn test_cells_service_error_code(err: CellsServiceError, code: Code) {
+        assert_eq!(Status::from(err).code(), code);
+    }
+
+    #[test]
+    fn test_validation_error_code() {
+        let err = ExecutableName::validate(Some("".into()), "name", None)
+            .expect_err("empty name");
+        assert_eq!(Status::from(err).code(), Code::InvalidArgument);
+    }
+}
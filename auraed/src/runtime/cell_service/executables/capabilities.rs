@@ -0,0 +1,187 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Capabilities dropped from the bounding set of executables.
+//!
+//! auraed runs as root, so the executables it starts get the full set of capabilities
+//! when they exec. The permitted capabilities of a process that execs as root are the
+//! capabilities of its bounding set, and a capability dropped from the bounding set
+//! can't be added back, by the process nor by the programs it starts.
+
+use nix::errno::Errno;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// The names of the capabilities known to auraed, at the index of their number.
+/// Docs: https://man7.org/linux/man-pages/man7/capabilities.7.html
+pub const CAPABILITIES: [&str; 41] = [
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+/// A capability known to auraed (see [CAPABILITIES]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capability(u32);
+
+impl Capability {
+    /// Returns the capability named `name` (e.g., "CAP_NET_RAW"), if known.
+    pub fn from_name(name: &str) -> Option<Self> {
+        CAPABILITIES
+            .iter()
+            .position(|known| *known == name)
+            .map(|number| Self(number as u32))
+    }
+
+    pub fn name(&self) -> &'static str {
+        CAPABILITIES[self.0 as usize]
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// Drops `capabilities` from the bounding set of the calling process, which requires
+/// CAP_SETPCAP. Capabilities that the kernel doesn't know are skipped, as no process
+/// can hold them.
+/// Only to be called in the child, between fork and exec (i.e., as a pre-exec hook).
+pub fn drop_from_bounding_set(capabilities: &[Capability]) -> io::Result<()> {
+    for capability in capabilities {
+        let res = Errno::result(unsafe {
+            libc::prctl(libc::PR_CAPBSET_DROP, capability.0 as libc::c_ulong)
+        });
+
+        match res {
+            Ok(_) | Err(Errno::EINVAL) => {}
+            Err(e) => return Err(io::Error::from_raw_os_error(e as i32)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case("CAP_CHOWN", 0; "first")]
+    #[test_case("CAP_NET_RAW", 13; "net raw")]
+    #[test_case("CAP_CHECKPOINT_RESTORE", 40; "last")]
+    #[test]
+    fn test_from_name(name: &str, number: u32) {
+        let capability = Capability::from_name(name).expect("known capability");
+        assert_eq!(capability, Capability(number));
+        assert_eq!(capability.name(), name);
+    }
+
+    #[test_case("NET_RAW"; "without prefix")]
+    #[test_case("cap_net_raw"; "lowercase")]
+    #[test_case("CAP_NET_RAWR"; "unknown")]
+    #[test_case(""; "empty")]
+    #[test]
+    fn test_unknown_name(name: &str) {
+        assert_eq!(Capability::from_name(name), None);
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[test]
+    fn test_dropped_capability_is_not_in_bounding_set_of_child() {
+        use std::os::unix::process::CommandExt;
+        use std::process::Command;
+
+        let net_raw = Capability::from_name("CAP_NET_RAW").expect("known");
+
+        let mut command = Command::new("cat");
+        let _ = command.arg("/proc/self/status");
+        let output = unsafe {
+            command.pre_exec(move || drop_from_bounding_set(&[net_raw]))
+        }
+        .output()
+        .expect("run cat");
+        assert!(output.status.success());
+
+        let status = String::from_utf8_lossy(&output.stdout);
+        let mask = |prefix: &str| {
+            let mask = status
+                .lines()
+                .find_map(|line| line.strip_prefix(prefix))
+                .expect("capabilities in status");
+            u64::from_str_radix(mask.trim(), 16).expect("capability mask")
+        };
+
+        assert_eq!(mask("CapBnd:") & (1 << net_raw.0), 0);
+        assert_eq!(mask("CapEff:") & (1 << net_raw.0), 0);
+        let chown = Capability::from_name("CAP_CHOWN").expect("known");
+        assert_ne!(mask("CapBnd:") & (1 << chown.0), 0);
+    }
+}
@@ -0,0 +1,179 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{ExecutableName, ExecutablesError, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+type Pending = HashMap<ExecutableName, Vec<ExecutableName>>;
+
+/// The dependencies of the executables whose start is waiting for them.
+///
+/// Starts wait for their dependencies without holding the lock on [super::Executables],
+/// so two starts waiting for each other would only fail once they time out. Registering
+/// a start that would close such a cycle is an error instead.
+#[derive(Debug, Clone, Default)]
+pub struct PendingStarts {
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl PendingStarts {
+    /// Registers that the start of `executable_name` waits for `depends_on`.
+    /// The registration is removed when the returned [PendingStart] is dropped.
+    pub fn register(
+        &self,
+        executable_name: &ExecutableName,
+        depends_on: &[ExecutableName],
+    ) -> Result<PendingStart> {
+        let mut pending = self.pending.lock().expect("lock");
+
+        if pending.contains_key(executable_name) {
+            return Err(ExecutablesError::ExecutableExists {
+                executable_name: executable_name.clone(),
+            });
+        }
+
+        // A cycle can only be closed by the start being registered, as every pending
+        // start was checked when it was registered.
+        let mut visited = HashSet::new();
+        if let Some(dependency) = depends_on.iter().find(|dependency| {
+            reaches(&pending, dependency, executable_name, &mut visited)
+        }) {
+            return Err(ExecutablesError::DependencyCycle {
+                executable_name: executable_name.clone(),
+                dependency: dependency.clone(),
+            });
+        }
+
+        let _ = pending.insert(executable_name.clone(), depends_on.to_vec());
+
+        Ok(PendingStart {
+            pending: self.pending.clone(),
+            executable_name: executable_name.clone(),
+        })
+    }
+}
+
+/// Returns true if `from` is `to`, or (transitively) waits for it.
+fn reaches(
+    pending: &Pending,
+    from: &ExecutableName,
+    to: &ExecutableName,
+    visited: &mut HashSet<ExecutableName>,
+) -> bool {
+    if from == to {
+        return true;
+    }
+
+    if !visited.insert(from.clone()) {
+        return false;
+    }
+
+    pending.get(from).map_or(false, |depends_on| {
+        depends_on
+            .iter()
+            .any(|dependency| reaches(pending, dependency, to, visited))
+    })
+}
+
+/// A start registered with [PendingStarts::register], until dropped.
+#[derive(Debug)]
+pub struct PendingStart {
+    pending: Arc<Mutex<Pending>>,
+    executable_name: ExecutableName,
+}
+
+impl Drop for PendingStart {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            let _ = pending.remove(&self.executable_name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validation::ValidatedField;
+
+    fn name(name: &str) -> ExecutableName {
+        ExecutableName::validate(Some(name.into()), "name", None)
+            .expect("valid name")
+    }
+
+    fn names(names: &[&str]) -> Vec<ExecutableName> {
+        names.iter().map(|n| name(n)).collect()
+    }
+
+    #[test]
+    fn test_cycle_is_rejected() {
+        let pending_starts = PendingStarts::default();
+        let _a = pending_starts
+            .register(&name("a"), &names(&["b"]))
+            .expect("a waits for b");
+        let _b = pending_starts
+            .register(&name("b"), &names(&["c"]))
+            .expect("b waits for c");
+
+        assert!(matches!(
+            pending_starts.register(&name("c"), &names(&["d", "a"])),
+            Err(ExecutablesError::DependencyCycle { dependency, .. })
+                if dependency == name("a")
+        ));
+        assert!(matches!(
+            pending_starts.register(&name("e"), &names(&["e"])),
+            Err(ExecutablesError::DependencyCycle { .. })
+        ));
+
+        // waiting for the same dependency more than once isn't a cycle
+        let _d = pending_starts
+            .register(&name("d"), &names(&["b", "c"]))
+            .expect("d waits for b and c");
+    }
+
+    #[test]
+    fn test_dropped_start_is_unregistered() {
+        let pending_starts = PendingStarts::default();
+        let a = pending_starts
+            .register(&name("a"), &names(&["b"]))
+            .expect("a waits for b");
+
+        assert!(matches!(
+            pending_starts.register(&name("a"), &[]),
+            Err(ExecutablesError::ExecutableExists { .. })
+        ));
+        assert!(pending_starts.register(&name("b"), &names(&["a"])).is_err());
+
+        drop(a);
+        let _b = pending_starts
+            .register(&name("b"), &names(&["a"]))
+            .expect("a is no longer pending");
+    }
+}
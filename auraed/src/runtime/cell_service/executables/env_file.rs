@@ -0,0 +1,137 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Loading of environment files in the dotenv format.
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! KEY=value
+//! export OTHER_KEY="quoted value"
+//! ```
+
+use std::io;
+use std::path::Path;
+
+/// Reads and parses an environment file on the host.
+pub fn read(path: &Path) -> io::Result<Vec<(String, String)>> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// Parses `KEY=VALUE` lines, in the order they appear.
+///
+/// Blank lines and lines starting with `#` are skipped, as is a leading `export `.
+/// Values wrapped in matching single or double quotes have the quotes removed.
+pub fn parse(contents: &str) -> io::Result<Vec<(String, String)>> {
+    let mut vars = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(invalid_line(i, "expected KEY=VALUE"));
+        };
+
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(invalid_line(i, "invalid key"));
+        }
+
+        vars.push((key.to_string(), unquote(value.trim()).to_string()));
+    }
+
+    Ok(vars)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(unquoted) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return unquoted;
+        }
+    }
+
+    value
+}
+
+fn invalid_line(index: usize, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {reason}", index + 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let contents = r#"
+# database settings
+DB_HOST=localhost
+
+export DB_PORT = 5432
+DB_PASSWORD="p@ss # word"
+GREETING='hello world'
+EMPTY=
+WITH_EQUALS=a=b
+"#;
+
+        let vars = parse(contents).expect("parse");
+
+        assert_eq!(
+            vars,
+            vec![
+                ("DB_HOST".to_string(), "localhost".to_string()),
+                ("DB_PORT".to_string(), "5432".to_string()),
+                ("DB_PASSWORD".to_string(), "p@ss # word".to_string()),
+                ("GREETING".to_string(), "hello world".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+                ("WITH_EQUALS".to_string(), "a=b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_line_is_error() {
+        let err = parse("A=1\nNOT A VAR\n").expect_err("invalid line");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"));
+
+        assert!(parse("=value").is_err());
+    }
+}
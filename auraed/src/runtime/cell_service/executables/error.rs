@@ -29,7 +29,7 @@
 \* -------------------------------------------------------------------------- */
 
 use super::ExecutableName;
-use std::io;
+use std::{ffi::OsString, io, path::PathBuf, time::Duration};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, ExecutablesError>;
@@ -40,14 +40,112 @@ pub enum ExecutablesError {
     ExecutableExists { executable_name: ExecutableName },
     #[error("executable '{executable_name}' not found")]
     ExecutableNotFound { executable_name: ExecutableName },
+    #[error(
+        "executable '{executable_name}' command '{}' not found",
+        program.to_string_lossy()
+    )]
+    CommandNotFound { executable_name: ExecutableName, program: OsString },
     #[error("executable '{executable_name}' failed to start: {source}")]
     FailedToStartExecutable {
         executable_name: ExecutableName,
         source: io::Error,
     },
+    #[error(
+        "executable '{executable_name}' failed to start: the cell's process limit (pids.max) was reached"
+    )]
+    ProcessLimitReached { executable_name: ExecutableName, source: io::Error },
+    #[error(
+        "executable '{executable_name}' failed to load env file: {source}"
+    )]
+    FailedToLoadEnvFile { executable_name: ExecutableName, source: io::Error },
+    #[error(
+        "executable '{executable_name}' failed to load seccomp profile: {source}"
+    )]
+    FailedToLoadSeccompProfile {
+        executable_name: ExecutableName,
+        source: io::Error,
+    },
+    #[error(
+        "executable '{executable_name}' failed to open working dir '{}': {source}",
+        working_dir.display()
+    )]
+    FailedToOpenWorkingDir {
+        executable_name: ExecutableName,
+        working_dir: PathBuf,
+        source: io::Error,
+    },
+    #[error(
+        "executable '{executable_name}' (pid {pid}) is in cgroup '{actual}', expected '{expected}'"
+    )]
+    ProcessNotInCell {
+        executable_name: ExecutableName,
+        pid: i32,
+        expected: String,
+        actual: String,
+    },
+    #[error(
+        "executable '{executable_name}' failed to verify placement: {source}"
+    )]
+    FailedToVerifyPlacement {
+        executable_name: ExecutableName,
+        source: io::Error,
+    },
+    #[error(
+        "executable '{executable_name}' did not log a ready line within {timeout:?}"
+    )]
+    ReadyLogTimedOut { executable_name: ExecutableName, timeout: Duration },
+    #[error(
+        "executable '{executable_name}' closed its output without logging a ready line"
+    )]
+    OutputClosedBeforeReady { executable_name: ExecutableName },
+    #[error(
+        "executable '{executable_name}' did not listen on port {port} within {timeout:?}"
+    )]
+    ReadyPortTimedOut {
+        executable_name: ExecutableName,
+        port: u16,
+        timeout: Duration,
+    },
+    #[error(
+        "executable '{executable_name}' exited before listening on port {port}"
+    )]
+    ExitedBeforeReady { executable_name: ExecutableName, port: u16 },
     #[error("executable '{executable_name}' failed to stop: {source}")]
     FailedToStopExecutable {
         executable_name: ExecutableName,
         source: io::Error,
     },
+    #[error(
+        "executable '{executable_name}' can't depend on '{dependency}', which waits for it"
+    )]
+    DependencyCycle {
+        executable_name: ExecutableName,
+        dependency: ExecutableName,
+    },
+    #[error(
+        "executable '{executable_name}' dependency '{dependency}' closed its output without logging a ready line"
+    )]
+    DependencyNotReady {
+        executable_name: ExecutableName,
+        dependency: ExecutableName,
+    },
+    #[error(
+        "executable '{executable_name}' dependencies were not ready within {timeout:?}"
+    )]
+    DependenciesTimedOut { executable_name: ExecutableName, timeout: Duration },
+    #[error("executable '{executable_name}' is not running")]
+    ExecutableNotRunning { executable_name: ExecutableName },
+    #[error(
+        "executable '{executable_name}' failed to list open fds: {source}"
+    )]
+    FailedToListOpenFds { executable_name: ExecutableName, source: io::Error },
+    #[error("executable '{executable_name}' failed to wait: {source}")]
+    FailedToWaitForExecutable {
+        executable_name: ExecutableName,
+        source: io::Error,
+    },
+    #[error(
+        "executable '{executable_name}' has no start request to restart from, use start instead"
+    )]
+    NoStartToRestart { executable_name: ExecutableName },
 }
@@ -1,21 +1,50 @@
-use super::{ExecutableName, ExecutableSpec};
+use super::{
+    ExecutableName, ExecutableSpec, OutputFraming, OutputLine,
+    OutputSubscription, OutputTail, ProcessGroup, ProcessTitle, ReadyLog,
+    RestartStats, StopPolicy, EVENT_STREAM,
+};
 use crate::logging::log_channel::LogChannel;
-use nix::unistd::Pid;
+use crate::runtime::cell_service::pre_exec::PreExecHooks;
+use nix::{
+    errno::Errno,
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
 use std::{
     ffi::OsString,
+    future::Future,
     io,
     process::{ExitStatus, Stdio},
+    time::Duration,
 };
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::AsyncRead;
 use tokio::process::{Child, Command};
 use tokio::task::JoinHandle;
-use tracing::info_span;
+use tokio::time::timeout;
+use tracing::{info_span, warn};
+
+/// How long the output of an exited executable is waited for. The processes it started
+/// (e.g., `sleep 600 &`) may hold its stdout open long after it exited, while the caller
+/// holds the lock on the executables.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Debug)]
 pub struct Executable {
     pub name: ExecutableName,
     pub description: String,
+    /// The command as provided by the user, before any wrapping.
+    pub original_command: OsString,
+    /// The argv the process is spawned with, including the program.
+    pub argv: Vec<OsString>,
+    /// The generation the executable was started in, if any.
+    pub generation: Option<u64>,
     state: ExecutableState,
+    restart_stats: RestartStats,
+    output_tail: OutputTail,
+    output_framing: OutputFraming,
+    ready_log: Option<ReadyLog>,
+    process_group: ProcessGroup,
+    process_title: Option<ProcessTitle>,
 }
 
 #[derive(Debug)]
@@ -37,9 +66,51 @@ enum ExecutableState {
 
 impl Executable {
     pub fn new<T: Into<ExecutableSpec>>(spec: T) -> Self {
-        let ExecutableSpec { name, description, command } = spec.into();
+        let spec = spec.into();
+        let argv = spec.argv();
+        let ExecutableSpec {
+            name,
+            description,
+            original_command,
+            mut command,
+            env_file: _,
+            working_dir: _,
+            process_title,
+            output_tail_capacity,
+            output_framing,
+            sigpipe,
+            ready_log_pattern,
+            process_group,
+            generation,
+            seccomp_profile: _,
+            pre_exec_hooks: spec_hooks,
+        } = spec;
+        let mut pre_exec_hooks = PreExecHooks::default();
+        pre_exec_hooks.push("sigpipe", move || sigpipe.apply());
+        if process_group.is_own() {
+            pre_exec_hooks.push("process_group", move || process_group.enter());
+        }
+        // The hooks of the spec run last, so a seccomp filter is installed right before exec
+        pre_exec_hooks.append(spec_hooks);
+        if !pre_exec_hooks.is_empty() {
+            // The hooks must only do what is safe between fork and exec (see [PreExecHooks])
+            let _ = unsafe { command.pre_exec(move || pre_exec_hooks.run()) };
+        }
         let state = ExecutableState::Init { command };
-        Self { name, description, state }
+        Self {
+            name,
+            description,
+            original_command,
+            argv,
+            generation,
+            state,
+            restart_stats: Default::default(),
+            output_tail: OutputTail::new(output_tail_capacity),
+            output_framing,
+            ready_log: ready_log_pattern.map(ReadyLog::new),
+            process_group,
+            process_title,
+        }
     }
 
     /// Starts the underlying process.
@@ -49,68 +120,153 @@ impl Executable {
             return Ok(());
         };
 
-        let mut child = command
-            .current_dir("/")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        // The symlink of the title is only needed until the program is executed,
+        // which is done once spawned
+        let link = match &self.process_title {
+            Some(process_title) => Some(process_title.link(command.as_std())?),
+            None => None,
+        };
+        let mut child =
+            command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        drop(link);
+        let program = match &self.process_title {
+            Some(process_title) => process_title.program().to_os_string(),
+            None => command.as_std().get_program().to_os_string(),
+        };
+        let args =
+            command.as_std().get_args().map(|arg| arg.to_os_string()).collect();
 
         let stdout = child.stdout.take().expect("stdout");
-        let log_channel = LogChannel::new(format!("{}::stdout", self.name));
-        let span = info_span!("running process", name = ?self.name);
-        let stdout = tokio::spawn(async move {
-            let log_channel = log_channel;
-            let mut span = Some(span);
-            let mut stdout = BufReader::new(stdout).lines();
-            while let Ok(Some(line)) = stdout.next_line().await {
-                let entered_span = span.take().expect("span").entered();
-                //info!(level = "info", channel = log_channel.name, line);
-                //println!("{line}");
-                log_channel.send(line);
-                span = Some(entered_span.exit());
-            }
-        });
+        let stdout = tokio::spawn(self.forward_output("stdout", stdout));
 
         let stderr = child.stderr.take().expect("stderr");
-        let log_channel = LogChannel::new(format!("{}::stderr", self.name));
+        let stderr = tokio::spawn(self.forward_output("stderr", stderr));
+
+        self.state =
+            ExecutableState::Started { program, args, child, stdout, stderr };
+
+        Ok(())
+    }
+
+    /// Returns a future that frames `output` (see [OutputFraming]), and passes each line to
+    /// the [LogChannel], [OutputTail] and [ReadyLog] of the [Executable].
+    fn forward_output<R>(
+        &self,
+        stream: &'static str,
+        output: R,
+    ) -> impl Future<Output = ()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let log_channel = LogChannel::new(format!("{}::{stream}", self.name));
+        let output_framing = self.output_framing;
+        let output_tail = self.output_tail.writer();
+        let ready_log = self.ready_log.clone();
         let span = info_span!("running process", name = ?self.name);
-        let stderr = tokio::spawn(async move {
-            let log_channel = log_channel;
+        async move {
             let mut span = Some(span);
-            let mut stderr = BufReader::new(stderr).lines();
-            while let Ok(Some(line)) = stderr.next_line().await {
-                let entered_span = span.take().expect("span").entered();
-                // info!(level = "error", channel = log_channel.name, line);
-                //println!("{line}");
-                log_channel.send(line);
-                span = Some(entered_span.exit());
+            output_framing
+                .read(output, |line| {
+                    let entered_span = span.take().expect("span").entered();
+                    output_tail.push(stream, &line);
+                    if let Some(ready_log) = &ready_log {
+                        ready_log.check(&line);
+                    }
+                    log_channel.send(line);
+                    span = Some(entered_span.exit());
+                })
+                .await;
+            if let Some(ready_log) = &ready_log {
+                ready_log.close();
             }
-        });
-
-        self.state = ExecutableState::Started {
-            program: command.as_std().get_program().to_os_string(),
-            args: command
-                .as_std()
-                .get_args()
-                .map(|arg| arg.to_os_string())
-                .collect(),
-            child,
-            stdout,
-            stderr,
-        };
+        }
+    }
 
-        Ok(())
+    /// Kills the executable right away (see [Executable::stop]).
+    pub async fn kill(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.stop(StopPolicy::KILL).await
     }
 
-    /// Stops the executable and returns the [ExitStatus].
+    /// Stops the executable with the signal of `policy`, and returns the [ExitStatus].
+    /// If it has not exited within the grace period of `policy`, it is escalated through
+    /// the signals of `policy`, and killed if it has not exited after the last one.
+    /// If the executable leads its own process group, the whole group is stopped.
+    /// If the executable already exited (e.g., it crashed), returns how it exited.
     /// If the executable has never been started, returns [None].
-    pub async fn kill(&mut self) -> io::Result<Option<ExitStatus>> {
+    pub async fn stop(
+        &mut self,
+        policy: StopPolicy,
+    ) -> io::Result<Option<ExitStatus>> {
         Ok(match &mut self.state {
             ExecutableState::Init { .. } => None,
             ExecutableState::Started { child, stdout, stderr, .. } => {
-                child.kill().await?;
-                let exit_status = child.wait().await?;
-                let _ = tokio::join!(stdout, stderr);
+                // The group is led by the pid until the executable is reaped below
+                let pid = child.id().map(|pid| Pid::from_raw(pid as i32));
+
+                let mut exit_status = None;
+                for (signal, grace_period) in policy.signals() {
+                    // The processes it started may outlive the executable. The group
+                    // includes the executable, which is signaled on its own otherwise.
+                    if let Some(pid) = pid {
+                        self.process_group.signal(pid, signal)?;
+                    }
+
+                    // exited before it was stopped (or escalated), nothing to wait for
+                    if let Some(status) = child.try_wait()? {
+                        exit_status = Some(status);
+                        break;
+                    }
+
+                    let own_group = self.process_group.is_own();
+                    if let (Some(pid), false) = (pid, own_group) {
+                        match kill(pid, signal) {
+                            Ok(()) | Err(Errno::ESRCH) => {}
+                            Err(e) => {
+                                return Err(io::Error::from_raw_os_error(
+                                    e as i32,
+                                ))
+                            }
+                        }
+                    }
+
+                    // There is nothing to escalate to after a SIGKILL
+                    if signal == Signal::SIGKILL {
+                        exit_status = Some(child.wait().await?);
+                        break;
+                    }
+
+                    if let Ok(status) =
+                        timeout(grace_period, child.wait()).await
+                    {
+                        exit_status = Some(status?);
+                        break;
+                    }
+
+                    warn!(
+                        executable_name = ?self.name,
+                        %signal,
+                        ?grace_period,
+                        "executable did not stop within its grace period"
+                    );
+                }
+
+                let exit_status = match exit_status {
+                    Some(exit_status) => exit_status,
+                    None => {
+                        warn!(
+                            executable_name = ?self.name,
+                            "executable did not stop after its last signal, killing it"
+                        );
+                        match pid {
+                            Some(pid) if self.process_group.is_own() => self
+                                .process_group
+                                .signal(pid, Signal::SIGKILL)?,
+                            _ => child.start_kill()?,
+                        }
+                        child.wait().await?
+                    }
+                };
+                drain_output(stdout, stderr).await;
                 self.state = ExecutableState::Stopped(exit_status);
                 Some(exit_status)
             }
@@ -118,6 +274,106 @@ impl Executable {
         })
     }
 
+    /// Returns the [ExitStatus] if the [Executable] has exited, without waiting for it.
+    /// Returns [None] while it is still running or if it was never started.
+    pub async fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Ok(match &mut self.state {
+            ExecutableState::Init { .. } => None,
+            ExecutableState::Started { child, stdout, stderr, .. } => {
+                let Some(exit_status) = child.try_wait()? else {
+                    return Ok(None);
+                };
+                drain_output(stdout, stderr).await;
+                self.state = ExecutableState::Stopped(exit_status);
+                Some(exit_status)
+            }
+            ExecutableState::Stopped(status) => Some(*status),
+        })
+    }
+
+    /// Returns true if the [Executable] has exited, like [Executable::try_wait], but
+    /// without reaping it, so it can be checked without exclusive access.
+    pub fn has_exited(&self) -> io::Result<bool> {
+        let pid = match &self.state {
+            ExecutableState::Init { .. } => return Ok(false),
+            ExecutableState::Started { child, .. } => match child.id() {
+                Some(pid) => pid,
+                // Reaped already
+                None => return Ok(true),
+            },
+            ExecutableState::Stopped(_) => return Ok(true),
+        };
+
+        // WNOWAIT leaves the child to be reaped by [Executable::try_wait]
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        if unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid,
+                &mut info,
+                libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+            )
+        } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The pid is only set if the child exited
+        Ok(unsafe { info.si_pid() } != 0)
+    }
+
+    /// Records that the [Executable] was restarted after exiting for `exit_reason`, carrying
+    /// over the [RestartStats] of the executable it replaces.
+    /// The first time the restart count crosses the threshold, a crash looping event is
+    /// pushed to its output, on the [EVENT_STREAM], where log streams observe it.
+    pub fn record_restart(
+        &mut self,
+        restart_stats: RestartStats,
+        exit_reason: String,
+    ) {
+        self.restart_stats = restart_stats;
+        if self.restart_stats.record(exit_reason) {
+            warn!(
+                executable_name = ?self.name,
+                restart_count = self.restart_stats.restart_count,
+                last_exit_reason = ?self.restart_stats.last_exit_reason,
+                "executable is crash looping"
+            );
+
+            let RestartStats { restart_count, last_exit_reason, .. } =
+                &self.restart_stats;
+            self.output_tail.push(
+                EVENT_STREAM,
+                &format!(
+                    "crash looping: restarted {restart_count} times, last exit: {}",
+                    last_exit_reason.as_deref().unwrap_or("unknown")
+                ),
+            );
+        }
+    }
+
+    /// Returns the [RestartStats] of the [Executable].
+    pub fn restart_stats(&self) -> &RestartStats {
+        &self.restart_stats
+    }
+
+    /// Returns up to `count` of the most recently captured output lines, oldest first.
+    /// Returns no lines unless the [Executable] was created with an output tail capacity.
+    pub fn output_tail(&self, count: usize) -> Vec<OutputLine> {
+        self.output_tail.last(count)
+    }
+
+    /// Returns the captured output lines, and subscribes to the lines written from now
+    /// on (see [OutputTail::subscribe]).
+    pub fn subscribe_output(&self) -> OutputSubscription {
+        self.output_tail.subscribe()
+    }
+
+    /// Returns the [ReadyLog] watching the output of the [Executable], if it has a ready log pattern.
+    pub fn ready_log(&self) -> Option<ReadyLog> {
+        self.ready_log.clone()
+    }
+
     /// Returns the [Pid] while [Executable] is running, otherwise returns [None].
     pub fn pid(&self) -> io::Result<Option<Pid>> {
         let ExecutableState::Started { child: process, .. } = &self.state else {
@@ -127,3 +383,14 @@ impl Executable {
         Ok(process.id().map(|id| Pid::from_raw(id as i32)))
     }
 }
+
+/// Waits for the output of an exited executable to be read until it is closed, for up
+/// to [OUTPUT_DRAIN_TIMEOUT]. Output written after that is still read in the background.
+async fn drain_output(
+    stdout: &mut JoinHandle<()>,
+    stderr: &mut JoinHandle<()>,
+) {
+    let _ =
+        timeout(OUTPUT_DRAIN_TIMEOUT, async { tokio::join!(stdout, stderr) })
+            .await;
+}
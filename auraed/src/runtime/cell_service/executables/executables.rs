@@ -29,7 +29,8 @@
 \* -------------------------------------------------------------------------- */
 
 use super::{
-    Executable, ExecutableName, ExecutableSpec, ExecutablesError, Result,
+    env_file, Executable, ExecutableName, ExecutableSpec, ExecutablesError,
+    OutputLine, RestartStats, Result, SeccompProfile, StopPolicy,
 };
 use std::collections::HashMap;
 use std::process::ExitStatus;
@@ -47,7 +48,7 @@ impl Executables {
         &mut self,
         executable_spec: T,
     ) -> Result<&Executable> {
-        let executable_spec = executable_spec.into();
+        let mut executable_spec = executable_spec.into();
 
         // TODO: replace with try_insert when it becomes stable
         // Check if there was already an executable with the same name.
@@ -57,6 +58,16 @@ impl Executables {
             });
         }
 
+        // Load before caching, so a bad env file doesn't leave the exe in the cache
+        executable_spec.load_env_file().map_err(|e| {
+            ExecutablesError::FailedToLoadEnvFile {
+                executable_name: executable_spec.name.clone(),
+                source: e,
+            }
+        })?;
+        executable_spec.check_working_dir()?;
+        executable_spec.load_seccomp_profile()?;
+
         let executable_name = executable_spec.name.clone();
         // `or_insert` will always insert as we've already assured ourselves that the key does not exist.
         let executable = self
@@ -66,46 +77,556 @@ impl Executables {
 
         // TODO: if we fail to start, the exe remains in the cache and start cannot be called again
         // solving ^^ was a borrow checker fight and I (future-highway) lost this round.
-        executable.start().map_err(|e| {
-            ExecutablesError::FailedToStartExecutable {
-                executable_name,
-                source: e,
-            }
-        })?;
+        executable.start().map_err(|e| start_error(executable_name, e))?;
 
         Ok(executable)
     }
 
-    pub async fn stop(
+    /// Returns the [Executable] with the given name, if it is in the cache.
+    pub fn get(&self, executable_name: &ExecutableName) -> Option<&Executable> {
+        self.cache.get(executable_name)
+    }
+
+    /// Records that the executable was restarted (see [Executable::record_restart]), and
+    /// returns its updated [RestartStats]. Returns [None] if it is not in the cache.
+    pub fn record_restart(
         &mut self,
         executable_name: &ExecutableName,
-    ) -> Result<ExitStatus> {
+        restart_stats: RestartStats,
+        exit_reason: String,
+    ) -> Option<RestartStats> {
+        let executable = self.cache.get_mut(executable_name)?;
+        executable.record_restart(restart_stats, exit_reason);
+        Some(executable.restart_stats().clone())
+    }
+
+    /// Returns all the [Executable]s in the cache.
+    pub fn list(&self) -> Vec<&Executable> {
+        self.cache.values().collect()
+    }
+
+    /// Checks that an [Executable] could be started from the spec, without starting it.
+    pub fn validate_start(
+        &self,
+        executable_spec: &ExecutableSpec,
+    ) -> Result<()> {
+        if self.cache.contains_key(&executable_spec.name) {
+            return Err(ExecutablesError::ExecutableExists {
+                executable_name: executable_spec.name.clone(),
+            });
+        }
+
+        if let Some(env_file) = &executable_spec.env_file {
+            let _ = env_file::read(env_file).map_err(|e| {
+                ExecutablesError::FailedToLoadEnvFile {
+                    executable_name: executable_spec.name.clone(),
+                    source: e,
+                }
+            })?;
+        }
+
+        if let Some(seccomp_profile) = &executable_spec.seccomp_profile {
+            let _ = SeccompProfile::load(seccomp_profile).map_err(|e| {
+                ExecutablesError::FailedToLoadSeccompProfile {
+                    executable_name: executable_spec.name.clone(),
+                    source: e,
+                }
+            })?;
+        }
+
+        executable_spec.check_working_dir()
+    }
+
+    /// Returns the [ExitStatus] of the executable if it has exited, without waiting for it.
+    /// The executable stays in the cache until it is stopped.
+    pub async fn try_wait(
+        &mut self,
+        executable_name: &ExecutableName,
+    ) -> Result<Option<ExitStatus>> {
         let Some(executable) = self.cache.get_mut(executable_name) else {
-            return Err(ExecutablesError::ExecutableNotFound { executable_name: executable_name.clone() });
+            return Err(ExecutablesError::ExecutableNotFound {
+                executable_name: executable_name.clone(),
+            });
         };
 
-        let exit_status = executable.kill().await.map_err(|e| {
-            ExecutablesError::FailedToStopExecutable {
+        executable.try_wait().await.map_err(|e| {
+            ExecutablesError::FailedToWaitForExecutable {
                 executable_name: executable_name.clone(),
                 source: e,
             }
-        })?;
+        })
+    }
+
+    /// Stops the executable as `stop_policy` says (see [Executable::stop]), and removes it
+    /// from the cache.
+    /// Returns the [ExitStatus] and up to `output_tail` of its most recently captured output lines.
+    pub async fn stop(
+        &mut self,
+        executable_name: &ExecutableName,
+        output_tail: usize,
+        stop_policy: StopPolicy,
+    ) -> Result<(ExitStatus, Vec<OutputLine>)> {
+        let executable = self.take(executable_name)?;
+        Self::stop_taken(executable, output_tail, stop_policy).await
+    }
+
+    /// Removes the executable from the cache, so it can be stopped without holding on to
+    /// the cache while it is given time to exit (see [Executables::stop_taken]).
+    pub fn take(
+        &mut self,
+        executable_name: &ExecutableName,
+    ) -> Result<Executable> {
+        self.cache.remove(executable_name).ok_or_else(|| {
+            ExecutablesError::ExecutableNotFound {
+                executable_name: executable_name.clone(),
+            }
+        })
+    }
+
+    /// Like [Executables::stop], for an executable already removed from the cache (see
+    /// [Executables::take]). If it fails to stop, it is killed, as it is no longer tracked.
+    pub async fn stop_taken(
+        mut executable: Executable,
+        output_tail: usize,
+        stop_policy: StopPolicy,
+    ) -> Result<(ExitStatus, Vec<OutputLine>)> {
+        let exit_status = match executable.stop(stop_policy).await {
+            Ok(exit_status) => exit_status,
+            Err(e) => {
+                let _best_effort = executable.kill().await;
+                return Err(ExecutablesError::FailedToStopExecutable {
+                    executable_name: executable.name,
+                    source: e,
+                });
+            }
+        };
 
         let Some(exit_status) = exit_status else {
             // Exes that never started return None
-            let executable = self.cache.remove(executable_name).expect("exe in cache");
             return Err(ExecutablesError::ExecutableNotFound {
                 executable_name: executable.name,
             });
         };
 
-        let _ = self.cache.remove(executable_name).ok_or_else(|| {
-            // get_mut would have already thrown this error, so we should never reach here
-            ExecutablesError::ExecutableNotFound {
-                executable_name: executable_name.clone(),
+        Ok((exit_status, executable.output_tail(output_tail)))
+    }
+
+    /// Stops the executables started in a generation older than `generation`, and removes
+    /// them from the cache. Executables started without a generation are left running.
+    /// Returns the names of the stopped executables, ordered by name.
+    pub async fn stop_generation(
+        &mut self,
+        generation: u64,
+    ) -> Result<Vec<ExecutableName>> {
+        let mut executable_names: Vec<ExecutableName> = self
+            .cache
+            .values()
+            .filter(|executable| {
+                executable.generation.map_or(false, |x| x < generation)
+            })
+            .map(|executable| executable.name.clone())
+            .collect();
+        executable_names.sort();
+
+        for executable_name in &executable_names {
+            match self.stop(executable_name, 0, StopPolicy::KILL).await {
+                // Exes that never started are removed from the cache all the same
+                Ok(_) | Err(ExecutablesError::ExecutableNotFound { .. }) => {}
+                Err(e) => return Err(e),
             }
-        })?;
+        }
+
+        Ok(executable_names)
+    }
+}
+
+/// Fork fails with EAGAIN once the cell's pids.max is reached, which is
+/// worth telling apart from any other failure to start.
+fn start_error(
+    executable_name: ExecutableName,
+    source: std::io::Error,
+) -> ExecutablesError {
+    if source.raw_os_error() == Some(libc::EAGAIN) {
+        ExecutablesError::ProcessLimitReached { executable_name, source }
+    } else {
+        ExecutablesError::FailedToStartExecutable { executable_name, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::{
+        executables::{restart_stats::CRASH_LOOP_THRESHOLD, EVENT_STREAM},
+        test_helpers::{command_spec, sleep_spec, temp_path},
+    };
+    use nix::{
+        sys::signal::{kill, Signal},
+        unistd::Pid,
+    };
+    use std::{
+        os::unix::process::ExitStatusExt,
+        time::{Duration, Instant},
+    };
+    use validation::ValidatedField;
+
+    #[tokio::test]
+    async fn test_stop_sends_signal() {
+        let mut executables = Executables::default();
+        let name = executables
+            .start(command_spec("ae-stop-int", "sleep 60"))
+            .expect("start")
+            .name
+            .clone();
+
+        let stop_policy = StopPolicy {
+            signal: Signal::SIGINT,
+            grace_period: Duration::from_secs(10),
+            escalation: vec![],
+        };
+        let (exit_status, _) =
+            executables.stop(&name, 0, stop_policy).await.expect("stop");
+        assert_eq!(exit_status.signal(), Some(libc::SIGINT));
+    }
+
+    #[tokio::test]
+    async fn test_stop_kills_executable_ignoring_signal_after_grace_period() {
+        let mut executables = Executables::default();
+        // ignored signals stay ignored across exec, so sleep ignores SIGTERM as well
+        let name = executables
+            .start(command_spec("ae-stop-term", "trap '' TERM; sleep 60"))
+            .expect("start")
+            .name
+            .clone();
+        // give the shell time to set up the trap
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stop_policy = StopPolicy {
+            signal: Signal::SIGTERM,
+            grace_period: Duration::from_millis(200),
+            escalation: vec![],
+        };
+        let started = Instant::now();
+        let (exit_status, _) = executables
+            .stop(&name, 0, stop_policy.clone())
+            .await
+            .expect("stop");
+
+        assert_eq!(exit_status.signal(), Some(libc::SIGKILL));
+        assert!(started.elapsed() >= stop_policy.grace_period);
+        assert!(executables.get(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stop_escalates_executable_ignoring_signal() {
+        let mut executables = Executables::default();
+        // sleep inherits the ignored SIGTERM, but not a handler for SIGINT
+        let name = executables
+            .start(command_spec(
+                "ae-stop-escalate",
+                "trap '' TERM; exec sleep 60",
+            ))
+            .expect("start")
+            .name
+            .clone();
+        // give the shell time to set up the trap
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stop_policy = StopPolicy {
+            signal: Signal::SIGTERM,
+            grace_period: Duration::from_millis(200),
+            escalation: vec![(Signal::SIGINT, Duration::from_secs(10))],
+        };
+        let started = Instant::now();
+        let (exit_status, _) = executables
+            .stop(&name, 0, stop_policy.clone())
+            .await
+            .expect("stop");
+
+        assert_eq!(exit_status.signal(), Some(libc::SIGINT));
+        assert!(started.elapsed() >= stop_policy.grace_period);
+        assert!(started.elapsed() < stop_policy.escalation[0].1);
+        assert!(executables.get(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_taken_executable_is_stopped_outside_of_the_cache() {
+        let mut executables = Executables::default();
+        let name = executables
+            .start(command_spec("ae-stop-taken", "trap '' TERM; sleep 60"))
+            .expect("start")
+            .name
+            .clone();
+        // give the shell time to set up the trap
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let executable = executables.take(&name).expect("take");
+        assert!(executables.get(&name).is_none());
+        assert!(matches!(
+            executables.take(&name),
+            Err(ExecutablesError::ExecutableNotFound { .. })
+        ));
+
+        let stop_policy = StopPolicy {
+            signal: Signal::SIGTERM,
+            grace_period: Duration::from_millis(200),
+            escalation: vec![],
+        };
+        let stop =
+            tokio::spawn(Executables::stop_taken(executable, 0, stop_policy));
+
+        // the name can be reused while the executable is still given time to exit
+        let _ = executables
+            .start(command_spec(&name, "sleep 60"))
+            .expect("start with the name of the stopping executable");
+
+        let (exit_status, _) = stop.await.expect("join").expect("stop");
+        assert_eq!(exit_status.signal(), Some(libc::SIGKILL));
+
+        let _ =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_stop_returns_status_of_exited_executable() {
+        let mut executables = Executables::default();
+        let name = executables
+            .start(command_spec("ae-stop-exited", "exit 3"))
+            .expect("start")
+            .name
+            .clone();
+        // exited, but not reaped until stopped
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stop_policy = StopPolicy {
+            signal: Signal::SIGTERM,
+            grace_period: Duration::from_secs(10),
+            escalation: vec![],
+        };
+        let started = Instant::now();
+        let (exit_status, _) = executables
+            .stop(&name, 0, stop_policy.clone())
+            .await
+            .expect("stop");
+
+        assert_eq!(exit_status.code(), Some(3));
+        assert!(started.elapsed() < stop_policy.grace_period);
+        assert!(executables.get(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_try_wait_does_not_wait_for_output_held_by_a_grandchild() {
+        let mut executables = Executables::default();
+        let mut spec = command_spec("ae-grandchild", "sleep 600 & echo $!");
+        spec.output_tail_capacity = 1;
+        let name = executables.start(spec).expect("start").name.clone();
+
+        let exit_status =
+            tokio::time::timeout(Duration::from_secs(10), async {
+                loop {
+                    if let Some(status) =
+                        executables.try_wait(&name).await.expect("wait")
+                    {
+                        return status;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("try_wait waited for the output held by the grandchild");
+        assert!(exit_status.success());
+
+        // the output written before the executable exited was read
+        let tail = executables.get(&name).expect("executable").output_tail(1);
+        let grandchild: i32 = tail[0].line.trim().parse().expect("pid");
+        kill(Pid::from_raw(grandchild), Signal::SIGKILL).expect("kill");
+        let _ =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_crash_loop_is_pushed_to_the_output() {
+        let mut executables = Executables::default();
+        let mut spec = sleep_spec("ae-crash-loop");
+        spec.output_tail_capacity = 10;
+        let name = executables.start(spec).expect("start").name.clone();
+        let subscription =
+            executables.get(&name).expect("executable").subscribe_output();
+        let mut live = subscription.live.expect("output is open");
+
+        let restart_stats = RestartStats {
+            restart_count: CRASH_LOOP_THRESHOLD - 2,
+            ..Default::default()
+        };
+        let restart_stats = executables
+            .record_restart(&name, restart_stats, "exit status: 1".into())
+            .expect("cached");
+        // not crash looping yet
+        assert!(executables
+            .get(&name)
+            .expect("executable")
+            .output_tail(10)
+            .is_empty());
+
+        let _ = executables
+            .record_restart(&name, restart_stats, "exit status: 2".into())
+            .expect("cached");
+        let event = live.recv().await.expect("event");
+        assert_eq!(event.stream, EVENT_STREAM);
+        assert_eq!(
+            event.line,
+            format!(
+                "crash looping: restarted {CRASH_LOOP_THRESHOLD} times, last exit: exit status: 2"
+            )
+        );
+        assert_eq!(
+            executables.get(&name).expect("executable").output_tail(10),
+            [event]
+        );
+
+        let _ =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_has_exited_does_not_reap() {
+        let mut executables = Executables::default();
+        let name = executables
+            .start(command_spec("ae-has-exited", "exit 3"))
+            .expect("start")
+            .name
+            .clone();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let executable = executables.get(&name).expect("executable");
+        assert!(executable.has_exited().expect("has exited"));
+        assert!(executable.pid().expect("pid").is_some());
+
+        let exit_status =
+            executables.try_wait(&name).await.expect("wait").expect("exited");
+        assert_eq!(exit_status.code(), Some(3));
+        let _ =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+    }
+
+    #[tokio::test]
+    async fn test_stop_generation_leaves_newer_executables_running() {
+        let mut executables = Executables::default();
+        for (name, generation) in [
+            ("ae-gen-1", Some(1)),
+            ("ae-gen-2", Some(2)),
+            ("ae-gen-3", Some(3)),
+            ("ae-gen-none", None),
+        ] {
+            let mut spec = sleep_spec(name);
+            spec.generation = generation;
+            let _ = executables.start(spec).expect("start");
+        }
+
+        let stopped =
+            executables.stop_generation(3).await.expect("stop generation");
+
+        let names: Vec<_> =
+            stopped.iter().map(|name| name.clone().into_inner()).collect();
+        assert_eq!(names, vec!["ae-gen-1", "ae-gen-2"]);
+
+        let mut running: Vec<_> = executables
+            .list()
+            .into_iter()
+            .map(|executable| executable.name.clone())
+            .collect();
+        running.sort();
+        for executable_name in &running {
+            assert_eq!(
+                executables.try_wait(executable_name).await.expect("wait"),
+                None
+            );
+        }
+        let running: Vec<_> =
+            running.into_iter().map(|name| name.into_inner()).collect();
+        assert_eq!(running, vec!["ae-gen-3", "ae-gen-none"]);
+
+        for executable_name in executables
+            .list()
+            .into_iter()
+            .map(|executable| executable.name.clone())
+            .collect::<Vec<_>>()
+        {
+            let _ = executables
+                .stop(&executable_name, 0, StopPolicy::KILL)
+                .await
+                .expect("stop");
+        }
+    }
+
+    #[test]
+    fn test_start_rejects_missing_seccomp_profile() {
+        let mut executables = Executables::default();
+        let mut spec = command_spec("ae-seccomp-missing", "true");
+        spec.seccomp_profile = Some(temp_path("seccomp-missing"));
+
+        assert!(matches!(
+            executables.start(spec),
+            Err(ExecutablesError::FailedToLoadSeccompProfile { .. })
+        ));
+        assert!(executables.list().is_empty());
+    }
+
+    // Ignored: requires sudo, which we don't have in CI
+    #[ignore]
+    #[tokio::test]
+    async fn test_seccomp_profile_is_installed() {
+        let names = if cfg!(target_arch = "x86_64") {
+            r#"["mkdir", "mkdirat"]"#
+        } else {
+            r#"["mkdirat"]"#
+        };
+        let profile = temp_path("seccomp");
+        std::fs::write(
+            &profile,
+            format!(
+                r#"{{
+                    "defaultAction": "SCMP_ACT_ALLOW",
+                    "syscalls": [{{"names": {names}, "action": "SCMP_ACT_ERRNO"}}]
+                }}"#
+            ),
+        )
+        .expect("write profile");
+        let dir = temp_path("seccomp");
+
+        let mut executables = Executables::default();
+        let mut spec = command_spec(
+            "ae-seccomp",
+            &format!("mkdir {}", dir.to_string_lossy()),
+        );
+        spec.seccomp_profile = Some(profile.clone());
+        let name = executables.start(spec).expect("start").name.clone();
+        std::fs::remove_file(&profile).expect("remove profile");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let (exit_status, _) =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+        assert_eq!(exit_status.code(), Some(1));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_start_error_reports_process_limit() {
+        let name =
+            ExecutableName::validate(Some("ae-exe".into()), "name", None)
+                .unwrap();
+
+        let err = start_error(
+            name.clone(),
+            std::io::Error::from_raw_os_error(libc::EAGAIN),
+        );
+        assert!(matches!(err, ExecutablesError::ProcessLimitReached { .. }));
 
-        Ok(exit_status)
+        let err =
+            start_error(name, std::io::Error::from_raw_os_error(libc::ENOENT));
+        assert!(matches!(
+            err,
+            ExecutablesError::FailedToStartExecutable { .. }
+        ));
     }
 }
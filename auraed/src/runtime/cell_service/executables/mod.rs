@@ -28,20 +28,501 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
+use super::pre_exec::PreExecHooks;
+pub use capabilities::{drop_from_bounding_set, Capability};
+pub use dependencies::{PendingStart, PendingStarts};
 pub use error::{ExecutablesError, Result};
 pub use executable::Executable;
 pub use executable_name::ExecutableName;
 pub use executables::Executables;
+use fancy_regex::Regex;
+pub use open_fds::{open_fds, OpenFd};
+pub use output_framing::{
+    OutputFraming, DEFAULT_MAX_LINE_LENGTH, MAX_FRAME_LENGTH, SPLIT_MARKER,
+};
+pub use output_tail::{
+    OutputLine, OutputSubscription, OutputTail, EVENT_STREAM,
+    MAX_OUTPUT_TAIL_CAPACITY,
+};
+pub use placement::verify_placement;
+pub use process_group::ProcessGroup;
+pub use process_title::ProcessTitle;
+pub use ready_log::{Readiness, ReadyLog};
+pub use ready_port::is_port_listening;
+pub use restart_stats::RestartStats;
+pub use seccomp_profile::SeccompProfile;
+pub use sigpipe::Sigpipe;
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    path::{Path, PathBuf},
+};
+pub use stop_policy::{
+    StopPolicy, DEFAULT_GRACE_PERIOD, MAX_STOP_ESCALATION, MAX_STOP_WAIT,
+    STOP_SIGNALS,
+};
 use tokio::process::Command;
 
+mod capabilities;
+mod dependencies;
+mod env_file;
 mod error;
 mod executable;
 mod executable_name;
 #[allow(clippy::module_inception)]
 mod executables;
+mod open_fds;
+mod output_framing;
+mod output_tail;
+mod placement;
+mod process_group;
+mod process_title;
+mod ready_log;
+mod ready_port;
+mod restart_stats;
+mod seccomp_profile;
+mod sigpipe;
+mod stop_policy;
 
 pub struct ExecutableSpec {
     pub name: ExecutableName,
     pub description: String,
+    /// The command as provided by the user, before any wrapping.
+    pub original_command: OsString,
     pub command: Command,
+    /// A dotenv formatted file on the host, loaded into the environment at start.
+    pub env_file: Option<PathBuf>,
+    /// The directory the process is started in, `/` if not set.
+    pub working_dir: Option<PathBuf>,
+    /// Replaces argv[0] and the name (comm) of the process, if set.
+    pub process_title: Option<ProcessTitle>,
+    /// Number of recent output lines to keep, 0 to disable capture.
+    pub output_tail_capacity: u32,
+    /// How stdout and stderr are split into lines.
+    pub output_framing: OutputFraming,
+    /// The disposition of SIGPIPE the process is started with.
+    pub sigpipe: Sigpipe,
+    /// Pattern of the output line that marks the process as ready, if any.
+    /// This is set from the start request rather than the executable.
+    pub ready_log_pattern: Option<Regex>,
+    /// The process group the process is started in.
+    /// This is set from the start request rather than the executable.
+    pub process_group: ProcessGroup,
+    /// The generation the executable was started in, if any, to stop a previous
+    /// generation at once (see [Executables::stop_generation]).
+    /// This is set from the start request rather than the executable.
+    pub generation: Option<u64>,
+    /// An OCI style seccomp profile on the host, loaded at start (see
+    /// [SeccompProfile]).
+    pub seccomp_profile: Option<PathBuf>,
+    /// Steps run in the child between fork and exec, in order.
+    pub pre_exec_hooks: PreExecHooks,
+}
+
+impl ExecutableSpec {
+    /// Returns the argv the process will be spawned with, including the program.
+    pub fn argv(&self) -> Vec<OsString> {
+        let command = self.command.as_std();
+        let arg0 = match &self.process_title {
+            Some(process_title) => process_title.title(),
+            None => command.get_program(),
+        };
+
+        std::iter::once(arg0)
+            .chain(command.get_args())
+            .map(|arg| arg.to_os_string())
+            .collect()
+    }
+
+    /// Returns the program the process will be spawned with.
+    /// With a title, the command executes it through a symlink (see [ProcessTitle]).
+    pub fn program(&self) -> &OsStr {
+        match &self.process_title {
+            Some(process_title) => process_title.program(),
+            None => self.command.as_std().get_program(),
+        }
+    }
+
+    /// Loads the env file (if any) into the environment of the command.
+    /// Variables already set on the command take precedence over the file.
+    pub fn load_env_file(&mut self) -> io::Result<()> {
+        let Some(env_file) = &self.env_file else {
+            return Ok(());
+        };
+
+        for (key, value) in env_file::read(env_file)? {
+            let is_set = self
+                .command
+                .as_std()
+                .get_envs()
+                .any(|(existing, _)| existing == key.as_str());
+
+            if !is_set {
+                let _ = self.command.env(key, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the seccomp profile (if any), and adds a hook to install it in the child.
+    /// The hook must be the last one added, so the rest of the setup of the child isn't
+    /// filtered. If installing the filter fails, the child exits instead of exec'ing.
+    pub fn load_seccomp_profile(&mut self) -> Result<()> {
+        let Some(seccomp_profile) = &self.seccomp_profile else {
+            return Ok(());
+        };
+
+        let profile = SeccompProfile::load(seccomp_profile).map_err(|e| {
+            ExecutablesError::FailedToLoadSeccompProfile {
+                executable_name: self.name.clone(),
+                source: e,
+            }
+        })?;
+        self.pre_exec_hooks.push("seccomp", move || profile.apply());
+
+        Ok(())
+    }
+
+    /// Checks that the working directory (if any) is a directory we can open.
+    pub fn check_working_dir(&self) -> Result<()> {
+        let Some(working_dir) = &self.working_dir else {
+            return Ok(());
+        };
+
+        match std::fs::read_dir(working_dir) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ExecutablesError::FailedToOpenWorkingDir {
+                executable_name: self.name.clone(),
+                working_dir: working_dir.clone(),
+                source: e,
+            }),
+        }
+    }
+
+    /// Checks that the program the process will be spawned with can be found.
+    /// PATH is taken from the command's environment, falling back to our own.
+    pub fn check_command_exists(&self) -> Result<()> {
+        let program = self.program();
+        let path = path(self.command.as_std());
+
+        match find_program(program, path.as_deref()) {
+            Some(_) => Ok(()),
+            None => Err(ExecutablesError::CommandNotFound {
+                executable_name: self.name.clone(),
+                program: program.to_os_string(),
+            }),
+        }
+    }
+}
+
+/// Returns the PATH of `command`, falling back to our own.
+fn path(command: &std::process::Command) -> Option<OsString> {
+    command
+        .get_envs()
+        .find(|(key, _)| *key == "PATH")
+        .map(|(_, value)| value.map(OsString::from))
+        .unwrap_or_else(|| std::env::var_os("PATH"))
+}
+
+/// Resolves a program the same way exec does: as a path if it contains a '/',
+/// otherwise by searching each directory of `path` in order.
+fn find_program(program: &OsStr, path: Option<&OsStr>) -> Option<PathBuf> {
+    if program.as_bytes().contains(&b'/') {
+        let program = Path::new(program);
+        return is_executable(program).then(|| program.to_path_buf());
+    }
+
+    std::env::split_paths(path?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata().map_or(false, |metadata| {
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::{
+        command_spec, executable, temp_dir, temp_path,
+    };
+    use crate::runtime::cell_service::validation::ValidatedExecutable;
+    use std::collections::HashMap;
+    use validation::{ValidatedType, ValidationError};
+
+    #[test]
+    fn test_argv_wraps_command_in_shell() {
+        let spec = command_spec("sample", "echo 'hello world' | tr a-z A-Z");
+
+        assert_eq!(
+            spec.original_command,
+            OsString::from("echo 'hello world' | tr a-z A-Z")
+        );
+        assert_eq!(
+            spec.argv(),
+            vec![
+                OsString::from("sh"),
+                OsString::from("-c"),
+                OsString::from("echo 'hello world' | tr a-z A-Z"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_args_are_passed_without_shell() {
+        let arg = r#"it's "quoted" with  spaces; $HOME"#;
+        let executable = aurae_proto::runtime::Executable {
+            name: "sample".into(),
+            command: "printf".into(),
+            args: vec!["%s".into(), arg.into()],
+            ..Default::default()
+        };
+        let mut spec: ExecutableSpec =
+            ValidatedExecutable::validate(executable, None).unwrap().into();
+
+        assert_eq!(
+            spec.argv(),
+            vec![
+                OsString::from("printf"),
+                OsString::from("%s"),
+                OsString::from(arg),
+            ]
+        );
+
+        let output = spec.command.output().await.expect("run printf");
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).expect("utf-8 output"),
+            arg
+        );
+    }
+
+    #[tokio::test]
+    async fn test_working_dir_is_applied() {
+        let pwd = |working_dir: &str| {
+            let executable = aurae_proto::runtime::Executable {
+                name: "sample".into(),
+                command: "pwd".into(),
+                working_dir: working_dir.into(),
+                ..Default::default()
+            };
+            ExecutableSpec::from(
+                ValidatedExecutable::validate(executable, None).unwrap(),
+            )
+        };
+
+        let dir = temp_dir("working-dir");
+        let mut spec = pwd(dir.to_str().expect("utf-8 path"));
+        assert!(spec.check_working_dir().is_ok());
+        let output = spec.command.output().await.expect("run pwd");
+        std::fs::remove_dir(&dir).expect("remove dir");
+        assert_eq!(
+            String::from_utf8(output.stdout).expect("utf-8 output").trim_end(),
+            dir.to_str().expect("utf-8 path")
+        );
+
+        // a missing working dir passes validation, but is detected before starting
+        assert!(matches!(
+            pwd(dir.to_str().expect("utf-8 path")).check_working_dir(),
+            Err(ExecutablesError::FailedToOpenWorkingDir { working_dir, .. })
+                if working_dir == dir
+        ));
+
+        // / by default
+        let output = pwd("").command.output().await.expect("run pwd");
+        assert_eq!(output.stdout, b"/\n");
+    }
+
+    #[test]
+    fn test_relative_working_dir_is_rejected() {
+        let executable = aurae_proto::runtime::Executable {
+            name: "sample".into(),
+            command: "pwd".into(),
+            working_dir: "app".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ValidatedExecutable::validate(executable, None),
+            Err(ValidationError::Invalid { field }) if field == "working_dir"
+        ));
+    }
+
+    #[test]
+    fn test_process_title_replaces_argv0() {
+        let executable = aurae_proto::runtime::Executable {
+            name: "sample".into(),
+            command: "sleep 60".into(),
+            process_title: "aurae-sample-worker-with-a-long-title".into(),
+            ..Default::default()
+        };
+        let spec: ExecutableSpec =
+            ValidatedExecutable::validate(executable, None).unwrap().into();
+
+        // argv[0] is not limited to the 15 bytes of comm
+        assert_eq!(
+            spec.argv(),
+            vec![
+                OsString::from("aurae-sample-worker-with-a-long-title"),
+                OsString::from("-c"),
+                OsString::from("sleep 60"),
+            ]
+        );
+        // the program that is executed is still the shell, through a symlink
+        // named after the title (see [ProcessTitle])
+        assert_eq!(spec.program(), "sh");
+        assert_eq!(
+            Path::new(spec.command.as_std().get_program()).file_name(),
+            Some(OsStr::new("aurae-sample-wo"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_title_sets_comm() {
+        let executable = aurae_proto::runtime::Executable {
+            name: "sample".into(),
+            command: "cat".into(),
+            args: vec!["/proc/self/comm".into(), "/proc/self/cmdline".into()],
+            process_title: "aurae-sample-worker-with-a-long-title".into(),
+            ..Default::default()
+        };
+        let mut spec: ExecutableSpec =
+            ValidatedExecutable::validate(executable, None).unwrap().into();
+
+        let process_title = spec.process_title.clone().expect("process title");
+        let link = process_title.link(spec.command.as_std()).expect("link");
+        let output = spec.command.output().await.expect("run cat");
+        drop(link);
+        assert!(output.status.success());
+
+        // comm is truncated to 15 bytes, argv[0] is not
+        let output = String::from_utf8(output.stdout).expect("utf-8 output");
+        let (comm, cmdline) = output.split_once('\n').expect("comm line");
+        assert_eq!(comm, "aurae-sample-wo");
+        assert_eq!(
+            cmdline.split('\0').next(),
+            Some("aurae-sample-worker-with-a-long-title")
+        );
+    }
+
+    #[test]
+    fn test_process_title_with_nul_is_rejected() {
+        let executable = aurae_proto::runtime::Executable {
+            name: "sample".into(),
+            command: "sleep 60".into(),
+            process_title: "aurae\0sample".into(),
+            ..Default::default()
+        };
+        assert!(ValidatedExecutable::validate(executable, None).is_err());
+    }
+
+    #[test]
+    fn test_env_with_invalid_key_is_rejected() {
+        for key in ["", "KEY=VALUE"] {
+            let executable = aurae_proto::runtime::Executable {
+                name: "sample".into(),
+                command: "sleep 60".into(),
+                env: HashMap::from([(key.to_string(), "value".to_string())]),
+                ..Default::default()
+            };
+            assert!(ValidatedExecutable::validate(executable, None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_check_command_exists_detects_missing_program() {
+        let mut spec = command_spec("sample", "true");
+
+        // the shell that wraps the command is on PATH
+        assert!(spec.check_command_exists().is_ok());
+
+        spec.command = Command::new(format!(
+            "aurae-missing-program-{}",
+            uuid::Uuid::new_v4()
+        ));
+        assert!(matches!(
+            spec.check_command_exists(),
+            Err(ExecutablesError::CommandNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_program_requires_executable_file() {
+        let dir = temp_path("path");
+        std::fs::create_dir_all(dir.join("subdir")).expect("create dir");
+        std::fs::write(dir.join("not-executable"), "").expect("write file");
+        std::fs::write(dir.join("executable"), "").expect("write file");
+        std::fs::set_permissions(
+            dir.join("executable"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .expect("set permissions");
+
+        let path = Some(dir.as_os_str());
+        let found = find_program(OsStr::new("executable"), path);
+        let not_executable = find_program(OsStr::new("not-executable"), path);
+        let directory = find_program(OsStr::new("subdir"), path);
+        let absolute = find_program(dir.join("executable").as_os_str(), None);
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+
+        assert_eq!(found, Some(dir.join("executable")));
+        assert_eq!(not_executable, None);
+        assert_eq!(directory, None);
+        assert_eq!(absolute, Some(dir.join("executable")));
+    }
+
+    #[test]
+    fn test_inline_env_takes_precedence_over_env_file() {
+        let env_file = temp_path("env");
+        std::fs::write(&env_file, "# comment\nSHARED=file\nFILE_ONLY=file\n")
+            .expect("write env file");
+
+        let mut spec: ExecutableSpec = ValidatedExecutable {
+            env: HashMap::from([("SHARED".to_string(), "inline".to_string())]),
+            env_file: Some(env_file.clone()),
+            ..executable("sample", "env")
+        }
+        .into();
+
+        spec.load_env_file().expect("load env file");
+        std::fs::remove_file(&env_file).expect("remove env file");
+
+        let envs: HashMap<_, _> = spec
+            .command
+            .as_std()
+            .get_envs()
+            .map(|(key, value)| (key.to_os_string(), value.map(OsString::from)))
+            .collect();
+
+        assert_eq!(envs.len(), 2);
+        assert_eq!(envs[&OsString::from("SHARED")], Some("inline".into()));
+        assert_eq!(envs[&OsString::from("FILE_ONLY")], Some("file".into()));
+    }
+
+    #[tokio::test]
+    async fn test_env_is_only_inherited_if_opted_in() {
+        let printenv = |inherit_env| async move {
+            let executable = aurae_proto::runtime::Executable {
+                name: "sample".into(),
+                command: "printenv PATH".into(),
+                inherit_env,
+                ..Default::default()
+            };
+            let mut spec: ExecutableSpec =
+                ValidatedExecutable::validate(executable, None).unwrap().into();
+            let output = spec.command.output().await.expect("run printenv");
+            String::from_utf8(output.stdout).expect("utf-8 output")
+        };
+
+        // the shell has a default PATH, but doesn't export it
+        assert_eq!(printenv(false).await, "");
+        assert_eq!(
+            printenv(true).await.trim_end(),
+            std::env::var("PATH").expect("PATH is set")
+        );
+    }
 }
@@ -0,0 +1,162 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Lists the open file descriptors of a running process, to debug leaked fds.
+//!
+//! The fds are read from `/proc/<pid>/fd`, while the process keeps running. An fd
+//! closed between listing the directory and reading its link is left out, and an fd
+//! whose link can't be read is listed without a target.
+
+use super::{ExecutableName, ExecutablesError, Result};
+use std::{fs, io, path::Path};
+
+const PROC: &str = "/proc";
+
+/// An open file descriptor of a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenFd {
+    pub fd: i32,
+    /// What the fd refers to (e.g., a path, or "socket:[12345]").
+    /// Empty if it could not be read.
+    pub target: String,
+}
+
+impl From<OpenFd> for aurae_proto::runtime::OpenFd {
+    fn from(value: OpenFd) -> Self {
+        let OpenFd { fd, target } = value;
+        Self { fd, target }
+    }
+}
+
+/// Returns the open fds of the process of an executable, ordered by fd.
+pub fn open_fds(
+    executable_name: &ExecutableName,
+    pid: i32,
+) -> Result<Vec<OpenFd>> {
+    open_fds_in(Path::new(PROC), executable_name, pid)
+}
+
+fn open_fds_in(
+    proc: &Path,
+    executable_name: &ExecutableName,
+    pid: i32,
+) -> Result<Vec<OpenFd>> {
+    let fd_dir = proc.join(pid.to_string()).join("fd");
+
+    let to_error = |source: io::Error| match source.kind() {
+        // exited since we got its pid
+        io::ErrorKind::NotFound => ExecutablesError::ExecutableNotRunning {
+            executable_name: executable_name.clone(),
+        },
+        _ => ExecutablesError::FailedToListOpenFds {
+            executable_name: executable_name.clone(),
+            source,
+        },
+    };
+
+    let mut fds = vec![];
+    for entry in fs::read_dir(&fd_dir).map_err(to_error)? {
+        let entry = entry.map_err(to_error)?;
+        let Some(fd) = entry.file_name().to_str().and_then(|x| x.parse().ok())
+        else {
+            continue;
+        };
+
+        let target = match fs::read_link(entry.path()) {
+            Ok(target) => target.to_string_lossy().into_owned(),
+            // closed since we listed it
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(_) => String::new(),
+        };
+
+        fds.push(OpenFd { fd, target });
+    }
+
+    fds.sort_by_key(|x| x.fd);
+
+    Ok(fds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+    use validation::ValidatedField;
+
+    /// A fake /proc with the fds of process 42.
+    fn fake_proc(fds: &[(&str, &str)]) -> PathBuf {
+        let proc = temp_path("proc");
+        let fd_dir = proc.join("42").join("fd");
+        fs::create_dir_all(&fd_dir).expect("create dir");
+        for (fd, target) in fds {
+            symlink(target, fd_dir.join(fd)).expect("create symlink");
+        }
+        proc
+    }
+
+    fn name() -> ExecutableName {
+        ExecutableName::validate(Some("sample".into()), "name", None).unwrap()
+    }
+
+    #[test]
+    fn test_open_fds_are_listed_in_order() {
+        let proc = fake_proc(&[
+            ("10", "socket:[12345]"),
+            ("2", "/dev/pts/0"),
+            ("0", "/dev/null"),
+            ("not-an-fd", "/etc/passwd"),
+        ]);
+        let res = open_fds_in(&proc, &name(), 42);
+        fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert_eq!(
+            res.expect("open fds"),
+            vec![
+                OpenFd { fd: 0, target: "/dev/null".into() },
+                OpenFd { fd: 2, target: "/dev/pts/0".into() },
+                OpenFd { fd: 10, target: "socket:[12345]".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exited_process_is_not_running() {
+        let proc = fake_proc(&[("0", "/dev/null")]);
+        let res = open_fds_in(&proc, &name(), 43);
+        fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert!(matches!(
+            res,
+            Err(ExecutablesError::ExecutableNotRunning { .. })
+        ));
+    }
+}
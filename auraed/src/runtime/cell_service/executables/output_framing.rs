@@ -0,0 +1,192 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// Default maximum length of a line, in bytes (see [OutputFraming::Lines]).
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Maximum length of a frame an [Executable] may be configured with, in bytes.
+///
+/// [Executable]: super::Executable
+pub const MAX_FRAME_LENGTH: usize = 1024 * 1024;
+
+/// Appended to the parts of a line that was split for being too long.
+pub const SPLIT_MARKER: &str = " [...]";
+
+/// How the stdout or stderr of an [Executable] is split into the frames (lines) that
+/// are logged, kept in the [OutputTail] and matched against the [ReadyLog].
+///
+/// Frames are bounded in length, so output without newlines can't be buffered
+/// without limit. Invalid UTF-8 is replaced with U+FFFD.
+///
+/// [Executable]: super::Executable
+/// [OutputTail]: super::OutputTail
+/// [ReadyLog]: super::ReadyLog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFraming {
+    /// Split on newlines. Lines longer than `max_length` bytes are split, and the
+    /// parts that were cut short end with [SPLIT_MARKER].
+    Lines { max_length: usize },
+    /// Not split on newlines: the output is passed on as it is read, in chunks of
+    /// at most `size` bytes. For programs with binary output.
+    Chunks { size: usize },
+}
+
+impl Default for OutputFraming {
+    fn default() -> Self {
+        Self::Lines { max_length: DEFAULT_MAX_LINE_LENGTH }
+    }
+}
+
+impl OutputFraming {
+    /// Reads `reader` until the end (or an error), calling `on_frame` with each frame.
+    pub async fn read<R, F>(self, reader: R, mut on_frame: F)
+    where
+        R: AsyncRead + Unpin,
+        F: FnMut(String),
+    {
+        let mut reader = BufReader::new(reader);
+        let mut frame = vec![];
+
+        loop {
+            let (consumed, end_of_line) = {
+                let buf = match reader.fill_buf().await {
+                    Ok(buf) if !buf.is_empty() => buf,
+                    _ => break,
+                };
+
+                match self {
+                    Self::Lines { max_length } => {
+                        let available = max_length - frame.len();
+                        // a newline right after a full line does not split it
+                        let newline = buf
+                            .iter()
+                            .take(available + 1)
+                            .position(|b| *b == b'\n');
+                        match newline {
+                            Some(i) => {
+                                frame.extend_from_slice(&buf[..i]);
+                                (i + 1, true)
+                            }
+                            None => {
+                                let n = buf.len().min(available);
+                                frame.extend_from_slice(&buf[..n]);
+                                (n, false)
+                            }
+                        }
+                    }
+                    Self::Chunks { size } => {
+                        let n = buf.len().min(size);
+                        frame.extend_from_slice(&buf[..n]);
+                        (n, true)
+                    }
+                }
+            };
+            reader.consume(consumed);
+
+            match self {
+                Self::Lines { .. } if end_of_line => {
+                    if frame.last() == Some(&b'\r') {
+                        let _ = frame.pop();
+                    }
+                    on_frame(take_frame(&mut frame));
+                }
+                Self::Lines { max_length } if frame.len() == max_length => {
+                    on_frame(take_frame(&mut frame) + SPLIT_MARKER);
+                }
+                Self::Lines { .. } => {}
+                Self::Chunks { .. } => on_frame(take_frame(&mut frame)),
+            }
+        }
+
+        // the last line may not end with a newline
+        if !frame.is_empty() {
+            on_frame(take_frame(&mut frame));
+        }
+    }
+}
+
+fn take_frame(frame: &mut Vec<u8>) -> String {
+    let text = String::from_utf8_lossy(frame).into_owned();
+    frame.clear();
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn frames(framing: OutputFraming, output: &[u8]) -> Vec<String> {
+        let mut frames = vec![];
+        framing.read(output, |frame| frames.push(frame)).await;
+        frames
+    }
+
+    #[tokio::test]
+    async fn test_lines() {
+        let framing = OutputFraming::default();
+
+        assert_eq!(
+            frames(framing, b"first\r\nsecond\n\nlast").await,
+            ["first", "second", "", "last"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_newline_is_split() {
+        let framing = OutputFraming::Lines { max_length: 4 };
+
+        assert_eq!(
+            frames(framing, b"abcdefghij").await,
+            ["abcd [...]", "efgh [...]", "ij"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_over_length_line_is_split() {
+        let framing = OutputFraming::Lines { max_length: 4 };
+
+        assert_eq!(
+            frames(framing, b"abcdef\nabcd\nab\n").await,
+            ["abcd [...]", "ef", "abcd", "ab"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chunks() {
+        let framing = OutputFraming::Chunks { size: 4 };
+
+        assert_eq!(
+            frames(framing, b"ab\ncdef\xffg").await,
+            ["ab\nc", "def\u{FFFD}", "g"]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+
+/// Maximum number of lines an [Executable] may keep in its [OutputTail].
+///
+/// [Executable]: super::Executable
+pub const MAX_OUTPUT_TAIL_CAPACITY: u32 = 10_000;
+
+/// Number of lines a subscriber may fall behind before it misses lines.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// The stream of the lines auraed reports events of an [Executable] on (e.g., that it is
+/// crash looping), in between the lines it writes.
+///
+/// [Executable]: super::Executable
+pub const EVENT_STREAM: &str = "event";
+
+/// A line written by an [Executable] to stdout or stderr, or an event of it.
+///
+/// [Executable]: super::Executable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    /// Either "stdout", "stderr" or [EVENT_STREAM].
+    pub stream: &'static str,
+    pub line: String,
+}
+
+impl From<OutputLine> for aurae_proto::runtime::OutputLine {
+    fn from(value: OutputLine) -> Self {
+        let OutputLine { stream, line } = value;
+        Self { stream: stream.into(), line }
+    }
+}
+
+/// Ring buffer of the most recent output lines of an [Executable], which also
+/// passes each line on to subscribers.
+///
+/// Clones share the same buffer, so the tasks reading stdout and stderr can
+/// each push into their own [OutputWriter].
+/// A capacity of 0 disables capture, but lines are still passed on to subscribers.
+///
+/// [Executable]: super::Executable
+#[derive(Debug, Clone)]
+pub struct OutputTail {
+    capacity: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    lines: VecDeque<OutputLine>,
+    /// [None] once every [OutputWriter] is dropped, which ends the subscriptions.
+    subscribers: Option<broadcast::Sender<OutputLine>>,
+    writers: usize,
+}
+
+/// The lines of an [OutputTail] at the time of subscribing, and the lines pushed since.
+#[derive(Debug)]
+pub struct OutputSubscription {
+    pub buffered: Vec<OutputLine>,
+    /// [None] if the output was already closed.
+    pub live: Option<broadcast::Receiver<OutputLine>>,
+}
+
+/// Pushes the lines of one stream of output (e.g., stdout) into an [OutputTail].
+/// The output is closed once every writer is dropped.
+#[derive(Debug)]
+pub struct OutputWriter {
+    tail: OutputTail,
+}
+
+impl Default for OutputTail {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl OutputTail {
+    pub fn new(capacity: u32) -> Self {
+        let capacity = capacity as usize;
+        let (subscribers, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(Inner {
+                lines: VecDeque::with_capacity(capacity),
+                subscribers: Some(subscribers),
+                writers: 0,
+            })),
+        }
+    }
+
+    /// Returns a writer for one stream of output. Subscriptions end once every writer
+    /// is dropped.
+    pub fn writer(&self) -> OutputWriter {
+        self.inner.lock().expect("output tail lock").writers += 1;
+        OutputWriter { tail: self.clone() }
+    }
+
+    /// Appends a line, dropping the oldest line if the buffer is full, and passes it on
+    /// to the subscribers.
+    pub fn push(&self, stream: &'static str, line: &str) {
+        let line = OutputLine { stream, line: line.to_string() };
+
+        let mut inner = self.inner.lock().expect("output tail lock");
+        if let Some(subscribers) = &inner.subscribers {
+            // send returns an Err if there are no subscribers. We ignore that.
+            let _ = subscribers.send(line.clone());
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if inner.lines.len() == self.capacity {
+            let _ = inner.lines.pop_front();
+        }
+        inner.lines.push_back(line);
+    }
+
+    /// Returns up to `count` of the most recent lines, oldest first.
+    pub fn last(&self, count: usize) -> Vec<OutputLine> {
+        let inner = self.inner.lock().expect("output tail lock");
+        let skip = inner.lines.len().saturating_sub(count);
+        inner.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// Returns the buffered lines, and subscribes to the lines pushed from now on.
+    /// Both are taken under the same lock, so no line is missed or repeated.
+    pub fn subscribe(&self) -> OutputSubscription {
+        let inner = self.inner.lock().expect("output tail lock");
+        OutputSubscription {
+            buffered: inner.lines.iter().cloned().collect(),
+            live: inner.subscribers.as_ref().map(|x| x.subscribe()),
+        }
+    }
+}
+
+impl OutputWriter {
+    pub fn push(&self, stream: &'static str, line: &str) {
+        self.tail.push(stream, line)
+    }
+}
+
+impl Drop for OutputWriter {
+    fn drop(&mut self) {
+        let mut inner = self.tail.inner.lock().expect("output tail lock");
+        inner.writers -= 1;
+        if inner.writers == 0 {
+            inner.subscribers = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(tail: &[OutputLine]) -> Vec<&str> {
+        tail.iter().map(|x| x.line.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tail_keeps_most_recent_lines() {
+        let tail = OutputTail::new(3);
+        for i in 0..5 {
+            tail.push("stdout", &format!("line {i}"));
+        }
+
+        assert_eq!(lines(&tail.last(10)), vec!["line 2", "line 3", "line 4"]);
+    }
+
+    #[test]
+    fn test_tail_respects_requested_count() {
+        let tail = OutputTail::new(10);
+        tail.push("stdout", "first");
+        tail.push("stderr", "second");
+        tail.push("stdout", "third");
+
+        assert_eq!(lines(&tail.last(2)), vec!["second", "third"]);
+        assert_eq!(tail.last(2)[0].stream, "stderr");
+        assert!(tail.last(0).is_empty());
+    }
+
+    #[test]
+    fn test_clones_share_the_buffer() {
+        let tail = OutputTail::new(2);
+        let stderr = tail.clone();
+        tail.push("stdout", "out");
+        stderr.push("stderr", "err");
+
+        assert_eq!(lines(&tail.last(2)), vec!["out", "err"]);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_capture() {
+        let tail = OutputTail::new(0);
+        tail.push("stdout", "ignored");
+
+        assert!(tail.last(10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_buffered_and_live_lines() {
+        let tail = OutputTail::new(10);
+        let writer = tail.writer();
+        writer.push("stdout", "before");
+
+        let mut first = tail.subscribe();
+        let mut second = tail.subscribe();
+        writer.push("stderr", "after");
+        drop(writer);
+
+        for subscription in [&mut first, &mut second] {
+            assert_eq!(lines(&subscription.buffered), vec!["before"]);
+            let live = subscription.live.as_mut().expect("output is open");
+            let line = live.recv().await.expect("live line");
+            assert_eq!((line.stream, line.line.as_str()), ("stderr", "after"));
+            // the writer was dropped, so the output is closed
+            assert!(live.recv().await.is_err());
+        }
+    }
+
+    #[test]
+    fn test_subscribing_to_closed_output() {
+        let tail = OutputTail::new(10);
+        let stdout = tail.writer();
+        let stderr = tail.writer();
+        stdout.push("stdout", "line");
+        drop(stdout);
+        assert!(tail.subscribe().live.is_some());
+
+        drop(stderr);
+        let subscription = tail.subscribe();
+        assert_eq!(lines(&subscription.buffered), vec!["line"]);
+        assert!(subscription.live.is_none());
+    }
+}
@@ -0,0 +1,163 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Confirms that a started process was placed in the cgroup of the cell.
+//!
+//! Processes inherit the cgroup of the auraed that spawns them, which is the cgroup
+//! of the cell. Rather than reading back `cgroup.procs` of that cgroup (whose path on
+//! the cgroup filesystem is not visible from inside the cell's cgroup namespace), we
+//! compare the cgroup of the process with our own, both from `/proc/<pid>/cgroup`.
+
+use super::{ExecutableName, ExecutablesError, Result};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const PROC: &str = "/proc";
+
+/// Returns an error if the process is not in the same cgroup as auraed.
+pub fn verify_placement(
+    executable_name: &ExecutableName,
+    pid: i32,
+) -> Result<()> {
+    verify_placement_in(Path::new(PROC), executable_name, pid)
+}
+
+fn verify_placement_in(
+    proc: &Path,
+    executable_name: &ExecutableName,
+    pid: i32,
+) -> Result<()> {
+    let read = |process: PathBuf| {
+        cgroup_of(&process).map_err(|source| {
+            ExecutablesError::FailedToVerifyPlacement {
+                executable_name: executable_name.clone(),
+                source,
+            }
+        })
+    };
+
+    let expected = read(proc.join("self"))?;
+    let actual = read(proc.join(pid.to_string()))?;
+
+    if actual != expected {
+        return Err(ExecutablesError::ProcessNotInCell {
+            executable_name: executable_name.clone(),
+            pid,
+            expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the cgroup v2 path of the process at `process` (a `/proc/<pid>` directory),
+/// relative to the cgroup namespace of the reader.
+fn cgroup_of(process: &Path) -> io::Result<String> {
+    let cgroup = fs::read_to_string(process.join("cgroup"))?;
+
+    cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim().to_string())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no cgroup v2 entry")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use validation::ValidatedField;
+
+    /// A fake /proc with the cgroup of auraed and of process 42.
+    fn fake_proc(ours: &str, theirs: &str) -> PathBuf {
+        let proc = temp_path("proc");
+        for (process, cgroup) in [("self", ours), ("42", theirs)] {
+            std::fs::create_dir_all(proc.join(process)).expect("create dir");
+            std::fs::write(proc.join(process).join("cgroup"), cgroup)
+                .expect("write cgroup");
+        }
+        proc
+    }
+
+    fn name() -> ExecutableName {
+        ExecutableName::validate(Some("sample".into()), "name", None).unwrap()
+    }
+
+    #[test]
+    fn test_process_in_our_cgroup_is_verified() {
+        let proc = fake_proc("0::/_\n", "0::/_\n");
+        let res = verify_placement_in(&proc, &name(), 42);
+        std::fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_escaped_process_fails_verification() {
+        let proc = fake_proc("0::/_\n", "0::/elsewhere\n");
+        let res = verify_placement_in(&proc, &name(), 42);
+        std::fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert!(matches!(
+            res,
+            Err(ExecutablesError::ProcessNotInCell { pid: 42, expected, actual })
+                if expected == "/_" && actual == "/elsewhere"
+        ));
+    }
+
+    #[test]
+    fn test_missing_process_fails_verification() {
+        let proc = fake_proc("0::/_\n", "0::/_\n");
+        let res = verify_placement_in(&proc, &name(), 43);
+        std::fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert!(matches!(
+            res,
+            Err(ExecutablesError::FailedToVerifyPlacement { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cgroup_v1_only_fails_verification() {
+        let proc = fake_proc("0::/_\n", "1:cpu:/\n");
+        let res = verify_placement_in(&proc, &name(), 42);
+        std::fs::remove_dir_all(&proc).expect("remove dir");
+
+        assert!(matches!(
+            res,
+            Err(ExecutablesError::FailedToVerifyPlacement { .. })
+        ));
+    }
+}
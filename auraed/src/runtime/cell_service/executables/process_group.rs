@@ -0,0 +1,144 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use nix::{
+    errno::Errno,
+    sys::signal::{killpg, Signal},
+    unistd::{setpgid, setsid, Pid},
+};
+use std::io;
+
+/// The process group (and session) an executable is started in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessGroup {
+    /// The process group and session of auraed.
+    #[default]
+    Inherit,
+    /// A new process group, led by the executable.
+    New,
+    /// A new session, and so a new process group, led by the executable.
+    /// This detaches it from the controlling terminal of auraed.
+    NewSession,
+}
+
+impl ProcessGroup {
+    pub fn new(new_session: bool, new_process_group: bool) -> Self {
+        match (new_session, new_process_group) {
+            (true, _) => Self::NewSession,
+            (false, true) => Self::New,
+            (false, false) => Self::Inherit,
+        }
+    }
+
+    /// Returns true if the executable leads its own process group, which can then be
+    /// signaled as a whole (see [ProcessGroup::signal]).
+    pub fn is_own(&self) -> bool {
+        !matches!(self, Self::Inherit)
+    }
+
+    /// Moves the calling process into the process group.
+    /// Only to be called in the child, between fork and exec (i.e., as a pre-exec hook).
+    pub fn enter(&self) -> io::Result<()> {
+        let res = match self {
+            Self::Inherit => return Ok(()),
+            Self::New => setpgid(Pid::from_raw(0), Pid::from_raw(0)),
+            // A session leader can't change its process group, so setsid is enough
+            Self::NewSession => setsid().map(|_| ()),
+        };
+
+        res.map_err(|e| io::Error::from_raw_os_error(e as i32))
+    }
+
+    /// Sends `signal` to every process in the process group led by `pid`, so that the
+    /// processes the executable started (e.g., the commands of a shell) are stopped
+    /// with it. Does nothing if the executable does not lead its own process group.
+    pub fn signal(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+        if !self.is_own() {
+            return Ok(());
+        }
+
+        match killpg(pid, signal) {
+            // all processes of the group exited
+            Ok(()) | Err(Errno::ESRCH) => Ok(()),
+            Err(e) => Err(io::Error::from_raw_os_error(e as i32)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::{
+        executables::Executable, test_helpers::command_spec,
+    };
+    use nix::unistd::{getpgid, getsid};
+
+    fn start(process_group: ProcessGroup) -> (Executable, Pid) {
+        let mut spec = command_spec("sample", "sleep 10");
+        spec.process_group = process_group;
+
+        let mut executable = Executable::new(spec);
+        executable.start().expect("start");
+        let pid = executable.pid().expect("pid").expect("running");
+        (executable, pid)
+    }
+
+    #[tokio::test]
+    async fn test_new_process_group() {
+        let (mut executable, pid) = start(ProcessGroup::New);
+        let pgid = getpgid(Some(pid));
+        let sid = getsid(Some(pid));
+        let _ = executable.kill().await.expect("kill");
+
+        assert_eq!(pgid.expect("pgid"), pid);
+        assert_ne!(pgid.expect("pgid"), getpgid(None).expect("our pgid"));
+        assert_eq!(sid.expect("sid"), getsid(None).expect("our sid"));
+    }
+
+    #[tokio::test]
+    async fn test_new_session() {
+        let (mut executable, pid) = start(ProcessGroup::NewSession);
+        let pgid = getpgid(Some(pid));
+        let sid = getsid(Some(pid));
+        let _ = executable.kill().await.expect("kill");
+
+        assert_eq!(pgid.expect("pgid"), pid);
+        assert_eq!(sid.expect("sid"), pid);
+    }
+
+    #[tokio::test]
+    async fn test_inherited_process_group() {
+        let (mut executable, pid) = start(ProcessGroup::Inherit);
+        let pgid = getpgid(Some(pid));
+        let _ = executable.kill().await.expect("kill");
+
+        assert_eq!(pgid.expect("pgid"), getpgid(None).expect("our pgid"));
+    }
+}
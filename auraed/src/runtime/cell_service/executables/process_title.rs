@@ -0,0 +1,174 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The title of executables, as shown by `ps` and `top`.
+//!
+//! A process shows under two names: its argv[0], shown by `ps -f` and `top -c`, and
+//! its name (comm), shown by `ps -o comm` and `top`. argv[0] is passed to exec, but
+//! comm is not: exec resets it to the file name of the program, truncated to 15 bytes.
+//! A comm set before exec (with `prctl(PR_SET_NAME)`) is lost, and only the process
+//! itself can change it afterwards. So to set comm, the program is executed through
+//! a symlink named after the title, created for the time of the spawn.
+
+use super::find_program;
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tracing::warn;
+
+/// The maximum length of comm in bytes (TASK_COMM_LEN, without the NUL).
+pub const MAX_COMM_LEN: usize = 15;
+
+/// The title an executable is started with, which replaces its argv[0] and comm.
+#[derive(Debug, Clone)]
+pub struct ProcessTitle {
+    title: OsString,
+    /// The program, as it would be executed without a title.
+    program: OsString,
+    /// The path of the symlink the program is executed through.
+    link: PathBuf,
+}
+
+impl ProcessTitle {
+    pub fn new(title: OsString, program: OsString) -> Self {
+        let link = std::env::temp_dir()
+            .join(format!("aurae-process-title-{}", uuid::Uuid::new_v4()))
+            .join(comm(&title));
+        Self { title, program, link }
+    }
+
+    /// The title, which is passed as argv[0].
+    pub fn title(&self) -> &OsStr {
+        &self.title
+    }
+
+    pub fn program(&self) -> &OsStr {
+        &self.program
+    }
+
+    /// The path to execute instead of the program, which only exists while
+    /// the [ProcessTitleLink] returned by [ProcessTitle::link] is alive.
+    pub fn link_path(&self) -> &Path {
+        &self.link
+    }
+
+    /// Creates the symlink to the program, resolved with the PATH of `command`.
+    /// The symlink can be removed (by dropping the returned [ProcessTitleLink]) as soon
+    /// as `command` is spawned, as the program has been executed by then.
+    pub fn link(&self, command: &Command) -> io::Result<ProcessTitleLink> {
+        let program =
+            find_program(&self.program, super::path(command).as_deref())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("program {:?} not found", self.program),
+                    )
+                })?;
+        // The symlink is not in the working dir of the program
+        let program = std::fs::canonicalize(program)?;
+
+        let dir = self.link.parent().expect("link in a dir");
+        std::fs::create_dir(dir)?;
+        let link = ProcessTitleLink { dir: dir.to_path_buf() };
+        std::os::unix::fs::symlink(program, &self.link)?;
+
+        Ok(link)
+    }
+}
+
+/// Removes the symlink created by [ProcessTitle::link] when dropped.
+#[derive(Debug)]
+pub struct ProcessTitleLink {
+    dir: PathBuf,
+}
+
+impl Drop for ProcessTitleLink {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            warn!("failed to remove process title link {:?}: {e}", self.dir);
+        }
+    }
+}
+
+/// Returns the comm of a process started with `title`: its first [MAX_COMM_LEN] bytes,
+/// made into a valid file name, as that is what the kernel takes comm from.
+pub fn comm(title: &OsStr) -> OsString {
+    let mut comm: Vec<u8> = title
+        .as_bytes()
+        .iter()
+        .take(MAX_COMM_LEN)
+        .map(|&byte| if byte == b'/' { b'_' } else { byte })
+        .collect();
+    // "." and ".." are not file names
+    if comm.iter().all(|&byte| byte == b'.') {
+        comm.fill(b'_');
+    }
+
+    OsString::from_vec(comm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comm_is_truncated_to_15_bytes() {
+        let comm = comm(OsStr::new("aurae-sample-worker-with-a-long-title"));
+        assert_eq!(comm, "aurae-sample-wo");
+        assert_eq!(comm.len(), MAX_COMM_LEN);
+
+        assert_eq!(super::comm(OsStr::new("short")), "short");
+    }
+
+    #[test]
+    fn test_comm_is_a_file_name() {
+        assert_eq!(comm(OsStr::new("worker/1")), "worker_1");
+        assert_eq!(comm(OsStr::new("..")), "__");
+    }
+
+    #[test]
+    fn test_link_is_removed_when_dropped() {
+        let title = ProcessTitle::new("ae-test-title".into(), "sh".into());
+        let link = title.link(&Command::new("sh")).expect("link");
+        assert!(title.link_path().is_symlink());
+        assert_eq!(
+            title.link_path().file_name(),
+            Some(OsStr::new("ae-test-title"))
+        );
+
+        drop(link);
+        assert!(!title.link_path().exists());
+        assert!(!title.link_path().parent().expect("dir").exists());
+    }
+}
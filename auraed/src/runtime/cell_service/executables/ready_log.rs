@@ -0,0 +1,167 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use fancy_regex::Regex;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::watch;
+
+/// Whether an [Executable] has written a line matching its ready log pattern.
+///
+/// [Executable]: super::Executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readiness {
+    Waiting,
+    Ready,
+    /// Both stdout and stderr were closed (e.g., the process exited) without a match.
+    OutputClosed,
+}
+
+/// Watches the output of an [Executable] for a line matching a pattern.
+/// Clones share the same state, so stdout and stderr can each be watched by their own clone.
+///
+/// [Executable]: super::Executable
+#[derive(Debug, Clone)]
+pub struct ReadyLog {
+    pattern: Regex,
+    readiness: Arc<watch::Sender<Readiness>>,
+    open_streams: Arc<AtomicUsize>,
+}
+
+impl ReadyLog {
+    /// Number of output streams (stdout and stderr) that are watched.
+    const STREAMS: usize = 2;
+
+    pub fn new(pattern: Regex) -> Self {
+        let (readiness, _) = watch::channel(Readiness::Waiting);
+        Self {
+            pattern,
+            readiness: Arc::new(readiness),
+            open_streams: Arc::new(AtomicUsize::new(Self::STREAMS)),
+        }
+    }
+
+    /// Marks the executable as ready if the line matches the pattern.
+    pub fn check(&self, line: &str) {
+        // lines are only matched until the first match
+        if *self.readiness.borrow() != Readiness::Waiting {
+            return;
+        }
+
+        if matches!(self.pattern.is_match(line), Ok(true)) {
+            let _ = self.readiness.send_replace(Readiness::Ready);
+        }
+    }
+
+    /// Records that one of the output streams was closed.
+    pub fn close(&self) {
+        if self.open_streams.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let _ = self.readiness.send_if_modified(|readiness| {
+                if *readiness != Readiness::Waiting {
+                    return false;
+                }
+                *readiness = Readiness::OutputClosed;
+                true
+            });
+        }
+    }
+
+    /// Waits until the executable is ready or its output is closed.
+    /// Lines written before waiting are taken into account.
+    pub async fn wait(&self) -> Readiness {
+        let mut readiness = self.readiness.subscribe();
+        loop {
+            let current = *readiness.borrow_and_update();
+            if current != Readiness::Waiting {
+                return current;
+            }
+
+            // the sender is kept alive by self, so this can't fail
+            let _ = readiness.changed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn ready_log(pattern: &str) -> ReadyLog {
+        ReadyLog::new(Regex::new(pattern).expect("valid regex"))
+    }
+
+    #[tokio::test]
+    async fn test_matching_line_makes_ready() {
+        let ready_log = ready_log(r"listening on \d+");
+        ready_log.check("starting server");
+        ready_log.check("listening on 8080");
+
+        assert_eq!(ready_log.wait().await, Readiness::Ready);
+    }
+
+    #[tokio::test]
+    async fn test_wait_sees_lines_written_while_waiting() {
+        let ready_log = ready_log("ready");
+        let writer = ready_log.clone();
+        let _writer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            writer.check("not yet");
+            writer.check("ready now");
+        });
+
+        assert_eq!(ready_log.wait().await, Readiness::Ready);
+    }
+
+    #[tokio::test]
+    async fn test_closed_output_without_match() {
+        let ready_log = ready_log("ready");
+        ready_log.check("something else");
+        ready_log.close();
+
+        // stderr is still open
+        assert_eq!(*ready_log.readiness.borrow(), Readiness::Waiting);
+
+        ready_log.close();
+        assert_eq!(ready_log.wait().await, Readiness::OutputClosed);
+    }
+
+    #[tokio::test]
+    async fn test_ready_is_kept_after_output_closes() {
+        let ready_log = ready_log("ready");
+        ready_log.check("ready");
+        ready_log.close();
+        ready_log.close();
+
+        assert_eq!(ready_log.wait().await, Readiness::Ready);
+    }
+}
@@ -0,0 +1,110 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use crate::logging::get_timestamp_sec;
+
+/// Number of restarts after which an [Executable] is considered to be crash looping.
+///
+/// [Executable]: super::Executable
+pub const CRASH_LOOP_THRESHOLD: u32 = 5;
+
+/// Bookkeeping of the restarts of an [Executable].
+///
+/// [Executable]: super::Executable
+#[derive(Debug, Clone, Default)]
+pub struct RestartStats {
+    /// Number of times the executable has been restarted.
+    pub restart_count: u32,
+    /// UNIX timestamp (seconds) of the last restart, if any.
+    pub last_restart_at: Option<i64>,
+    /// Why the executable exited before it was last restarted, if known.
+    pub last_exit_reason: Option<String>,
+}
+
+impl RestartStats {
+    /// Records a restart caused by `exit_reason`.
+    ///
+    /// Returns true when this restart makes the restart count reach [CRASH_LOOP_THRESHOLD].
+    /// Only the restart crossing the threshold returns true, so callers can report
+    /// a crash loop once rather than on every subsequent restart.
+    pub fn record(&mut self, exit_reason: String) -> bool {
+        self.restart_count = self.restart_count.saturating_add(1);
+        self.last_restart_at = Some(get_timestamp_sec());
+        self.last_exit_reason = Some(exit_reason);
+
+        self.restart_count == CRASH_LOOP_THRESHOLD
+    }
+
+    /// Returns true if the restart count has reached [CRASH_LOOP_THRESHOLD].
+    pub fn is_crash_looping(&self) -> bool {
+        self.restart_count >= CRASH_LOOP_THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_increments() {
+        let mut stats = RestartStats::default();
+        assert_eq!(stats.restart_count, 0);
+        assert!(stats.last_restart_at.is_none());
+        assert!(stats.last_exit_reason.is_none());
+
+        let _ = stats.record("exit status: 1".into());
+        assert_eq!(stats.restart_count, 1);
+        assert!(stats.last_restart_at.is_some());
+        assert_eq!(stats.last_exit_reason.as_deref(), Some("exit status: 1"));
+
+        let _ = stats.record("signal: 9 (SIGKILL)".into());
+        assert_eq!(stats.restart_count, 2);
+        assert_eq!(
+            stats.last_exit_reason.as_deref(),
+            Some("signal: 9 (SIGKILL)")
+        );
+    }
+
+    #[test]
+    fn test_crash_loop_threshold_is_reported_once() {
+        let mut stats = RestartStats::default();
+
+        for _ in 1..CRASH_LOOP_THRESHOLD {
+            assert!(!stats.record("exit status: 1".into()));
+            assert!(!stats.is_crash_looping());
+        }
+
+        assert!(stats.record("exit status: 1".into()));
+        assert!(stats.is_crash_looping());
+
+        assert!(!stats.record("exit status: 1".into()));
+        assert!(stats.is_crash_looping());
+    }
+}
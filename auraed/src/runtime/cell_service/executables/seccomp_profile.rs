@@ -0,0 +1,417 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Seccomp profiles of executables, in the format of the `seccomp` section of the
+//! OCI runtime spec (as used by runc and docker). A profile is compiled to a BPF
+//! filter when the executable starts, and installed as the last step before exec.
+//!
+//! Only a subset of the format is supported: the default action, and the actions of
+//! syscalls by name. Profiles using more (e.g., conditions on the arguments of a
+//! syscall) fail to load, rather than being applied partially.
+//!
+//! Docs: https://github.com/opencontainers/runtime-spec/blob/main/config-linux.md#seccomp
+
+use crate::runtime::cell_service::seccomp_bpf::{
+    jump, load, ret, AUDIT_ARCH, SECCOMP_DATA_ARCH_OFFSET,
+    SECCOMP_DATA_NR_OFFSET, X32_SYSCALL_BIT,
+};
+use serde::Deserialize;
+use std::{
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+/// From linux/bpf_common.h
+const BPF_MAXINSNS: usize = 4096;
+
+/// The syscalls a profile can name, with their numbers on the architecture auraed
+/// was built for.
+macro_rules! syscalls {
+    ($($name:ident),* $(,)?) => {
+        &[$((stringify!($name), libc::$name)),*]
+    };
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[rustfmt::skip]
+const SYSCALLS: &[(&str, libc::c_long)] = syscalls![
+    SYS_accept, SYS_accept4, SYS_acct, SYS_add_key, SYS_adjtimex, SYS_bind,
+    SYS_bpf, SYS_brk, SYS_capget, SYS_capset, SYS_chdir, SYS_chroot,
+    SYS_clock_adjtime, SYS_clock_getres, SYS_clock_gettime, SYS_clock_nanosleep,
+    SYS_clock_settime, SYS_clone, SYS_clone3, SYS_close, SYS_close_range,
+    SYS_connect, SYS_copy_file_range, SYS_delete_module, SYS_dup, SYS_dup3,
+    SYS_epoll_create1, SYS_epoll_ctl, SYS_epoll_pwait, SYS_epoll_pwait2,
+    SYS_eventfd2, SYS_execve, SYS_execveat, SYS_exit, SYS_exit_group,
+    SYS_faccessat, SYS_faccessat2, SYS_fallocate, SYS_fanotify_init,
+    SYS_fanotify_mark, SYS_fchdir, SYS_fchmod, SYS_fchmodat, SYS_fchown,
+    SYS_fchownat, SYS_fcntl, SYS_fdatasync, SYS_fgetxattr, SYS_finit_module,
+    SYS_flistxattr, SYS_flock, SYS_fremovexattr, SYS_fsconfig, SYS_fsetxattr,
+    SYS_fsmount, SYS_fsopen, SYS_fspick, SYS_fstat, SYS_fstatfs, SYS_fsync,
+    SYS_ftruncate, SYS_futex, SYS_futex_waitv, SYS_get_mempolicy,
+    SYS_get_robust_list, SYS_getcpu, SYS_getcwd, SYS_getdents64, SYS_getegid,
+    SYS_geteuid, SYS_getgid, SYS_getgroups, SYS_getitimer, SYS_getpeername,
+    SYS_getpgid, SYS_getpid, SYS_getppid, SYS_getpriority, SYS_getrandom,
+    SYS_getresgid, SYS_getresuid, SYS_getrusage, SYS_getsid, SYS_getsockname,
+    SYS_getsockopt, SYS_gettid, SYS_gettimeofday, SYS_getuid, SYS_getxattr,
+    SYS_init_module, SYS_inotify_add_watch, SYS_inotify_init1,
+    SYS_inotify_rm_watch, SYS_io_cancel, SYS_io_destroy, SYS_io_getevents,
+    SYS_io_setup, SYS_io_submit, SYS_io_uring_enter, SYS_io_uring_register,
+    SYS_io_uring_setup, SYS_ioctl, SYS_ioprio_get, SYS_ioprio_set, SYS_kcmp,
+    SYS_kexec_load, SYS_keyctl, SYS_kill, SYS_landlock_add_rule,
+    SYS_landlock_create_ruleset, SYS_landlock_restrict_self, SYS_lgetxattr,
+    SYS_linkat, SYS_listen, SYS_listxattr, SYS_llistxattr, SYS_lookup_dcookie,
+    SYS_lremovexattr, SYS_lseek, SYS_lsetxattr, SYS_madvise, SYS_mbind,
+    SYS_membarrier, SYS_memfd_create, SYS_memfd_secret, SYS_migrate_pages,
+    SYS_mincore, SYS_mkdirat, SYS_mknodat, SYS_mlock, SYS_mlock2, SYS_mlockall,
+    SYS_mmap, SYS_mount, SYS_mount_setattr, SYS_move_mount, SYS_move_pages,
+    SYS_mprotect, SYS_mq_getsetattr, SYS_mq_notify, SYS_mq_open,
+    SYS_mq_timedreceive, SYS_mq_timedsend, SYS_mq_unlink, SYS_mremap,
+    SYS_msgctl, SYS_msgget, SYS_msgrcv, SYS_msgsnd, SYS_msync, SYS_munlock,
+    SYS_munlockall, SYS_munmap, SYS_name_to_handle_at, SYS_nanosleep,
+    SYS_newfstatat, SYS_nfsservctl, SYS_open_by_handle_at, SYS_open_tree,
+    SYS_openat, SYS_openat2, SYS_perf_event_open, SYS_personality,
+    SYS_pidfd_getfd, SYS_pidfd_open, SYS_pidfd_send_signal, SYS_pipe2,
+    SYS_pivot_root, SYS_pkey_alloc, SYS_pkey_free, SYS_pkey_mprotect, SYS_ppoll,
+    SYS_prctl, SYS_pread64, SYS_preadv, SYS_preadv2, SYS_prlimit64,
+    SYS_process_madvise, SYS_process_mrelease, SYS_process_vm_readv,
+    SYS_process_vm_writev, SYS_pselect6, SYS_ptrace, SYS_pwrite64, SYS_pwritev,
+    SYS_pwritev2, SYS_quotactl, SYS_quotactl_fd, SYS_read, SYS_readahead,
+    SYS_readlinkat, SYS_readv, SYS_reboot, SYS_recvfrom, SYS_recvmmsg,
+    SYS_recvmsg, SYS_remap_file_pages, SYS_removexattr, SYS_renameat2,
+    SYS_request_key, SYS_restart_syscall, SYS_rt_sigaction, SYS_rt_sigpending,
+    SYS_rt_sigprocmask, SYS_rt_sigqueueinfo, SYS_rt_sigreturn,
+    SYS_rt_sigsuspend, SYS_rt_sigtimedwait, SYS_rt_tgsigqueueinfo,
+    SYS_sched_get_priority_max, SYS_sched_get_priority_min,
+    SYS_sched_getaffinity, SYS_sched_getattr, SYS_sched_getparam,
+    SYS_sched_getscheduler, SYS_sched_rr_get_interval, SYS_sched_setaffinity,
+    SYS_sched_setattr, SYS_sched_setparam, SYS_sched_setscheduler,
+    SYS_sched_yield, SYS_seccomp, SYS_semctl, SYS_semget, SYS_semop,
+    SYS_semtimedop, SYS_sendmmsg, SYS_sendmsg, SYS_sendto, SYS_set_mempolicy,
+    SYS_set_mempolicy_home_node, SYS_set_robust_list, SYS_set_tid_address,
+    SYS_setdomainname, SYS_setfsgid, SYS_setfsuid, SYS_setgid, SYS_setgroups,
+    SYS_sethostname, SYS_setitimer, SYS_setns, SYS_setpgid, SYS_setpriority,
+    SYS_setregid, SYS_setresgid, SYS_setresuid, SYS_setreuid, SYS_setsid,
+    SYS_setsockopt, SYS_settimeofday, SYS_setuid, SYS_setxattr, SYS_shmat,
+    SYS_shmctl, SYS_shmdt, SYS_shmget, SYS_shutdown, SYS_sigaltstack,
+    SYS_signalfd4, SYS_socket, SYS_socketpair, SYS_splice, SYS_statfs,
+    SYS_statx, SYS_swapoff, SYS_swapon, SYS_symlinkat, SYS_sync, SYS_syncfs,
+    SYS_sysinfo, SYS_syslog, SYS_tee, SYS_tgkill, SYS_timer_create,
+    SYS_timer_delete, SYS_timer_getoverrun, SYS_timer_gettime,
+    SYS_timer_settime, SYS_timerfd_create, SYS_timerfd_gettime,
+    SYS_timerfd_settime, SYS_times, SYS_tkill, SYS_truncate, SYS_umask,
+    SYS_umount2, SYS_uname, SYS_unlinkat, SYS_unshare, SYS_userfaultfd,
+    SYS_utimensat, SYS_vhangup, SYS_vmsplice, SYS_wait4, SYS_waitid, SYS_write,
+    SYS_writev,
+];
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const SYSCALLS: &[(&str, libc::c_long)] = &[];
+
+/// The syscalls that newer architectures replaced (e.g., open with openat).
+#[cfg(target_arch = "x86_64")]
+#[rustfmt::skip]
+const LEGACY_SYSCALLS: &[(&str, libc::c_long)] = syscalls![
+    SYS__sysctl, SYS_access, SYS_afs_syscall, SYS_alarm, SYS_arch_prctl,
+    SYS_chmod, SYS_chown, SYS_creat, SYS_create_module, SYS_dup2,
+    SYS_epoll_create, SYS_epoll_ctl_old, SYS_epoll_wait, SYS_epoll_wait_old,
+    SYS_eventfd, SYS_fadvise64, SYS_fork, SYS_futimesat, SYS_get_kernel_syms,
+    SYS_get_thread_area, SYS_getdents, SYS_getpgrp, SYS_getpmsg, SYS_getrlimit,
+    SYS_inotify_init, SYS_ioperm, SYS_iopl, SYS_kexec_file_load, SYS_lchown,
+    SYS_link, SYS_lstat, SYS_mkdir, SYS_mknod, SYS_modify_ldt, SYS_open,
+    SYS_pause, SYS_pipe, SYS_poll, SYS_putpmsg, SYS_query_module, SYS_readlink,
+    SYS_rename, SYS_renameat, SYS_rmdir, SYS_security, SYS_select, SYS_sendfile,
+    SYS_set_thread_area, SYS_setrlimit, SYS_signalfd, SYS_stat, SYS_symlink,
+    SYS_sync_file_range, SYS_sysfs, SYS_time, SYS_tuxcall, SYS_unlink,
+    SYS_uselib, SYS_ustat, SYS_utime, SYS_utimes, SYS_vfork, SYS_vserver,
+];
+#[cfg(not(target_arch = "x86_64"))]
+const LEGACY_SYSCALLS: &[(&str, libc::c_long)] = &[];
+
+fn syscall_number(name: &str) -> Option<libc::c_long> {
+    SYSCALLS
+        .iter()
+        .chain(LEGACY_SYSCALLS)
+        .find(|(sys_name, _)| sys_name.strip_prefix("SYS_") == Some(name))
+        .map(|(_, nr)| *nr)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Profile {
+    default_action: Action,
+    default_errno_ret: Option<u32>,
+    /// Accepted for compatibility, but only the syscalls of the architecture auraed was
+    /// built for are filtered. Syscalls made with any other kill the process.
+    #[serde(default, rename = "architectures")]
+    _architectures: Vec<String>,
+    #[serde(default)]
+    syscalls: Vec<SyscallRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SyscallRule {
+    names: Vec<String>,
+    action: Action,
+    errno_ret: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+enum Action {
+    #[serde(rename = "SCMP_ACT_ALLOW")]
+    Allow,
+    #[serde(rename = "SCMP_ACT_ERRNO")]
+    Errno,
+    #[serde(rename = "SCMP_ACT_KILL", alias = "SCMP_ACT_KILL_THREAD")]
+    KillThread,
+    #[serde(rename = "SCMP_ACT_KILL_PROCESS")]
+    KillProcess,
+    #[serde(rename = "SCMP_ACT_TRAP")]
+    Trap,
+    #[serde(rename = "SCMP_ACT_LOG")]
+    Log,
+}
+
+impl Action {
+    /// `errno` is only used by [Action::Errno]. EPERM if not set, as with runc.
+    fn seccomp_ret(&self, errno: Option<u32>) -> u32 {
+        match self {
+            Self::Allow => libc::SECCOMP_RET_ALLOW,
+            Self::Errno => {
+                let errno = errno.unwrap_or(libc::EPERM as u32);
+                libc::SECCOMP_RET_ERRNO | (errno & libc::SECCOMP_RET_DATA)
+            }
+            Self::KillThread => libc::SECCOMP_RET_KILL_THREAD,
+            Self::KillProcess => libc::SECCOMP_RET_KILL_PROCESS,
+            Self::Trap => libc::SECCOMP_RET_TRAP,
+            Self::Log => libc::SECCOMP_RET_LOG,
+        }
+    }
+}
+
+/// A seccomp profile, compiled to the BPF filter installed by [SeccompProfile::apply].
+#[derive(Clone)]
+pub struct SeccompProfile {
+    filter: Vec<libc::sock_filter>,
+}
+
+impl SeccompProfile {
+    /// Reads and compiles the profile at `path`.
+    /// Returns an [ErrorKind::InvalidData] error if the profile is not valid, or names a
+    /// syscall that is unknown on this architecture.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let profile = std::fs::read(path)?;
+        let profile: Profile = serde_json::from_slice(&profile)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Self::compile(&profile)
+    }
+
+    fn compile(profile: &Profile) -> io::Result<Self> {
+        let Some(audit_arch) = AUDIT_ARCH else {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "seccomp profiles are not supported on this architecture",
+            ));
+        };
+
+        let kill = libc::SECCOMP_RET_KILL_PROCESS;
+        let mut filter = vec![
+            load(SECCOMP_DATA_ARCH_OFFSET),
+            jump(libc::BPF_JEQ, audit_arch, 1, 0),
+            ret(kill),
+            load(SECCOMP_DATA_NR_OFFSET),
+        ];
+        if cfg!(target_arch = "x86_64") {
+            filter.push(jump(libc::BPF_JGE, X32_SYSCALL_BIT, 0, 1));
+            filter.push(ret(kill));
+        }
+
+        // The first rule naming a syscall wins
+        for rule in &profile.syscalls {
+            let action = rule.action.seccomp_ret(rule.errno_ret);
+            for name in &rule.names {
+                let Some(nr) = syscall_number(name) else {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown syscall '{name}'"),
+                    ));
+                };
+                filter.push(jump(libc::BPF_JEQ, nr as u32, 0, 1));
+                filter.push(ret(action));
+            }
+        }
+        filter.push(ret(profile
+            .default_action
+            .seccomp_ret(profile.default_errno_ret)));
+
+        if filter.len() > BPF_MAXINSNS {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "profile names too many syscalls",
+            ));
+        }
+
+        Ok(Self { filter })
+    }
+
+    /// Installs the filter on the calling thread. The filter is inherited by all
+    /// children and can't be removed.
+    ///
+    /// Only to be called in the child, between fork and exec: it doesn't allocate.
+    /// Requires CAP_SYS_ADMIN (or no_new_privs to be set).
+    pub fn apply(&self) -> io::Result<()> {
+        let prog = libc::sock_fprog {
+            len: self.filter.len() as u16,
+            filter: self.filter.as_ptr() as *mut libc::sock_filter,
+        };
+
+        if unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                std::ptr::addr_of!(prog),
+            )
+        } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::seccomp_bpf;
+    use crate::runtime::cell_service::test_helpers::temp_path;
+    use std::{os::unix::process::CommandExt, path::PathBuf, process::Command};
+
+    fn write_profile(profile: &str) -> PathBuf {
+        let path = temp_path("seccomp");
+        std::fs::write(&path, profile).expect("write profile");
+        path
+    }
+
+    fn load_profile(profile: &str) -> io::Result<SeccompProfile> {
+        let path = write_profile(profile);
+        let res = SeccompProfile::load(&path);
+        std::fs::remove_file(&path).expect("remove profile");
+        res
+    }
+
+    /// Runs the filter for a syscall of the native architecture
+    fn run(filter: &[libc::sock_filter], nr: u32) -> u32 {
+        seccomp_bpf::run(filter, AUDIT_ARCH.expect("arch"), nr)
+    }
+
+    /// A profile denying mkdir with EACCES, e.g., to the mkdir command
+    fn deny_mkdir() -> String {
+        let names = if cfg!(target_arch = "x86_64") {
+            r#"["mkdir", "mkdirat"]"#
+        } else {
+            r#"["mkdirat"]"#
+        };
+        format!(
+            r#"{{
+                "defaultAction": "SCMP_ACT_ALLOW",
+                "syscalls": [
+                    {{"names": {names}, "action": "SCMP_ACT_ERRNO", "errnoRet": 13}}
+                ]
+            }}"#
+        )
+    }
+
+    #[test]
+    fn test_filter() {
+        let profile = load_profile(&deny_mkdir()).expect("valid profile");
+
+        let mkdirat = libc::SYS_mkdirat as u32;
+        assert_eq!(
+            run(&profile.filter, mkdirat),
+            libc::SECCOMP_RET_ERRNO | libc::EACCES as u32
+        );
+        let getpid = libc::SYS_getpid as u32;
+        assert_eq!(run(&profile.filter, getpid), libc::SECCOMP_RET_ALLOW);
+    }
+
+    #[test]
+    fn test_errno_defaults_to_eperm() {
+        let profile = load_profile(
+            r#"{"defaultAction": "SCMP_ACT_ERRNO", "syscalls": []}"#,
+        )
+        .expect("valid profile");
+
+        let getpid = libc::SYS_getpid as u32;
+        assert_eq!(
+            run(&profile.filter, getpid),
+            libc::SECCOMP_RET_ERRNO | libc::EPERM as u32
+        );
+    }
+
+    #[test]
+    fn test_invalid_profiles_fail_to_load() {
+        for profile in [
+            "not json",
+            r#"{"syscalls": []}"#,
+            r#"{"defaultAction": "SCMP_ACT_NOTIFY"}"#,
+            r#"{"defaultAction": "SCMP_ACT_ALLOW", "syscalls": [{"names": ["not_a_syscall"], "action": "SCMP_ACT_ERRNO"}]}"#,
+            r#"{"defaultAction": "SCMP_ACT_ALLOW", "syscalls": [{"names": ["personality"], "action": "SCMP_ACT_ALLOW", "args": [{"index": 0, "value": 0, "op": "SCMP_CMP_EQ"}]}]}"#,
+        ] {
+            let e = load_profile(profile).err().expect("invalid profile");
+            assert_eq!(e.kind(), ErrorKind::InvalidData, "{profile}");
+        }
+
+        let missing = temp_path("seccomp-missing");
+        let e = SeccompProfile::load(&missing).err().expect("missing profile");
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_apply() {
+        let profile = load_profile(&deny_mkdir()).expect("valid profile");
+        let dir = temp_path("seccomp");
+
+        let mut command = Command::new("mkdir");
+        let _ = command.arg(&dir);
+        let _ = unsafe {
+            command.pre_exec(move || {
+                // Unprivileged processes may only install a filter with no_new_privs
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                profile.apply()
+            })
+        };
+        let status = command.status().expect("run mkdir");
+
+        assert!(!status.success());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,120 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The disposition of SIGPIPE in executables.
+//!
+//! Rust programs, auraed included, ignore SIGPIPE, so that writing to a closed pipe or
+//! socket fails with EPIPE rather than killing the process. An ignored signal stays
+//! ignored across exec, so executables would inherit it. Most programs rely on the
+//! default disposition instead: a command writing into `head` is expected to be killed
+//! once `head` exits, not to get EPIPE errors it may not handle (and report, or retry).
+//! The standard library already resets SIGPIPE when spawning, but we don't rely on it,
+//! as that depends on how auraed is built (`-Zon-broken-pipe`).
+
+use nix::sys::signal::{signal, SigHandler, Signal};
+use std::io;
+
+/// The disposition of SIGPIPE an executable is started with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sigpipe {
+    /// The default disposition, which terminates the process.
+    #[default]
+    Default,
+    /// Ignored, as in auraed, so writes to a closed pipe fail with EPIPE.
+    Ignore,
+}
+
+impl Sigpipe {
+    pub fn new(ignore_sigpipe: bool) -> Self {
+        if ignore_sigpipe {
+            Self::Ignore
+        } else {
+            Self::Default
+        }
+    }
+
+    /// Sets the disposition of SIGPIPE in the calling process.
+    /// Only to be called in the child, between fork and exec (i.e., as a pre-exec hook).
+    pub fn apply(&self) -> io::Result<()> {
+        let handler = match self {
+            Self::Default => SigHandler::SigDfl,
+            Self::Ignore => SigHandler::SigIgn,
+        };
+
+        unsafe { signal(Signal::SIGPIPE, handler) }
+            .map(|_| ())
+            .map_err(|e| io::Error::from_raw_os_error(e as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    /// Returns true if SIGPIPE is in the ignored signals of a process started with
+    /// the given disposition, as listed in its /proc/self/status.
+    /// SIGPIPE is ignored in the child before, as it would be if the standard library
+    /// didn't reset it, so the disposition has to be applied to be the default.
+    fn is_ignored_in_child(sigpipe: Sigpipe) -> bool {
+        let mut command = Command::new("cat");
+        let _ = command.arg("/proc/self/status");
+        let output = unsafe {
+            command.pre_exec(move || {
+                Sigpipe::Ignore.apply()?;
+                sigpipe.apply()
+            })
+        }
+        .output()
+        .expect("run cat");
+        assert!(output.status.success());
+
+        let status = String::from_utf8_lossy(&output.stdout);
+        let sig_ign = status
+            .lines()
+            .find_map(|line| line.strip_prefix("SigIgn:"))
+            .expect("SigIgn in status");
+        let sig_ign =
+            u64::from_str_radix(sig_ign.trim(), 16).expect("SigIgn mask");
+
+        sig_ign & (1 << (libc::SIGPIPE - 1)) != 0
+    }
+
+    #[test]
+    fn test_inherited_ignored_sigpipe_is_reset_in_child() {
+        assert!(!is_ignored_in_child(Sigpipe::default()));
+    }
+
+    #[test]
+    fn test_sigpipe_can_be_ignored_in_child() {
+        assert!(is_ignored_in_child(Sigpipe::Ignore));
+    }
+}
@@ -0,0 +1,80 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use nix::sys::signal::Signal;
+use std::time::Duration;
+
+/// The signals an executable can be asked to stop with. Signals that report a fault
+/// (e.g., SIGSEGV) or that don't stop a process (e.g., SIGCONT) are left out.
+pub const STOP_SIGNALS: &[Signal] = &[
+    Signal::SIGTERM,
+    Signal::SIGKILL,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGHUP,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+];
+
+/// How long an executable is given to exit after its stop signal, if not set.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// The most signals an executable can be escalated through after its stop signal.
+pub const MAX_STOP_ESCALATION: usize = 8;
+
+/// The longest an executable can be given to exit after any one of its signals.
+pub const MAX_STOP_WAIT: Duration = Duration::from_secs(300);
+
+/// How an executable is stopped: `signal` is sent to its process (and process group,
+/// if it leads one), and if it has not exited after `grace_period`, it is sent the
+/// signals of `escalation` in order, each after the wait of the one before it. If it
+/// has not exited after the wait of the last one, it is killed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopPolicy {
+    pub signal: Signal,
+    pub grace_period: Duration,
+    pub escalation: Vec<(Signal, Duration)>,
+}
+
+impl StopPolicy {
+    /// Kills the executable right away, e.g., when cleaning up after a failure.
+    pub const KILL: Self = Self {
+        signal: Signal::SIGKILL,
+        grace_period: Duration::ZERO,
+        escalation: Vec::new(),
+    };
+
+    /// Returns the signals the executable is sent, in order, with how long it is given
+    /// to exit after each of them.
+    pub fn signals(&self) -> impl Iterator<Item = (Signal, Duration)> + '_ {
+        std::iter::once((self.signal, self.grace_period))
+            .chain(self.escalation.iter().copied())
+    }
+}
@@ -1,9 +1,18 @@
 pub use cell_service::CellService;
+pub(crate) use cells::cgroups::{
+    ensure_daemon_cgroup, CgroupStats, DaemonCgroup, KillEscalation,
+};
 use error::Result;
+pub(crate) use validation::validate_cell;
 
 #[allow(clippy::module_inception)]
 mod cell_service;
 mod cells;
 mod error;
 mod executables;
+mod pre_exec;
+mod seccomp_bpf;
+mod start_timeout;
+#[cfg(test)]
+mod test_helpers;
 mod validation;
@@ -0,0 +1,132 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use std::{fmt, io};
+
+/// A step of the child setup, run in the child between fork and exec.
+///
+/// Hooks run after fork in a copy of the parent, so they must only do what is safe
+/// there (see [std::os::unix::process::CommandExt::pre_exec]): mainly syscalls, and
+/// no locking of mutexes the parent may have held.
+pub(crate) type PreExecHook = Box<dyn FnMut() -> io::Result<()> + Send + Sync>;
+
+/// An ordered list of [PreExecHook]s, so the steps of the child setup (isolation,
+/// seccomp, etc.) can be composed instead of hardcoded in the spawn path.
+#[derive(Default)]
+pub(crate) struct PreExecHooks {
+    hooks: Vec<(&'static str, PreExecHook)>,
+}
+
+impl PreExecHooks {
+    /// Adds `hook` after the hooks already added. `name` is used for debugging.
+    pub fn push<F>(&mut self, name: &'static str, hook: F)
+    where
+        F: FnMut() -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.push((name, Box::new(hook)));
+    }
+
+    /// Adds the hooks of `other` after the hooks already added.
+    pub fn append(&mut self, mut other: PreExecHooks) {
+        self.hooks.append(&mut other.hooks);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Runs the hooks in order, stopping at the first that fails.
+    /// Only to be called in the child, between fork and exec.
+    pub fn run(&mut self) -> io::Result<()> {
+        for (_, hook) in &mut self.hooks {
+            hook()?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PreExecHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.hooks.iter().map(|(name, _)| name)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut hooks = PreExecHooks::default();
+        for name in ["first", "second", "third"] {
+            let calls = calls.clone();
+            hooks.push(name, move || {
+                calls.lock().expect("lock").push(name);
+                Ok(())
+            });
+        }
+
+        hooks.run().expect("run hooks");
+
+        assert_eq!(*calls.lock().expect("lock"), ["first", "second", "third"]);
+        assert_eq!(format!("{hooks:?}"), r#"["first", "second", "third"]"#);
+    }
+
+    #[test]
+    fn test_appended_hooks_run_after() {
+        let mut hooks = PreExecHooks::default();
+        hooks.push("first", || Ok(()));
+        let mut other = PreExecHooks::default();
+        other.push("second", || Ok(()));
+
+        hooks.append(other);
+
+        assert_eq!(format!("{hooks:?}"), r#"["first", "second"]"#);
+    }
+
+    #[test]
+    fn test_hooks_stop_at_first_error() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut hooks = PreExecHooks::default();
+        hooks.push("fails", || Err(io::ErrorKind::PermissionDenied.into()));
+        let after = calls.clone();
+        hooks.push("after", move || {
+            after.lock().expect("lock").push("after");
+            Ok(())
+        });
+
+        let err = hooks.run().expect_err("hook fails");
+
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(calls.lock().expect("lock").is_empty());
+    }
+}
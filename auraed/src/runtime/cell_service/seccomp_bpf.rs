@@ -0,0 +1,119 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! The building blocks of the seccomp BPF filters of cells (see
+//! [super::cells::SeccompControls]) and executables (see
+//! [super::executables::SeccompProfile]).
+//!
+//! Docs: https://docs.kernel.org/userspace-api/seccomp_filter.html
+
+// From linux/audit.h
+pub(crate) const AUDIT_ARCH_X86_64: u32 = 0xC000_003E;
+pub(crate) const AUDIT_ARCH_I386: u32 = 0x4000_0003;
+pub(crate) const AUDIT_ARCH_AARCH64: u32 = 0xC000_00B7;
+pub(crate) const AUDIT_ARCH_ARM: u32 = 0x4000_0028;
+
+/// The audit arch of the architecture auraed was built for, if filtering its syscalls
+/// by number is supported.
+#[cfg(target_arch = "x86_64")]
+pub(crate) const AUDIT_ARCH: Option<u32> = Some(AUDIT_ARCH_X86_64);
+#[cfg(target_arch = "aarch64")]
+pub(crate) const AUDIT_ARCH: Option<u32> = Some(AUDIT_ARCH_AARCH64);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub(crate) const AUDIT_ARCH: Option<u32> = None;
+
+/// x32 syscalls are reported as [AUDIT_ARCH_X86_64], with this bit set in the syscall number.
+pub(crate) const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+// Offsets in struct seccomp_data
+pub(crate) const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+pub(crate) const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+
+/// Loads the word at `offset` in struct seccomp_data.
+pub(crate) fn load(offset: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code: (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16,
+        jt: 0,
+        jf: 0,
+        k: offset,
+    }
+}
+
+/// Compares the loaded word to `k`, and skips `jt` instructions if `op` holds, or `jf`
+/// instructions if not.
+pub(crate) fn jump(op: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: (libc::BPF_JMP | op | libc::BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    }
+}
+
+/// Returns the seccomp action `k`.
+pub(crate) fn ret(k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code: (libc::BPF_RET | libc::BPF_K) as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+/// Runs the subset of BPF built by [load], [jump] and [ret] for a syscall, and
+/// returns the seccomp action.
+#[cfg(test)]
+pub(crate) fn run(filter: &[libc::sock_filter], arch: u32, nr: u32) -> u32 {
+    let mut acc = 0;
+    let mut pc = 0;
+    loop {
+        let ins = filter[pc];
+        pc += 1;
+        match ins.code as u32 {
+            c if c == libc::BPF_LD | libc::BPF_W | libc::BPF_ABS => {
+                acc = match ins.k {
+                    SECCOMP_DATA_NR_OFFSET => nr,
+                    SECCOMP_DATA_ARCH_OFFSET => arch,
+                    k => panic!("unexpected load offset {k}"),
+                };
+            }
+            c if c == libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K => {
+                let offset = if acc == ins.k { ins.jt } else { ins.jf };
+                pc += offset as usize;
+            }
+            c if c == libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K => {
+                let offset = if acc >= ins.k { ins.jt } else { ins.jf };
+                pc += offset as usize;
+            }
+            c if c == libc::BPF_RET | libc::BPF_K => return ins.k,
+            c => panic!("unexpected instruction {c:#x}"),
+        }
+    }
+}
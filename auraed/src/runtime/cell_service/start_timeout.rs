@@ -0,0 +1,369 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+use super::{
+    error::CellsServiceError,
+    executables::{
+        ExecutableName, ExecutableSpec, Executables, ReadyLog, StopPolicy,
+    },
+    pre_exec::PreExecHooks,
+};
+use nix::{
+    errno::Errno,
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, Read},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{Mutex, OwnedMutexGuard},
+};
+use tracing::warn;
+
+/// Runs `start` to completion, unless `timeout` elapses first.
+///
+/// When the timeout elapses, `start` is dropped and `cleanup` is awaited to tear down
+/// anything `start` may have set up before it was interrupted (e.g., kill a spawned child).
+/// A `timeout` of [None] waits for `start` indefinitely.
+pub(crate) async fn start_with_timeout<T, E, S, C, CF>(
+    timeout: Option<Duration>,
+    start: S,
+    cleanup: C,
+) -> Result<T, E>
+where
+    S: Future<Output = Result<T, E>>,
+    C: FnOnce() -> CF,
+    CF: Future<Output = ()>,
+    E: From<CellsServiceError>,
+{
+    let Some(timeout) = timeout else {
+        return start.await;
+    };
+
+    match tokio::time::timeout(timeout, start).await {
+        Ok(res) => res,
+        Err(_) => {
+            cleanup().await;
+            Err(CellsServiceError::StartTimedOut { timeout }.into())
+        }
+    }
+}
+
+/// An executable started by [start_executable].
+#[derive(Debug)]
+pub(crate) struct StartedExecutable {
+    /// The lock on the executables, held since the executable was started.
+    pub executables: OwnedMutexGuard<Executables>,
+    pub pid: i32,
+    pub ready_log: Option<ReadyLog>,
+}
+
+/// Starts the executable of `spec` (see [Executables::start]) on a blocking thread.
+/// Spawning waits for the pre-exec hooks of the child to complete, which may block
+/// (e.g., on a slow mount), and would keep a timeout from firing on a runtime thread.
+///
+/// If the returned future is dropped while spawning (e.g., by [start_with_timeout]),
+/// the child is killed through the pid it reported before the hooks of `spec` ran, and
+/// the executable is stopped once spawned. The lock on the executables is held until
+/// then, so awaiting it waits for the child to be gone.
+pub(crate) async fn start_executable(
+    executables: Arc<Mutex<Executables>>,
+    mut spec: ExecutableSpec,
+) -> Result<StartedExecutable, CellsServiceError> {
+    let (pid_report, report_pid) = pid_pipe()?;
+    let report_fd = report_pid.as_raw_fd();
+    let mut pre_exec_hooks = PreExecHooks::default();
+    pre_exec_hooks.push("report_pid", move || write_pid(report_fd));
+    pre_exec_hooks.append(std::mem::take(&mut spec.pre_exec_hooks));
+    spec.pre_exec_hooks = pre_exec_hooks;
+
+    let state = Arc::new(std::sync::Mutex::new(SpawnState::Spawning));
+    let abandon_on_drop = AbandonOnDrop {
+        state: state.clone(),
+        pid_report,
+        executables: executables.clone(),
+        executable_name: spec.name.clone(),
+    };
+
+    let mut executables = executables.lock_owned().await;
+    let spawn = tokio::task::spawn_blocking(move || {
+        // The child writes to its copy of the fd until it execs, so the fd must not be
+        // closed (and reused) before it was forked
+        let _report_pid = report_pid;
+        if *state.lock().expect("lock") == SpawnState::Abandoned {
+            return None;
+        }
+
+        let executable_name = spec.name.clone();
+        let res = executables
+            .start(spec)
+            .map_err(CellsServiceError::from)
+            .and_then(|executable| {
+                let pid = executable.pid()?.expect("pid").as_raw();
+                Ok((pid, executable.ready_log()))
+            });
+
+        let mut state = state.lock().expect("lock");
+        if *state == SpawnState::Abandoned {
+            drop(state);
+            if res.is_ok() {
+                let stopped = Handle::current().block_on(executables.stop(
+                    &executable_name,
+                    0,
+                    StopPolicy::KILL,
+                ));
+                if let Err(e) = stopped {
+                    warn!("failed to stop abandoned executable: {e:?}");
+                }
+            }
+            return None;
+        }
+        if res.is_ok() {
+            *state = SpawnState::Spawned;
+        }
+
+        Some((executables, res))
+    });
+
+    let res = match spawn.await {
+        Ok(res) => res,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    };
+    abandon_on_drop.disarm();
+
+    let (executables, res) = res.expect("only abandoned once dropped");
+    let (pid, ready_log) = res?;
+    Ok(StartedExecutable { executables, pid, ready_log })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpawnState {
+    Spawning,
+    /// Spawned, but not yet returned by [start_executable].
+    Spawned,
+    /// [start_executable] was dropped before it returned.
+    Abandoned,
+    Done,
+}
+
+/// Kills the child being spawned by [start_executable] when dropped, unless disarmed.
+#[derive(Debug)]
+struct AbandonOnDrop {
+    state: Arc<std::sync::Mutex<SpawnState>>,
+    pid_report: File,
+    executables: Arc<Mutex<Executables>>,
+    executable_name: ExecutableName,
+}
+
+impl AbandonOnDrop {
+    fn disarm(self) {
+        *self.state.lock().expect("lock") = SpawnState::Done;
+    }
+}
+
+impl Drop for AbandonOnDrop {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("lock");
+        match *state {
+            SpawnState::Spawning => {
+                // The spawning thread stops the executable, once the kill unblocked it
+                *state = SpawnState::Abandoned;
+                if let Some(pid) = read_pid(&mut self.pid_report) {
+                    match kill(pid, Signal::SIGKILL) {
+                        Ok(()) | Err(Errno::ESRCH) => {}
+                        Err(e) => warn!("failed to kill abandoned child: {e}"),
+                    }
+                }
+            }
+            SpawnState::Spawned => {
+                *state = SpawnState::Done;
+                let executables = self.executables.clone();
+                let executable_name = self.executable_name.clone();
+                let _ = tokio::spawn(async move {
+                    let mut executables = executables.lock().await;
+                    if let Err(e) = executables
+                        .stop(&executable_name, 0, StopPolicy::KILL)
+                        .await
+                    {
+                        warn!("failed to stop abandoned executable: {e:?}");
+                    }
+                });
+            }
+            SpawnState::Abandoned | SpawnState::Done => {}
+        }
+    }
+}
+
+/// Returns the read and write ends of a pipe, which is non blocking, so the pid of a
+/// child that was not forked yet reads as [None].
+fn pid_pipe() -> io::Result<(File, File)> {
+    let mut fds = [-1; 2];
+    Errno::result(unsafe {
+        libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK)
+    })
+    .map_err(|e| io::Error::from_raw_os_error(e as i32))?;
+    let [read, write] = fds;
+
+    // SAFETY: the fds were just created, and are only owned here
+    Ok(unsafe { (File::from_raw_fd(read), File::from_raw_fd(write)) })
+}
+
+/// Runs in the child, before exec. A write of up to PIPE_BUF bytes is atomic, so the
+/// pid is read either whole or not at all.
+fn write_pid(fd: RawFd) -> io::Result<()> {
+    let pid = std::process::id().to_ne_bytes();
+    Errno::result(unsafe { libc::write(fd, pid.as_ptr().cast(), pid.len()) })
+        .map(drop)
+        .map_err(|e| io::Error::from_raw_os_error(e as i32))
+}
+
+fn read_pid(pid_report: &mut File) -> Option<Pid> {
+    let mut pid = [0; 4];
+    match pid_report.read(&mut pid) {
+        Ok(4) => Some(Pid::from_raw(u32::from_ne_bytes(pid) as i32)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::cell_service::test_helpers::{
+        command_spec, sleep_spec,
+    };
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Instant,
+    };
+
+    #[tokio::test]
+    async fn test_start_completes_before_timeout() {
+        let cleaned_up = Arc::new(AtomicBool::new(false));
+
+        let res: Result<i32, CellsServiceError> = start_with_timeout(
+            Some(Duration::from_secs(5)),
+            async { Ok(42) },
+            || async { cleaned_up.store(true, Ordering::SeqCst) },
+        )
+        .await;
+
+        assert!(matches!(res, Ok(42)));
+        assert!(!cleaned_up.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_start_times_out_on_slow_isolation_and_cleans_up() {
+        let spawned = Arc::new(AtomicBool::new(false));
+
+        let res: Result<(), CellsServiceError> = start_with_timeout(
+            Some(Duration::from_millis(10)),
+            async {
+                spawned.store(true, Ordering::SeqCst);
+                // mocked isolation step that hangs (e.g., a blocking mount)
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            },
+            || async { spawned.store(false, Ordering::SeqCst) },
+        )
+        .await;
+
+        assert!(matches!(
+            res,
+            Err(CellsServiceError::StartTimedOut { timeout })
+                if timeout == Duration::from_millis(10)
+        ));
+        assert!(
+            !spawned.load(Ordering::SeqCst),
+            "partial start was not cleaned up"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_timeout_waits_for_start() {
+        let res: Result<(), CellsServiceError> = start_with_timeout(
+            None,
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(())
+            },
+            || async { unreachable!("cleanup without a timeout") },
+        )
+        .await;
+
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_start_executable_returns_the_started_executable() {
+        let executables = Arc::new(Mutex::new(Executables::default()));
+        let spec = sleep_spec("ae-start-executable");
+        let name = spec.name.clone();
+
+        let started =
+            start_executable(executables.clone(), spec).await.expect("start");
+        assert!(started.pid > 0);
+        assert!(started.ready_log.is_none());
+
+        let mut executables = started.executables;
+        let _ =
+            executables.stop(&name, 0, StopPolicy::KILL).await.expect("stop");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dropped_start_kills_child_blocked_in_pre_exec_hook() {
+        let executables = Arc::new(Mutex::new(Executables::default()));
+        let mut spec = command_spec("ae-slow-hook", "true");
+        let name = spec.name.clone();
+        // mocked isolation step that hangs (e.g., a blocking mount)
+        spec.pre_exec_hooks.push("slow", || {
+            std::thread::sleep(Duration::from_secs(60));
+            Ok(())
+        });
+
+        let started_at = Instant::now();
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            start_executable(executables.clone(), spec),
+        )
+        .await;
+        assert!(res.is_err(), "the spawn kept the timeout from firing");
+
+        // The lock is held until the abandoned executable was stopped
+        assert!(executables.lock().await.get(&name).is_none());
+        assert!(started_at.elapsed() < Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,96 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Fixtures shared by the tests of the cell service.
+
+use super::{
+    executables::{ExecutableName, ExecutableSpec},
+    validation::ValidatedExecutable,
+    CellService,
+};
+use crate::{audit::AuditLog, config::ReloadableConfig};
+use std::{path::PathBuf, sync::Arc};
+use tokio::sync::RwLock;
+use validation::ValidatedField;
+
+/// Returns a path in the temp dir, unique to the test, that doesn't exist yet.
+pub(crate) fn temp_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("aurae-{prefix}-{}", uuid::Uuid::new_v4()))
+}
+
+/// Creates a directory at a [temp_path], which the test removes when done.
+pub(crate) fn temp_dir(prefix: &str) -> PathBuf {
+    let dir = temp_path(prefix);
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
+
+/// A cell service with the default config, which doesn't audit.
+pub(crate) fn test_cell_service() -> CellService {
+    test_cell_service_with_config(ReloadableConfig::default())
+}
+
+/// A cell service with `config`, which doesn't audit.
+pub(crate) fn test_cell_service_with_config(
+    config: ReloadableConfig,
+) -> CellService {
+    CellService::new(Arc::new(RwLock::new(config)), AuditLog::default())
+}
+
+/// An executable running `command` through the shell, with every option left unset.
+pub(crate) fn executable(name: &str, command: &str) -> ValidatedExecutable {
+    ValidatedExecutable {
+        name: ExecutableName::validate(Some(name.into()), "name", None)
+            .expect("valid name"),
+        command: command.into(),
+        args: vec![],
+        description: String::new(),
+        env: Default::default(),
+        env_file: None,
+        working_dir: None,
+        process_title: None,
+        output_tail_capacity: 0,
+        output_framing: Default::default(),
+        ignore_sigpipe: false,
+        inherit_env: false,
+        drop_capabilities: vec![],
+        seccomp_profile: None,
+    }
+}
+
+pub(crate) fn command_spec(name: &str, command: &str) -> ExecutableSpec {
+    executable(name, command).into()
+}
+
+/// An executable that runs until the test stops it.
+pub(crate) fn sleep_spec(name: &str) -> ExecutableSpec {
+    command_spec(name, "sleep 60")
+}
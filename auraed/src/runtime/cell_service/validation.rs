@@ -1,18 +1,46 @@
 use super::cells::{
     cgroups::{
         self,
+        cpu::Uclamp,
         cpuset::{Cpus, Mems},
-        CgroupSpec, Limit, Weight,
+        io::DeviceNumber,
+        memory::MemoryPinning,
+        pids::PidsMax,
+        CgroupSpec, Limit, NestingLimits, Weight,
     },
-    CellNamePath, IsolationControls,
+    Architecture, BandwidthLimit, BandwidthLimits, BridgeConfig, CellNamePath,
+    CellSpec, ClientCredentials, DenyAction, DeviceMapping, DnsConfig,
+    FreeChildrenPolicy, IdMapping, IsolationControls, LabelSelector, Mount,
+    NetworkMode, SeccompControls, MAX_ID_MAPPINGS,
 };
-use super::executables::ExecutableName;
+use super::executables::{
+    drop_from_bounding_set, Capability, ExecutableName, OutputFraming,
+    ProcessGroup, ProcessTitle, Sigpipe, DEFAULT_GRACE_PERIOD,
+    MAX_FRAME_LENGTH, MAX_OUTPUT_TAIL_CAPACITY, MAX_STOP_ESCALATION,
+    MAX_STOP_WAIT, STOP_SIGNALS,
+};
+use super::pre_exec::PreExecHooks;
 use aurae_proto::runtime::{
-    Cell, CellServiceAllocateRequest, CellServiceFreeRequest,
-    CellServiceStartRequest, CellServiceStopRequest, CpuController,
-    CpusetController, Executable,
+    Cell, CellServiceAllocateRequest, CellServiceDescribeRequest,
+    CellServiceDrainRequest, CellServiceFreeBySelectorRequest,
+    CellServiceFreeRequest, CellServiceFreezeRequest,
+    CellServiceGetCellByTidRequest, CellServiceListExecutablesRequest,
+    CellServiceListFdsRequest, CellServiceListRequest,
+    CellServiceLogStreamRequest, CellServiceRestartRequest,
+    CellServiceRunRequest, CellServiceStartRequest, CellServiceStatRequest,
+    CellServiceStopGenerationRequest, CellServiceStopRequest,
+    CellServiceThawRequest, CellServiceUpdateRequest, CpuController,
+    CpusetController, Executable, IoController, IoMax, MemoryController,
+    PidsController, Seccomp,
 };
+use fancy_regex::Regex;
+use ipnetwork::IpNetwork;
+use nix::sys::signal::Signal;
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::process::Command;
 use validation::{ValidatedField, ValidatedType, ValidationError};
 use validation_macros::ValidatedType;
@@ -26,6 +54,12 @@ use validation_macros::ValidatedType;
 pub struct ValidatedCellServiceAllocateRequest {
     #[field_type(Option<Cell>)]
     pub cell: ValidatedCell,
+
+    #[validate(none)]
+    pub reuse_existing: bool,
+
+    #[field_type(String)]
+    pub template: (),
 }
 
 impl CellServiceAllocateRequestTypeValidator
@@ -38,11 +72,241 @@ impl CellServiceAllocateRequestTypeValidator
     ) -> Result<ValidatedCell, ValidationError> {
         let cell = validation::required(cell, field_name, parent_name)?;
 
-        ValidatedCell::validate(
-            cell,
-            Some(&validation::field_name(field_name, parent_name)),
-        )
+        let parent_name = validation::field_name(field_name, parent_name);
+        let cell = ValidatedCell::validate(cell, Some(&parent_name))?;
+        validate_cell_combinations(&cell, Some(&parent_name))?;
+
+        Ok(cell)
+    }
+
+    fn validate_template(
+        template: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        // Templates are applied to the cell before validation
+        // (see [crate::config::ReloadableConfig::apply_template]).
+        if !template.is_empty() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates a [Cell] the way the cell of a [CellServiceAllocateRequest] is validated.
+pub(crate) fn validate_cell(cell: Cell) -> Result<(), ValidationError> {
+    let cell = ValidatedCell::validate(cell, None)?;
+    validate_cell_combinations(&cell, None)
+}
+
+/// Rejects settings of a cell that are valid on their own, but contradict each other.
+/// Checks that only involve the fields of a single controller (e.g., uclamp_min and
+/// uclamp_max) are done when validating that controller.
+///
+/// Cells are always domain cgroups (the cgroup type can't be requested), so the cgroup
+/// type can't conflict with the isolation of the nested auraed or with the controllers.
+fn validate_cell_combinations(
+    cell: &ValidatedCell,
+    parent_name: Option<&str>,
+) -> Result<(), ValidationError> {
+    // Mounts are applied in the mount namespace of the cell,
+    // which only exists with isolate_process.
+    if !cell.mounts.is_empty() && !cell.isolate_process {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("mounts", parent_name),
+        });
+    }
+
+    // The overlay is mounted in the mount namespace of the cell as well.
+    if cell.ephemeral_root && !cell.isolate_process {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("ephemeral_root", parent_name),
+        });
+    }
+
+    // Device nodes are created in the mount namespace of the cell as well.
+    if !cell.devices.is_empty() && !cell.isolate_process {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("devices", parent_name),
+        });
+    }
+
+    // The resolv.conf is bind mounted in the mount namespace of the cell, and the
+    // resolver configuration of the host only makes sense on the network of the host.
+    if cell.dns.is_some() && !(cell.isolate_process && cell.isolate_network) {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("dns", parent_name),
+        });
+    }
+
+    // The veth pair connects the network namespace of the cell to the host.
+    if cell.bridge.is_some() && !cell.isolate_network {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("bridge", parent_name),
+        });
+    }
+
+    // The user namespace is created along with the namespaces of isolate_process, and
+    // the nested auraed needs both maps to switch to root of the cell.
+    if cell.uid_map.is_empty() != cell.gid_map.is_empty() {
+        let field = if cell.uid_map.is_empty() { "uid_map" } else { "gid_map" };
+        return Err(ValidationError::Required {
+            field: validation::field_name(field, parent_name),
+        });
+    }
+    if !cell.uid_map.is_empty() && !cell.isolate_process {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("uid_map", parent_name),
+        });
+    }
+
+    // Creating device nodes requires CAP_MKNOD on the host.
+    if !cell.devices.is_empty() && !cell.uid_map.is_empty() {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("devices", parent_name),
+        });
+    }
+
+    // The nested auraed sets these after switching to root of the cell, and lowering
+    // the oom_score_adj requires CAP_SYS_RESOURCE on the host.
+    if !cell.uid_map.is_empty() && cell.oom_score_adj.map_or(false, |x| x < 0) {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name("oom_score_adj", parent_name),
+        });
+    }
+
+    // So does raising the RLIMIT_MEMLOCK above the hard limit inherited from auraed.
+    let memlock_limit = cell
+        .memory
+        .clone()
+        .map(cgroups::memory::MemoryController::from)
+        .and_then(|x| x.memlock_limit());
+    if let Some(memlock_limit) = memlock_limit {
+        if !cell.uid_map.is_empty() && memlock_limit > memlock_hard_limit() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name("memory.pinned", parent_name),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the hard RLIMIT_MEMLOCK of auraed, which the nested auraed inherits.
+fn memlock_hard_limit() -> u64 {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    match unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } {
+        0 => limit.rlim_max,
+        // Reject rather than have the cell fail to start
+        _ => 0,
+    }
+}
+
+/// Validates the uid_map or gid_map of a cell: each mapping must have a size, the
+/// ranges must not overlap (in the cell nor on the host), and id 0 must be mapped.
+fn validate_id_map(
+    map: Vec<aurae_proto::runtime::IdMapping>,
+    field_name: &str,
+    parent_name: Option<&str>,
+) -> Result<Vec<IdMapping>, ValidationError> {
+    if map.is_empty() {
+        return Ok(vec![]);
+    }
+
+    validation::maximum_length(
+        &map,
+        MAX_ID_MAPPINGS as u64,
+        "mappings",
+        field_name,
+        parent_name,
+    )?;
+
+    let field_name = validation::field_name(field_name, parent_name);
+
+    let mut mappings: Vec<IdMapping> = Vec::with_capacity(map.len());
+    for (i, x) in map.into_iter().enumerate() {
+        validation::minimum_value(
+            x.size,
+            1,
+            "ids",
+            &format!("{field_name}[{i}].size"),
+            None,
+        )?;
+
+        // The last id of the range has to fit in a u32, in the cell and on the host
+        let fits = |id: u32| id.checked_add(x.size - 1).is_some();
+        if !fits(x.container_id) || !fits(x.host_id) {
+            return Err(ValidationError::Invalid {
+                field: format!("{field_name}[{i}]"),
+            });
+        }
+
+        let mapping = IdMapping {
+            container_id: x.container_id,
+            host_id: x.host_id,
+            size: x.size,
+        };
+        if mappings.iter().any(|other| other.overlaps(&mapping)) {
+            return Err(ValidationError::Invalid {
+                field: format!("{field_name}[{i}]"),
+            });
+        }
+        mappings.push(mapping);
+    }
+
+    if !mappings.iter().any(|x| x.contains(0)) {
+        return Err(ValidationError::Invalid { field: field_name });
+    }
+
+    Ok(mappings)
+}
+
+/// Validates a bandwidth limit of a bridged cell: the rate must have a unit, as a bare
+/// number means bytes per second to `tc`, and both must be positive.
+fn validate_bandwidth_limit(
+    limit: Option<aurae_proto::runtime::BandwidthLimit>,
+    field_name: &str,
+    parent_name: Option<&str>,
+) -> Result<Option<BandwidthLimit>, ValidationError> {
+    let Some(limit) = limit else {
+        return Ok(None);
+    };
+
+    let field_name = validation::field_name(field_name, parent_name);
+
+    let rate = limit.rate.parse().map_err(|()| ValidationError::Invalid {
+        field: format!("{field_name}.rate"),
+    })?;
+    let burst = limit.burst.parse().map_err(|()| ValidationError::Invalid {
+        field: format!("{field_name}.burst"),
+    })?;
+
+    Ok(Some(BandwidthLimit { rate, burst }))
+}
+
+/// Validates a path of the client credentials of a cell: it must be absolute. The files
+/// are on the host, so they are checked when the cell is allocated.
+fn validate_credential_path(
+    path: String,
+    field_name: &str,
+    parent_name: &str,
+) -> Result<PathBuf, ValidationError> {
+    let path = PathBuf::from(validation::required_not_empty(
+        Some(path),
+        field_name,
+        Some(parent_name),
+    )?);
+
+    if !path.is_absolute() {
+        return Err(ValidationError::Invalid {
+            field: validation::field_name(field_name, Some(parent_name)),
+        });
     }
+
+    Ok(path)
 }
 
 #[derive(ValidatedType, Debug, Clone)]
@@ -56,11 +320,56 @@ pub struct ValidatedCell {
     #[field_type(Option<CpusetController>)]
     pub cpuset: Option<ValidatedCpusetController>,
 
+    #[field_type(Option<MemoryController>)]
+    pub memory: Option<ValidatedMemoryController>,
+
+    #[field_type(Option<IoController>)]
+    pub io: Option<ValidatedIoController>,
+
+    #[field_type(Option<PidsController>)]
+    pub pids: Option<ValidatedPidsController>,
+
+    pub labels: HashMap<String, String>,
+
     #[validate(none)]
     pub isolate_process: bool,
 
     #[validate(none)]
     pub isolate_network: bool,
+
+    #[field_type(Option<Seccomp>)]
+    pub seccomp: ValidatedSeccomp,
+
+    #[field_type(Vec<aurae_proto::runtime::Mount>)]
+    pub mounts: Vec<Mount>,
+
+    #[validate(none)]
+    pub ephemeral_root: bool,
+
+    pub max_depth: Option<u32>,
+
+    pub max_descendants: Option<u32>,
+
+    #[field_type(Option<i32>)]
+    pub oom_score_adj: Option<i16>,
+
+    #[field_type(Vec<aurae_proto::runtime::DeviceMapping>)]
+    pub devices: Vec<DeviceMapping>,
+
+    #[field_type(Option<aurae_proto::runtime::DnsConfig>)]
+    pub dns: Option<DnsConfig>,
+
+    #[field_type(Option<aurae_proto::runtime::NetworkBridge>)]
+    pub bridge: Option<BridgeConfig>,
+
+    #[field_type(Vec<aurae_proto::runtime::IdMapping>)]
+    pub uid_map: Vec<IdMapping>,
+
+    #[field_type(Vec<aurae_proto::runtime::IdMapping>)]
+    pub gid_map: Vec<IdMapping>,
+
+    #[field_type(Option<aurae_proto::runtime::ClientCredentials>)]
+    pub client_credentials: Option<ClientCredentials>,
 }
 
 impl CellTypeValidator for CellValidator {
@@ -89,10 +398,58 @@ impl CellTypeValidator for CellValidator {
             return Ok(None);
         };
 
-        Ok(Some(ValidatedCpuController::validate(
-            cpu,
-            Some(&*validation::field_name(field_name, parent_name)),
-        )?))
+        let parent_name = validation::field_name(field_name, parent_name);
+        let cpu = ValidatedCpuController::validate(cpu, Some(&*parent_name))?;
+
+        if cpu.max.is_some() && cpu.cpu_percent.is_some() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(
+                    "cpu_percent",
+                    Some(&*parent_name),
+                ),
+            });
+        }
+
+        // The period and burst are those of the quota
+        let quota = cpu.quota();
+        if quota.is_none() {
+            for (field, value) in [("period", cpu.period), ("burst", cpu.burst)]
+            {
+                if value.is_some() {
+                    return Err(ValidationError::Invalid {
+                        field: validation::field_name(
+                            field,
+                            Some(&*parent_name),
+                        ),
+                    });
+                }
+            }
+        }
+
+        if let (Some(burst), Some(quota)) = (cpu.burst, &quota) {
+            validation::maximum_value(
+                burst,
+                **quota as u64,
+                "microseconds",
+                "burst",
+                Some(&*parent_name),
+            )?;
+        }
+
+        if let (Some(uclamp_min), Some(uclamp_max)) =
+            (&cpu.uclamp_min, &cpu.uclamp_max)
+        {
+            if uclamp_min > uclamp_max {
+                return Err(ValidationError::Invalid {
+                    field: validation::field_name(
+                        "uclamp_min",
+                        Some(&*parent_name),
+                    ),
+                });
+            }
+        }
+
+        Ok(Some(cpu))
     }
 
     fn validate_cpuset(
@@ -109,170 +466,3041 @@ impl CellTypeValidator for CellValidator {
             Some(&*validation::field_name(field_name, parent_name)),
         )?))
     }
-}
 
-impl From<ValidatedCell> for super::cells::CellSpec {
-    fn from(x: ValidatedCell) -> Self {
-        let ValidatedCell {
-            name: _,
-            cpu,
-            cpuset,
-            isolate_process,
-            isolate_network,
-        } = x;
+    fn validate_memory(
+        memory: Option<MemoryController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedMemoryController>, ValidationError> {
+        let Some(memory) = memory else {
+            return Ok(None);
+        };
 
-        Self {
-            cgroup_spec: CgroupSpec {
-                cpu: cpu.map(|x| x.into()),
-                cpuset: cpuset.map(|x| x.into()),
-            },
-            iso_ctl: IsolationControls { isolate_process, isolate_network },
-        }
+        Ok(Some(ValidatedMemoryController::validate(
+            memory,
+            Some(&*validation::field_name(field_name, parent_name)),
+        )?))
     }
-}
-
-#[derive(ValidatedType, Debug, Clone)]
-pub struct ValidatedCpuController {
-    #[field_type(Option<u64>)]
-    #[validate(opt)]
-    pub weight: Option<Weight>,
-
-    #[field_type(Option<i64>)]
-    #[validate(opt)]
-    pub max: Option<Limit>,
-}
 
-impl CpuControllerTypeValidator for CpuControllerValidator {}
+    fn validate_io(
+        io: Option<IoController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedIoController>, ValidationError> {
+        let Some(io) = io else {
+            return Ok(None);
+        };
 
-impl From<ValidatedCpuController> for cgroups::cpu::CpuController {
-    fn from(value: ValidatedCpuController) -> Self {
-        let ValidatedCpuController { weight, max } = value;
-        Self { weight, max }
+        Ok(Some(ValidatedIoController::validate(
+            io,
+            Some(&*validation::field_name(field_name, parent_name)),
+        )?))
     }
-}
-
-#[derive(ValidatedType, Debug, Clone)]
-pub struct ValidatedCpusetController {
-    #[field_type(Option<String>)]
-    #[validate(opt)]
-    pub cpus: Option<Cpus>,
-
-    #[field_type(Option<String>)]
-    #[validate(opt)]
-    pub mems: Option<Mems>,
-}
 
-impl CpusetControllerTypeValidator for CpusetControllerValidator {}
+    fn validate_pids(
+        pids: Option<PidsController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedPidsController>, ValidationError> {
+        let Some(pids) = pids else {
+            return Ok(None);
+        };
 
-impl From<ValidatedCpusetController> for cgroups::cpuset::CpusetController {
-    fn from(value: ValidatedCpusetController) -> Self {
-        let ValidatedCpusetController { cpus, mems } = value;
-        Self { cpus, mems }
+        Ok(Some(ValidatedPidsController::validate(
+            pids,
+            Some(&*validation::field_name(field_name, parent_name)),
+        )?))
     }
-}
-
-#[derive(Debug, ValidatedType)]
-pub struct ValidatedCellServiceFreeRequest {
-    #[field_type(String)]
-    pub cell_name: CellNamePath,
-}
 
-impl CellServiceFreeRequestTypeValidator for CellServiceFreeRequestValidator {
-    fn validate_cell_name(
-        cell_name: String,
+    fn validate_labels(
+        labels: HashMap<String, String>,
         field_name: &str,
         parent_name: Option<&str>,
-    ) -> Result<CellNamePath, ValidationError> {
-        let cell_name =
-            CellNamePath::validate(Some(cell_name), field_name, parent_name)?;
-
-        if matches!(cell_name, CellNamePath::Empty) {
-            return Err(ValidationError::Required {
+    ) -> Result<HashMap<String, String>, ValidationError> {
+        if labels.keys().any(|key| key.is_empty()) {
+            return Err(ValidationError::Invalid {
                 field: validation::field_name(field_name, parent_name),
             });
         }
 
-        Ok(cell_name)
+        Ok(labels)
     }
-}
-
-#[derive(Debug, ValidatedType)]
-pub struct ValidatedCellServiceStartRequest {
-    #[field_type(String)]
-    #[validate]
-    pub cell_name: CellNamePath,
-    #[field_type(Option<Executable>)]
-    pub executable: ValidatedExecutable,
-}
 
-impl CellServiceStartRequestTypeValidator for CellServiceStartRequestValidator {
-    fn validate_executable(
-        executable: Option<Executable>,
+    fn validate_seccomp(
+        seccomp: Option<Seccomp>,
         field_name: &str,
         parent_name: Option<&str>,
-    ) -> Result<ValidatedExecutable, ValidationError> {
-        let executable =
-            validation::required(executable, field_name, parent_name)?;
-        ValidatedExecutable::validate(
-            executable,
+    ) -> Result<ValidatedSeccomp, ValidationError> {
+        ValidatedSeccomp::validate(
+            seccomp.unwrap_or_default(),
             Some(&*validation::field_name(field_name, parent_name)),
         )
     }
-}
-
-#[derive(Debug, ValidatedType)]
-pub struct ValidatedCellServiceStopRequest {
-    #[field_type(String)]
-    #[validate]
-    pub cell_name: CellNamePath,
-    #[field_type(String)]
-    #[validate]
-    pub executable_name: ExecutableName,
-}
 
-impl CellServiceStopRequestTypeValidator for CellServiceStopRequestValidator {}
+    fn validate_mounts(
+        mounts: Vec<aurae_proto::runtime::Mount>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<Mount>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
 
-#[derive(ValidatedType, Debug)]
-pub struct ValidatedExecutable {
-    #[field_type(String)]
-    #[validate(create)]
-    pub name: ExecutableName,
+        mounts
+            .iter()
+            .enumerate()
+            .map(|(i, mount)| {
+                Mount::new(
+                    &mount.destination,
+                    &mount.r#type,
+                    &mount.source,
+                    &mount.options,
+                )
+                .map_err(|e| ValidationError::Invalid {
+                    field: format!("{field_name}[{i}].{}", e.field()),
+                })
+            })
+            .collect()
+    }
 
-    #[field_type(String)]
-    pub command: OsString,
+    fn validate_devices(
+        devices: Vec<aurae_proto::runtime::DeviceMapping>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<DeviceMapping>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
 
-    // TODO: `#[validate(none)] is used to skip validation. Actually validate when restrictions are known.
-    #[validate(none)]
-    pub description: String,
-}
+        devices
+            .iter()
+            .enumerate()
+            .map(|(i, device)| {
+                DeviceMapping::new(
+                    &device.path,
+                    device.major,
+                    device.minor,
+                    &device.permissions,
+                )
+                .map_err(|e| ValidationError::Invalid {
+                    field: format!("{field_name}[{i}].{}", e.field()),
+                })
+            })
+            .collect()
+    }
 
-impl ExecutableTypeValidator for ExecutableValidator {
-    fn validate_command(
-        command: String,
+    fn validate_dns(
+        dns: Option<aurae_proto::runtime::DnsConfig>,
         field_name: &str,
         parent_name: Option<&str>,
-    ) -> Result<OsString, ValidationError> {
-        let command = validation::required_not_empty(
-            Some(command),
-            field_name,
-            parent_name,
+    ) -> Result<Option<DnsConfig>, ValidationError> {
+        let Some(dns) = dns else {
+            return Ok(None);
+        };
+
+        let field_name = validation::field_name(field_name, parent_name);
+
+        // glibc only uses the first 3 nameservers (MAXNS)
+        validation::maximum_length(
+            &dns.nameservers,
+            3,
+            "nameservers",
+            "nameservers",
+            Some(&field_name),
         )?;
 
-        Ok(OsString::from(command))
+        let nameservers = dns
+            .nameservers
+            .iter()
+            .enumerate()
+            .map(|(i, nameserver)| {
+                nameserver.parse::<IpAddr>().map_err(|_| {
+                    ValidationError::Invalid {
+                        field: format!("{field_name}.nameservers[{i}]"),
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        if let Some(i) = dns
+            .search
+            .iter()
+            .position(|x| x.is_empty() || x.contains(char::is_whitespace))
+        {
+            return Err(ValidationError::Invalid {
+                field: format!("{field_name}.search[{i}]"),
+            });
+        }
+
+        Ok(Some(DnsConfig { nameservers, search: dns.search }))
     }
-}
 
-impl From<ValidatedExecutable> for super::executables::ExecutableSpec {
-    fn from(x: ValidatedExecutable) -> Self {
-        let ValidatedExecutable { name, command, description } = x;
+    fn validate_bridge(
+        bridge: Option<aurae_proto::runtime::NetworkBridge>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<BridgeConfig>, ValidationError> {
+        let Some(bridge) = bridge else {
+            return Ok(None);
+        };
 
-        let mut c = Command::new("sh");
-        let _ = c.args([OsString::from("-c"), command]);
+        let field_name = validation::field_name(field_name, parent_name);
 
-        // We are checking that command has an arg to assure ourselves that `command.arg`
-        // mutates command, and is not making a clone to return
-        assert_eq!(c.as_std().get_args().len(), 2);
+        let name = validation::required_not_empty(
+            Some(bridge.name),
+            "name",
+            Some(&field_name),
+        )?;
+        // Interface names are at most 15 bytes (IFNAMSIZ, without the NUL byte)
+        validation::maximum_length(
+            name.as_bytes(),
+            15,
+            "bytes",
+            "name",
+            Some(&field_name),
+        )?;
+        if name.contains(|c: char| c == '/' || c.is_whitespace()) {
+            return Err(ValidationError::Invalid {
+                field: format!("{field_name}.name"),
+            });
+        }
 
-        Self { name, command: c, description }
+        let address = bridge.address.parse::<IpNetwork>().map_err(|_| {
+            ValidationError::Invalid { field: format!("{field_name}.address") }
+        })?;
+
+        let gateway = match bridge.gateway {
+            None => None,
+            Some(gateway) => match gateway.parse::<IpAddr>() {
+                Ok(gateway) if gateway.is_ipv4() == address.is_ipv4() => {
+                    Some(gateway)
+                }
+                _ => {
+                    return Err(ValidationError::Invalid {
+                        field: format!("{field_name}.gateway"),
+                    })
+                }
+            },
+        };
+
+        let bandwidth = BandwidthLimits {
+            ingress: validate_bandwidth_limit(
+                bridge.ingress,
+                "ingress",
+                Some(&field_name),
+            )?,
+            egress: validate_bandwidth_limit(
+                bridge.egress,
+                "egress",
+                Some(&field_name),
+            )?,
+        };
+
+        Ok(Some(BridgeConfig { bridge: name, address, gateway, bandwidth }))
+    }
+
+    fn validate_uid_map(
+        uid_map: Vec<aurae_proto::runtime::IdMapping>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<IdMapping>, ValidationError> {
+        validate_id_map(uid_map, field_name, parent_name)
+    }
+
+    fn validate_gid_map(
+        gid_map: Vec<aurae_proto::runtime::IdMapping>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<IdMapping>, ValidationError> {
+        validate_id_map(gid_map, field_name, parent_name)
+    }
+
+    fn validate_client_credentials(
+        client_credentials: Option<aurae_proto::runtime::ClientCredentials>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ClientCredentials>, ValidationError> {
+        let Some(client_credentials) = client_credentials else {
+            return Ok(None);
+        };
+
+        let field_name = validation::field_name(field_name, parent_name);
+
+        Ok(Some(ClientCredentials {
+            ca_crt: validate_credential_path(
+                client_credentials.ca_crt,
+                "ca_crt",
+                &field_name,
+            )?,
+            client_crt: validate_credential_path(
+                client_credentials.client_crt,
+                "client_crt",
+                &field_name,
+            )?,
+            client_key: validate_credential_path(
+                client_credentials.client_key,
+                "client_key",
+                &field_name,
+            )?,
+        }))
+    }
+
+    fn validate_max_depth(
+        max_depth: Option<u32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<u32>, ValidationError> {
+        if let Some(max_depth) = max_depth {
+            validation::minimum_value(
+                max_depth,
+                1,
+                "levels",
+                field_name,
+                parent_name,
+            )?;
+        }
+        Ok(max_depth)
+    }
+
+    fn validate_max_descendants(
+        max_descendants: Option<u32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<u32>, ValidationError> {
+        if let Some(max_descendants) = max_descendants {
+            validation::minimum_value(
+                max_descendants,
+                1,
+                "cgroups",
+                field_name,
+                parent_name,
+            )?;
+        }
+        Ok(max_descendants)
+    }
+
+    fn validate_oom_score_adj(
+        oom_score_adj: Option<i32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<i16>, ValidationError> {
+        let Some(oom_score_adj) = oom_score_adj else {
+            return Ok(None);
+        };
+
+        validation::minimum_value(
+            oom_score_adj,
+            -1000,
+            "unit",
+            field_name,
+            parent_name,
+        )?;
+        validation::maximum_value(
+            oom_score_adj,
+            1000,
+            "unit",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(Some(oom_score_adj as i16))
+    }
+}
+
+impl From<ValidatedCell> for CellSpec {
+    fn from(x: ValidatedCell) -> Self {
+        let ValidatedCell {
+            name: _,
+            cpu,
+            cpuset,
+            memory,
+            io,
+            pids,
+            labels,
+            isolate_process,
+            isolate_network,
+            seccomp,
+            mounts,
+            ephemeral_root,
+            max_depth,
+            max_descendants,
+            oom_score_adj,
+            devices,
+            dns,
+            bridge,
+            uid_map,
+            gid_map,
+            client_credentials,
+        } = x;
+
+        // Validation rejects a bridge without isolate_network
+        let network = match (isolate_network, bridge) {
+            (false, _) => NetworkMode::None,
+            (true, None) => NetworkMode::Isolated,
+            (true, Some(bridge)) => NetworkMode::Bridged(bridge),
+        };
+
+        let memory: Option<cgroups::memory::MemoryController> =
+            memory.map(|x| x.into());
+        let memlock_limit = memory.as_ref().and_then(|x| x.memlock_limit());
+
+        Self {
+            cgroup_spec: CgroupSpec {
+                cpu: cpu.map(|x| x.into()),
+                cpuset: cpuset.map(|x| x.into()),
+                memory,
+                io: io.map(|x| x.into()),
+                pids: pids.map(|x| x.into()),
+            },
+            nesting_limits: NestingLimits { max_depth, max_descendants },
+            iso_ctl: IsolationControls {
+                isolate_process,
+                network,
+                seccomp: seccomp.into(),
+                ephemeral_root,
+                mounts,
+                devices,
+                oom_score_adj,
+                memlock_limit,
+                dns,
+                uid_map,
+                gid_map,
+            },
+            client_credentials,
+            labels,
+        }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedSeccomp {
+    #[field_type(Vec<String>)]
+    pub allowed_architectures: Vec<Architecture>,
+
+    #[field_type(i32)]
+    pub deny_action: DenyAction,
+}
+
+impl SeccompTypeValidator for SeccompValidator {
+    fn validate_allowed_architectures(
+        allowed_architectures: Vec<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<Architecture>, ValidationError> {
+        if allowed_architectures.is_empty() {
+            return Ok(vec![Architecture::native()]);
+        }
+
+        allowed_architectures
+            .iter()
+            .map(|arch| {
+                arch.parse().map_err(|_| ValidationError::Invalid {
+                    field: validation::field_name(field_name, parent_name),
+                })
+            })
+            .collect()
+    }
+
+    fn validate_deny_action(
+        deny_action: i32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<DenyAction, ValidationError> {
+        validation::valid_enum(deny_action, field_name, parent_name)
+    }
+}
+
+impl From<ValidatedSeccomp> for SeccompControls {
+    fn from(x: ValidatedSeccomp) -> Self {
+        let ValidatedSeccomp { allowed_architectures, deny_action } = x;
+        Self { allowed_architectures, deny_action }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedCpuController {
+    #[field_type(Option<u64>)]
+    #[validate(opt)]
+    pub weight: Option<Weight>,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub max: Option<Limit>,
+
+    #[field_type(Option<u32>)]
+    #[validate(opt)]
+    pub uclamp_min: Option<Uclamp>,
+
+    #[field_type(Option<u32>)]
+    #[validate(opt)]
+    pub uclamp_max: Option<Uclamp>,
+
+    #[field_type(Option<f64>)]
+    pub cpu_percent: Option<f64>,
+
+    pub period: Option<u64>,
+
+    #[validate(none)]
+    pub burst: Option<u64>,
+}
+
+impl ValidatedCpuController {
+    /// Returns the quota of `cpu.max`, set as max or converted from cpu_percent.
+    fn quota(&self) -> Option<Limit> {
+        // max and cpu_percent are mutually exclusive (see validate_cpu)
+        self.max.clone().or_else(|| {
+            let period = self.period.unwrap_or(cgroups::cpu::DEFAULT_PERIOD);
+            self.cpu_percent
+                .map(|percent| cgroups::cpu::max_from_percent(percent, period))
+        })
+    }
+}
+
+impl CpuControllerTypeValidator for CpuControllerValidator {
+    fn validate_period(
+        period: Option<u64>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<u64>, ValidationError> {
+        let Some(period) = period else {
+            return Ok(None);
+        };
+
+        validation::minimum_value(
+            period,
+            cgroups::cpu::MIN_PERIOD,
+            "microseconds",
+            field_name,
+            parent_name,
+        )?;
+        validation::maximum_value(
+            period,
+            cgroups::cpu::MAX_PERIOD,
+            "microseconds",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(Some(period))
+    }
+
+    fn validate_cpu_percent(
+        cpu_percent: Option<f64>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<f64>, ValidationError> {
+        let Some(cpu_percent) = cpu_percent else {
+            return Ok(None);
+        };
+
+        // also rejects NaN
+        if !(cpu_percent > 0.0 && cpu_percent.is_finite()) {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Some(cpu_percent))
+    }
+}
+
+impl From<ValidatedCpuController> for cgroups::cpu::CpuController {
+    fn from(value: ValidatedCpuController) -> Self {
+        let max = value.quota();
+        let ValidatedCpuController {
+            weight,
+            max: _,
+            uclamp_min,
+            uclamp_max,
+            cpu_percent: _,
+            period,
+            burst,
+        } = value;
+        Self { weight, max, period, burst, uclamp_min, uclamp_max }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedCpusetController {
+    #[field_type(Option<String>)]
+    #[validate(opt)]
+    pub cpus: Option<Cpus>,
+
+    #[field_type(Option<String>)]
+    #[validate(opt)]
+    pub mems: Option<Mems>,
+}
+
+impl CpusetControllerTypeValidator for CpusetControllerValidator {}
+
+impl From<ValidatedCpusetController> for cgroups::cpuset::CpusetController {
+    fn from(value: ValidatedCpusetController) -> Self {
+        let ValidatedCpusetController { cpus, mems } = value;
+        Self { cpus, mems }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedMemoryController {
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub max: Option<Limit>,
+
+    #[field_type(Option<u64>)]
+    #[validate(opt)]
+    pub shares: Option<Weight>,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub zswap_max: Option<Limit>,
+
+    #[field_type(Option<i64>)]
+    pub pinned: Option<Limit>,
+
+    #[field_type(i32)]
+    pub pinning: MemoryPinning,
+
+    #[field_type(Option<u32>)]
+    pub swappiness: Option<u8>,
+}
+
+impl MemoryControllerTypeValidator for MemoryControllerValidator {
+    fn validate_pinned(
+        pinned: Option<i64>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<Limit>, ValidationError> {
+        let Some(pinned) = pinned else {
+            return Ok(None);
+        };
+
+        validation::minimum_value(pinned, 1, "bytes", field_name, parent_name)?;
+
+        Ok(Some(Limit::validate(Some(pinned), field_name, parent_name)?))
+    }
+
+    fn validate_pinning(
+        pinning: i32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<MemoryPinning, ValidationError> {
+        validation::valid_enum(pinning, field_name, parent_name)
+    }
+
+    fn validate_swappiness(
+        swappiness: Option<u32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<u8>, ValidationError> {
+        let Some(swappiness) = swappiness else {
+            return Ok(None);
+        };
+
+        validation::maximum_value(
+            swappiness,
+            100,
+            "unit",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(Some(swappiness as u8))
+    }
+
+    fn post_validate(
+        output: &ValidatedMemoryController,
+        parent_name: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        // The cell can't use more memory than it pinned
+        if let (Some(max), Some(pinned)) = (&output.max, &output.pinned) {
+            if max < pinned {
+                return Err(ValidationError::Invalid {
+                    field: validation::field_name("max", parent_name),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ValidatedMemoryController> for cgroups::memory::MemoryController {
+    fn from(value: ValidatedMemoryController) -> Self {
+        let ValidatedMemoryController {
+            max,
+            shares,
+            zswap_max,
+            pinned,
+            pinning,
+            swappiness,
+        } = value;
+        Self { max, shares, zswap_max, pinned, pinning, swappiness }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedPidsController {
+    #[field_type(Option<String>)]
+    #[validate(opt)]
+    pub max: Option<PidsMax>,
+}
+
+impl PidsControllerTypeValidator for PidsControllerValidator {}
+
+impl From<ValidatedPidsController> for cgroups::pids::PidsController {
+    fn from(value: ValidatedPidsController) -> Self {
+        let ValidatedPidsController { max } = value;
+        Self { max }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedIoController {
+    #[field_type(Option<u64>)]
+    #[validate(opt)]
+    pub weight: Option<Weight>,
+
+    #[field_type(Vec<aurae_proto::runtime::IoMax>)]
+    pub max: Vec<ValidatedIoMax>,
+}
+
+impl IoControllerTypeValidator for IoControllerValidator {
+    fn validate_max(
+        max: Vec<aurae_proto::runtime::IoMax>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<ValidatedIoMax>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
+
+        let mut validated: Vec<ValidatedIoMax> = vec![];
+        for (i, io_max) in max.into_iter().enumerate() {
+            let parent_name = format!("{field_name}[{i}]");
+            let io_max = ValidatedIoMax::validate(io_max, Some(&parent_name))?;
+
+            // A device can only be written once, or the rollback of the limits as a
+            // group would restore the first write of the device
+            if validated.iter().any(|x| x.device == io_max.device) {
+                return Err(ValidationError::Invalid {
+                    field: validation::field_name("device", Some(&parent_name)),
+                });
+            }
+
+            validated.push(io_max);
+        }
+
+        Ok(validated)
+    }
+}
+
+impl From<ValidatedIoController> for cgroups::io::IoController {
+    fn from(value: ValidatedIoController) -> Self {
+        let ValidatedIoController { weight, max } = value;
+        Self { weight, max: max.into_iter().map(|x| x.into()).collect() }
+    }
+}
+
+#[derive(ValidatedType, Debug, Clone)]
+pub struct ValidatedIoMax {
+    #[field_type(String)]
+    #[validate]
+    pub device: DeviceNumber,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub rbps: Option<Limit>,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub wbps: Option<Limit>,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub riops: Option<Limit>,
+
+    #[field_type(Option<i64>)]
+    #[validate(opt)]
+    pub wiops: Option<Limit>,
+}
+
+impl IoMaxTypeValidator for IoMaxValidator {}
+
+impl From<ValidatedIoMax> for cgroups::io::IoMax {
+    fn from(value: ValidatedIoMax) -> Self {
+        let ValidatedIoMax { device, rbps, wbps, riops, wiops } = value;
+        Self { device, rbps, wbps, riops, wiops }
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceUpdateRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+
+    #[field_type(Option<CpuController>)]
+    pub cpu: Option<ValidatedCpuController>,
+
+    #[field_type(Option<CpusetController>)]
+    pub cpuset: Option<ValidatedCpusetController>,
+
+    #[field_type(Option<MemoryController>)]
+    pub memory: Option<ValidatedMemoryController>,
+
+    #[field_type(Option<IoController>)]
+    pub io: Option<ValidatedIoController>,
+
+    #[field_type(Option<PidsController>)]
+    pub pids: Option<ValidatedPidsController>,
+}
+
+/// The controllers are validated as those of a [Cell].
+impl CellServiceUpdateRequestTypeValidator
+    for CellServiceUpdateRequestValidator
+{
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+
+    fn validate_cpu(
+        cpu: Option<CpuController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedCpuController>, ValidationError> {
+        CellValidator::validate_cpu(cpu, field_name, parent_name)
+    }
+
+    fn validate_cpuset(
+        cpuset: Option<CpusetController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedCpusetController>, ValidationError> {
+        CellValidator::validate_cpuset(cpuset, field_name, parent_name)
+    }
+
+    fn validate_memory(
+        memory: Option<MemoryController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedMemoryController>, ValidationError> {
+        CellValidator::validate_memory(memory, field_name, parent_name)
+    }
+
+    fn validate_io(
+        io: Option<IoController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedIoController>, ValidationError> {
+        CellValidator::validate_io(io, field_name, parent_name)
+    }
+
+    fn validate_pids(
+        pids: Option<PidsController>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<ValidatedPidsController>, ValidationError> {
+        CellValidator::validate_pids(pids, field_name, parent_name)
+    }
+}
+
+impl From<ValidatedCellServiceUpdateRequest> for CgroupSpec {
+    fn from(x: ValidatedCellServiceUpdateRequest) -> Self {
+        let ValidatedCellServiceUpdateRequest {
+            cell_name: _,
+            cpu,
+            cpuset,
+            memory,
+            io,
+            pids,
+        } = x;
+
+        Self {
+            cpu: cpu.map(|x| x.into()),
+            cpuset: cpuset.map(|x| x.into()),
+            memory: memory.map(|x| x.into()),
+            io: io.map(|x| x.into()),
+            pids: pids.map(|x| x.into()),
+        }
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceFreeRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+
+    #[validate(none)]
+    pub return_final_stats: bool,
+
+    #[field_type(i32)]
+    pub children_policy: FreeChildrenPolicy,
+}
+
+impl CellServiceFreeRequestTypeValidator for CellServiceFreeRequestValidator {
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        let cell_name =
+            CellNamePath::validate(Some(cell_name), field_name, parent_name)?;
+
+        if matches!(cell_name, CellNamePath::Empty) {
+            return Err(ValidationError::Required {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(cell_name)
+    }
+
+    fn validate_children_policy(
+        children_policy: i32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<FreeChildrenPolicy, ValidationError> {
+        validation::valid_enum(children_policy, field_name, parent_name)
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceDescribeRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+}
+
+impl CellServiceDescribeRequestTypeValidator
+    for CellServiceDescribeRequestValidator
+{
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceStatRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+}
+
+impl CellServiceStatRequestTypeValidator for CellServiceStatRequestValidator {
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceDrainRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+
+    #[validate(none)]
+    pub include_memory_stat: bool,
+}
+
+impl CellServiceDrainRequestTypeValidator for CellServiceDrainRequestValidator {
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceFreezeRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+}
+
+impl CellServiceFreezeRequestTypeValidator
+    for CellServiceFreezeRequestValidator
+{
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceThawRequest {
+    #[field_type(String)]
+    pub cell_name: CellNamePath,
+}
+
+impl CellServiceThawRequestTypeValidator for CellServiceThawRequestValidator {
+    fn validate_cell_name(
+        cell_name: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<CellNamePath, ValidationError> {
+        CellServiceFreeRequestValidator::validate_cell_name(
+            cell_name,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceFreeBySelectorRequest {
+    #[field_type(HashMap<String, String>)]
+    #[validate]
+    pub selector: LabelSelector,
+}
+
+impl CellServiceFreeBySelectorRequestTypeValidator
+    for CellServiceFreeBySelectorRequestValidator
+{
+}
+
+/// Time to wait for a line matching the ready log pattern, if the request doesn't set one.
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceStartRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(Option<Executable>)]
+    pub executable: ValidatedExecutable,
+    #[validate(none)]
+    pub validate_only: bool,
+    #[field_type(u64)]
+    pub start_timeout_ms: Option<Duration>,
+    #[validate(none)]
+    pub check_command_exists: bool,
+    #[field_type(Option<bool>)]
+    pub verify_placement: bool,
+    #[field_type(String)]
+    pub ready_log_pattern: Option<Regex>,
+    #[field_type(u64)]
+    pub ready_timeout_ms: Duration,
+    #[field_type(Vec<String>)]
+    pub depends_on: Vec<ExecutableName>,
+    #[validate(none)]
+    pub wait_for_ready: bool,
+    #[validate(none)]
+    pub new_session: bool,
+    #[validate(none)]
+    pub new_process_group: bool,
+    #[validate(none)]
+    pub generation: Option<u64>,
+    #[field_type(Option<u32>)]
+    pub ready_tcp_port: Option<u16>,
+    #[validate(none)]
+    pub free_cell_on_exit: bool,
+}
+
+impl CellServiceStartRequestTypeValidator for CellServiceStartRequestValidator {
+    fn validate_executable(
+        executable: Option<Executable>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<ValidatedExecutable, ValidationError> {
+        let executable =
+            validation::required(executable, field_name, parent_name)?;
+        ValidatedExecutable::validate(
+            executable,
+            Some(&*validation::field_name(field_name, parent_name)),
+        )
+    }
+
+    fn validate_start_timeout_ms(
+        start_timeout_ms: u64,
+        _field_name: &str,
+        _parent_name: Option<&str>,
+    ) -> Result<Option<Duration>, ValidationError> {
+        Ok(match start_timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        })
+    }
+
+    fn validate_verify_placement(
+        verify_placement: Option<bool>,
+        _field_name: &str,
+        _parent_name: Option<&str>,
+    ) -> Result<bool, ValidationError> {
+        Ok(verify_placement.unwrap_or(true))
+    }
+
+    fn validate_ready_log_pattern(
+        ready_log_pattern: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<Regex>, ValidationError> {
+        if ready_log_pattern.is_empty() {
+            return Ok(None);
+        }
+
+        let pattern = Regex::new(&ready_log_pattern).map_err(|_| {
+            ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            }
+        })?;
+
+        Ok(Some(pattern))
+    }
+
+    fn validate_ready_timeout_ms(
+        ready_timeout_ms: u64,
+        _field_name: &str,
+        _parent_name: Option<&str>,
+    ) -> Result<Duration, ValidationError> {
+        Ok(match ready_timeout_ms {
+            0 => DEFAULT_READY_TIMEOUT,
+            ms => Duration::from_millis(ms),
+        })
+    }
+
+    fn validate_depends_on(
+        depends_on: Vec<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<ExecutableName>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
+
+        let mut validated: Vec<ExecutableName> = vec![];
+        for (i, dependency) in depends_on.into_iter().enumerate() {
+            let dependency = ExecutableName::validate(
+                Some(dependency),
+                &format!("{field_name}[{i}]"),
+                None,
+            )?;
+
+            if validated.contains(&dependency) {
+                return Err(ValidationError::Invalid {
+                    field: format!("{field_name}[{i}]"),
+                });
+            }
+
+            validated.push(dependency);
+        }
+
+        Ok(validated)
+    }
+
+    fn validate_ready_tcp_port(
+        ready_tcp_port: Option<u32>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<u16>, ValidationError> {
+        let Some(ready_tcp_port) = ready_tcp_port else {
+            return Ok(None);
+        };
+
+        match u16::try_from(ready_tcp_port) {
+            Ok(port) if port != 0 => Ok(Some(port)),
+            _ => Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            }),
+        }
+    }
+
+    fn post_validate(
+        output: &ValidatedCellServiceStartRequest,
+        parent_name: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        // An executable can't wait for itself to be started
+        if output.depends_on.contains(&output.executable.name) {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name("depends_on", parent_name),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceStopRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(String)]
+    #[validate]
+    pub executable_name: ExecutableName,
+    #[validate(none)]
+    pub return_output_tail: u32,
+    #[field_type(String)]
+    pub signal: Signal,
+    #[field_type(u64)]
+    pub grace_period_ms: Duration,
+    #[field_type(Vec<aurae_proto::runtime::StopSignal>)]
+    pub signal_escalation: Vec<(Signal, Duration)>,
+}
+
+impl CellServiceStopRequestTypeValidator for CellServiceStopRequestValidator {
+    fn validate_signal(
+        signal: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Signal, ValidationError> {
+        if signal.is_empty() {
+            return Ok(Signal::SIGTERM);
+        }
+
+        parse_stop_signal(&signal).ok_or_else(|| ValidationError::Invalid {
+            field: validation::field_name(field_name, parent_name),
+        })
+    }
+
+    fn validate_signal_escalation(
+        signal_escalation: Vec<aurae_proto::runtime::StopSignal>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<(Signal, Duration)>, ValidationError> {
+        validation::maximum_length(
+            &signal_escalation,
+            MAX_STOP_ESCALATION as u64,
+            "signals",
+            field_name,
+            parent_name,
+        )?;
+
+        let field_name = validation::field_name(field_name, parent_name);
+        let last = signal_escalation.len().saturating_sub(1);
+
+        signal_escalation
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let signal = match parse_stop_signal(&x.signal) {
+                    // Nothing is escalated to after a SIGKILL
+                    Some(Signal::SIGKILL) if i != last => None,
+                    signal => signal,
+                }
+                .ok_or_else(|| ValidationError::Invalid {
+                    field: format!("{field_name}[{i}].signal"),
+                })?;
+
+                let wait_field_name = format!("{field_name}[{i}].wait_ms");
+                validation::minimum_value(
+                    x.wait_ms,
+                    1,
+                    "ms",
+                    &wait_field_name,
+                    None,
+                )?;
+                validation::maximum_value(
+                    x.wait_ms,
+                    MAX_STOP_WAIT.as_millis() as u32,
+                    "ms",
+                    &wait_field_name,
+                    None,
+                )?;
+
+                Ok((signal, Duration::from_millis(x.wait_ms.into())))
+            })
+            .collect()
+    }
+
+    fn validate_grace_period_ms(
+        grace_period_ms: u64,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Duration, ValidationError> {
+        validation::maximum_value(
+            grace_period_ms,
+            MAX_STOP_WAIT.as_millis() as u64,
+            "ms",
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(match grace_period_ms {
+            0 => DEFAULT_GRACE_PERIOD,
+            ms => Duration::from_millis(ms),
+        })
+    }
+
+    fn post_validate(
+        output: &ValidatedCellServiceStopRequest,
+        parent_name: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        // Nothing is escalated to after a SIGKILL
+        if output.signal == Signal::SIGKILL
+            && !output.signal_escalation.is_empty()
+        {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name("signal_escalation", parent_name),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the name of one of the [STOP_SIGNALS] (e.g., SIGINT).
+fn parse_stop_signal(signal: &str) -> Option<Signal> {
+    signal.parse::<Signal>().ok().filter(|x| STOP_SIGNALS.contains(x))
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceRestartRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(String)]
+    #[validate]
+    pub executable_name: ExecutableName,
+}
+
+impl CellServiceRestartRequestTypeValidator
+    for CellServiceRestartRequestValidator
+{
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceStopGenerationRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[validate(none)]
+    pub below: u64,
+}
+
+impl CellServiceStopGenerationRequestTypeValidator
+    for CellServiceStopGenerationRequestValidator
+{
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceRunRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(Option<Executable>)]
+    pub executable: ValidatedExecutable,
+    #[field_type(u64)]
+    pub timeout_ms: Option<Duration>,
+    #[field_type(u32)]
+    pub return_output_tail: u32,
+    #[validate(none)]
+    pub free_cell: bool,
+}
+
+impl CellServiceRunRequestTypeValidator for CellServiceRunRequestValidator {
+    fn validate_executable(
+        executable: Option<Executable>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<ValidatedExecutable, ValidationError> {
+        CellServiceStartRequestValidator::validate_executable(
+            executable,
+            field_name,
+            parent_name,
+        )
+    }
+
+    fn validate_timeout_ms(
+        timeout_ms: u64,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<Duration>, ValidationError> {
+        CellServiceStartRequestValidator::validate_start_timeout_ms(
+            timeout_ms,
+            field_name,
+            parent_name,
+        )
+    }
+
+    fn validate_return_output_tail(
+        return_output_tail: u32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<u32, ValidationError> {
+        // The output tail capacity of the executable is raised to this value
+        ExecutableValidator::validate_output_tail_capacity(
+            return_output_tail,
+            field_name,
+            parent_name,
+        )
+    }
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceListRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[validate(none)]
+    pub include_stats: bool,
+    #[validate(none)]
+    pub recursive: bool,
+}
+
+impl CellServiceListRequestTypeValidator for CellServiceListRequestValidator {}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceListExecutablesRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+}
+
+impl CellServiceListExecutablesRequestTypeValidator
+    for CellServiceListExecutablesRequestValidator
+{
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceListFdsRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(String)]
+    #[validate]
+    pub executable_name: ExecutableName,
+}
+
+impl CellServiceListFdsRequestTypeValidator
+    for CellServiceListFdsRequestValidator
+{
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceLogStreamRequest {
+    #[field_type(String)]
+    #[validate]
+    pub cell_name: CellNamePath,
+    #[field_type(String)]
+    #[validate]
+    pub executable_name: ExecutableName,
+}
+
+impl CellServiceLogStreamRequestTypeValidator
+    for CellServiceLogStreamRequestValidator
+{
+}
+
+#[derive(Debug, ValidatedType)]
+pub struct ValidatedCellServiceGetCellByTidRequest {
+    #[field_type(i32)]
+    pub tid: i32,
+}
+
+impl CellServiceGetCellByTidRequestTypeValidator
+    for CellServiceGetCellByTidRequestValidator
+{
+    fn validate_tid(
+        tid: i32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<i32, ValidationError> {
+        validation::minimum_value(tid, 1, "unit", field_name, parent_name)?;
+        Ok(tid)
+    }
+}
+
+#[derive(ValidatedType, Debug)]
+pub struct ValidatedExecutable {
+    #[field_type(String)]
+    #[validate(create)]
+    pub name: ExecutableName,
+
+    #[field_type(String)]
+    pub command: OsString,
+
+    #[field_type(Vec<String>)]
+    pub args: Vec<OsString>,
+
+    // TODO: `#[validate(none)] is used to skip validation. Actually validate when restrictions are known.
+    #[validate(none)]
+    pub description: String,
+
+    pub env: HashMap<String, String>,
+
+    #[field_type(String)]
+    pub env_file: Option<PathBuf>,
+
+    #[field_type(String)]
+    pub working_dir: Option<PathBuf>,
+
+    #[field_type(String)]
+    pub process_title: Option<OsString>,
+
+    pub output_tail_capacity: u32,
+
+    #[field_type(Option<aurae_proto::runtime::OutputFraming>)]
+    pub output_framing: OutputFraming,
+
+    #[validate(none)]
+    pub ignore_sigpipe: bool,
+
+    #[validate(none)]
+    pub inherit_env: bool,
+
+    #[field_type(Vec<String>)]
+    pub drop_capabilities: Vec<Capability>,
+
+    #[field_type(String)]
+    pub seccomp_profile: Option<PathBuf>,
+}
+
+impl ExecutableTypeValidator for ExecutableValidator {
+    fn validate_command(
+        command: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<OsString, ValidationError> {
+        let command = validation::required_not_empty(
+            Some(command),
+            field_name,
+            parent_name,
+        )?;
+
+        Ok(OsString::from(command))
+    }
+
+    fn validate_args(
+        args: Vec<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<OsString>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
+
+        args.into_iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                if arg.contains('\0') {
+                    return Err(ValidationError::Invalid {
+                        field: format!("{field_name}[{i}]"),
+                    });
+                }
+                Ok(OsString::from(arg))
+            })
+            .collect()
+    }
+
+    fn validate_env(
+        env: HashMap<String, String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<HashMap<String, String>, ValidationError> {
+        let is_invalid = |key: &String, value: &String| {
+            key.is_empty() || key.contains(['=', '\0']) || value.contains('\0')
+        };
+
+        if env.iter().any(|(key, value)| is_invalid(key, value)) {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(env)
+    }
+
+    fn validate_env_file(
+        env_file: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<PathBuf>, ValidationError> {
+        if env_file.is_empty() {
+            return Ok(None);
+        }
+
+        // The file is on the host, so it is checked to exist when the executable starts
+        let env_file = PathBuf::from(env_file);
+        if !env_file.is_absolute() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Some(env_file))
+    }
+
+    fn validate_working_dir(
+        working_dir: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<PathBuf>, ValidationError> {
+        if working_dir.is_empty() {
+            return Ok(None);
+        }
+
+        // The directory is checked to exist when the executable starts
+        let working_dir = PathBuf::from(working_dir);
+        if !working_dir.is_absolute() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Some(working_dir))
+    }
+
+    fn validate_process_title(
+        process_title: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<OsString>, ValidationError> {
+        if process_title.is_empty() {
+            return Ok(None);
+        }
+
+        if process_title.contains('\0') {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Some(OsString::from(process_title)))
+    }
+
+    fn validate_output_tail_capacity(
+        output_tail_capacity: u32,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<u32, ValidationError> {
+        validation::maximum_value(
+            output_tail_capacity,
+            MAX_OUTPUT_TAIL_CAPACITY,
+            "lines",
+            field_name,
+            parent_name,
+        )?;
+        Ok(output_tail_capacity)
+    }
+
+    fn validate_output_framing(
+        output_framing: Option<aurae_proto::runtime::OutputFraming>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<OutputFraming, ValidationError> {
+        let aurae_proto::runtime::OutputFraming { max_line_length, chunk_size } =
+            output_framing.unwrap_or_default();
+
+        let parent_name = validation::field_name(field_name, parent_name);
+        let validate_length = |length: u32, field_name: &str| {
+            validation::maximum_value(
+                length as usize,
+                MAX_FRAME_LENGTH,
+                "bytes",
+                field_name,
+                Some(&parent_name),
+            )
+            .map(|_| length as usize)
+        };
+
+        match (max_line_length, chunk_size) {
+            (0, 0) => Ok(OutputFraming::default()),
+            (max_length, 0) => Ok(OutputFraming::Lines {
+                max_length: validate_length(max_length, "max_line_length")?,
+            }),
+            (0, size) => Ok(OutputFraming::Chunks {
+                size: validate_length(size, "chunk_size")?,
+            }),
+            (_, _) => Err(ValidationError::Invalid {
+                field: validation::field_name("chunk_size", Some(&parent_name)),
+            }),
+        }
+    }
+
+    fn validate_drop_capabilities(
+        drop_capabilities: Vec<String>,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Vec<Capability>, ValidationError> {
+        let field_name = validation::field_name(field_name, parent_name);
+
+        drop_capabilities
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                Capability::from_name(name).ok_or_else(|| {
+                    ValidationError::Invalid {
+                        field: format!("{field_name}[{i}]"),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn validate_seccomp_profile(
+        seccomp_profile: String,
+        field_name: &str,
+        parent_name: Option<&str>,
+    ) -> Result<Option<PathBuf>, ValidationError> {
+        if seccomp_profile.is_empty() {
+            return Ok(None);
+        }
+
+        // The profile is on the host, so it is loaded when the executable starts
+        let seccomp_profile = PathBuf::from(seccomp_profile);
+        if !seccomp_profile.is_absolute() {
+            return Err(ValidationError::Invalid {
+                field: validation::field_name(field_name, parent_name),
+            });
+        }
+
+        Ok(Some(seccomp_profile))
+    }
+}
+
+impl From<ValidatedExecutable> for super::executables::ExecutableSpec {
+    fn from(x: ValidatedExecutable) -> Self {
+        let ValidatedExecutable {
+            name,
+            command,
+            args,
+            description,
+            env,
+            env_file,
+            working_dir,
+            process_title,
+            output_tail_capacity,
+            output_framing,
+            ignore_sigpipe,
+            inherit_env,
+            drop_capabilities,
+            seccomp_profile,
+        } = x;
+
+        let program = if args.is_empty() {
+            OsString::from("sh")
+        } else {
+            // Run the program directly, to skip the quoting rules of the shell
+            command.clone()
+        };
+        let process_title = process_title.map(|process_title| {
+            ProcessTitle::new(process_title, program.clone())
+        });
+
+        // With a title, the program is executed through a symlink named after it,
+        // which sets the name of the process (see [ProcessTitle])
+        let mut c = Command::new(match &process_title {
+            Some(process_title) => process_title.link_path().as_os_str(),
+            None => program.as_os_str(),
+        });
+        if args.is_empty() {
+            let _ = c.args([OsString::from("-c"), command.clone()]);
+            // We are checking that command has an arg to assure ourselves that `command.arg`
+            // mutates command, and is not making a clone to return
+            assert_eq!(c.as_std().get_args().len(), 2);
+        } else {
+            let _ = c.args(args);
+        }
+        // Don't leak the environment of auraed into executables by default
+        if !inherit_env {
+            let _ = c.env_clear();
+        }
+        let _ = c.envs(env);
+        let _ = c.current_dir(working_dir.as_deref().unwrap_or(Path::new("/")));
+        if let Some(process_title) = &process_title {
+            let _ = c.arg0(process_title.title());
+        }
+
+        let mut pre_exec_hooks = PreExecHooks::default();
+        if !drop_capabilities.is_empty() {
+            pre_exec_hooks.push("drop_capabilities", move || {
+                drop_from_bounding_set(&drop_capabilities)
+            });
+        }
+
+        Self {
+            name,
+            description,
+            original_command: command,
+            command: c,
+            env_file,
+            working_dir,
+            process_title,
+            output_tail_capacity,
+            output_framing,
+            sigpipe: Sigpipe::new(ignore_sigpipe),
+            ready_log_pattern: None,
+            process_group: ProcessGroup::default(),
+            generation: None,
+            seccomp_profile,
+            pre_exec_hooks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seccomp_defaults_to_native_architecture() {
+        let seccomp = ValidatedSeccomp::validate(
+            Seccomp::default(),
+            Some("cell.seccomp"),
+        )
+        .expect("valid seccomp");
+
+        assert_eq!(seccomp.allowed_architectures, vec![Architecture::native()]);
+        assert_eq!(seccomp.deny_action, DenyAction::Enosys);
+    }
+
+    #[test]
+    fn test_seccomp_unknown_architecture_is_rejected() {
+        let seccomp = Seccomp {
+            allowed_architectures: vec!["x86_64".into(), "mips".into()],
+            deny_action: 0,
+        };
+
+        assert!(matches!(
+            ValidatedSeccomp::validate(seccomp, Some("cell.seccomp")),
+            Err(ValidationError::Invalid { field })
+                if field == "cell.seccomp.allowed_architectures"
+        ));
+    }
+
+    #[test]
+    fn test_seccomp_unknown_deny_action_is_rejected() {
+        let seccomp = Seccomp { allowed_architectures: vec![], deny_action: 7 };
+
+        assert!(matches!(
+            ValidatedSeccomp::validate(seccomp, None),
+            Err(ValidationError::Invalid { field }) if field == "deny_action"
+        ));
+    }
+
+    fn cell_with_uclamp(uclamp_min: u32, uclamp_max: u32) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            cpu: Some(CpuController {
+                uclamp_min: Some(uclamp_min),
+                uclamp_max: Some(uclamp_max),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_uclamp_is_validated() {
+        let cell = ValidatedCell::validate(cell_with_uclamp(20, 80), None)
+            .expect("valid cell");
+        let cpu = cell.cpu.expect("cpu");
+        assert_eq!(cpu.uclamp_min, Some(Uclamp::new(20)));
+        assert_eq!(cpu.uclamp_max, Some(Uclamp::new(80)));
+
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_uclamp(20, 101), None),
+            Err(ValidationError::Maximum { field, .. }) if field == "cpu.uclamp_max"
+        ));
+    }
+
+    #[test]
+    fn test_stop_signal_is_validated() {
+        let stop_request = |signal: &str, grace_period_ms| {
+            ValidatedCellServiceStopRequest::validate(
+                CellServiceStopRequest {
+                    executable_name: "ae-exe".into(),
+                    signal: signal.into(),
+                    grace_period_ms,
+                    ..Default::default()
+                },
+                None,
+            )
+        };
+
+        let request = stop_request("", 0).expect("valid request");
+        assert_eq!(request.signal, Signal::SIGTERM);
+        assert_eq!(request.grace_period_ms, DEFAULT_GRACE_PERIOD);
+
+        let request = stop_request("SIGINT", 500).expect("valid request");
+        assert_eq!(request.signal, Signal::SIGINT);
+        assert_eq!(request.grace_period_ms, Duration::from_millis(500));
+
+        for signal in ["SIGSEGV", "SIGSTOP", "TERM", "15"] {
+            assert!(matches!(
+                stop_request(signal, 0),
+                Err(ValidationError::Invalid { field }) if field == "signal"
+            ));
+        }
+
+        assert!(stop_request("", 300_000).is_ok());
+        assert!(matches!(
+            stop_request("", 300_001),
+            Err(ValidationError::Maximum { field, .. }) if field == "grace_period_ms"
+        ));
+    }
+
+    #[test]
+    fn test_signal_escalation_is_validated() {
+        let stop_request = |signal_escalation: &[(&str, u32)]| {
+            ValidatedCellServiceStopRequest::validate(
+                CellServiceStopRequest {
+                    executable_name: "ae-exe".into(),
+                    signal_escalation: signal_escalation
+                        .iter()
+                        .map(|(signal, wait_ms)| {
+                            aurae_proto::runtime::StopSignal {
+                                signal: signal.to_string(),
+                                wait_ms: *wait_ms,
+                            }
+                        })
+                        .collect(),
+                    ..Default::default()
+                },
+                None,
+            )
+        };
+
+        let request = stop_request(&[]).expect("valid request");
+        assert!(request.signal_escalation.is_empty());
+
+        let request = stop_request(&[("SIGINT", 5000), ("SIGKILL", 1)])
+            .expect("valid request");
+        assert_eq!(
+            request.signal_escalation,
+            vec![
+                (Signal::SIGINT, Duration::from_secs(5)),
+                (Signal::SIGKILL, Duration::from_millis(1)),
+            ]
+        );
+
+        for (signal_escalation, field) in [
+            (
+                &[("SIGINT", 5000), ("SIGSTOP", 5000)][..],
+                "signal_escalation[1].signal",
+            ),
+            (
+                &[("SIGKILL", 5000), ("SIGINT", 5000)][..],
+                "signal_escalation[0].signal",
+            ),
+            (&[("SIGINT", 0)][..], "signal_escalation[0].wait_ms"),
+            (&[("SIGINT", 300_001)][..], "signal_escalation[0].wait_ms"),
+            (&[("SIGHUP", 1000); 9][..], "signal_escalation"),
+        ] {
+            let e = stop_request(signal_escalation)
+                .expect_err("invalid signal escalation");
+            assert_eq!(e.get_field(), field);
+        }
+
+        let e = ValidatedCellServiceStopRequest::validate(
+            CellServiceStopRequest {
+                executable_name: "ae-exe".into(),
+                signal: "SIGKILL".into(),
+                signal_escalation: vec![aurae_proto::runtime::StopSignal {
+                    signal: "SIGKILL".into(),
+                    wait_ms: 1000,
+                }],
+                ..Default::default()
+            },
+            None,
+        )
+        .expect_err("nothing to escalate to after SIGKILL");
+        assert_eq!(e.get_field(), "signal_escalation");
+    }
+
+    #[test]
+    fn test_cpu_percent_is_converted_to_max() {
+        let cell = Cell {
+            name: "ae-1".into(),
+            cpu: Some(CpuController {
+                cpu_percent: Some(150.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let cell = ValidatedCell::validate(cell, None).expect("valid cell");
+        let cpu: cgroups::cpu::CpuController =
+            cell.cpu.expect("cpu controller").into();
+        assert_eq!(cpu.max, Some(Limit::new(1500000)));
+    }
+
+    #[test]
+    fn test_cpu_percent_is_validated() {
+        for cpu_percent in [0.0, -50.0, f64::NAN, f64::INFINITY] {
+            let cell = Cell {
+                name: "ae-1".into(),
+                cpu: Some(CpuController {
+                    cpu_percent: Some(cpu_percent),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert!(matches!(
+                ValidatedCell::validate(cell, None),
+                Err(ValidationError::Invalid { field }) if field == "cpu.cpu_percent"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_cpu_percent_and_max_are_mutually_exclusive() {
+        let cell = Cell {
+            name: "ae-1".into(),
+            cpu: Some(CpuController {
+                max: Some(500000),
+                cpu_percent: Some(50.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ValidatedCell::validate(cell, None),
+            Err(ValidationError::Invalid { field }) if field == "cpu.cpu_percent"
+        ));
+    }
+
+    #[test]
+    fn test_update_request_is_validated_as_a_cell() {
+        let request = CellServiceUpdateRequest {
+            cell_name: "ae-1".into(),
+            pids: Some(PidsController { max: Some("10".into()) }),
+            ..Default::default()
+        };
+        let validated =
+            ValidatedCellServiceUpdateRequest::validate(request.clone(), None)
+                .expect("valid request");
+        let cgroup_spec: CgroupSpec = validated.into();
+        assert!(cgroup_spec.pids.is_some());
+        assert!(cgroup_spec.cpu.is_none());
+
+        let request = CellServiceUpdateRequest {
+            cpu: Some(CpuController {
+                max: Some(500000),
+                cpu_percent: Some(50.0),
+                ..Default::default()
+            }),
+            ..request
+        };
+        assert!(matches!(
+            ValidatedCellServiceUpdateRequest::validate(request, None),
+            Err(ValidationError::Invalid { field }) if field == "cpu.cpu_percent"
+        ));
+
+        assert!(matches!(
+            ValidatedCellServiceUpdateRequest::validate(
+                CellServiceUpdateRequest::default(),
+                None
+            ),
+            Err(ValidationError::Required { field }) if field == "cell_name"
+        ));
+    }
+
+    fn cell_with_cpu_quota(
+        max: Option<i64>,
+        cpu_percent: Option<f64>,
+        period: Option<u64>,
+        burst: Option<u64>,
+    ) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            cpu: Some(CpuController {
+                max,
+                cpu_percent,
+                period,
+                burst,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cpu_period_and_burst_are_validated() {
+        let cell = ValidatedCell::validate(
+            cell_with_cpu_quota(Some(50000), None, Some(100000), Some(20000)),
+            None,
+        )
+        .expect("valid cell");
+        let cpu: cgroups::cpu::CpuController =
+            cell.cpu.expect("cpu controller").into();
+        assert_eq!(cpu.max, Some(Limit::new(50000)));
+        assert_eq!(cpu.period, Some(100000));
+        assert_eq!(cpu.burst, Some(20000));
+
+        // cpu_percent is converted to a quota of the period
+        let cell = ValidatedCell::validate(
+            cell_with_cpu_quota(None, Some(50.0), Some(100000), Some(50000)),
+            None,
+        )
+        .expect("valid cell");
+        let cpu: cgroups::cpu::CpuController =
+            cell.cpu.expect("cpu controller").into();
+        assert_eq!(cpu.max, Some(Limit::new(50000)));
+
+        for (cell, field) in [
+            (
+                cell_with_cpu_quota(Some(500), None, Some(999), None),
+                "cpu.period",
+            ),
+            (
+                cell_with_cpu_quota(Some(500000), None, Some(1000001), None),
+                "cpu.period",
+            ),
+            (
+                cell_with_cpu_quota(
+                    Some(50000),
+                    None,
+                    Some(100000),
+                    Some(50001),
+                ),
+                "cpu.burst",
+            ),
+            (
+                cell_with_cpu_quota(
+                    None,
+                    Some(50.0),
+                    Some(100000),
+                    Some(50001),
+                ),
+                "cpu.burst",
+            ),
+            (cell_with_cpu_quota(None, None, Some(100000), None), "cpu.period"),
+            (cell_with_cpu_quota(None, None, None, Some(20000)), "cpu.burst"),
+        ] {
+            let e = ValidatedCell::validate(cell, None)
+                .expect_err("invalid cpu controller");
+            assert_eq!(e.get_field(), field);
+        }
+    }
+
+    #[test]
+    fn test_uclamp_min_greater_than_max_is_rejected() {
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_uclamp(80, 20), None),
+            Err(ValidationError::Invalid { field }) if field == "cpu.uclamp_min"
+        ));
+    }
+
+    #[test]
+    fn test_nesting_limits_are_validated() {
+        let cell = Cell {
+            name: "ae-1".into(),
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let spec: CellSpec =
+            ValidatedCell::validate(cell, None).expect("valid cell").into();
+        assert_eq!(
+            spec.nesting_limits,
+            NestingLimits { max_depth: Some(2), max_descendants: None }
+        );
+
+        let cell = Cell {
+            name: "ae-1".into(),
+            max_descendants: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ValidatedCell::validate(cell, Some("cell")),
+            Err(ValidationError::Minimum { field, .. }) if field == "cell.max_descendants"
+        ));
+    }
+
+    #[test]
+    fn test_oom_score_adj_is_validated() {
+        let cell = Cell {
+            name: "ae-1".into(),
+            oom_score_adj: Some(-1000),
+            ..Default::default()
+        };
+        let spec: CellSpec =
+            ValidatedCell::validate(cell, None).expect("valid cell").into();
+        assert_eq!(spec.iso_ctl.oom_score_adj, Some(-1000));
+
+        let cell = Cell {
+            name: "ae-1".into(),
+            oom_score_adj: Some(1001),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ValidatedCell::validate(cell, Some("cell")),
+            Err(ValidationError::Maximum { field, .. }) if field == "cell.oom_score_adj"
+        ));
+    }
+
+    #[test]
+    fn test_memory_controller_round_trip() {
+        let memory = MemoryController {
+            max: Some(1 << 30),
+            shares: None,
+            zswap_max: Some(0),
+            ..Default::default()
+        };
+
+        let validated =
+            ValidatedMemoryController::validate(memory.clone(), None)
+                .expect("valid memory controller");
+        assert_eq!(validated.zswap_max, Some(Limit::new(0)));
+
+        let controller: cgroups::memory::MemoryController = validated.into();
+        assert_eq!(MemoryController::from(controller), memory);
+
+        assert!(matches!(
+            ValidatedMemoryController::validate(
+                MemoryController { zswap_max: Some(-1), ..Default::default() },
+                Some("memory"),
+            ),
+            Err(ValidationError::Minimum { field, .. }) if field == "memory.zswap_max"
+        ));
+    }
+
+    #[test]
+    fn test_pinned_memory_is_validated() {
+        let memory = MemoryController {
+            pinned: Some(1 << 30),
+            pinning: aurae_proto::runtime::MemoryPinning::ReserveAndMlock
+                as i32,
+            ..Default::default()
+        };
+        let validated =
+            ValidatedMemoryController::validate(memory.clone(), None)
+                .expect("valid memory controller");
+        assert_eq!(validated.pinning, MemoryPinning::ReserveAndMlock);
+
+        let controller: cgroups::memory::MemoryController = validated.into();
+        assert_eq!(MemoryController::from(controller), memory);
+
+        assert!(matches!(
+            ValidatedMemoryController::validate(
+                MemoryController { pinned: Some(0), ..Default::default() },
+                Some("memory"),
+            ),
+            Err(ValidationError::Minimum { field, .. }) if field == "memory.pinned"
+        ));
+
+        assert!(matches!(
+            ValidatedMemoryController::validate(
+                MemoryController { pinning: 3, ..Default::default() },
+                Some("memory"),
+            ),
+            Err(ValidationError::Invalid { field }) if field == "memory.pinning"
+        ));
+
+        // the cell can't use less memory than it pinned
+        assert!(matches!(
+            ValidatedMemoryController::validate(
+                MemoryController {
+                    max: Some(1 << 29),
+                    pinned: Some(1 << 30),
+                    ..Default::default()
+                },
+                Some("memory"),
+            ),
+            Err(ValidationError::Invalid { field }) if field == "memory.max"
+        ));
+    }
+
+    #[test]
+    fn test_swappiness_range() {
+        for swappiness in [0, 60, 100] {
+            let validated = ValidatedMemoryController::validate(
+                MemoryController {
+                    swappiness: Some(swappiness),
+                    ..Default::default()
+                },
+                None,
+            )
+            .expect("valid swappiness");
+            assert_eq!(validated.swappiness, Some(swappiness as u8));
+        }
+
+        assert!(matches!(
+            ValidatedMemoryController::validate(
+                MemoryController { swappiness: Some(101), ..Default::default() },
+                Some("memory"),
+            ),
+            Err(ValidationError::Maximum { field, .. }) if field == "memory.swappiness"
+        ));
+    }
+
+    fn cell_with_mount(isolate_process: bool, kind: &str) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            isolate_process,
+            mounts: vec![aurae_proto::runtime::Mount {
+                destination: "/tmp".into(),
+                r#type: kind.into(),
+                source: "tmpfs".into(),
+                options: vec!["nosuid".into(), "size=64m".into()],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mounts_are_validated() {
+        let cell =
+            ValidatedCell::validate(cell_with_mount(true, "tmpfs"), None)
+                .expect("valid cell");
+        assert_eq!(cell.mounts.len(), 1);
+        assert_eq!(cell.mounts[0].data.as_deref(), Some("size=64m"));
+
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_mount(true, "nfs"), Some("cell")),
+            Err(ValidationError::Invalid { field }) if field == "cell.mounts[0].type"
+        ));
+    }
+
+    #[test]
+    fn test_mounts_require_isolate_process() {
+        assert!(validate_cell(cell_with_mount(true, "tmpfs")).is_ok());
+        assert!(matches!(
+            validate_cell(cell_with_mount(false, "tmpfs")),
+            Err(ValidationError::Invalid { field }) if field == "mounts"
+        ));
+    }
+
+    fn cell_with_io(devices: &[&str], rbps: i64) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            io: Some(IoController {
+                weight: Some(500),
+                max: devices
+                    .iter()
+                    .map(|device| aurae_proto::runtime::IoMax {
+                        device: device.to_string(),
+                        rbps: Some(rbps),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_io_is_validated() {
+        let cell =
+            ValidatedCell::validate(cell_with_io(&["8:0", "8:16"], 1024), None)
+                .expect("valid cell");
+        let spec = CellSpec::from(cell);
+        let io = spec.cgroup_spec.io.expect("io");
+        assert_eq!(io.weight, Some(Weight::new(500)));
+        assert_eq!(io.max.len(), 2);
+        assert_eq!(io.max[1].device.to_string(), "8:16");
+        assert_eq!(io.max[1].wbps, None);
+
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_io(&["8:0", "sdb"], 1024), Some("cell")),
+            Err(ValidationError::Invalid { field }) if field == "cell.io.max[1].device"
+        ));
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_io(&["8:0"], -1), Some("cell")),
+            Err(ValidationError::Minimum { field, .. }) if field == "cell.io.max[0].rbps"
+        ));
+    }
+
+    #[test]
+    fn test_io_devices_are_unique() {
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_io(&["8:0", "8:16", "8:0"], 1024), None),
+            Err(ValidationError::Invalid { field }) if field == "io.max[2].device"
+        ));
+    }
+
+    #[test]
+    fn test_pids_is_validated() {
+        let cell = Cell {
+            name: "ae-1".into(),
+            pids: Some(PidsController { max: Some("32".into()) }),
+            ..Default::default()
+        };
+        let spec: CellSpec =
+            ValidatedCell::validate(cell, None).expect("valid cell").into();
+        let pids = spec.cgroup_spec.pids.expect("pids");
+        assert_eq!(pids.max.map(|x| x.to_string()).as_deref(), Some("32"));
+
+        let cell = Cell {
+            name: "ae-1".into(),
+            pids: Some(PidsController { max: Some("0".into()) }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ValidatedCell::validate(cell, Some("cell")),
+            Err(ValidationError::Minimum { field, .. }) if field == "cell.pids.max"
+        ));
+    }
+
+    fn cell_with_device(isolate_process: bool, minor: u32) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            isolate_process,
+            devices: vec![aurae_proto::runtime::DeviceMapping {
+                path: "/dev/null".into(),
+                major: 1,
+                minor,
+                permissions: "rw".into(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_devices_are_validated() {
+        let cell = ValidatedCell::validate(cell_with_device(true, 3), None)
+            .expect("valid cell");
+        assert_eq!(cell.devices.len(), 1);
+        assert_eq!(cell.devices[0].mode, 0o666);
+
+        assert!(matches!(
+            ValidatedCell::validate(cell_with_device(true, 5), Some("cell")),
+            Err(ValidationError::Invalid { field }) if field == "cell.devices[0].minor"
+        ));
+    }
+
+    #[test]
+    fn test_devices_require_isolate_process() {
+        assert!(validate_cell(cell_with_device(true, 3)).is_ok());
+        assert!(matches!(
+            validate_cell(cell_with_device(false, 3)),
+            Err(ValidationError::Invalid { field }) if field == "devices"
+        ));
+    }
+
+    #[test]
+    fn test_ephemeral_root_requires_isolate_process() {
+        let cell = |isolate_process| Cell {
+            name: "ae-1".into(),
+            isolate_process,
+            ephemeral_root: true,
+            ..Default::default()
+        };
+
+        assert!(validate_cell(cell(true)).is_ok());
+        assert!(matches!(
+            validate_cell(cell(false)),
+            Err(ValidationError::Invalid { field }) if field == "ephemeral_root"
+        ));
+    }
+
+    fn cell_with_dns(
+        isolate_network: bool,
+        nameservers: &[&str],
+        search: &[&str],
+    ) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            isolate_process: true,
+            isolate_network,
+            dns: Some(aurae_proto::runtime::DnsConfig {
+                nameservers: nameservers
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect(),
+                search: search.iter().map(|x| x.to_string()).collect(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dns_is_validated() {
+        let cell = ValidatedCell::validate(
+            cell_with_dns(
+                true,
+                &["10.0.0.53", "2001:db8::53"],
+                &["cell.local"],
+            ),
+            None,
+        )
+        .expect("valid cell");
+        let dns = cell.dns.expect("dns");
+        assert_eq!(dns.nameservers.len(), 2);
+        assert_eq!(dns.search, vec!["cell.local".to_string()]);
+
+        assert!(matches!(
+            ValidatedCell::validate(
+                cell_with_dns(true, &["10.0.0.53", "ns.local"], &[]),
+                Some("cell"),
+            ),
+            Err(ValidationError::Invalid { field }) if field == "cell.dns.nameservers[1]"
+        ));
+
+        assert!(matches!(
+            ValidatedCell::validate(
+                cell_with_dns(true, &["1.1.1.1", "1.0.0.1", "8.8.8.8", "8.8.4.4"], &[]),
+                Some("cell"),
+            ),
+            Err(ValidationError::Maximum { field, .. }) if field == "cell.dns.nameservers"
+        ));
+
+        assert!(matches!(
+            ValidatedCell::validate(
+                cell_with_dns(true, &["1.1.1.1"], &["a.local b.local"]),
+                Some("cell"),
+            ),
+            Err(ValidationError::Invalid { field }) if field == "cell.dns.search[0]"
+        ));
+    }
+
+    #[test]
+    fn test_dns_requires_isolate_network() {
+        assert!(validate_cell(cell_with_dns(true, &["1.1.1.1"], &[])).is_ok());
+        assert!(matches!(
+            validate_cell(cell_with_dns(false, &["1.1.1.1"], &[])),
+            Err(ValidationError::Invalid { field }) if field == "dns"
+        ));
+    }
+
+    fn cell_with_bridge(
+        isolate_network: bool,
+        name: &str,
+        address: &str,
+        gateway: Option<&str>,
+    ) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            isolate_network,
+            bridge: Some(aurae_proto::runtime::NetworkBridge {
+                name: name.into(),
+                address: address.into(),
+                gateway: gateway.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bridge_is_validated() {
+        let cell = ValidatedCell::validate(
+            cell_with_bridge(true, "br0", "10.0.0.2/24", Some("10.0.0.1")),
+            None,
+        )
+        .expect("valid cell");
+        let spec = CellSpec::from(cell);
+        assert_eq!(
+            spec.iso_ctl.network,
+            NetworkMode::Bridged(BridgeConfig {
+                bridge: "br0".into(),
+                address: "10.0.0.2/24".parse().expect("valid address"),
+                gateway: Some("10.0.0.1".parse().expect("valid gateway")),
+                bandwidth: Default::default(),
+            })
+        );
+
+        for (cell, field) in [
+            (
+                cell_with_bridge(true, "", "10.0.0.2/24", None),
+                "cell.bridge.name",
+            ),
+            (
+                cell_with_bridge(
+                    true,
+                    "a-very-long-bridge",
+                    "10.0.0.2/24",
+                    None,
+                ),
+                "cell.bridge.name",
+            ),
+            (
+                cell_with_bridge(true, "br 0", "10.0.0.2/24", None),
+                "cell.bridge.name",
+            ),
+            (
+                cell_with_bridge(true, "br0", "10.0.0.2/33", None),
+                "cell.bridge.address",
+            ),
+            (
+                cell_with_bridge(true, "br0", "10.0.0.2/24", Some("gateway")),
+                "cell.bridge.gateway",
+            ),
+            (
+                cell_with_bridge(true, "br0", "10.0.0.2/24", Some("fd00::1")),
+                "cell.bridge.gateway",
+            ),
+        ] {
+            let e = ValidatedCell::validate(cell, Some("cell"))
+                .expect_err("invalid bridge");
+            assert_eq!(e.get_field(), field);
+        }
+    }
+
+    #[test]
+    fn test_bridge_bandwidth_is_validated() {
+        let limit =
+            |rate: &str, burst: &str| aurae_proto::runtime::BandwidthLimit {
+                rate: rate.into(),
+                burst: burst.into(),
+            };
+        let mut cell =
+            cell_with_bridge(true, "br0", "10.0.0.2/24", Some("10.0.0.1"));
+        let bridge = cell.bridge.as_mut().expect("bridge");
+        bridge.ingress = Some(limit("10mbit", "32kb"));
+
+        let spec = CellSpec::from(
+            ValidatedCell::validate(cell.clone(), None).expect("valid cell"),
+        );
+        let NetworkMode::Bridged(config) = spec.iso_ctl.network else {
+            panic!("expected a bridged cell");
+        };
+        assert_eq!(
+            config.bandwidth,
+            BandwidthLimits {
+                ingress: Some(BandwidthLimit {
+                    rate: "10mbit".parse().expect("rate"),
+                    burst: "32kb".parse().expect("burst"),
+                }),
+                egress: None,
+            }
+        );
+
+        for (ingress, egress, field) in [
+            (Some(limit("10", "32kb")), None, "cell.bridge.ingress.rate"),
+            (Some(limit("10mbit", "")), None, "cell.bridge.ingress.burst"),
+            (None, Some(limit("0mbit", "32kb")), "cell.bridge.egress.rate"),
+            (
+                None,
+                Some(limit("1mbit", "32 furlongs")),
+                "cell.bridge.egress.burst",
+            ),
+        ] {
+            let bridge = cell.bridge.as_mut().expect("bridge");
+            bridge.ingress = ingress;
+            bridge.egress = egress;
+            let e = ValidatedCell::validate(cell.clone(), Some("cell"))
+                .expect_err("invalid bandwidth limit");
+            assert_eq!(e.get_field(), field);
+        }
+    }
+
+    #[test]
+    fn test_bridge_requires_isolate_network() {
+        let spec = CellSpec::from(
+            ValidatedCell::validate(
+                Cell {
+                    name: "ae-1".into(),
+                    isolate_network: true,
+                    ..Default::default()
+                },
+                None,
+            )
+            .expect("valid cell"),
+        );
+        assert_eq!(spec.iso_ctl.network, NetworkMode::Isolated);
+
+        assert!(matches!(
+            validate_cell(cell_with_bridge(false, "br0", "10.0.0.2/24", None)),
+            Err(ValidationError::Invalid { field }) if field == "bridge"
+        ));
+    }
+
+    fn cell_with_client_credentials(
+        ca_crt: &Path,
+        client_crt: &Path,
+        client_key: &Path,
+    ) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            client_credentials: Some(aurae_proto::runtime::ClientCredentials {
+                ca_crt: ca_crt.to_string_lossy().into(),
+                client_crt: client_crt.to_string_lossy().into(),
+                client_key: client_key.to_string_lossy().into(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_client_credentials_are_validated() {
+        let dir = Path::new("/etc/aurae/cells/ae-1");
+        let (ca_crt, client_crt, client_key) = (
+            dir.join("ca.crt"),
+            dir.join("client.crt"),
+            dir.join("client.key"),
+        );
+
+        let spec = CellSpec::from(
+            ValidatedCell::validate(
+                cell_with_client_credentials(&ca_crt, &client_crt, &client_key),
+                None,
+            )
+            .expect("valid cell"),
+        );
+
+        for (cell, field) in [
+            (
+                cell_with_client_credentials(
+                    Path::new(""),
+                    &client_crt,
+                    &client_key,
+                ),
+                "cell.client_credentials.ca_crt",
+            ),
+            (
+                cell_with_client_credentials(
+                    &ca_crt,
+                    Path::new("client.crt"),
+                    &client_key,
+                ),
+                "cell.client_credentials.client_crt",
+            ),
+            (
+                cell_with_client_credentials(
+                    &ca_crt,
+                    &client_crt,
+                    Path::new("client.key"),
+                ),
+                "cell.client_credentials.client_key",
+            ),
+        ] {
+            let e = ValidatedCell::validate(cell, Some("cell"))
+                .expect_err("invalid client credentials");
+            assert_eq!(e.get_field(), field);
+        }
+
+        assert_eq!(
+            spec.client_credentials,
+            Some(ClientCredentials { ca_crt, client_crt, client_key })
+        );
+    }
+
+    #[test]
+    fn test_client_credentials_default_to_none() {
+        let spec = CellSpec::from(
+            ValidatedCell::validate(
+                Cell { name: "ae-1".into(), ..Default::default() },
+                None,
+            )
+            .expect("valid cell"),
+        );
+        assert_eq!(spec.client_credentials, None);
+    }
+
+    fn id_mapping(
+        container_id: u32,
+        host_id: u32,
+        size: u32,
+    ) -> aurae_proto::runtime::IdMapping {
+        aurae_proto::runtime::IdMapping { container_id, host_id, size }
+    }
+
+    fn cell_with_id_maps(
+        uid_map: Vec<aurae_proto::runtime::IdMapping>,
+        gid_map: Vec<aurae_proto::runtime::IdMapping>,
+    ) -> Cell {
+        Cell {
+            name: "ae-1".into(),
+            isolate_process: true,
+            uid_map,
+            gid_map,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_id_maps_are_validated() {
+        let map =
+            vec![id_mapping(0, 100000, 1000), id_mapping(1000, 200000, 1)];
+        let spec = CellSpec::from(
+            ValidatedCell::validate(cell_with_id_maps(map.clone(), map), None)
+                .expect("valid cell"),
+        );
+        assert!(spec.iso_ctl.has_user_namespace());
+        assert_eq!(
+            spec.iso_ctl.uid_map[1],
+            IdMapping { container_id: 1000, host_id: 200000, size: 1 }
+        );
+
+        for (uid_map, field) in [
+            (vec![id_mapping(0, 100000, 0)], "cell.uid_map[0].size"),
+            (vec![id_mapping(1, 100000, 1000)], "cell.uid_map"),
+            (vec![id_mapping(0, u32::MAX, 2)], "cell.uid_map[0]"),
+            (
+                vec![id_mapping(0, 100000, 1000), id_mapping(999, 200000, 1)],
+                "cell.uid_map[1]",
+            ),
+            (
+                vec![id_mapping(0, 100000, 1000), id_mapping(1000, 100999, 1)],
+                "cell.uid_map[1]",
+            ),
+        ] {
+            let cell =
+                cell_with_id_maps(uid_map, vec![id_mapping(0, 100000, 1000)]);
+            let e = ValidatedCell::validate(cell, Some("cell"))
+                .expect_err("invalid uid_map");
+            assert_eq!(e.get_field(), field);
+        }
+    }
+
+    #[test]
+    fn test_id_map_combinations() {
+        let map = vec![id_mapping(0, 100000, 65536)];
+        assert!(
+            validate_cell(cell_with_id_maps(map.clone(), map.clone())).is_ok()
+        );
+
+        assert!(matches!(
+            validate_cell(cell_with_id_maps(map.clone(), vec![])),
+            Err(ValidationError::Required { field }) if field == "gid_map"
+        ));
+
+        let mut cell = cell_with_id_maps(map.clone(), map.clone());
+        cell.isolate_process = false;
+        assert!(matches!(
+            validate_cell(cell),
+            Err(ValidationError::Invalid { field }) if field == "uid_map"
+        ));
+
+        let mut cell = cell_with_id_maps(map.clone(), map);
+        cell.devices = vec![aurae_proto::runtime::DeviceMapping {
+            path: "/dev/null".into(),
+            major: 1,
+            minor: 3,
+            permissions: "rw".into(),
+        }];
+        assert!(matches!(
+            validate_cell(cell),
+            Err(ValidationError::Invalid { field }) if field == "devices"
+        ));
+    }
+
+    #[test]
+    fn test_id_maps_reject_settings_that_require_root_of_the_host() {
+        let map = vec![id_mapping(0, 100000, 65536)];
+
+        let mut cell = cell_with_id_maps(map.clone(), map.clone());
+        cell.oom_score_adj = Some(500);
+        assert!(validate_cell(cell).is_ok());
+
+        let mut cell = cell_with_id_maps(map.clone(), map.clone());
+        cell.oom_score_adj = Some(-1);
+        assert!(matches!(
+            validate_cell(cell),
+            Err(ValidationError::Invalid { field }) if field == "oom_score_adj"
+        ));
+
+        let pinned = |pinned: u64| {
+            let mut cell = cell_with_id_maps(map.clone(), map.clone());
+            cell.memory = Some(MemoryController {
+                pinned: Some(pinned as i64),
+                pinning: aurae_proto::runtime::MemoryPinning::ReserveAndMlock
+                    as i32,
+                ..Default::default()
+            });
+            cell
+        };
+
+        let hard_limit = memlock_hard_limit();
+        if hard_limit > 0 && hard_limit < i64::MAX as u64 {
+            assert!(validate_cell(pinned(hard_limit)).is_ok());
+        }
+        if hard_limit < i64::MAX as u64 {
+            assert!(matches!(
+                validate_cell(pinned(hard_limit + 1)),
+                Err(ValidationError::Invalid { field }) if field == "memory.pinned"
+            ));
+        }
+
+        // Without a user namespace, the nested auraed raises the limit as root of the host
+        let mut cell = pinned(u32::MAX as u64 + 1);
+        cell.uid_map.clear();
+        cell.gid_map.clear();
+        assert!(validate_cell(cell).is_ok());
+    }
+
+    fn executable_with_framing(
+        max_line_length: u32,
+        chunk_size: u32,
+    ) -> Executable {
+        Executable {
+            name: "server".into(),
+            command: "python3 -m http.server".into(),
+            output_framing: Some(aurae_proto::runtime::OutputFraming {
+                max_line_length,
+                chunk_size,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_output_framing_is_validated() {
+        let validate = |executable| {
+            ValidatedExecutable::validate(executable, None)
+                .map(|executable| executable.output_framing)
+        };
+
+        assert_eq!(
+            validate(executable_with_framing(0, 0)).expect("valid framing"),
+            OutputFraming::default()
+        );
+        assert_eq!(
+            validate(executable_with_framing(1024, 0)).expect("valid framing"),
+            OutputFraming::Lines { max_length: 1024 }
+        );
+        assert_eq!(
+            validate(executable_with_framing(0, 4096)).expect("valid framing"),
+            OutputFraming::Chunks { size: 4096 }
+        );
+
+        assert!(matches!(
+            validate(executable_with_framing(1024, 4096)),
+            Err(ValidationError::Invalid { field }) if field == "output_framing.chunk_size"
+        ));
+        assert!(matches!(
+            validate(executable_with_framing(2 * 1024 * 1024, 0)),
+            Err(ValidationError::Maximum { field, .. }) if field == "output_framing.max_line_length"
+        ));
+    }
+
+    #[test]
+    fn test_drop_capabilities_are_validated() {
+        let executable = |drop_capabilities: &[&str]| Executable {
+            name: "server".into(),
+            command: "python3 -m http.server".into(),
+            drop_capabilities: drop_capabilities
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            ..Default::default()
+        };
+
+        let validated = ValidatedExecutable::validate(
+            executable(&["CAP_NET_RAW", "CAP_SYS_ADMIN"]),
+            None,
+        )
+        .expect("valid executable");
+        assert_eq!(
+            validated.drop_capabilities,
+            vec![
+                Capability::from_name("CAP_NET_RAW").expect("known"),
+                Capability::from_name("CAP_SYS_ADMIN").expect("known"),
+            ]
+        );
+
+        // An unknown capability is rejected rather than skipped
+        assert!(matches!(
+            ValidatedExecutable::validate(
+                executable(&["CAP_NET_RAW", "CAP_NET_RAWR"]),
+                Some("executable"),
+            ),
+            Err(ValidationError::Invalid { field })
+                if field == "executable.drop_capabilities[1]"
+        ));
+    }
+
+    #[test]
+    fn test_seccomp_profile_is_validated() {
+        let executable = |seccomp_profile: &Path| Executable {
+            name: "server".into(),
+            command: "python3 -m http.server".into(),
+            seccomp_profile: seccomp_profile.to_string_lossy().into(),
+            ..Default::default()
+        };
+        // The profile is only loaded at start, where a missing one fails the start
+        let profile = Path::new("/etc/aurae/seccomp/missing.json");
+        let validated =
+            ValidatedExecutable::validate(executable(profile), None)
+                .expect("valid executable");
+        assert_eq!(validated.seccomp_profile.as_deref(), Some(profile));
+
+        assert!(matches!(
+            ValidatedExecutable::validate(
+                executable(Path::new("profile.json")),
+                Some("executable"),
+            ),
+            Err(ValidationError::Invalid { field })
+                if field == "executable.seccomp_profile"
+        ));
+    }
+
+    fn start_request(ready_log_pattern: &str) -> CellServiceStartRequest {
+        CellServiceStartRequest {
+            executable: Some(Executable {
+                name: "server".into(),
+                command: "python3 -m http.server".into(),
+                ..Default::default()
+            }),
+            ready_log_pattern: ready_log_pattern.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ready_log_pattern_is_validated() {
+        let request = ValidatedCellServiceStartRequest::validate(
+            start_request(r"Serving HTTP on .* port \d+"),
+            None,
+        )
+        .expect("valid request");
+        let pattern = request.ready_log_pattern.expect("pattern");
+        assert!(pattern
+            .is_match(
+                "Serving HTTP on 0.0.0.0 port 8000 (http://0.0.0.0:8000/) ..."
+            )
+            .expect("match"));
+        assert!(!pattern
+            .is_match("Traceback (most recent call last):")
+            .expect("match"));
+        assert_eq!(request.ready_timeout_ms, DEFAULT_READY_TIMEOUT);
+
+        assert!(matches!(
+            ValidatedCellServiceStartRequest::validate(start_request("listening on ("), None),
+            Err(ValidationError::Invalid { field }) if field == "ready_log_pattern"
+        ));
+    }
+
+    #[test]
+    fn test_depends_on_is_validated() {
+        let mut request = start_request("");
+        request.depends_on = vec!["db".into(), "cache".into()];
+        let request = ValidatedCellServiceStartRequest::validate(request, None)
+            .expect("valid request");
+        assert_eq!(request.depends_on.len(), 2);
+
+        let mut request = start_request("");
+        request.depends_on = vec!["db".into(), "db".into()];
+        assert!(matches!(
+            ValidatedCellServiceStartRequest::validate(request, None),
+            Err(ValidationError::Invalid { field }) if field == "depends_on[1]"
+        ));
+
+        let mut request = start_request("");
+        request.depends_on = vec!["server".into()];
+        assert!(matches!(
+            ValidatedCellServiceStartRequest::validate(request, None),
+            Err(ValidationError::Invalid { field }) if field == "depends_on"
+        ));
+    }
+
+    #[test]
+    fn test_ready_tcp_port_is_validated() {
+        let mut request = start_request("");
+        request.ready_tcp_port = Some(8080);
+        let request = ValidatedCellServiceStartRequest::validate(request, None)
+            .expect("valid request");
+        assert_eq!(request.ready_tcp_port, Some(8080));
+
+        for port in [0, 65536] {
+            let mut request = start_request("");
+            request.ready_tcp_port = Some(port);
+            assert!(matches!(
+                ValidatedCellServiceStartRequest::validate(request, None),
+                Err(ValidationError::Invalid { field }) if field == "ready_tcp_port"
+            ));
+        }
     }
 }
@@ -28,7 +28,10 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
-pub(crate) use cell_service::CellService;
+pub(crate) use cell_service::{
+    ensure_daemon_cgroup, validate_cell, CellService, CgroupStats,
+    DaemonCgroup, KillEscalation,
+};
 pub(crate) use pod_service::PodService;
 
 mod cell_service;
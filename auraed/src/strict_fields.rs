@@ -0,0 +1,566 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Strict mode for unknown proto fields.
+//!
+//! proto3 ignores fields it doesn't know, so a request from a client built with newer
+//! protos (e.g., setting a limit this auraed doesn't have yet) is served as if the field
+//! was never set. In strict mode, auraed instead rejects requests with fields that are
+//! not in the protos it was built with, to catch the version skew early.
+//!
+//! Generated messages drop unknown fields when decoding, so requests are checked in a
+//! [tower] layer, against the descriptors of the protos, before they are decoded.
+
+use futures::future::BoxFuture;
+use prost::{encoding::decode_varint, Message};
+use prost_types::{
+    field_descriptor_proto::Type, DescriptorProto, FileDescriptorSet,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter},
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tonic::{
+    body::BoxBody,
+    codegen::http::{Request, Response},
+    transport::Body,
+    Status,
+};
+use tower::{Layer, Service};
+
+/// A field of a request that is not in the descriptor of its message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnknownField {
+    pub message: String,
+    pub number: u32,
+}
+
+impl Display for UnknownField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown field {} in {}", self.number, self.message)
+    }
+}
+
+/// The request is not a valid message, which is left for decoding to report.
+struct Malformed;
+
+/// The fields of the messages, and the request message of the methods, of a set of protos.
+#[derive(Debug, Default)]
+pub(crate) struct MessageDescriptors {
+    /// The fields of each message by number, with the message type of message fields.
+    /// Messages are keyed by their fully qualified name (e.g., `.aurae.runtime.v0.Cell`).
+    messages: HashMap<String, HashMap<u32, Option<String>>>,
+    /// The request message of each unary method, by its path (e.g., `/aurae.runtime.v0.CellService/Allocate`).
+    /// Streaming methods are left out, as their requests can't be read before they are served.
+    methods: HashMap<String, String>,
+}
+
+impl MessageDescriptors {
+    /// The descriptors of the services served by auraed.
+    pub(crate) fn auraed() -> Result<Self, prost::DecodeError> {
+        Self::new(&[
+            aurae_proto::discovery::FILE_DESCRIPTOR_SET,
+            aurae_proto::grpc::health::FILE_DESCRIPTOR_SET,
+            aurae_proto::runtime::FILE_DESCRIPTOR_SET,
+        ])
+    }
+
+    fn new(
+        encoded_file_descriptor_sets: &[&[u8]],
+    ) -> Result<Self, prost::DecodeError> {
+        let mut descriptors = Self::default();
+
+        for encoded in encoded_file_descriptor_sets {
+            for file in FileDescriptorSet::decode(*encoded)?.file {
+                let package = file.package();
+                let scope = if package.is_empty() {
+                    String::new()
+                } else {
+                    format!(".{package}")
+                };
+
+                for message in &file.message_type {
+                    descriptors.add_message(&scope, message);
+                }
+
+                for service in &file.service {
+                    let service_name = match package {
+                        "" => service.name().to_string(),
+                        package => format!("{package}.{}", service.name()),
+                    };
+                    for method in &service.method {
+                        if method.client_streaming()
+                            || method.server_streaming()
+                        {
+                            continue;
+                        }
+                        let _ = descriptors.methods.insert(
+                            format!("/{service_name}/{}", method.name()),
+                            method.input_type().to_string(),
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(descriptors)
+    }
+
+    fn add_message(&mut self, scope: &str, message: &DescriptorProto) {
+        let name = format!("{scope}.{}", message.name());
+
+        // Map fields are messages nested in the message that declares them
+        for nested in &message.nested_type {
+            self.add_message(&name, nested);
+        }
+
+        let fields = message
+            .field
+            .iter()
+            .map(|field| {
+                let message_type = (field.r#type() == Type::Message)
+                    .then(|| field.type_name().to_string());
+                (field.number() as u32, message_type)
+            })
+            .collect();
+        let _ = self.messages.insert(name, fields);
+    }
+
+    /// Returns true if requests to the method at `path` are checked for unknown fields.
+    /// Only requests of unary methods are checked.
+    fn checks(&self, path: &str) -> bool {
+        self.methods.contains_key(path)
+    }
+
+    /// Returns the first unknown field of `request`, an encoded request of the method at
+    /// `path`, including the fields of the messages nested in it.
+    /// Returns [None] if the method is unknown or `request` is malformed.
+    pub(crate) fn unknown_field(
+        &self,
+        path: &str,
+        request: &[u8],
+    ) -> Option<UnknownField> {
+        let message = self.methods.get(path)?;
+        self.unknown_field_in(message, request).ok().flatten()
+    }
+
+    fn unknown_field_in(
+        &self,
+        message: &str,
+        mut buf: &[u8],
+    ) -> Result<Option<UnknownField>, Malformed> {
+        let Some(fields) = self.messages.get(message) else {
+            return Ok(None);
+        };
+
+        while !buf.is_empty() {
+            let key = decode_varint(&mut buf).map_err(|_| Malformed)?;
+            let number = (key >> 3) as u32;
+
+            let Some(message_type) = fields.get(&number) else {
+                return Ok(Some(UnknownField {
+                    message: message.trim_start_matches('.').to_string(),
+                    number,
+                }));
+            };
+
+            let len = match key & 0x7 {
+                // varint
+                0 => {
+                    let _ = decode_varint(&mut buf).map_err(|_| Malformed)?;
+                    continue;
+                }
+                // 64-bit
+                1 => 8,
+                // length-delimited
+                2 => decode_varint(&mut buf).map_err(|_| Malformed)? as usize,
+                // 32-bit
+                5 => 4,
+                // groups are not used by proto3
+                _ => return Err(Malformed),
+            };
+
+            if len > buf.len() {
+                return Err(Malformed);
+            }
+            let (value, rest) = buf.split_at(len);
+            buf = rest;
+
+            if let Some(message_type) = message_type {
+                if let Some(unknown) =
+                    self.unknown_field_in(message_type, value)?
+                {
+                    return Ok(Some(unknown));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the first unknown field of the messages in `body`, the gRPC framed body
+    /// of a request to the method at `path`. Compressed messages are not checked.
+    fn unknown_field_in_body(
+        &self,
+        path: &str,
+        mut body: &[u8],
+    ) -> Option<UnknownField> {
+        // Each message is prefixed with a compressed flag and its length
+        while body.len() >= 5 {
+            let compressed = body[0] != 0;
+            let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]])
+                as usize;
+            let message = body.get(5..5 + len)?;
+            body = &body[5 + len..];
+
+            if compressed {
+                continue;
+            }
+            if let Some(unknown) = self.unknown_field(path, message) {
+                return Some(unknown);
+            }
+        }
+
+        None
+    }
+}
+
+/// Rejects requests with unknown fields, if `enabled`
+/// (see [MessageDescriptors::unknown_field]).
+#[derive(Debug, Clone)]
+pub(crate) struct StrictFieldsLayer {
+    descriptors: Option<Arc<MessageDescriptors>>,
+}
+
+impl StrictFieldsLayer {
+    pub(crate) fn new(enabled: bool) -> Result<Self, prost::DecodeError> {
+        let descriptors = if enabled {
+            Some(Arc::new(MessageDescriptors::auraed()?))
+        } else {
+            None
+        };
+        Ok(Self { descriptors })
+    }
+}
+
+impl<S> Layer<S> for StrictFieldsLayer {
+    type Service = StrictFields<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StrictFields { inner, descriptors: self.descriptors.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StrictFields<S> {
+    inner: S,
+    descriptors: Option<Arc<MessageDescriptors>>,
+}
+
+impl<S> Service<Request<Body>> for StrictFields<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Only the (complete) body of a unary request is read before it is served
+        let Some(descriptors) = self
+            .descriptors
+            .clone()
+            .filter(|descriptors| descriptors.checks(request.uri().path()))
+        else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        // The clone may not be ready, so we call the one that was polled
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = match hyper::body::to_bytes(body).await {
+                Ok(body) => body,
+                Err(e) => return Ok(read_error_status(&e).to_http()),
+            };
+
+            if let Some(unknown) =
+                descriptors.unknown_field_in_body(parts.uri.path(), &body)
+            {
+                return Ok(Status::invalid_argument(format!(
+                    "request has {unknown}, which this auraed does not support"
+                ))
+                .to_http());
+            }
+
+            inner.call(Request::from_parts(parts, Body::from(body))).await
+        })
+    }
+}
+
+/// Returns the [Status] the body of a request was failed with by an outer layer (e.g.,
+/// [crate::max_request_size]), or [Status::cancelled] if it failed otherwise.
+fn read_error_status(error: &hyper::Error) -> Status {
+    let mut source = error.source();
+    while let Some(e) = source {
+        if let Some(status) = e.downcast_ref::<Status>() {
+            return Status::new(status.code(), status.message());
+        }
+        source = e.source();
+    }
+
+    Status::cancelled(format!("failed to read request: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::max_request_size::MaxRequestSizeLayer;
+    use aurae_proto::grpc::health::{
+        health_client::HealthClient, HealthCheckRequest, HealthCheckResponse,
+    };
+    use aurae_proto::runtime::{Cell, CellServiceAllocateRequest};
+    use prost::encoding::encode_varint;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+    use tonic::{
+        codec::ProstCodec,
+        codegen::http::uri::PathAndQuery,
+        transport::{Channel, Server},
+        Code,
+    };
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, ServerReflectionRequest,
+    };
+
+    const ALLOCATE: &str = "/aurae.runtime.v0.CellService/Allocate";
+
+    /// Appends a varint field, unknown to the protos of auraed, to `message`.
+    fn with_unknown_field(mut message: Vec<u8>, number: u64) -> Vec<u8> {
+        encode_varint(number << 3, &mut message);
+        encode_varint(1, &mut message);
+        message
+    }
+
+    #[test]
+    fn test_known_fields() {
+        let descriptors = MessageDescriptors::auraed().expect("descriptors");
+        let request = CellServiceAllocateRequest {
+            cell: Some(Cell { name: "cell".into(), ..Default::default() }),
+            reuse_existing: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            descriptors.unknown_field(ALLOCATE, &request.encode_to_vec()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_of_nested_message() {
+        let descriptors = MessageDescriptors::auraed().expect("descriptors");
+        let cell = Cell { name: "cell".into(), ..Default::default() };
+        let cell = with_unknown_field(cell.encode_to_vec(), 999);
+
+        // the cell is field 1 of the request
+        let mut request = vec![];
+        encode_varint(1 << 3 | 2, &mut request);
+        encode_varint(cell.len() as u64, &mut request);
+        request.extend(cell);
+
+        assert_eq!(
+            descriptors.unknown_field(ALLOCATE, &request),
+            Some(UnknownField {
+                message: "aurae.runtime.v0.Cell".into(),
+                number: 999,
+            })
+        );
+    }
+
+    #[test]
+    fn test_only_unary_methods_are_checked() {
+        let descriptors = MessageDescriptors::auraed().expect("descriptors");
+
+        assert!(descriptors.checks(ALLOCATE));
+        assert!(descriptors.checks("/grpc.health.v1.Health/Check"));
+        assert!(!descriptors.checks("/grpc.health.v1.Health/Watch"));
+        assert!(!descriptors.checks(
+            "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo"
+        ));
+    }
+
+    /// A HealthCheckRequest from a client with a newer proto.
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct NewerHealthCheckRequest {
+        #[prost(string, tag = "1")]
+        service: String,
+        #[prost(uint32, tag = "100")]
+        newer_field: u32,
+    }
+
+    async fn check_health(strict: bool, newer_field: u32) -> Code {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+        let (_reporter, health_service) =
+            tonic_health::server::health_reporter();
+
+        let _server = tokio::spawn(
+            Server::builder()
+                .layer(StrictFieldsLayer::new(strict).expect("layer"))
+                .add_service(health_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .expect("uri")
+            .connect()
+            .await
+            .expect("connect");
+        // The generated client only sends a HealthCheckRequest
+        let mut client = tonic::client::Grpc::new(channel);
+        client.ready().await.expect("ready");
+
+        let request =
+            NewerHealthCheckRequest { service: String::new(), newer_field };
+        let res = client
+            .unary::<_, HealthCheckResponse, _>(
+                tonic::Request::new(request),
+                PathAndQuery::from_static("/grpc.health.v1.Health/Check"),
+                ProstCodec::default(),
+            )
+            .await;
+
+        match res {
+            Ok(_) => Code::Ok,
+            Err(status) => status.code(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_unknown_field() {
+        assert_eq!(check_health(true, 1).await, Code::InvalidArgument);
+        // a field set to its default value is not sent
+        assert_eq!(check_health(true, 0).await, Code::Ok);
+        assert_eq!(check_health(false, 1).await, Code::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_keeps_the_status_of_an_oversized_request() {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+        let (_reporter, health_service) =
+            tonic_health::server::health_reporter();
+
+        // as auraed stacks the layers
+        let _server = tokio::spawn(
+            Server::builder()
+                .layer(MaxRequestSizeLayer::new(16))
+                .layer(StrictFieldsLayer::new(true).expect("layer"))
+                .add_service(health_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .expect("uri")
+            .connect()
+            .await
+            .expect("connect");
+        let e = HealthClient::new(channel)
+            .check(HealthCheckRequest { service: "a".repeat(64) })
+            .await
+            .expect_err("oversized request");
+
+        assert_eq!(e.code(), Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_does_not_hold_up_streaming_requests() {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").await.expect("bind listener");
+        let addr = listener.local_addr().expect("local addr");
+
+        let _server = tokio::spawn(
+            Server::builder()
+                .layer(StrictFieldsLayer::new(true).expect("layer"))
+                .add_service(
+                    crate::reflection::service().expect("reflection service"),
+                )
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client =
+            ServerReflectionClient::connect(format!("http://{addr}"))
+                .await
+                .expect("connect");
+
+        // the request stream is never closed
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let requests =
+            tokio_stream::once(request).chain(tokio_stream::pending());
+
+        let response = tokio::time::timeout(Duration::from_secs(5), async {
+            client
+                .server_reflection_info(requests)
+                .await
+                .expect("reflection request")
+                .into_inner()
+                .next()
+                .await
+        })
+        .await
+        .expect("the streaming request was held up");
+
+        assert!(matches!(response, Some(Ok(_))));
+    }
+}
@@ -0,0 +1,296 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Cell tests against a real auraed (see [common::Auraed]).
+
+mod common;
+
+use aurae_client::runtime::cell_service::CellServiceClient;
+use aurae_proto::runtime::{
+    Cell, CellServiceFreeRequest, CellServiceListExecutablesRequest,
+    CellServiceListRequest, CellServiceRunRequest, CellServiceStartRequest,
+    CellServiceStatRequest, CpuController, Executable, FreeChildrenPolicy,
+};
+use common::Auraed;
+use std::time::{Duration, Instant};
+use tonic::Code;
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_allocate_and_free_cells() {
+    let auraed = Auraed::start().await;
+    auraed.assert_cells(&[]).await;
+
+    let _ = auraed.allocate("ae-harness-1").await.expect("allocate");
+    let _ = auraed
+        .allocate_cell(Cell {
+            name: "ae-harness-2".into(),
+            cpu: Some(CpuController {
+                weight: Some(100),
+                max: Some(500000),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await
+        .expect("allocate with cpu controller");
+    auraed.assert_cells(&["ae-harness-1", "ae-harness-2"]).await;
+
+    let e = auraed.allocate("ae-harness-1").await.expect_err("cell exists");
+    assert_eq!(e.code(), Code::AlreadyExists);
+
+    let _ = auraed.free("ae-harness-1").await.expect("free");
+    auraed.assert_cells(&["ae-harness-2"]).await;
+
+    let e = auraed.free("ae-harness-1").await.expect_err("cell is freed");
+    assert_eq!(e.code(), Code::NotFound);
+
+    let _ = auraed.free("ae-harness-2").await.expect("free");
+    auraed.assert_cells(&[]).await;
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_concurrent_sibling_allocations() {
+    const SIBLINGS: usize = 16;
+
+    let auraed = Auraed::start().await;
+    let names: Vec<_> =
+        (0..SIBLINGS).map(|i| format!("ae-harness-sibling-{i:02}")).collect();
+
+    // each enables the cpu controller in the cgroup.subtree_control of their parent
+    let allocations = names.iter().map(|name| {
+        auraed.allocate_cell(Cell {
+            name: name.clone(),
+            cpu: Some(CpuController {
+                weight: Some(100),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    });
+    for response in futures::future::join_all(allocations).await {
+        let _ = response.expect("allocate");
+    }
+    let expected: Vec<_> = names.iter().map(String::as_str).collect();
+    auraed.assert_cells(&expected).await;
+
+    for name in &names {
+        let _ = auraed.free(name).await.expect("free");
+    }
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_free_cell_on_exit() {
+    let auraed = Auraed::start().await;
+    let _ = auraed.allocate("ae-harness-job").await.expect("allocate");
+
+    let request = CellServiceStartRequest {
+        cell_name: "ae-harness-job".into(),
+        executable: Some(Executable {
+            name: "ae-job".into(),
+            command: "true".into(),
+            ..Default::default()
+        }),
+        free_cell_on_exit: true,
+        ..Default::default()
+    };
+    let _ = auraed.client.start(request).await.expect("start");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !auraed.cell_names().await.is_empty() {
+        assert!(Instant::now() < deadline, "cell was not freed");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_processes_in_cell_inherit_oom_score_adj() {
+    let auraed = Auraed::start().await;
+    let _ = auraed
+        .allocate_cell(Cell {
+            name: "ae-harness-oom".into(),
+            oom_score_adj: Some(500),
+            ..Default::default()
+        })
+        .await
+        .expect("allocate");
+
+    let request = CellServiceRunRequest {
+        cell_name: "ae-harness-oom".into(),
+        executable: Some(Executable {
+            name: "ae-oom-score-adj".into(),
+            command: "cat /proc/self/oom_score_adj".into(),
+            ..Default::default()
+        }),
+        return_output_tail: 10,
+        ..Default::default()
+    };
+    let response = auraed.client.run(request).await.expect("run").into_inner();
+
+    assert_eq!(response.exit_code, Some(0));
+    let lines: Vec<_> = response
+        .output_tail
+        .iter()
+        .map(|line| (line.stream.as_str(), line.line.as_str()))
+        .collect();
+    assert_eq!(lines, [("stdout", "500")]);
+
+    let _ = auraed.free("ae-harness-oom").await.expect("free");
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_free_orphan_rejects_nested_cells() {
+    let auraed = Auraed::start().await;
+    let _ = auraed.allocate("ae-harness-parent").await.expect("allocate");
+    let _ = auraed
+        .allocate("ae-harness-parent/ae-harness-child")
+        .await
+        .expect("allocate nested cell");
+
+    let free = |children_policy: FreeChildrenPolicy| {
+        auraed.client.free(CellServiceFreeRequest {
+            cell_name: "ae-harness-parent".into(),
+            return_final_stats: false,
+            children_policy: children_policy as i32,
+        })
+    };
+
+    // the child is allocated by the nested auraed of the cell
+    let e = free(FreeChildrenPolicy::Orphan).await.expect_err("orphan");
+    assert_eq!(e.code(), Code::FailedPrecondition);
+
+    let _ = free(FreeChildrenPolicy::Recursive).await.expect("free");
+    auraed.assert_cells(&[]).await;
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_start_validate_only_in_cell_creates_nothing() {
+    let auraed = Auraed::start().await;
+    let _ = auraed.allocate("ae-harness-plan").await.expect("allocate");
+
+    let request = CellServiceStartRequest {
+        cell_name: "ae-harness-plan".into(),
+        executable: Some(Executable {
+            name: "ae-validate-only".into(),
+            command: "sleep 60".into(),
+            ..Default::default()
+        }),
+        validate_only: true,
+        free_cell_on_exit: true,
+        ..Default::default()
+    };
+    let response = auraed
+        .client
+        .start(request)
+        .await
+        .expect("validate only start")
+        .into_inner();
+
+    assert_eq!(response.pid, 0);
+    let plan = response.plan.expect("plan");
+    assert_eq!(plan.cell_name, "ae-harness-plan");
+    assert!(!plan.cgroup.is_empty());
+
+    let executables = auraed
+        .client
+        .list_executables(CellServiceListExecutablesRequest {
+            cell_name: "ae-harness-plan".into(),
+        })
+        .await
+        .expect("list executables")
+        .into_inner();
+    assert!(executables.executables.is_empty());
+
+    // free_cell_on_exit is not armed for a plan, so the cell is still allocated
+    auraed.assert_cells(&["ae-harness-plan"]).await;
+
+    let _ = auraed.free("ae-harness-plan").await.expect("free");
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_list_includes_stats_on_request() {
+    let auraed = Auraed::start().await;
+    let _ = auraed.allocate("ae-harness-stats").await.expect("allocate");
+
+    let auraed = &auraed;
+    let list = |include_stats| async move {
+        let request = CellServiceListRequest {
+            cell_name: String::new(),
+            include_stats,
+            recursive: false,
+        };
+        let mut cells =
+            auraed.client.list(request).await.expect("list").into_inner().cells;
+        assert_eq!(cells.len(), 1);
+        cells.remove(0)
+    };
+
+    assert!(list(false).await.stats.is_none());
+
+    let stats = list(true).await.stats.expect("stats");
+    // cpu.stat exists whether or not the cpu controller is enabled
+    assert!(stats.cpu_usage_usec.is_some());
+
+    let _ = auraed.free("ae-harness-stats").await.expect("free");
+}
+
+// Ignored: requires the certs of `make certs` and a delegated cgroup (see [common])
+#[ignore]
+#[tokio::test]
+async fn test_stat() {
+    let auraed = Auraed::start().await;
+    let _ = auraed.allocate("ae-harness-stat").await.expect("allocate");
+
+    let response = auraed
+        .client
+        .stat(CellServiceStatRequest { cell_name: "ae-harness-stat".into() })
+        .await
+        .expect("stat")
+        .into_inner();
+    assert_eq!(response.cell_name, "ae-harness-stat");
+    let stats = response.stats.expect("stats");
+    // cpu.stat exists whether or not the cpu controller is enabled
+    assert!(stats.cpu_usage_usec.is_some());
+
+    let _ = auraed.free("ae-harness-stat").await.expect("free");
+}
@@ -0,0 +1,241 @@
+/* -------------------------------------------------------------------------- *\
+ *        Apache 2.0 License Copyright © 2022-2023 The Aurae Authors          *
+ *                                                                            *
+ *                +--------------------------------------------+              *
+ *                |   █████╗ ██╗   ██╗██████╗  █████╗ ███████╗ |              *
+ *                |  ██╔══██╗██║   ██║██╔══██╗██╔══██╗██╔════╝ |              *
+ *                |  ███████║██║   ██║██████╔╝███████║█████╗   |              *
+ *                |  ██╔══██║██║   ██║██╔══██╗██╔══██║██╔══╝   |              *
+ *                |  ██║  ██║╚██████╔╝██║  ██║██║  ██║███████╗ |              *
+ *                |  ╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝ |              *
+ *                +--------------------------------------------+              *
+ *                                                                            *
+ *                         Distributed Systems Runtime                        *
+ *                                                                            *
+ * -------------------------------------------------------------------------- *
+ *                                                                            *
+ *   Licensed under the Apache License, Version 2.0 (the "License");          *
+ *   you may not use this file except in compliance with the License.         *
+ *   You may obtain a copy of the License at                                  *
+ *                                                                            *
+ *       http://www.apache.org/licenses/LICENSE-2.0                           *
+ *                                                                            *
+ *   Unless required by applicable law or agreed to in writing, software      *
+ *   distributed under the License is distributed on an "AS IS" BASIS,        *
+ *   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. *
+ *   See the License for the specific language governing permissions and      *
+ *   limitations under the License.                                           *
+ *                                                                            *
+\* -------------------------------------------------------------------------- */
+
+//! Runs a real auraed for integration tests (see [Auraed::start]), in a private user,
+//! cgroup and mount namespace, so cells can be allocated without root.
+//!
+//! Requires:
+//! * The certs of `make certs`, in the `pki` directory of the repository.
+//! * The `unshare` command, and unprivileged user namespaces.
+//! * That the tests run in a leaf of a cgroup delegated to the user running them,
+//!   with the controllers cells use enabled in its `cgroup.subtree_control`. Each
+//!   auraed gets its own cgroup in the delegated one, which it allocates cells in.
+//!   See `.github/workflows/037-*.yml` for how CI sets this up.
+
+use aurae_client::{
+    runtime::cell_service::CellServiceClient, AuraeClient, AuraeConfig,
+    AuthConfig, SystemConfig,
+};
+use aurae_proto::runtime::{
+    Cell, CellServiceAllocateRequest, CellServiceAllocateResponse,
+    CellServiceFreeRequest, CellServiceFreeResponse, CellServiceListRequest,
+};
+use backoff::ExponentialBackoffBuilder;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::process::{Child, Command};
+use tonic::Status;
+
+/// Moves the shell into the cgroup of the first argument, then runs the others.
+const ENTER_CGROUP_AND_EXEC: &str =
+    r#"echo $$ > "$1/cgroup.procs" && shift && exec "$@""#;
+
+/// Mounts the cgroup filesystem of our cgroup namespace, then runs the arguments.
+const MOUNT_CGROUP_AND_EXEC: &str =
+    r#"mount -t cgroup2 none /sys/fs/cgroup && exec "$@""#;
+
+/// Where the cgroup filesystem is mounted.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// How long auraed is given to start accepting connections.
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running auraed, which is killed when dropped, along with every process left in
+/// its cells. Tests should still free the cells they allocate, whose cgroups are
+/// otherwise left behind.
+pub struct Auraed {
+    pub client: AuraeClient,
+    dir: PathBuf,
+    cgroup: PathBuf,
+    _child: Child,
+}
+
+impl Auraed {
+    /// Starts auraed in a new cgroup, next to the one the tests run in, and in a new
+    /// user namespace, in which we are root, with a new cgroup and mount namespace, in
+    /// which the cgroup filesystem is mounted again so that its root is the new cgroup.
+    /// Returns once auraed accepts connections on its socket, which is in a new temp
+    /// directory.
+    pub async fn start() -> Self {
+        let pki = Path::new(env!("CARGO_MANIFEST_DIR")).join("../pki");
+        assert!(
+            pki.join("ca.crt").exists(),
+            "missing certs in {}, run `make certs`",
+            pki.display()
+        );
+
+        let dir = std::env::temp_dir()
+            .join(format!("aurae-harness-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("create dir");
+        let socket = dir.join("aurae.sock");
+        let cgroup = harness_cgroup();
+
+        let child = Command::new("sh")
+            .args(["-c", ENTER_CGROUP_AND_EXEC, "sh"])
+            .arg(&cgroup)
+            .arg("unshare")
+            .args(["--user", "--map-root-user", "--cgroup", "--mount", "--"])
+            .args(["sh", "-c", MOUNT_CGROUP_AND_EXEC, "sh"])
+            .arg(env!("CARGO_BIN_EXE_auraed"))
+            .arg("--socket")
+            .arg(&socket)
+            .arg("--runtime-dir")
+            .arg(dir.join("run"))
+            .arg("--server-crt")
+            .arg(pki.join("_signed.server.crt"))
+            .arg("--server-key")
+            .arg(pki.join("server.key"))
+            .arg("--ca-crt")
+            .arg(pki.join("ca.crt"))
+            // we start out in the root of our cgroup namespace, which cells are created in
+            .arg("--migrate-to-leaf-cgroup")
+            .kill_on_drop(true)
+            .spawn()
+            .expect("spawn auraed");
+
+        let config = AuraeConfig {
+            auth: AuthConfig {
+                ca_crt: path_string(pki.join("ca.crt")),
+                client_crt: path_string(pki.join("_signed.client.nova.crt")),
+                client_key: path_string(pki.join("client.nova.key")),
+            },
+            system: SystemConfig { socket: path_string(socket) },
+        };
+        let backoff = ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_millis(50))
+            .with_max_interval(Duration::from_millis(500))
+            .with_max_elapsed_time(Some(START_TIMEOUT))
+            .build();
+        let client = AuraeClient::connect_with_retry(config, u32::MAX, backoff)
+            .await
+            .expect("connect to auraed");
+
+        Self { client, dir, cgroup, _child: child }
+    }
+
+    /// Allocates a cell with no other settings than its name.
+    pub async fn allocate(
+        &self,
+        cell_name: &str,
+    ) -> Result<CellServiceAllocateResponse, Status> {
+        self.allocate_cell(Cell {
+            name: cell_name.into(),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Allocates `cell`.
+    pub async fn allocate_cell(
+        &self,
+        cell: Cell,
+    ) -> Result<CellServiceAllocateResponse, Status> {
+        let request = CellServiceAllocateRequest {
+            cell: Some(cell),
+            ..Default::default()
+        };
+
+        self.client.allocate(request).await.map(|res| res.into_inner())
+    }
+
+    /// Frees the cell, rejecting it if it has nested cells.
+    pub async fn free(
+        &self,
+        cell_name: &str,
+    ) -> Result<CellServiceFreeResponse, Status> {
+        let request = CellServiceFreeRequest {
+            cell_name: cell_name.into(),
+            ..Default::default()
+        };
+
+        self.client.free(request).await.map(|res| res.into_inner())
+    }
+
+    /// Returns the names of the (top level) cells of auraed, ordered by name.
+    pub async fn cell_names(&self) -> Vec<String> {
+        self.client
+            .list(CellServiceListRequest::default())
+            .await
+            .expect("list cells")
+            .into_inner()
+            .cells
+            .into_iter()
+            .map(|cell| cell.cell_name)
+            .collect()
+    }
+
+    /// Asserts that auraed has exactly the cells `expected`, in any order.
+    pub async fn assert_cells(&self, expected: &[&str]) {
+        let mut expected: Vec<_> =
+            expected.iter().map(|name| name.to_string()).collect();
+        expected.sort();
+
+        assert_eq!(self.cell_names().await, expected);
+    }
+}
+
+impl Drop for Auraed {
+    fn drop(&mut self) {
+        // auraed itself is killed by dropping the child, which does not wait for it
+        let _ = fs::write(self.cgroup.join("cgroup.kill"), "1");
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Creates a new cgroup in the parent of the cgroup the tests run in, which must be
+/// delegated to us.
+fn harness_cgroup() -> PathBuf {
+    let own = fs::read_to_string("/proc/self/cgroup").expect("read cgroup");
+    let own = own
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .expect("cgroup v2")
+        .trim();
+    let delegated = Path::new(own).parent().unwrap_or_else(|| {
+        panic!(
+            "the tests must run in a leaf of a delegated cgroup, not in {own}"
+        )
+    });
+
+    let cgroup = Path::new(CGROUP_ROOT)
+        .join(delegated.strip_prefix("/").expect("absolute cgroup"))
+        .join(format!("aurae-harness-{}", uuid::Uuid::new_v4()));
+    fs::create_dir(&cgroup).unwrap_or_else(|e| {
+        panic!("create {}, is its parent delegated? {e}", cgroup.display())
+    });
+    cgroup
+}
+
+fn path_string(path: PathBuf) -> String {
+    path.to_str().expect("utf-8 path").to_string()
+}
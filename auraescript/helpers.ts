@@ -13,4 +13,35 @@ function toString(value: any): string {
     } else {
         return value?.toString() + "\n";
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Waits until the cell no longer exists (i.e., its cgroup has been removed),
+ * polling every pollIntervalMs. Rejects if the cell still exists after timeoutMs.
+ *
+ * Useful after freeing a cell with running processes, before reallocating a
+ * cell with the same name.
+ */
+export async function waitUntilFreed(
+    cellName: string,
+    timeoutMs: number,
+    pollIntervalMs: number = 100,
+): Promise<void> {
+    // @ts-ignore
+    return Deno.core.ops.op_wait_until_freed(cellName, timeoutMs, pollIntervalMs);
+}
+
+/**
+ * Waits until auraed accepts a connection, doubling the delay between attempts,
+ * starting from initialIntervalMs. Rejects after maxAttempts failed attempts.
+ *
+ * Useful at the start of long-running scripts, which would otherwise fail their
+ * first call while auraed is (re)starting.
+ */
+export async function connectWithRetry(
+    maxAttempts: number = 10,
+    initialIntervalMs: number = 100,
+): Promise<void> {
+    // @ts-ignore
+    return Deno.core.ops.op_connect_with_retry(maxAttempts, initialIntervalMs);
+}
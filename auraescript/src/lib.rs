@@ -81,6 +81,7 @@ pub fn init() -> JsRuntime {
 fn stdlib() -> Vec<OpDecl> {
     let mut ops = vec![];
     ops.extend(runtime::op_decls());
+    ops.extend(runtime::helper_op_decls());
     ops.extend(discovery::op_decls());
     ops.extend(health::op_decls());
     ops
@@ -28,14 +28,44 @@
  *                                                                            *
 \* -------------------------------------------------------------------------- */
 
+use anyhow::bail;
+use aurae_client::{
+    runtime::cell_service::CellServiceClient, AuraeClient, AuraeConfig,
+};
+use aurae_proto::runtime::CellServiceDescribeRequest;
+use backoff::ExponentialBackoffBuilder;
+use deno_core::OpDecl;
+use std::time::{Duration, Instant};
+use tonic::Code;
+
+/// Used when a poll interval of 0 is given to [op_wait_until_freed].
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 macros::ops_generator!(
     runtime,
     {
         CellService,
         allocate(CellServiceAllocateRequest) -> CellServiceAllocateResponse,
+        update(CellServiceUpdateRequest) -> CellServiceUpdateResponse,
         free(CellServiceFreeRequest) -> CellServiceFreeResponse,
         start(CellServiceStartRequest) -> CellServiceStartResponse,
         stop(CellServiceStopRequest) -> CellServiceStopResponse,
+        restart(CellServiceRestartRequest) -> CellServiceRestartResponse,
+        stop_generation(CellServiceStopGenerationRequest) -> CellServiceStopGenerationResponse,
+        run(CellServiceRunRequest) -> CellServiceRunResponse,
+        list(CellServiceListRequest) -> CellServiceListResponse,
+        free_by_selector(CellServiceFreeBySelectorRequest) -> CellServiceFreeBySelectorResponse,
+        list_executables(CellServiceListExecutablesRequest) -> CellServiceListExecutablesResponse,
+        get_cell_by_tid(CellServiceGetCellByTidRequest) -> CellServiceGetCellByTidResponse,
+        describe(CellServiceDescribeRequest) -> CellServiceDescribeResponse,
+        stat(CellServiceStatRequest) -> CellServiceStatResponse,
+        drain(CellServiceDrainRequest) -> CellServiceDrainResponse,
+        freeze(CellServiceFreezeRequest) -> CellServiceFreezeResponse,
+        thaw(CellServiceThawRequest) -> CellServiceThawResponse,
+        list_fds(CellServiceListFdsRequest) -> CellServiceListFdsResponse,
+        commitment(CellServiceCommitmentRequest) -> CellServiceCommitmentResponse,
+        retry_config(CellServiceRetryConfigRequest) -> CellServiceRetryConfigResponse,
+        list_orphans(CellServiceListOrphansRequest) -> CellServiceListOrphansResponse,
     },
     {
         PodService,
@@ -45,3 +75,69 @@ macros::ops_generator!(
         stop(PodServiceStopRequest) -> PodServiceStopResponse,
     },
 );
+
+pub(crate) fn helper_op_decls() -> Vec<OpDecl> {
+    vec![op_wait_until_freed::decl(), op_connect_with_retry::decl()]
+}
+
+/// Blocks until auraed accepts a connection (see [AuraeClient::connect_with_retry]),
+/// doubling the delay between attempts, starting from `initial_interval_ms`.
+/// Errors if auraed can't be connected to after `max_attempts` attempts.
+///
+/// Useful at the start of long-running scripts, which would otherwise fail their first
+/// call while auraed is (re)starting.
+#[deno_core::op]
+pub(crate) async fn op_connect_with_retry(
+    max_attempts: u32,
+    initial_interval_ms: u64,
+) -> Result<(), anyhow::Error> {
+    let backoff = ExponentialBackoffBuilder::new()
+        .with_initial_interval(Duration::from_millis(initial_interval_ms))
+        .with_multiplier(2.0)
+        .with_max_elapsed_time(None)
+        .build();
+
+    let config = AuraeConfig::try_default()?;
+    let _client =
+        AuraeClient::connect_with_retry(config, max_attempts, backoff).await?;
+    Ok(())
+}
+
+/// Blocks until the cell no longer exists (i.e., its cgroup has been removed), by
+/// polling describe every `poll_interval_ms`. Errors if the cell still exists after
+/// `timeout_ms`.
+///
+/// Useful after freeing a cell with running processes, to avoid racing a reallocation
+/// of the same name against the removal of the cgroup.
+#[deno_core::op]
+pub(crate) async fn op_wait_until_freed(
+    cell_name: String,
+    timeout_ms: u64,
+    poll_interval_ms: u64,
+) -> Result<(), anyhow::Error> {
+    let timeout = Duration::from_millis(timeout_ms);
+    let poll_interval = match poll_interval_ms {
+        0 => DEFAULT_POLL_INTERVAL,
+        ms => Duration::from_millis(ms),
+    };
+
+    let client = AuraeClient::default().await?;
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let request =
+            CellServiceDescribeRequest { cell_name: cell_name.clone() };
+        match CellServiceClient::describe(&client, request).await {
+            Err(status) if status.code() == Code::NotFound => return Ok(()),
+            Err(status) => return Err(status.into()),
+            Ok(_) => {}
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            bail!("cell '{cell_name}' was not freed within {timeout:?}");
+        }
+
+        tokio::time::sleep(poll_interval.min(deadline - now)).await;
+    }
+}
@@ -134,11 +134,68 @@ impl ValidationError {
             Self::AllowRegexViolation { field, .. } => field,
         }
     }
+
+    /// Describes why the field is invalid, without naming the field.
+    pub fn get_reason(&self) -> String {
+        match self {
+            Self::Required { .. } => "required".to_string(),
+            Self::Minimum { minimum, units, .. } => {
+                format!("minimum is {minimum} {units}")
+            }
+            Self::Maximum { maximum, units, .. } => {
+                format!("maximum is {maximum} {units}")
+            }
+            #[cfg(feature = "regex")]
+            Self::AllowRegexViolation { pattern, .. } => {
+                format!("must match regex {pattern}")
+            }
+            Self::Invalid { .. } => "invalid".to_string(),
+        }
+    }
 }
 
+/// Converts to an `InvalidArgument` [tonic::Status] with a `google.rpc.BadRequest`
+/// detail, containing a `FieldViolation` for the invalid field.
 #[cfg(feature = "tonic")]
 impl From<ValidationError> for tonic::Status {
     fn from(e: ValidationError) -> Self {
-        Self::failed_precondition(e.to_string())
+        use tonic_types::{ErrorDetails, StatusExt};
+
+        let details = ErrorDetails::with_bad_request_violation(
+            e.get_field(),
+            e.get_reason(),
+        );
+
+        Self::with_error_details(
+            tonic::Code::InvalidArgument,
+            e.to_string(),
+            details,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "tonic"))]
+mod tests {
+    use super::*;
+    use tonic_types::StatusExt;
+
+    #[test]
+    fn test_status_has_bad_request_details() {
+        let e = ValidationError::Minimum {
+            field: field_name("weight", Some("cell.cpu")),
+            minimum: "1".to_string(),
+            units: "unit".to_string(),
+        };
+
+        let status = tonic::Status::from(e);
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let bad_request =
+            status.get_details_bad_request().expect("bad request details");
+        assert_eq!(bad_request.field_violations.len(), 1);
+
+        let violation = &bad_request.field_violations[0];
+        assert_eq!(violation.field, "cell.cpu.weight");
+        assert_eq!(violation.description, "minimum is 1 unit");
     }
 }